@@ -1,27 +1,354 @@
 use anyhow::{bail, Context, Result};
 use hdf5::File;
 use rdr::{
-    config::{get_default, Config, ProductSpec},
-    write_rdr_granule, GranuleMeta, Meta, Rdr, Time,
+    config::{get_default_for_platform, Config, ProductSpec},
+    get_granule_start, verify_rdr, write_all_data_attrs, write_rdr_granule, AggrReport, GranuleGap,
+    GranuleMeta, IetMicros, Meta, RawDatasetDetail, Rdr, SupersededGranule, Time, WriteOptions,
 };
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
     path::{Path, PathBuf},
 };
-use tracing::{error, info, info_span, warn};
+use tracing::{debug, error, info, info_span, warn};
 
-use crate::command_extract::extract;
+use crate::command_create::print_dry_run;
+use crate::command_extract::{
+    extract, granule_datasets, lenient_granule_datasets, ExtractedOutput,
+};
+use crate::manifest::{sha256_file, ManifestFile, RunManifest};
 
-struct Item {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ManifestGranule {
+    short_name: String,
+    granule_id: String,
     path: PathBuf,
+    sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ManifestInput {
+    /// Size of the input when it was extracted, used to detect changed inputs
+    size: u64,
+    granules: Vec<ManifestGranule>,
+}
+
+/// Record of the granules extracted to a working directory, so that a re-run using the same
+/// working directory can resume rather than re-extracting every input.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WorkdirManifest {
+    /// Input file path to granules extracted from it
+    inputs: HashMap<PathBuf, ManifestInput>,
+}
+
+impl WorkdirManifest {
+    const NAME: &str = "manifest.json";
+
+    /// Load the manifest from `workdir`, or an empty manifest if there is not a valid one.
+    fn load(workdir: &Path) -> Self {
+        let path = workdir.join(Self::NAME);
+        let Ok(file) = std::fs::File::open(&path) else {
+            return Self::default();
+        };
+        match serde_json::from_reader(file) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                warn!("ignoring invalid workdir manifest {path:?}: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    fn save(&self, workdir: &Path) -> Result<()> {
+        // write then rename so an interrupted write does not leave a truncated manifest
+        let path = workdir.join(Self::NAME);
+        let tmp_path = path.with_extension("json.tmp");
+        let file = std::fs::File::create(&tmp_path)
+            .with_context(|| format!("creating manifest {tmp_path:?}"))?;
+        serde_json::to_writer_pretty(file, self)?;
+        std::fs::rename(&tmp_path, &path).with_context(|| format!("renaming {tmp_path:?}"))?;
+        Ok(())
+    }
+
+    /// Granules previously extracted from `input`, if the input is unchanged and all the
+    /// extracted files exist with matching checksums.
+    fn extracted(&self, input: &Path) -> Option<Vec<InputGranule>> {
+        let entry = self.inputs.get(input)?;
+        if std::fs::metadata(input).ok()?.len() != entry.size {
+            debug!("input {input:?} has changed since it was extracted");
+            return None;
+        }
+        let mut granules = Vec::default();
+        for granule in &entry.granules {
+            match sha256_file(&granule.path) {
+                Ok(sha256) if sha256 == granule.sha256 => {}
+                _ => {
+                    debug!("extracted granule {:?} missing or invalid", granule.path);
+                    return None;
+                }
+            }
+            granules.push(InputGranule {
+                short_name: granule.short_name.clone(),
+                granule_id: granule.granule_id.clone(),
+                source: Source::Extracted(granule.path.clone()),
+            });
+        }
+        Some(granules)
+    }
+
+    fn record(&mut self, input: &Path, outputs: &[ExtractedOutput]) -> Result<()> {
+        let mut granules = Vec::default();
+        for output in outputs {
+            granules.push(ManifestGranule {
+                short_name: output.short_name.clone(),
+                granule_id: output.granule_id.clone(),
+                path: output.path.clone(),
+                sha256: sha256_file(&output.path)?,
+            });
+        }
+        let size = std::fs::metadata(input)
+            .with_context(|| format!("reading metadata for {input:?}"))?
+            .len();
+        self.inputs
+            .insert(input.to_path_buf(), ManifestInput { size, granules });
+        Ok(())
+    }
+}
+
+/// Location of the Common RDR data for a granule to be aggregated.
+enum Source {
+    /// Granule data previously extracted to a file in the working directory.
+    Extracted(PathBuf),
+    /// RawApplicationPackets dataset that is copied directly from an input file.
+    Dataset { input: PathBuf, path: String },
+}
+
+impl Source {
+    /// Read the Common RDR bytes, using `files` to cache open input files.
+    fn read(&self, files: &mut HashMap<PathBuf, File>) -> Result<Vec<u8>> {
+        match self {
+            Source::Extracted(path) => {
+                std::fs::read(path).with_context(|| format!("reading {path:?}"))
+            }
+            Source::Dataset { input, path } => {
+                let file = match files.entry(input.clone()) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => entry
+                        .insert(File::open(input).with_context(|| format!("opening {input:?}"))?),
+                };
+                let arr = file
+                    .dataset(path)
+                    .with_context(|| format!("opening dataset {path}"))?
+                    .read_1d::<u8>()
+                    .with_context(|| format!("reading dataset {path}"))?;
+                Ok(arr.into_raw_vec())
+            }
+        }
+    }
+}
+
+/// Short name, granule id, and data source for a single input granule.
+struct InputGranule {
+    short_name: String,
+    granule_id: String,
+    source: Source,
+}
+
+/// How to handle multiple versions of the same granule, i.e., granules with the same
+/// `N_Granule_ID`, such as from reprocessing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VersionPolicy {
+    /// Aggregate all versions
+    #[default]
+    Keep,
+    /// Fail if there are multiple versions of a granule
+    Error,
+    /// Aggregate only the version with the latest `N_Creation_Date`/`N_Creation_Time`
+    Latest,
+}
+
+/// How time gaps between the aggregated granules of a collection are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Contiguity {
+    /// Fail without writing the output
+    Error,
+    /// Log a warning and record the gaps in the report
+    Warn,
+}
+
+struct Item {
+    /// Input file the granule was found in
+    input: PathBuf,
+    source: Source,
     product: ProductSpec,
     meta: GranuleMeta,
 }
 
-fn get_config(satid: &str) -> Result<Config> {
-    get_default(satid)
-        .expect("failed to get default config")
-        .context("lookup failed")
+/// Find the granules in `input`, extracting them to `workdir` if a `manifest` is provided.
+///
+/// Inputs recorded in the manifest as already extracted are not extracted again.
+fn input_granules(
+    input: &Path,
+    workdir: &Path,
+    manifest: Option<&mut WorkdirManifest>,
+    lenient: bool,
+    config: Option<&Config>,
+) -> Result<Vec<InputGranule>> {
+    if let Some(manifest) = manifest {
+        if let Some(granules) = manifest.extracted(input) {
+            info!(
+                "using {} previously extracted granules from {input:?}",
+                granules.len()
+            );
+            return Ok(granules);
+        }
+        let outputs = extract(config, input, workdir, None, None, lenient)?;
+        manifest.record(input, &outputs)?;
+        manifest.save(workdir)?;
+        return Ok(outputs
+            .into_iter()
+            .map(|output| InputGranule {
+                short_name: output.short_name,
+                granule_id: output.granule_id,
+                source: Source::Extracted(output.path),
+            })
+            .collect());
+    }
+    let granules = if lenient {
+        lenient_granule_datasets(input, config, None)?
+    } else {
+        let file = File::open(input).with_context(|| format!("opening {input:?}"))?;
+        granule_datasets(&file, None)?
+    };
+    Ok(granules
+        .into_iter()
+        .map(|granule| InputGranule {
+            short_name: granule.short_name,
+            granule_id: granule.granule_id,
+            source: Source::Dataset {
+                input: input.to_path_buf(),
+                path: granule.path,
+            },
+        })
+        .collect())
+}
+
+/// Granule creation date/time for ordering granule versions.
+///
+/// Creation times are `HHMMSS.ffffffZ` and are compared numerically because the fractional
+/// digits are not always zero padded.
+fn creation_key(meta: &GranuleMeta) -> (String, f64) {
+    let time = meta.creation_time.trim_end_matches('Z');
+    (meta.creation_date.clone(), time.parse().unwrap_or_default())
+}
+
+/// Apply `policy` to granules of `short_name` with the same granule id.
+fn select_versions(
+    short_name: &str,
+    items: Vec<Item>,
+    policy: VersionPolicy,
+    report: &mut AggrReport,
+) -> Result<Vec<Item>> {
+    if policy == VersionPolicy::Keep {
+        return Ok(items);
+    }
+    // Granule id to the index of the selected version
+    let mut selected: HashMap<String, usize> = HashMap::default();
+    for (idx, item) in items.iter().enumerate() {
+        match selected.entry(item.meta.id.clone()) {
+            Entry::Vacant(entry) => {
+                entry.insert(idx);
+            }
+            Entry::Occupied(mut entry) => {
+                if policy == VersionPolicy::Error {
+                    bail!(
+                        "multiple versions of {short_name} granule {} in {:?} and {:?}",
+                        item.meta.id,
+                        items[*entry.get()].input,
+                        item.input
+                    );
+                }
+                if creation_key(&item.meta) > creation_key(&items[*entry.get()].meta) {
+                    entry.insert(idx);
+                }
+            }
+        }
+    }
+
+    let selected_inputs: HashMap<String, PathBuf> = selected
+        .iter()
+        .map(|(id, idx)| (id.clone(), items[*idx].input.clone()))
+        .collect();
+    let mut kept = Vec::default();
+    for (idx, item) in items.into_iter().enumerate() {
+        if selected[&item.meta.id] == idx {
+            kept.push(item);
+            continue;
+        }
+        info!(
+            collection = %short_name,
+            granule_id = %item.meta.id,
+            "{short_name}/{} from {:?} superseded by a later version",
+            item.meta.id,
+            item.input
+        );
+        report.supersede_granule(SupersededGranule {
+            input: item.input,
+            short_name: short_name.to_string(),
+            granule_id: item.meta.id.clone(),
+            creation: format!("{} {}", item.meta.creation_date, item.meta.creation_time),
+            superseded_by: selected_inputs[&item.meta.id].clone(),
+        });
+    }
+    Ok(kept)
+}
+
+/// Gaps between the granules in `items`, all of the same product, with the ids of the
+/// granules missing from each gap.
+fn granule_gaps(config: &Config, items: &[Item]) -> Result<Vec<GranuleGap>> {
+    let Some(product) = items.first().map(|item| &item.product) else {
+        return Ok(Vec::default());
+    };
+    let sat = &config.satellite;
+    let mut granules: Vec<&GranuleMeta> = items.iter().map(|item| &item.meta).collect();
+    granules.sort_by_key(|g| (g.begin_time_iet, g.end_time_iet));
+
+    let mut gaps = Vec::default();
+    // Granule with the latest end time so far
+    let mut prev: Option<&GranuleMeta> = None;
+    for granule in granules {
+        if let Some(prev) = prev {
+            if granule.begin_time_iet > prev.end_time_iet {
+                // First granule boundary at or after the end of the previous granule
+                let end = prev.end_time_iet;
+                let mut start = get_granule_start(end, product.gran_len, sat.base_time);
+                if start < end {
+                    start = start + product.gran_len;
+                }
+                let mut missing = Vec::default();
+                while start < granule.begin_time_iet {
+                    missing.push(sat.granule_id(start)?);
+                    start = start + product.gran_len;
+                }
+                gaps.push(GranuleGap {
+                    short_name: product.short_name.clone(),
+                    prev_granule_id: prev.id.clone(),
+                    next_granule_id: granule.id.clone(),
+                    delta: granule.begin_time_iet - prev.end_time_iet,
+                    missing,
+                });
+            }
+            if granule.end_time_iet <= prev.end_time_iet {
+                continue;
+            }
+        }
+        prev = Some(granule);
+    }
+    Ok(gaps)
+}
+
+fn get_config(platform: &str) -> Result<Config> {
+    get_default_for_platform(platform)?.context("lookup failed")
 }
 
 pub fn create_file(
@@ -30,6 +357,7 @@ pub fn create_file(
     end: &Time,
     product_ids: &[String],
     workdir: &Path,
+    opts: &WriteOptions,
 ) -> Result<(PathBuf, File)> {
     let mut product_ids = Vec::from_iter(product_ids.iter().cloned());
     product_ids.sort();
@@ -46,21 +374,54 @@ pub fn create_file(
     let fpath = workdir.join(&fname);
     let file = File::create(&fpath)?;
 
-    rdr::write_rdr_meta(
-        &file,
-        &config.distributor,
-        &config.satellite.mission,
-        &config.satellite.short_name,
-        &config.distributor,
-        &created,
-    )?;
+    let meta = Meta {
+        created,
+        ..Meta::from_config(config)
+    };
+    rdr::write_rdr_meta(&file, &meta, opts)?;
 
     file.create_group("/All_Data")?;
     file.create_group("/Data_Products")?;
     Ok((fpath, file))
 }
 
-pub fn aggreggate<O: AsRef<Path>>(inputs: &[PathBuf], workdir: O) -> Result<PathBuf> {
+/// Aggregate the granules from `inputs` into a single RDR.
+///
+/// Granule data is copied directly from the input files unless `extract_granules` is set, in
+/// which case granules are first extracted to `workdir`. Extracted granules are recorded in a
+/// manifest in `workdir` so a re-run with the same `workdir` only extracts new inputs. The
+/// output file is created in `workdir` and then copied to `outdir`.
+///
+/// Inputs that cannot be read and granules that cannot be aggregated are skipped and recorded
+/// in the returned [AggrReport]. Multiple versions of the same granule are handled according
+/// to `versions`, with superseded granules also recorded in the report. If `verify` is set the
+/// output is verified before it is copied from `workdir`, failing if verification fails. If
+/// `dry_run` is set the output file and its granules are printed rather than written, and
+/// granules are read from the inputs rather than extracted, so nothing is written. If
+/// `lenient` is set, inputs with missing or incomplete `Data_Products` entries are read using
+/// granule metadata reconstructed from their Common RDRs. See [Meta::from_file_lenient]. If
+/// `manifest` is set a [RunManifest] of the output is written to `outdir`.
+///
+/// The software versions that produced the aggregated granules are counted in the report. If
+/// `restamp` is set, each granule's `N_Software_Version` is set to this software's version and
+/// the original recorded in a granule attribute. See [GranuleMeta::restamp_software].
+///
+/// If `contiguity` is set, the granules of each collection are checked for time gaps, which
+/// are recorded in the report with the ids of the missing granules. With [Contiguity::Error]
+/// aggregation fails before writing the output if there are any gaps.
+pub fn aggreggate<O: AsRef<Path>>(
+    inputs: &[PathBuf],
+    workdir: O,
+    outdir: &Path,
+    extract_granules: bool,
+    versions: VersionPolicy,
+    verify: bool,
+    dry_run: bool,
+    lenient: bool,
+    manifest: bool,
+    restamp: bool,
+    contiguity: Option<Contiguity>,
+) -> Result<AggrReport> {
     assert!(!inputs.is_empty());
 
     let workdir = workdir.as_ref().to_path_buf();
@@ -69,10 +430,19 @@ pub fn aggreggate<O: AsRef<Path>>(inputs: &[PathBuf], workdir: O) -> Result<Path
     let mut granule_count: usize = 0;
     let mut start = Time::now();
     let mut end = Time::from_iet(0);
+    // Time range of all granules, used for file times when there are no science granules
+    let mut have_science = false;
+    let mut all_start = start.iet_micros();
+    let mut all_end = IetMicros(0);
     let mut product_ids: HashSet<String> = HashSet::default();
     let mut config: Option<Config> = None;
+    // Nothing is extracted for a dry run, granules are located in the inputs instead
+    let mut manifest = (extract_granules && !dry_run).then(|| WorkdirManifest::load(&workdir));
+    let mut report = AggrReport::default();
+    // short_name to All_Data group attributes, with the first input's value kept for each
+    let mut all_data_attrs: HashMap<String, BTreeMap<String, String>> = HashMap::default();
 
-    // Extract RDR data to workdir in dirs named for input file names. Collect data necessary to
+    // Locate (or extract to workdir) the RDR data for each input. Collect data necessary to
     // construct aggregated file in next step.
     for input in inputs {
         let name = input.file_name().expect("should have file name");
@@ -80,26 +450,51 @@ pub fn aggreggate<O: AsRef<Path>>(inputs: &[PathBuf], workdir: O) -> Result<Path
         let span = info_span!("rdr_input", ?name);
         let _guard = span.enter();
 
-        // Extract RDR granules
-        let extracted_outputs = match extract(input, &workdir, None, None) {
-            Ok(arr) => arr,
+        // Find RDR granules
+        let granules =
+            match input_granules(input, &workdir, manifest.as_mut(), lenient, config.as_ref()) {
+                Ok(arr) => arr,
+                Err(err) => {
+                    error!(
+                        path = ?input,
+                        "failed to read granules from {input:?}; skipping: {err}"
+                    );
+                    report.input_failed(input, format!("{err:#}"));
+                    continue;
+                }
+            };
+
+        let input_meta = if lenient {
+            Meta::from_file_lenient(input, config.as_ref(), RawDatasetDetail::None)
+        } else {
+            Meta::from_file(input)
+        };
+        let mut input_meta = match input_meta {
+            Ok(meta) => meta,
             Err(err) => {
-                error!("failed to extract granules from {input:?}; skipping: {err}");
+                error!(path = ?input, "failed to read metadata from {input:?}; skipping: {err}");
+                report.input_failed(input, format!("reading metadata: {err}"));
                 continue;
             }
         };
-
-        let mut input_meta = Meta::from_file(input)?;
-        let input_satid = input_meta.platform.to_lowercase().clone();
+        let platform = input_meta.platform.clone();
+        for (short_name, product_meta) in &input_meta.products {
+            let attrs = all_data_attrs.entry(short_name.clone()).or_default();
+            for (name, value) in &product_meta.all_data_attrs {
+                attrs.entry(name.clone()).or_insert_with(|| value.clone());
+            }
+        }
 
         // Get config for the satellite indicated by the input, otherwise bail
         if config.is_none() {
-            config = Some(get_config(&input_satid).with_context(|| {
-                format!("Failed to lookup spacecraft config for {input_satid:?}")
-            })?);
+            config =
+                Some(get_config(&platform).with_context(|| {
+                    format!("Failed to lookup spacecraft config for {platform:?}")
+                })?);
         }
         let config = config.as_ref().expect("we set config above");
         // Make sure input satellites match
+        let input_satid = get_config(&platform).map_or(platform.to_lowercase(), |c| c.satellite.id);
         if config.satellite.id != input_satid {
             bail!(
                 "Cannot aggregate multiple satellites: {} != {}",
@@ -108,17 +503,30 @@ pub fn aggreggate<O: AsRef<Path>>(inputs: &[PathBuf], workdir: O) -> Result<Path
             );
         }
 
-        for output in &extracted_outputs {
+        let mut input_granule_count = 0;
+        for output in granules {
             granule_count += 1;
 
             // lookup product spec for this rdr in config
-            info!("extracted {}/{}", output.short_name, output.granule_id);
+            info!(
+                collection = %output.short_name,
+                granule_id = %output.granule_id,
+                "found {}/{}",
+                output.short_name,
+                output.granule_id
+            );
             let Some(product) = config
                 .products
                 .iter()
                 .find(|p| p.short_name == output.short_name)
             else {
                 warn!("no product for short_name {}; skipping", output.short_name);
+                report.skip_granule(
+                    input,
+                    &output.short_name,
+                    &output.granule_id,
+                    "no product in spacecraft config",
+                );
                 continue;
             };
 
@@ -134,6 +542,12 @@ pub fn aggreggate<O: AsRef<Path>>(inputs: &[PathBuf], workdir: O) -> Result<Path
                     "no granule in metadata matching granule id {}; skipping",
                     output.granule_id
                 );
+                report.skip_granule(
+                    input,
+                    &output.short_name,
+                    &output.granule_id,
+                    "no matching granule in metadata",
+                );
                 continue;
             };
 
@@ -142,62 +556,267 @@ pub fn aggreggate<O: AsRef<Path>>(inputs: &[PathBuf], workdir: O) -> Result<Path
                 .entry(output.short_name.clone())
                 .or_default()
                 .push(Item {
-                    path: output.path.clone(),
+                    input: input.clone(),
+                    source: output.source,
                     meta: meta.clone(),
                     product: product.clone(),
                 });
 
             if meta.collection.contains("SCIENCE") {
-                start = Time::from_iet(std::cmp::min(start.iet(), meta.begin_time_iet));
-                end = Time::from_iet(std::cmp::max(end.iet(), meta.end_time_iet));
+                start = Time::from(std::cmp::min(start.iet_micros(), meta.begin_time_iet));
+                end = Time::from(std::cmp::max(end.iet_micros(), meta.end_time_iet));
+                have_science = true;
             }
+            all_start = std::cmp::min(all_start, meta.begin_time_iet);
+            all_end = std::cmp::max(all_end, meta.end_time_iet);
             product_ids.insert(product.product_id.to_string());
+            input_granule_count += 1;
         }
+        report.input_ok(input, input_granule_count);
     }
     if granule_count == 0 {
-        bail!("No RDRs extracted");
+        bail!("No RDRs found");
     }
 
     info!(
-        "extracted {} extracted granules from {} files",
+        "found {} granules from {} files",
         granule_count,
         inputs.len()
     );
+    if !have_science {
+        start = Time::from(all_start);
+        end = Time::from(all_end);
+    }
+
+    let mut outputs: HashMap<String, Vec<Item>> = outputs
+        .into_iter()
+        .map(|(short_name, items)| {
+            let items = select_versions(&short_name, items, versions, &mut report)?;
+            Ok((short_name, items))
+        })
+        .collect::<Result<_>>()?;
+    for item in outputs.values_mut().flatten() {
+        report.add_software_version(item.meta.source_software_version());
+        if restamp {
+            item.meta.restamp_software();
+        }
+    }
+    if report.software_versions.len() > 1 {
+        let versions: Vec<&String> = report.software_versions.keys().collect();
+        info!("aggregating granules from multiple software versions: {versions:?}");
+    }
+    if let Some(contiguity) = contiguity {
+        let config = config
+            .as_ref()
+            .expect("config should have been determined by inputs");
+        let mut short_names: Vec<&String> = outputs.keys().collect();
+        short_names.sort();
+        for short_name in short_names {
+            for gap in granule_gaps(config, &outputs[short_name])? {
+                warn!(
+                    collection = %short_name,
+                    "gap of {}us between {} and {}; missing granules {:?}",
+                    gap.delta,
+                    gap.prev_granule_id,
+                    gap.next_granule_id,
+                    gap.missing
+                );
+                report.add_gap(gap);
+            }
+        }
+        if contiguity == Contiguity::Error && !report.is_contiguous() {
+            let gaps: Vec<String> = report
+                .gaps
+                .iter()
+                .map(|gap| {
+                    format!(
+                        "{} {}..{} missing {:?}",
+                        gap.short_name, gap.prev_granule_id, gap.next_granule_id, gap.missing
+                    )
+                })
+                .collect();
+            bail!(
+                "aggregated granules are not contiguous; {} gaps: {}",
+                gaps.len(),
+                gaps.join(", ")
+            );
+        }
+    }
+
+    if dry_run {
+        let config = config.expect("config should have been determined by inputs");
+        let mut product_ids = Vec::from_iter(product_ids);
+        product_ids.sort();
+        let fname = rdr::filename(
+            &config.satellite.id,
+            &config.origin,
+            &config.mode,
+            &Time::now(),
+            &start,
+            &end,
+            &product_ids,
+        );
+        let mut short_names: Vec<&String> = outputs.keys().collect();
+        short_names.sort();
+        let mut granules: Vec<&GranuleMeta> = Vec::default();
+        for short_name in short_names {
+            let mut items: Vec<&Item> = outputs[short_name].iter().collect();
+            items.sort_by_key(|item| item.meta.begin_time_iet);
+            granules.extend(items.iter().map(|item| &item.meta));
+        }
+        print_dry_run(Path::new(&fname), granules);
+        return Ok(report);
+    }
 
     // Create new file from previously extracted rdrs
+    let config = config.expect("config should have been determined by inputs");
+    let opts = WriteOptions::default().with_attr_truncation(config.attr_truncation);
     let (fpath, file) = create_file(
-        &config.expect("config should have been determined by inputs"),
+        &config,
         &start,
         &end,
         &Vec::from_iter(product_ids),
         &workdir,
+        &opts,
     )?;
     info!("created {fpath:?}");
 
-    // For each of our extracted RDRs, write it to the file we created
+    // For each of our input RDRs, write it to the file we created
+    let mut input_files: HashMap<PathBuf, File> = HashMap::default();
     for (short_name, granules) in outputs.iter_mut() {
         // granules must be sorted by time
         granules.sort_unstable_by_key(|item| item.meta.begin_time_iet);
         for (gran_idx, item) in granules.iter().enumerate() {
-            let data = std::fs::read(&item.path)?;
+            let data = item.source.read(&mut input_files)?;
             let rdr = Rdr {
                 product_id: item.product.product_id.to_string(),
                 meta: item.meta.clone(),
                 data,
             };
-            write_rdr_granule(&file, gran_idx, &rdr)
+            write_rdr_granule(&file, gran_idx, &rdr, &opts)
+                .inspect_err(|err| {
+                    error!(
+                        collection = %short_name,
+                        granule_id = %item.meta.id,
+                        path = ?fpath,
+                        "failed to write granule: {err}"
+                    )
+                })
                 .with_context(|| format!("writing RDR {short_name} granule {gran_idx}"))?;
         }
+        if let Some(attrs) = all_data_attrs.get(short_name) {
+            write_all_data_attrs(&file, short_name, attrs, config.attr_truncation)
+                .with_context(|| format!("writing {short_name} All_Data attributes"))?;
+        }
     }
     file.close().context("closing h5 file")?;
+    if verify {
+        let num = verify_rdr(&fpath).with_context(|| format!("verifying {fpath:?}"))?;
+        info!("verified {num} granules in {fpath:?}");
+    }
 
     let fname = fpath.file_name().context("getting file name")?;
+    let dest = outdir.join(fname);
     let mut fdest =
-        std::fs::File::create(fname).with_context(|| format!("creating dest {fname:?}"))?;
+        std::fs::File::create(&dest).with_context(|| format!("creating dest {dest:?}"))?;
     let mut fsrc =
         std::fs::File::open(&fpath).with_context(|| format!("opening aggr file {fpath:?}"))?;
     std::io::copy(&mut fsrc, &mut fdest)
-        .with_context(|| format!("copying {fpath:?} to {fname:?}"))?;
+        .with_context(|| format!("copying {fpath:?} to {dest:?}"))?;
+
+    if manifest {
+        let mut run_manifest = RunManifest::new("aggr");
+        let granules = outputs.values().flatten().map(|item| &item.meta);
+        run_manifest
+            .files
+            .push(ManifestFile::new(outdir, &dest, granules)?);
+        let path = run_manifest.write(outdir)?;
+        info!("wrote manifest to {path:?}");
+    }
+
+    report.outputs.push(dest);
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rdr::{config::get_default, fixtures};
+
+    /// Item for the fixture product granule beginning `offset` microseconds after granule
+    /// `num` following the configured base time.
+    fn item(config: &Config, num: u64, offset: u64) -> Item {
+        let product = fixtures::product(config).unwrap().clone();
+        let begin = config.satellite.base_time + product.gran_len * num + offset;
+        let meta = GranuleMeta::new(Time::from(begin), &config.satellite, &product).unwrap();
+        Item {
+            input: PathBuf::from("input.h5"),
+            source: Source::Extracted(PathBuf::from("granule.dat")),
+            product,
+            meta,
+        }
+    }
+
+    fn granule_id(config: &Config, num: u64) -> String {
+        let gran_len = fixtures::product(config).unwrap().gran_len;
+        config
+            .satellite
+            .granule_id(config.satellite.base_time + gran_len * num)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_granule_gaps_contiguous() {
+        let config = get_default("npp").unwrap().unwrap();
+        // out of order to check they are sorted
+        let items = vec![
+            item(&config, 12, 0),
+            item(&config, 10, 0),
+            item(&config, 11, 0),
+        ];
+
+        let gaps = granule_gaps(&config, &items).unwrap();
+
+        assert!(gaps.is_empty(), "{gaps:?}");
+    }
 
-    Ok(fname.into())
+    #[test]
+    fn test_granule_gaps_gap() {
+        let config = get_default("npp").unwrap().unwrap();
+        let gran_len = fixtures::product(&config).unwrap().gran_len;
+        let items = vec![
+            item(&config, 10, 0),
+            item(&config, 11, 0),
+            item(&config, 14, 0),
+        ];
+
+        let gaps = granule_gaps(&config, &items).unwrap();
+
+        assert_eq!(gaps.len(), 1, "{gaps:?}");
+        let gap = &gaps[0];
+        assert_eq!(gap.prev_granule_id, granule_id(&config, 11));
+        assert_eq!(gap.next_granule_id, granule_id(&config, 14));
+        assert_eq!(gap.delta, gran_len.micros() * 2);
+        assert_eq!(
+            gap.missing,
+            vec![granule_id(&config, 12), granule_id(&config, 13)]
+        );
+    }
+
+    #[test]
+    fn test_granule_gaps_overlap() {
+        let config = get_default("npp").unwrap().unwrap();
+        let gran_len = fixtures::product(&config).unwrap().gran_len;
+        // the misaligned granule overlaps both of its neighbors
+        let items = vec![
+            item(&config, 10, 0),
+            item(&config, 10, gran_len.micros() / 2),
+            item(&config, 11, 0),
+        ];
+
+        let gaps = granule_gaps(&config, &items).unwrap();
+
+        assert!(gaps.is_empty(), "{gaps:?}");
+    }
 }
@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use ccsds::spacepacket::{collect_groups, decode_packets, Apid};
+use rdr::{config::Config, open_packet_file, Time};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+#[derive(Debug, Default)]
+struct ApidStats {
+    packets: usize,
+    first: Option<Time>,
+    last: Option<Time>,
+    /// Number of packet groups whose time could not be decoded
+    bad_times: usize,
+}
+
+fn format_time(time: Option<&Time>) -> String {
+    time.map_or_else(|| "-".to_string(), |t| t.format_utc("%Y-%m-%dT%H:%M:%SZ"))
+}
+
+/// Report the APIDs in `input` with packet counts, first/last packet times, and the configured
+/// product, if any, each APID maps to.
+pub fn apids(config: &Config, input: &Path) -> Result<()> {
+    let mut products: HashMap<Apid, (&str, &str)> = HashMap::default();
+    for product in &config.products {
+        for apid in &product.apids {
+            products.insert(apid.num, (&product.product_id, &product.short_name));
+        }
+    }
+
+    let file = open_packet_file(input).with_context(|| format!("opening {input:?}"))?;
+    let packets = decode_packets(file).filter_map(Result::ok);
+    let time_decoder = config.satellite.timecode.decoder();
+
+    let mut stats: BTreeMap<Apid, ApidStats> = BTreeMap::default();
+    for group in collect_groups(packets).filter_map(Result::ok) {
+        let Some(first) = group.packets.first() else {
+            continue;
+        };
+        let stats = stats.entry(first.header.apid).or_default();
+        stats.packets += group.packets.len();
+        match time_decoder.decode(first) {
+            Ok(epoch) => {
+                let time = Time::from_epoch(epoch);
+                if stats.first.as_ref().map_or(true, |t| &time < t) {
+                    stats.first = Some(time.clone());
+                }
+                if stats.last.as_ref().map_or(true, |t| &time > t) {
+                    stats.last = Some(time);
+                }
+            }
+            Err(_) => stats.bad_times += 1,
+        }
+    }
+
+    println!(
+        "{:>5} {:>10} {:>21} {:>21} {:>9} {:>8} product",
+        "apid", "packets", "first", "last", "bad_times", "id"
+    );
+    for (apid, stats) in &stats {
+        let (product_id, short_name) = products.get(apid).copied().unwrap_or(("-", "-"));
+        println!(
+            "{apid:>5} {:>10} {:>21} {:>21} {:>9} {product_id:>8} {short_name}",
+            stats.packets,
+            format_time(stats.first.as_ref()),
+            format_time(stats.last.as_ref()),
+            stats.bad_times,
+        );
+    }
+
+    Ok(())
+}
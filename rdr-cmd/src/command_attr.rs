@@ -0,0 +1,192 @@
+use anyhow::{bail, Context, Result};
+use hdf5::{
+    types::{FixedAscii, FixedUnicode, TypeDescriptor, VarLenAscii, VarLenUnicode},
+    Attribute, File, Location,
+};
+use serde_json::{Map, Value};
+use std::path::Path;
+
+use crate::util::glob_match;
+
+/// Fixed length strings are read using this maximum length.
+const MAX_STR_LEN: usize = 1024;
+
+fn has_glob(s: &str) -> bool {
+    s.contains(['*', '?'])
+}
+
+/// Read all the values of `attr` typed according to its HDF5 datatype; a single value for
+/// attributes with one element, otherwise an array of the values in row-major order.
+fn attr_value(attr: &Attribute) -> Result<Value> {
+    let values: Vec<Value> = match attr.dtype()?.to_descriptor()? {
+        TypeDescriptor::Integer(_) => attr
+            .read_raw::<i64>()?
+            .into_iter()
+            .map(Value::from)
+            .collect(),
+        TypeDescriptor::Unsigned(_) => attr
+            .read_raw::<u64>()?
+            .into_iter()
+            .map(Value::from)
+            .collect(),
+        TypeDescriptor::Float(_) => attr
+            .read_raw::<f64>()?
+            .into_iter()
+            .map(Value::from)
+            .collect(),
+        TypeDescriptor::Boolean => attr
+            .read_raw::<bool>()?
+            .into_iter()
+            .map(Value::from)
+            .collect(),
+        TypeDescriptor::FixedAscii(_) => attr
+            .read_raw::<FixedAscii<MAX_STR_LEN>>()?
+            .iter()
+            .map(|s| Value::from(s.as_str()))
+            .collect(),
+        TypeDescriptor::FixedUnicode(_) => attr
+            .read_raw::<FixedUnicode<MAX_STR_LEN>>()?
+            .iter()
+            .map(|s| Value::from(s.as_str()))
+            .collect(),
+        TypeDescriptor::VarLenAscii => attr
+            .read_raw::<VarLenAscii>()?
+            .iter()
+            .map(|s| Value::from(s.as_str()))
+            .collect(),
+        TypeDescriptor::VarLenUnicode => attr
+            .read_raw::<VarLenUnicode>()?
+            .iter()
+            .map(|s| Value::from(s.as_str()))
+            .collect(),
+        other => bail!("unsupported attribute type {other}"),
+    };
+    Ok(match <[Value; 1]>::try_from(values) {
+        Ok([value]) => value,
+        Err(values) => Value::Array(values),
+    })
+}
+
+/// Open the group or dataset at `path`.
+fn location(file: &File, path: &str) -> Result<Location> {
+    if let Ok(group) = file.group(path) {
+        return Ok((*group).clone());
+    }
+    let dataset = file
+        .dataset(path)
+        .with_context(|| format!("no group or dataset {path}"))?;
+    Ok((**dataset).clone())
+}
+
+/// Paths of the objects matching `pattern`, an absolute object path whose components may
+/// contain `*` and `?` wildcards, e.g., `/Data_Products/*/*_Gran_0`.
+fn match_objects(file: &File, pattern: &str) -> Result<Vec<String>> {
+    let mut paths = vec![String::default()];
+    for component in pattern.split('/').filter(|c| !c.is_empty()) {
+        let mut next = Vec::default();
+        for path in &paths {
+            if !has_glob(component) {
+                next.push(format!("{path}/{component}"));
+                continue;
+            }
+            // Only groups have members
+            let Ok(group) = file.group(if path.is_empty() { "/" } else { path }) else {
+                continue;
+            };
+            let mut names = group.member_names()?;
+            names.sort();
+            next.extend(
+                names
+                    .into_iter()
+                    .filter(|n| glob_match(component, n))
+                    .map(|n| format!("{path}/{n}")),
+            );
+        }
+        paths = next;
+    }
+    Ok(paths
+        .into_iter()
+        .map(|p| if p.is_empty() { "/".to_string() } else { p })
+        .collect())
+}
+
+/// Split `selector` into object path and attribute name patterns.
+///
+/// Selectors are an object path followed by `@` and an attribute name, e.g.,
+/// `/Data_Products/*/*_Gran_0@N_Granule_ID`. Without an `@` the last path component is the
+/// attribute name, e.g., `/Mission_Name`.
+fn parse_selector(selector: &str) -> Result<(&str, &str)> {
+    let (path, name) = match selector.rsplit_once('@') {
+        Some(parts) => parts,
+        None => selector.rsplit_once('/').unwrap_or(("/", selector)),
+    };
+    if name.is_empty() {
+        bail!("selector {selector:?} has no attribute name");
+    }
+    Ok((if path.is_empty() { "/" } else { path }, name))
+}
+
+/// Print the attributes in `input` matching `selector`.
+///
+/// A selector matching a single attribute without wildcards prints its value as JSON, or as
+/// plain text if `raw`, with each value of an array on its own line. Otherwise a JSON object
+/// of `<path>@<name>` to value for each matching attribute is printed.
+pub fn attr_get(input: &Path, selector: &str, raw: bool) -> Result<()> {
+    let (path_pattern, name_pattern) = parse_selector(selector)?;
+    let file = File::open(input).with_context(|| format!("opening {input:?}"))?;
+
+    let mut matches = Map::default();
+    for path in match_objects(&file, path_pattern)? {
+        let Ok(loc) = location(&file, &path) else {
+            continue;
+        };
+        let mut names = loc.attr_names()?;
+        names.sort();
+        for name in names.iter().filter(|n| glob_match(name_pattern, n)) {
+            let value =
+                attr_value(&loc.attr(name)?).with_context(|| format!("reading {path}@{name}"))?;
+            matches.insert(format!("{path}@{name}"), value);
+        }
+    }
+    if matches.is_empty() {
+        bail!("no attributes match {selector:?}");
+    }
+
+    if has_glob(selector) || matches.len() > 1 {
+        println!("{}", serde_json::to_string_pretty(&Value::Object(matches))?);
+        return Ok(());
+    }
+    let (_, value) = matches.into_iter().next().expect("checked not empty");
+    match (raw, value) {
+        (true, Value::String(s)) => println!("{s}"),
+        (true, Value::Array(values)) => {
+            for value in values {
+                match value {
+                    Value::String(s) => println!("{s}"),
+                    other => println!("{other}"),
+                }
+            }
+        }
+        (_, value) => println!("{value}"),
+    }
+    Ok(())
+}
+
+/// Print the name, type, shape, and value of each attribute of the object at `path` in
+/// `input`, one attribute per line, tab separated.
+pub fn attr_ls(input: &Path, path: &str) -> Result<()> {
+    let file = File::open(input).with_context(|| format!("opening {input:?}"))?;
+    let loc = location(&file, path)?;
+    let mut names = loc.attr_names()?;
+    names.sort();
+    for name in names {
+        let attr = loc.attr(&name)?;
+        let dtype = attr.dtype()?.to_descriptor()?;
+        let value = match attr_value(&attr) {
+            Ok(value) => value.to_string(),
+            Err(err) => format!("<{err}>"),
+        };
+        println!("{name}\t{dtype}\t{:?}\t{value}", attr.shape());
+    }
+    Ok(())
+}
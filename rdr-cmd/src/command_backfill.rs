@@ -0,0 +1,244 @@
+use anyhow::{bail, Context, Result};
+use ccsds::spacepacket::{collect_groups, decode_packets};
+use rdr::{config::Config, open_packet_file, Collector, GranuleMeta, Meta, PacketTimeIter, Rdr};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info, warn};
+
+use crate::command_create::print_dry_run;
+use crate::{command_extract::granule_datasets, util::is_rdr};
+
+/// Call `f` with each granule from `input` whose metadata matches `filter`.
+///
+/// `input` may be an RDR, i.e., a file with a `.h5` extension, or a level-0 file, which is
+/// collected into granules using `config`. Granules are streamed; granule data is only read,
+/// or kept, for matching granules.
+pub fn for_each_granule<P, F>(config: &Config, input: &Path, filter: P, f: F) -> Result<()>
+where
+    P: Fn(&GranuleMeta) -> bool,
+    F: FnMut(Rdr) -> Result<()>,
+{
+    if is_rdr(input) {
+        read_rdr_granules(config, input, filter, f)
+    } else {
+        collect_granules(config, input, filter, f)
+    }
+}
+
+/// Call `f` with each granule from the RDR at `path` matching `filter`.
+fn read_rdr_granules<P, F>(config: &Config, path: &Path, filter: P, mut f: F) -> Result<()>
+where
+    P: Fn(&GranuleMeta) -> bool,
+    F: FnMut(Rdr) -> Result<()>,
+{
+    let meta = Meta::from_file(path).with_context(|| format!("reading {path:?}"))?;
+    let mut granules: HashMap<(String, String), GranuleMeta> = meta
+        .granules
+        .into_values()
+        .flatten()
+        .filter(|g| filter(g))
+        .map(|g| ((g.collection.clone(), g.id.clone()), g))
+        .collect();
+
+    let file = hdf5::File::open(path).with_context(|| format!("opening {path:?}"))?;
+    for dataset in granule_datasets(&file, None)? {
+        let Some(granule) = granules.remove(&(dataset.short_name, dataset.granule_id)) else {
+            continue;
+        };
+        let Some(product) = config
+            .products
+            .iter()
+            .find(|p| p.short_name == granule.collection)
+        else {
+            warn!("no product configured for {}; skipping", granule.collection);
+            continue;
+        };
+        let data = file
+            .dataset(&dataset.path)
+            .and_then(|ds| ds.read_raw::<u8>())
+            .with_context(|| format!("reading {}", dataset.path))?;
+        f(Rdr {
+            meta: granule,
+            product_id: product.product_id.clone(),
+            data,
+        })?;
+    }
+    Ok(())
+}
+
+/// Call `f` with each granule collected from the level-0 file at `path` matching `filter`,
+/// as each is completed.
+fn collect_granules<P, F>(config: &Config, path: &Path, filter: P, mut f: F) -> Result<()>
+where
+    P: Fn(&GranuleMeta) -> bool,
+    F: FnMut(Rdr) -> Result<()>,
+{
+    let file = open_packet_file(path).with_context(|| format!("opening {path:?}"))?;
+    let packets = decode_packets(file).filter_map(Result::ok);
+    let groups = collect_groups(packets).filter_map(Result::ok);
+
+    let mut collector = Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+    let mut emit = |rdrs: Vec<Rdr>| -> Result<()> {
+        for rdr in rdrs.into_iter().filter(|r| filter(&r.meta)) {
+            f(rdr)?;
+        }
+        Ok(())
+    };
+    for (pkt, pkt_time) in PacketTimeIter::new(groups).with_timecode(&config.satellite.timecode) {
+        if let Some(completed) = collector.add(&pkt_time, pkt)? {
+            emit(completed)?;
+        }
+        for completed in collector.take_completed() {
+            emit(completed)?;
+        }
+    }
+    for completed in collector.finish()? {
+        emit(completed)?;
+    }
+    Ok(())
+}
+
+/// Insert the granules from `inputs` that are missing from the aggregated RDR `target`, in
+/// place.
+///
+/// Inputs may be RDRs, i.e., files with a `.h5` extension, or level-0 files, which are
+/// collected into granules using `config`. Only granules for collections already in `target`
+/// that overlap its time range are inserted, and a granule is considered missing if `target`
+/// has no granule with the same collection and granule id. Inserted granules are appended,
+/// then the granule datasets are renumbered into time order and the `_Aggr` attributes are
+/// updated. A `target` with an IDPS style name is renamed to reflect its new time range.
+///
+/// Granules are inserted into a copy of `target` that replaces it once complete, so a failure
+/// leaves `target` unchanged.
+///
+/// Returns the path to `target`, which may differ from `target` if it was renamed.
+pub fn backfill(
+    config: &Config,
+    target: &Path,
+    inputs: &[PathBuf],
+    dry_run: bool,
+) -> Result<PathBuf> {
+    let meta = Meta::from_file(target).with_context(|| format!("reading {target:?}"))?;
+    let existing: HashSet<(&str, &str)> = meta
+        .granules
+        .values()
+        .flatten()
+        .map(|g| (g.collection.as_str(), g.id.as_str()))
+        .collect();
+    let all = meta.granules.values().flatten();
+    let (Some(begin), Some(end)) = (
+        all.clone().map(|g| g.begin_time_iet).min(),
+        all.map(|g| g.end_time_iet).max(),
+    ) else {
+        bail!("{target:?} has no granules");
+    };
+
+    let mut missing: Vec<Rdr> = Vec::default();
+    let mut seen: HashSet<(String, String)> = HashSet::default();
+    for input in inputs {
+        let filter = |g: &GranuleMeta| {
+            meta.granules.contains_key(&g.collection)
+                && !existing.contains(&(g.collection.as_str(), g.id.as_str()))
+                && g.end_time_iet > begin
+                && g.begin_time_iet < end
+        };
+        let before = missing.len();
+        for_each_granule(config, input, filter, |rdr| {
+            if seen.insert((rdr.meta.collection.clone(), rdr.meta.id.clone())) {
+                missing.push(rdr);
+            }
+            Ok(())
+        })?;
+        debug!("{} missing granules in {input:?}", missing.len() - before);
+    }
+    missing.sort_by_key(|r| r.meta.begin_time_iet);
+
+    if missing.is_empty() {
+        info!("no missing granules found for {target:?}");
+        return Ok(target.to_path_buf());
+    }
+    if dry_run {
+        print_dry_run(target, missing.iter().map(|r| &r.meta));
+        return Ok(target.to_path_buf());
+    }
+
+    // Work on a copy, in the same directory so it can be renamed into place, so a failure
+    // leaves the target unchanged
+    let parent = target
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let workdir = tempfile::Builder::new()
+        .prefix(".backfill")
+        .tempdir_in(parent)
+        .with_context(|| format!("creating working directory in {parent:?}"))?;
+    let Some(fname) = target.file_name() else {
+        bail!("invalid target {target:?}");
+    };
+    let mut path = workdir.path().join(fname);
+    std::fs::copy(target, &path).with_context(|| format!("copying {target:?}"))?;
+    for rdr in &missing {
+        path = rdr::append_rdr_granule(&path, rdr).with_context(|| {
+            format!(
+                "inserting {} granule {} into {path:?}",
+                rdr.meta.collection, rdr.meta.id
+            )
+        })?;
+        info!(
+            collection = %rdr.meta.collection,
+            granule_id = %rdr.meta.id,
+            "inserted granule into {path:?}"
+        );
+    }
+    let renumbered =
+        rdr::normalize_rdr(&path).with_context(|| format!("renumbering granules in {path:?}"))?;
+
+    let dest_name = path.file_name().unwrap_or(fname).to_os_string();
+    let dest = target.with_file_name(&dest_name);
+    std::fs::rename(&path, &dest).with_context(|| format!("renaming {path:?} to {dest:?}"))?;
+    // Names are compared rather than paths, which may differ for the same file, e.g., X.h5
+    // and ./X.h5
+    if dest_name.as_os_str() != fname {
+        std::fs::remove_file(target).with_context(|| format!("removing {target:?}"))?;
+    }
+    info!(
+        "inserted {} granules into {dest:?}, renumbered {renumbered} granules",
+        missing.len()
+    );
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rdr::{config::get_default, create_rdr, fixtures};
+
+    #[test]
+    fn test_backfill_relative_target() {
+        let config = get_default("npp").unwrap().unwrap();
+        let short_name = fixtures::product(&config).unwrap().short_name.clone();
+        let rdrs: Vec<Rdr> = fixtures::viirs_granules(&config, 0, 3)
+            .unwrap()
+            .into_iter()
+            .map(|mut rdrs| rdrs.remove(0))
+            .collect();
+        let meta = || Meta::from_products(&[short_name.clone()], &config).unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        // target is missing the interior granule, so its name does not change
+        let target = dir.path().join("X.h5");
+        create_rdr(&target, meta(), &[rdrs[0].clone(), rdrs[2].clone()]).unwrap();
+        let input = dir.path().join("input.h5");
+        create_rdr(&input, meta(), &rdrs).unwrap();
+
+        let cwd = std::env::current_dir().unwrap();
+        std::env::set_current_dir(dir.path()).unwrap();
+        let zult = backfill(&config, Path::new("X.h5"), &[input], false);
+        std::env::set_current_dir(cwd).unwrap();
+
+        assert_eq!(zult.unwrap().file_name().unwrap(), "X.h5");
+        let meta = Meta::from_file(&target).unwrap();
+        assert_eq!(meta.granules[&short_name].len(), 3);
+    }
+}
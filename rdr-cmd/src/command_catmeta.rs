@@ -0,0 +1,366 @@
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use rdr::{GranuleMeta, IetMicros, Meta, Time};
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions},
+    io::{stdout, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info, warn};
+
+use crate::util::glob_match;
+
+/// Output format for [cat_meta].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CatFormat {
+    /// Newline delimited JSON, one object per granule
+    Json,
+    /// CSV with a header row, one row per granule
+    Csv,
+}
+
+/// Files are read in parallel in batches of this size, with output flushed after each batch,
+/// so an interrupted run loses at most a batch of work.
+const BATCH_SIZE: usize = 256;
+
+const CSV_HEADER: &str = "path,collection,granule_id,begin,end,begin_time_iet,end_time_iet,\
+    orbit_number,status,version,percent_missing,num_packets";
+
+/// Catalog record for a single granule.
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    path: &'a str,
+    collection: &'a str,
+    granule_id: &'a str,
+    begin: String,
+    end: String,
+    begin_time_iet: IetMicros,
+    end_time_iet: IetMicros,
+    orbit_number: u64,
+    status: &'a str,
+    version: &'a str,
+    percent_missing: f32,
+    num_packets: u64,
+}
+
+impl<'a> Record<'a> {
+    fn new(path: &'a str, gran: &'a GranuleMeta) -> Self {
+        let datetime = |iet: IetMicros| Time::from(iet).format_utc("%Y-%m-%dT%H:%M:%S.%fZ");
+        Record {
+            path,
+            collection: &gran.collection,
+            granule_id: &gran.id,
+            begin: datetime(gran.begin_time_iet),
+            end: datetime(gran.end_time_iet),
+            begin_time_iet: gran.begin_time_iet,
+            end_time_iet: gran.end_time_iet,
+            orbit_number: gran.orbit_number,
+            status: &gran.status,
+            version: &gran.version,
+            percent_missing: gran.percent_missing,
+            num_packets: gran.packet_type_count.iter().map(|c| u64::from(*c)).sum(),
+        }
+    }
+
+    fn write<W: Write>(&self, format: CatFormat, mut writer: W) -> Result<()> {
+        match format {
+            CatFormat::Json => serde_json::to_writer(&mut writer, self)?,
+            CatFormat::Csv => {
+                let fields = [
+                    csv_field(self.path),
+                    csv_field(self.collection),
+                    csv_field(self.granule_id),
+                    csv_field(&self.begin),
+                    csv_field(&self.end),
+                    self.begin_time_iet.to_string(),
+                    self.end_time_iet.to_string(),
+                    self.orbit_number.to_string(),
+                    csv_field(self.status),
+                    csv_field(self.version),
+                    self.percent_missing.to_string(),
+                    self.num_packets.to_string(),
+                ];
+                write!(writer, "{}", fields.join(","))?;
+            }
+        }
+        writeln!(writer)?;
+        Ok(())
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// First field of a CSV line written by [Record::write].
+fn csv_first_field(line: &str) -> Option<String> {
+    let Some(quoted) = line.strip_prefix('"') else {
+        return line.split(',').next().map(String::from);
+    };
+    let mut field = String::default();
+    let mut chars = quoted.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '"' {
+            if chars.peek() == Some(&'"') {
+                chars.next();
+            } else {
+                return Some(field);
+            }
+        }
+        field.push(c);
+    }
+    None
+}
+
+/// Files in `dir` with names matching `pattern`, descending into subdirectories if
+/// `recursive`. Files are sorted by path.
+fn find_files(dir: &Path, recursive: bool, pattern: &str) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::default();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("reading {dir:?}"))? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if recursive {
+                    dirs.push(path);
+                }
+            } else if path
+                .file_name()
+                .is_some_and(|name| glob_match(pattern, &name.to_string_lossy()))
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Path of the file listing the input files completely written to the catalog `output`, one
+/// per line, used to resume.
+fn progress_path(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(".done");
+    PathBuf::from(path)
+}
+
+/// Complete lines of `content`, ignoring any partial trailing line.
+fn complete_lines(content: &[u8]) -> impl Iterator<Item = std::io::Result<String>> + '_ {
+    let complete = content
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map_or(0, |i| i + 1);
+    BufReader::new(&content[..complete]).lines()
+}
+
+/// Input file path of a catalog record `line`, if valid.
+fn record_path(line: &str, format: CatFormat) -> Option<String> {
+    match format {
+        CatFormat::Json => serde_json::from_str::<serde_json::Value>(line)
+            .ok()
+            .and_then(|v| v.get("path")?.as_str().map(String::from)),
+        CatFormat::Csv => csv_first_field(line),
+    }
+}
+
+/// Read the paths of input files completely cataloged in the existing output at `path`, per
+/// its [progress_path] file, and remove any records for other files, e.g., the partial
+/// records of a file being written when a previous run was interrupted.
+fn resume_from(path: &Path, format: CatFormat) -> Result<HashSet<String>> {
+    let progress = progress_path(path);
+    let mut content = Vec::default();
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut content))
+        .with_context(|| format!("reading {path:?}"))?;
+    let mut done = HashSet::default();
+    match std::fs::read(&progress) {
+        Ok(listed) => {
+            for line in complete_lines(&listed) {
+                done.insert(line?);
+            }
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            warn!("no {progress:?}; assuming all files in {path:?} are complete");
+            for (num, line) in complete_lines(&content).enumerate() {
+                let line = line?;
+                if format == CatFormat::Csv && num == 0 {
+                    continue;
+                }
+                done.extend(record_path(&line, format));
+            }
+        }
+        Err(err) => return Err(err).with_context(|| format!("reading {progress:?}")),
+    }
+
+    let mut kept: Vec<u8> = Vec::with_capacity(content.len());
+    let mut dropped = 0;
+    for (num, line) in complete_lines(&content).enumerate() {
+        let line = line?;
+        let header = format == CatFormat::Csv && num == 0;
+        if header || record_path(&line, format).is_some_and(|p| done.contains(&p)) {
+            kept.extend(line.as_bytes());
+            kept.push(b'\n');
+        } else {
+            dropped += 1;
+        }
+    }
+    if kept.len() != content.len() {
+        warn!("removing {dropped} records of incomplete files from {path:?}");
+        let tmp = rdr::in_progress_path(path);
+        std::fs::write(&tmp, &kept).with_context(|| format!("writing {tmp:?}"))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("renaming {tmp:?}"))?;
+    }
+    Ok(done)
+}
+
+/// Write a catalog of the granules in all RDR files in `dir` with file names matching the glob
+/// `pattern`, including files in subdirectories if `recursive`.
+///
+/// Files are read in parallel. Output is written to `output`, or stdout if not provided, with a
+/// record per granule in file path order. Files whose records are completely written to
+/// `output` are listed in a `.done` file next to it. If `resume` and `output` exists, listed
+/// files are skipped, records of unlisted files are removed, and new records are appended, so
+/// an interrupted run can be restarted. Files that cannot be read are skipped with a warning,
+/// as are files with no granules, and both are retried when resuming.
+pub fn cat_meta(
+    dir: &Path,
+    recursive: bool,
+    pattern: &str,
+    format: CatFormat,
+    output: Option<&Path>,
+    resume: bool,
+) -> Result<()> {
+    let mut files = find_files(dir, recursive, pattern)?;
+    info!("found {} files matching {pattern:?}", files.len());
+
+    let mut progress = match output {
+        Some(path) => {
+            let progress = progress_path(path);
+            let file = OpenOptions::new()
+                .create(true)
+                .append(resume)
+                .write(true)
+                .truncate(!resume)
+                .open(&progress)
+                .with_context(|| format!("opening {progress:?}"))?;
+            Some(BufWriter::new(file))
+        }
+        None => None,
+    };
+    let (mut writer, header): (Box<dyn Write>, bool) = match output {
+        Some(path) if resume && path.exists() => {
+            let done = resume_from(path, format)?;
+            files.retain(|f| !done.contains(f.to_string_lossy().as_ref()));
+            info!(
+                "resuming; {} files already cataloged, {} remaining",
+                done.len(),
+                files.len()
+            );
+            let file = OpenOptions::new().append(true).open(path)?;
+            let empty = file.metadata()?.len() == 0;
+            (Box::new(BufWriter::new(file)), empty)
+        }
+        Some(path) => {
+            let file = File::create(path).with_context(|| format!("creating {path:?}"))?;
+            (Box::new(BufWriter::new(file)), true)
+        }
+        None => (Box::new(stdout()), true),
+    };
+    if header && format == CatFormat::Csv {
+        writeln!(writer, "{CSV_HEADER}")?;
+    }
+
+    let mut num_granules = 0;
+    for (batch_num, batch) in files.chunks(BATCH_SIZE).enumerate() {
+        let metas: Vec<_> = batch
+            .par_iter()
+            .map(|path| (path, Meta::from_file(path)))
+            .collect();
+        let mut completed = Vec::default();
+        for (path, meta) in metas {
+            let meta = match meta {
+                Ok(meta) => meta,
+                Err(err) => {
+                    warn!("failed to read {path:?}; skipping: {err}");
+                    continue;
+                }
+            };
+            let path = path.to_string_lossy();
+            let mut collections: Vec<_> = meta.granules.iter().collect();
+            collections.sort_by_key(|(name, _)| *name);
+            for (_, granules) in collections {
+                let mut granules: Vec<&GranuleMeta> = granules.iter().collect();
+                granules.sort_by_key(|g| g.begin_time_iet);
+                for gran in granules {
+                    Record::new(&path, gran).write(format, &mut writer)?;
+                    num_granules += 1;
+                }
+            }
+            if !meta.granules.values().all(Vec::is_empty) {
+                completed.push(path.to_string());
+            }
+        }
+        writer.flush()?;
+        // Only after the records are flushed, so listed files are never partially written
+        if let Some(progress) = progress.as_mut() {
+            for path in completed {
+                writeln!(progress, "{path}")?;
+            }
+            progress.flush()?;
+        }
+        debug!("cataloged batch {}", batch_num + 1);
+    }
+    info!(
+        "cataloged {num_granules} granules from {} files",
+        files.len()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_from_removes_incomplete_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let output = dir.path().join("catalog.csv");
+        std::fs::write(
+            &output,
+            format!("{CSV_HEADER}\na.h5,VIIRS-SCIENCE-RDR,1\nb.h5,VIIRS-SCIENCE-RDR,1\nb.h5,VII"),
+        )
+        .unwrap();
+        // b.h5 was interrupted after its first record was flushed
+        std::fs::write(progress_path(&output), "a.h5\nb.h").unwrap();
+
+        let done = resume_from(&output, CatFormat::Csv).unwrap();
+
+        assert_eq!(done, HashSet::from(["a.h5".to_string()]));
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            format!("{CSV_HEADER}\na.h5,VIIRS-SCIENCE-RDR,1\n")
+        );
+    }
+
+    #[test]
+    fn test_resume_from_without_progress() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let output = dir.path().join("catalog.json");
+        std::fs::write(&output, "{\"path\":\"a.h5\"}\n{\"path\":\"b.h5\"}\n{\"pa").unwrap();
+
+        let done = resume_from(&output, CatFormat::Json).unwrap();
+
+        assert_eq!(done.len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            "{\"path\":\"a.h5\"}\n{\"path\":\"b.h5\"}\n"
+        );
+    }
+}
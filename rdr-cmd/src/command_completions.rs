@@ -0,0 +1,21 @@
+use anyhow::{Context, Result};
+use clap::Command;
+use clap_complete::Shell;
+use std::{io::Write, path::Path};
+use tracing::info;
+
+/// Write the completion script for `shell` to `out`.
+pub fn completions<W: Write>(cmd: &mut Command, shell: Shell, mut out: W) -> Result<()> {
+    let name = cmd.get_name().to_string();
+    clap_complete::generate(shell, cmd, name, &mut out);
+    Ok(())
+}
+
+/// Generate man pages for the command and each of its subcommands in `outdir`.
+pub fn manpages(cmd: Command, outdir: &Path) -> Result<()> {
+    std::fs::create_dir_all(outdir).with_context(|| format!("creating directory {outdir:?}"))?;
+    clap_mangen::generate_to(cmd, outdir)
+        .with_context(|| format!("generating man pages in {outdir:?}"))?;
+    info!("wrote man pages to {outdir:?}");
+    Ok(())
+}
@@ -2,67 +2,374 @@ use anyhow::{bail, Context, Result};
 use ccsds::spacepacket::{collect_groups, decode_packets, PacketGroup};
 use crossbeam::channel;
 use rdr::{
-    config::{get_default, Config},
-    jpss_merge, Collector, Meta, PacketTimeIter, Rdr, Time,
+    config::{get_default, get_default_content, Config, TimecodeSpec},
+    jpss_merge_from, BaseTimePolicy, Collector, CreateSummary, DuplicatePolicy, GranuleMeta,
+    IetMicros, Meta, OrphanPolicy, PacketTimeIter, RateLimitedWarnings, Rdr, RdrError, RdrWriter,
+    StorageOrder, Time, TimeBounds,
 };
 use std::{
     collections::{HashMap, HashSet},
-    fs::{create_dir, File},
-    io::{BufReader, BufWriter},
+    fs::{create_dir, read_to_string, File},
+    io::BufWriter,
     path::{Path, PathBuf},
     thread,
+    time::{Duration, Instant},
 };
 use tempfile::TempDir;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, info_span, warn};
 
-fn get_config(satellite: Option<String>, fpath: Option<PathBuf>) -> Result<Option<Config>> {
-    match (satellite, fpath) {
-        (Some(satid), None) | (Some(satid), Some(_)) => {
-            get_default(&satid).context("getting default config")
+use crate::{
+    manifest::{ManifestFile, RunManifest},
+    staging::{is_s3, open_input},
+};
+
+pub fn get_config(
+    satellite: Option<String>,
+    fpath: Option<PathBuf>,
+    overlay: Option<PathBuf>,
+) -> Result<Option<Config>> {
+    let Some(overlay) = overlay else {
+        return match (satellite, fpath) {
+            (Some(satid), None) | (Some(satid), Some(_)) => {
+                get_default(&satid).context("getting default config")
+            }
+            (None, Some(fpath)) => Ok(Some(Config::with_path(&fpath).context("Invalid config")?)),
+            (None, None) => bail!("One of satellite or path is required to get config"),
+        };
+    };
+    let base = match (satellite, fpath) {
+        (Some(satid), _) => match get_default_content(&satid) {
+            Some(content) => content.to_string(),
+            None => return Ok(None),
+        },
+        (None, Some(fpath)) => {
+            read_to_string(&fpath).with_context(|| format!("reading config {fpath:?}"))?
         }
-        (None, Some(fpath)) => Ok(Some(Config::with_path(&fpath).context("Invalid config")?)),
         (None, None) => bail!("One of satellite or path is required to get config"),
-    }
+    };
+    let overlay =
+        read_to_string(&overlay).with_context(|| format!("reading config override {overlay:?}"))?;
+    Ok(Some(
+        Config::with_overrides(&base, &overlay).context("Invalid config override")?,
+    ))
 }
 
-pub fn rdr_filename_meta(rdrs: &[Rdr]) -> (Time, Time, Vec<String>) {
+/// File start, end, and sorted product ids used to name a file containing `rdrs`, where a
+/// primary RDR is first.
+///
+/// File times are determined by the granules of product `time_from`, if provided, otherwise by
+/// the `SCIENCE` granules. If there are no such granules the primary RDR is used.
+pub fn rdr_filename_meta(rdrs: &[Rdr], time_from: Option<&str>) -> (Time, Time, Vec<String>) {
     assert!(!rdrs.is_empty());
-    let mut start = Time::now().iet();
-    let mut end = 0;
+    let mut start = Time::now().iet_micros();
+    let mut end = IetMicros(0);
     let mut product_ids: HashSet<String> = HashSet::default();
+    let mut have_science = false;
     for rdr in rdrs {
-        // Only science types determine file time. There should only be one science type but we
-        // leave that to the caller and just compute times based on all science types.
-        if rdr.meta.collection.contains("SCIENCE") {
+        // Only science types determine file time by default. There should only be one science
+        // type but we leave that to the caller and just compute times based on all science types.
+        let determines_time = match time_from {
+            Some(product_id) => rdr.product_id == product_id,
+            None => rdr.meta.collection.contains("SCIENCE"),
+        };
+        if determines_time {
             start = std::cmp::min(start, rdr.meta.begin_time_iet);
             end = std::cmp::max(end, rdr.meta.end_time_iet);
+            have_science = true;
         }
         product_ids.insert(rdr.product_id.to_string());
     }
+    // Otherwise, e.g., housekeeping, use the primary RDRs, where the first is a primary.
+    if !have_science {
+        let primary = &rdrs[0].product_id;
+        start = rdrs[0].meta.begin_time_iet;
+        end = rdrs[0].meta.end_time_iet;
+        for rdr in rdrs.iter().filter(|r| &r.product_id == primary) {
+            start = std::cmp::min(start, rdr.meta.begin_time_iet);
+            end = std::cmp::max(end, rdr.meta.end_time_iet);
+        }
+    }
     let mut product_ids = Vec::from_iter(product_ids);
     product_ids.sort();
 
-    (Time::from_iet(start), Time::from_iet(end), product_ids)
+    (Time::from(start), Time::from(end), product_ids)
+}
+
+/// Handling of granules with fewer packets than their product's configured `min_packets`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UndersizedGranules {
+    /// Write to the `quarantine` directory in the output directory
+    #[default]
+    Quarantine,
+    /// Do not write, only count in the summary
+    Skip,
+}
+
+/// Options controlling packet collection for [create_rdr].
+#[derive(Debug, Clone, Default)]
+pub struct CreateOptions {
+    /// Spill granule packet data to files in this directory rather than keeping it in memory
+    pub spill_dir: Option<PathBuf>,
+    pub time_bounds: TimeBounds,
+    pub duplicates: DuplicatePolicy,
+    pub undersized: UndersizedGranules,
+    /// Re-open and verify each file after it is written, removing files that fail
+    pub verify: bool,
+    /// Do not create any directories; use with [DryRunWriter]
+    pub dry_run: bool,
+    /// See [Collector::with_packed_retention]
+    pub packed_retention: Option<Duration>,
+    pub storage_order: StorageOrder,
+    pub orphans: OrphanPolicy,
+    /// Output directory layout, overriding the config `output_layout`. See
+    /// [rdr::output_subdir].
+    pub output_layout: Option<String>,
+    /// `N_Granule_Version` for all granules, rather than the default A1
+    pub granule_version: Option<String>,
+    /// Bump the version of granules that already exist in the output directory, rather than
+    /// writing another granule with the same version. See [rdr::next_granule_version].
+    pub bump_versions: bool,
+    /// Process each input independently, without merging, writing the outputs for each input
+    /// to its own subdirectory of the output directory. See [batch_output_dir].
+    pub batch: bool,
+    /// Write a [RunManifest] of the files written to the output directory. Ignored for dry runs.
+    /// A manifest of the files written before an abort is always written.
+    pub manifest: bool,
+    /// When to start a new output file. See [Rotation].
+    pub rotation: Rotation,
+    /// Abort if more than this many packet groups have undecodable times. See
+    /// [PacketTimeIter::with_max_time_errors].
+    pub max_time_errors: Option<usize>,
+    /// Expected start and end of the input data, e.g., a pass. Inputs with no packets in the
+    /// window are rejected before processing. See [check_inputs].
+    pub pass_window: Option<(Time, Time)>,
+    /// Handling of packets with times before the spacecraft base time. See [BaseTimePolicy].
+    pub base_time_policy: BaseTimePolicy,
+}
+
+/// Rules for starting a new output file, so a file can contain the granules of multiple
+/// primary granules rather than one file per primary granule. Each collection is rotated
+/// independently and files are written when complete, i.e., when a rule starts a new file or
+/// collection finishes.
+///
+/// The default starts a new file for every primary granule.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rotation {
+    /// Start a new file after this many primary granules
+    pub granules: Option<usize>,
+    /// Start a new file when a primary granule begins in a different UTC time window of this
+    /// length, e.g., windows of 1 hour start on the hour.
+    pub window: Option<Duration>,
+}
+
+impl Rotation {
+    fn is_enabled(&self) -> bool {
+        self.granules.is_some_and(|n| n > 1) || self.window.is_some()
+    }
+}
+
+/// Granules for a file that is not yet complete. See [Rotator].
+struct PendingFile {
+    /// Window of the first primary granule, if rotating on time windows
+    window: Option<u64>,
+    num_primary: usize,
+    rdrs: Vec<Rdr>,
 }
 
-pub fn create_rdr<P>(config: &Config, packet_groups: P, dest: &Path) -> Result<()>
+/// Groups the RDRs for each collected primary granule into files according to a [Rotation].
+struct Rotator {
+    rotation: Rotation,
+    /// Primary product id to its pending file
+    pending: HashMap<String, PendingFile>,
+}
+
+impl Rotator {
+    fn new(rotation: Rotation) -> Self {
+        Self {
+            rotation,
+            pending: HashMap::default(),
+        }
+    }
+
+    /// UTC window containing the begin time of the primary granule in `rdrs`.
+    fn window(&self, rdrs: &[Rdr]) -> Option<u64> {
+        let window = self.rotation.window?;
+        let micros = u64::try_from(window.as_micros()).unwrap_or(u64::MAX).max(1);
+        Some(Time::from(rdrs[0].meta.begin_time_iet).utc() / micros)
+    }
+
+    /// Add the RDRs for a primary granule, where the primary RDR is first, returning the RDRs
+    /// for any files that are complete.
+    fn push(&mut self, rdrs: Vec<Rdr>) -> Vec<Vec<Rdr>> {
+        if !self.rotation.is_enabled() {
+            return vec![rdrs];
+        }
+        let mut complete = Vec::default();
+        let window = self.window(&rdrs);
+        let key = rdrs[0].product_id.clone();
+        if self.pending.get(&key).is_some_and(|p| p.window != window) {
+            complete.extend(self.pending.remove(&key).map(|p| p.rdrs));
+        }
+        let pending = self.pending.entry(key.clone()).or_insert(PendingFile {
+            window,
+            num_primary: 0,
+            rdrs: Vec::default(),
+        });
+        pending.num_primary += 1;
+        for rdr in rdrs {
+            // Packed granules overlapping consecutive primary granules are only written once
+            if !pending
+                .rdrs
+                .iter()
+                .any(|r| r.meta.collection == rdr.meta.collection && r.meta.id == rdr.meta.id)
+            {
+                pending.rdrs.push(rdr);
+            }
+        }
+        if self
+            .rotation
+            .granules
+            .is_some_and(|n| pending.num_primary >= n)
+        {
+            complete.extend(self.pending.remove(&key).map(|p| p.rdrs));
+        }
+        complete
+    }
+
+    /// RDRs for all files not yet complete, in time order.
+    fn finish(self) -> Vec<Vec<Rdr>> {
+        let mut files: Vec<Vec<Rdr>> = self.pending.into_values().map(|p| p.rdrs).collect();
+        files.sort_by_key(|rdrs| rdrs[0].meta.begin_time_iet);
+        files
+    }
+}
+
+/// Set the version of each granule in `rdrs` to `version`, if provided, or to the version
+/// following the latest version of the same granule in `existing`, if any. Versions set are
+/// recorded in `existing` so later re-generations in the same run are also bumped.
+fn set_versions(
+    rdrs: &mut [Rdr],
+    version: Option<&str>,
+    mut existing: Option<&mut HashMap<(String, String), String>>,
+) -> rdr::Result<()> {
+    for rdr in rdrs {
+        if let Some(version) = version {
+            rdr.meta.set_version(version)?;
+        }
+        let Some(existing) = existing.as_deref_mut() else {
+            continue;
+        };
+        let key = (rdr.meta.collection.clone(), rdr.meta.id.clone());
+        if let Some(latest) = existing.get(&key) {
+            // Never go backwards, e.g., when the configured version is later than existing
+            if *latest >= rdr.meta.version {
+                rdr.meta.set_version(&rdr::next_granule_version(latest)?)?;
+                debug!(
+                    collection = %rdr.meta.collection,
+                    granule_id = %rdr.meta.id,
+                    "re-generated granule version {latest} bumped to {}",
+                    rdr.meta.version
+                );
+            }
+        }
+        existing.insert(key, rdr.meta.version.clone());
+    }
+    Ok(())
+}
+
+/// Print the file `fpath` and its `granules` as they would be written.
+pub fn print_dry_run<'a>(fpath: &Path, granules: impl IntoIterator<Item = &'a GranuleMeta>) {
+    println!("{}", fpath.display());
+    for granule in granules {
+        println!(
+            "  {} {} {} {} packets={}",
+            granule.collection,
+            granule.id,
+            granule.begin.format_utc("%Y-%m-%dT%H:%M:%S.%fZ"),
+            granule.end.format_utc("%Y-%m-%dT%H:%M:%S.%fZ"),
+            granule.num_packets()
+        );
+    }
+}
+
+/// [RdrWriter] that only prints the files and granules that would be written.
+pub struct DryRunWriter;
+
+impl RdrWriter for DryRunWriter {
+    fn write(&self, dest: &Path, _meta: Meta, rdrs: &[Rdr]) -> rdr::Result<PathBuf> {
+        print_dry_run(dest, rdrs.iter().map(|r| &r.meta));
+        Ok(dest.to_path_buf())
+    }
+}
+
+/// Returns true if the primary granule in `rdrs` has fewer packets than its product requires.
+fn is_undersized(config: &Config, rdrs: &[Rdr]) -> bool {
+    let primary = &rdrs[0];
+    config
+        .products
+        .iter()
+        .find(|p| p.product_id == primary.product_id)
+        .is_some_and(|p| primary.meta.num_packets() < p.min_packets)
+}
+
+pub fn create_rdr<P>(
+    config: &Config,
+    packet_groups: P,
+    dest: &Path,
+    opts: &CreateOptions,
+    writer: &dyn RdrWriter,
+) -> Result<CreateSummary>
 where
     P: Iterator<Item = PacketGroup> + Send,
 {
-    let mut collector = Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+    let started = Instant::now();
+    let mut collector = Collector::new(config.satellite.clone(), &config.rdrs, &config.products)
+        .with_time_bounds(opts.time_bounds.clone())
+        .with_duplicate_policy(opts.duplicates)
+        .with_storage_order(opts.storage_order)
+        .with_orphan_policy(opts.orphans)
+        .with_base_time_policy(opts.base_time_policy);
+    if let Some(retention) = opts.packed_retention {
+        collector = collector.with_packed_retention(retention);
+    }
+    if let Some(dir) = &opts.spill_dir {
+        if !dir.exists() {
+            create_dir(dir).with_context(|| format!("creating spill dir {dir:?}"))?;
+        }
+        collector = collector.with_spill_dir(dir);
+    }
 
-    if !dest.exists() {
+    if !opts.dry_run && !dest.exists() {
         create_dir(dest)?;
     }
 
     let (tx, rx) = channel::unbounded();
-    thread::scope(|s| {
-        s.spawn(move || {
-            for (pkt, pkt_time) in PacketTimeIter::new(packet_groups) {
+    let (mut summary, manifest, aborted) = thread::scope(|s| {
+        let collect_handle = s.spawn(move || {
+            // number of packets processed and dropped
+            let mut packets: usize = 0;
+            let mut dropped: usize = 0;
+            let mut warnings = RateLimitedWarnings::default();
+            let mut packet_times =
+                PacketTimeIter::new(packet_groups).with_timecode(&config.satellite.timecode);
+            if let Some(max) = opts.max_time_errors {
+                packet_times = packet_times.with_max_time_errors(max);
+            }
+            let mut failed = None;
+            for (pkt, pkt_time) in packet_times.by_ref() {
+                packets += 1;
                 let complete = match collector.add(&pkt_time, pkt) {
                     Ok(o) => o,
+                    Err(e @ rdr::Error::RdrError(RdrError::InvalidGranuleStart(_)))
+                        if opts.base_time_policy == BaseTimePolicy::Fail =>
+                    {
+                        dropped += 1;
+                        failed = Some(e);
+                        break;
+                    }
                     Err(e) => {
-                        warn!("failed to add packet: {e}");
+                        warnings.warn("failed to add packet", e);
+                        dropped += 1;
                         continue;
                     }
                 };
@@ -74,22 +381,97 @@ where
                     debug!("collected RDR {:?} {:?}", &rdrs[0].meta.begin, counts);
                     let _ = tx.send(rdrs);
                 }
+                for rdrs in collector.take_completed() {
+                    debug!("collected RDR {:?}", &rdrs[0].meta.begin);
+                    let _ = tx.send(rdrs);
+                }
+                for rdrs in collector.take_orphans() {
+                    debug!("collected orphaned RDR {:?}", &rdrs[0].meta.begin);
+                    let _ = tx.send(rdrs);
+                }
             }
-            for rdrs in collector.finish().expect("finishing collection") {
-                let mut counts: HashMap<String, usize> = HashMap::default();
-                for r in &rdrs {
-                    *counts.entry(r.meta.collection.to_string()).or_default() += 1;
+            let quarantined = collector.num_quarantined();
+            let duplicates = collector.num_duplicates();
+            let pruned = collector.num_packed_pruned();
+            let before_base_time = collector.num_before_base_time();
+            if before_base_time > 0 && opts.base_time_policy != BaseTimePolicy::Fail {
+                let action = match opts.base_time_policy {
+                    BaseTimePolicy::Clamp => "clamped to",
+                    _ => "skipped as",
+                };
+                warn!(
+                    "{before_base_time} packets {action} before the spacecraft base time {}",
+                    Time::from(config.satellite.base_time).format_utc("%Y-%m-%dT%H:%M:%SZ")
+                );
+            }
+            // Granules still being collected are not written when aborting
+            let aborted = failed.or_else(|| packet_times.check().err());
+            if aborted.is_none() {
+                for rdrs in collector.finish().expect("finishing collection") {
+                    let mut counts: HashMap<String, usize> = HashMap::default();
+                    for r in &rdrs {
+                        *counts.entry(r.meta.collection.to_string()).or_default() += 1;
+                    }
+                    debug!("collected RDR {:?} {:?}", &rdrs[0].meta.begin, counts);
+                    let _ = tx.send(rdrs);
                 }
-                debug!("collected RDR {:?} {:?}", &rdrs[0].meta.begin, counts);
-                let _ = tx.send(rdrs);
             }
+            let times = packet_times.stats().clone();
+            (
+                packets,
+                dropped,
+                quarantined,
+                duplicates,
+                pruned,
+                before_base_time,
+                times,
+                aborted,
+            )
         });
 
-        s.spawn(move || {
+        let write_handle = s.spawn(move || {
+            let mut summary = CreateSummary::default();
+            // Always collected so files written before an abort can be identified
+            let mut manifest = (!opts.dry_run).then(|| RunManifest::new("create"));
             let created = Time::now();
-            for rdrs in rx {
-                let (start, end, pids) = rdr_filename_meta(&rdrs);
-                let fpath = dest.join(rdr::filename(
+            let layout = opts
+                .output_layout
+                .as_deref()
+                .or(config.output_layout.as_deref());
+            // Output directory to the latest existing version of each granule in it
+            let mut existing_versions: HashMap<PathBuf, HashMap<(String, String), String>> =
+                HashMap::default();
+            let mut write_file = |mut outdir: PathBuf, mut rdrs: Vec<Rdr>| {
+                let (collection, granule_id) =
+                    (rdrs[0].meta.collection.clone(), rdrs[0].meta.id.clone());
+                let (start, end, pids) =
+                    rdr_filename_meta(&rdrs, config.time_from(&rdrs[0].product_id));
+                if let Some(layout) = layout {
+                    match rdr::output_subdir(
+                        layout,
+                        &config.satellite.id,
+                        &collection,
+                        &rdrs[0].product_id,
+                        &start,
+                    ) {
+                        Ok(subdir) => outdir = outdir.join(subdir),
+                        Err(err) => {
+                            error!(
+                                %collection,
+                                %granule_id,
+                                "invalid output layout {layout:?}: {err}"
+                            );
+                            return;
+                        }
+                    }
+                    if !opts.dry_run {
+                        if let Err(err) = std::fs::create_dir_all(&outdir) {
+                            error!("failed to create output dir {outdir:?}: {err}");
+                            return;
+                        }
+                    }
+                }
+                let fpath = outdir.join(rdr::filename(
                     &config.satellite.id,
                     &config.origin,
                     &config.mode,
@@ -98,69 +480,465 @@ where
                     &end,
                     &pids,
                 ));
-                let short_names: Vec<String> =
+                let mut short_names: Vec<String> =
                     rdrs.iter().map(|r| r.meta.collection.to_string()).collect();
+                // Include all configured packed products, even if there are no granules for
+                // them, so the writer can decide how to handle empty collections.
+                for spec in config
+                    .rdrs
+                    .iter()
+                    .filter(|r| r.product == rdrs[0].product_id)
+                {
+                    for packed_id in &spec.packed_with {
+                        let Some(product) =
+                            config.products.iter().find(|p| &p.product_id == packed_id)
+                        else {
+                            warn!(
+                                product = %spec.product,
+                                "packed product {packed_id} not configured; skipping it"
+                            );
+                            continue;
+                        };
+                        if !short_names.contains(&product.short_name) {
+                            short_names.push(product.short_name.clone());
+                        }
+                    }
+                }
+                let existing = if opts.bump_versions {
+                    if !existing_versions.contains_key(&outdir) {
+                        let versions = if outdir.exists() {
+                            match rdr::granule_versions(&outdir) {
+                                Ok(versions) => versions,
+                                Err(err) => {
+                                    error!("failed to read granule versions in {outdir:?}: {err}");
+                                    return;
+                                }
+                            }
+                        } else {
+                            HashMap::default()
+                        };
+                        existing_versions.insert(outdir.clone(), versions);
+                    }
+                    existing_versions.get_mut(&outdir)
+                } else {
+                    None
+                };
+                if let Err(err) = set_versions(&mut rdrs, opts.granule_version.as_deref(), existing)
+                {
+                    error!(%collection, %granule_id, "failed to set granule version: {err}");
+                    return;
+                }
                 let Some(meta) = Meta::from_products(&short_names, config) else {
                     warn!(
                         "RDR generated with one or more unknown product ids: {:?}",
                         short_names
                     );
-                    continue;
+                    return;
                 };
-                match rdr::create_rdr(&fpath, meta, &rdrs) {
-                    Ok(_) => info!("wrote {} to {fpath:?}", &rdrs[0]),
-                    Err(err) => error!("failed to write {fpath:?}: {err}"),
+                match writer.write(&fpath, meta, &rdrs) {
+                    Ok(fpath) => {
+                        if opts.verify {
+                            if let Err(err) = rdr::verify_rdr(&fpath) {
+                                error!(
+                                    %collection,
+                                    %granule_id,
+                                    path = ?fpath,
+                                    "verification failed for {fpath:?}; removing: {err}"
+                                );
+                                summary.verify_failures += 1;
+                                if let Err(err) = std::fs::remove_file(&fpath) {
+                                    error!("failed to remove {fpath:?}: {err}");
+                                }
+                                return;
+                            }
+                        }
+                        info!(
+                            %collection,
+                            %granule_id,
+                            path = ?fpath,
+                            "wrote {} to {fpath:?}",
+                            &rdrs[0]
+                        );
+                        summary.add_file(&rdrs);
+                        if let Some(manifest) = manifest.as_mut() {
+                            match ManifestFile::new(dest, &fpath, rdrs.iter().map(|r| &r.meta)) {
+                                Ok(file) => manifest.files.push(file),
+                                Err(err) => error!("failed to add {fpath:?} to manifest: {err:#}"),
+                            }
+                        }
+                    }
+                    Err(err) => error!(
+                        %collection,
+                        %granule_id,
+                        path = ?fpath,
+                        "failed to write {fpath:?}: {err}"
+                    ),
+                }
+            };
+            let mut rotator = Rotator::new(opts.rotation);
+            let mut undersized = 0;
+            for rdrs in rx {
+                let (collection, granule_id) = (&rdrs[0].meta.collection, &rdrs[0].meta.id);
+                if !is_undersized(config, &rdrs) {
+                    for rdrs in rotator.push(rdrs) {
+                        write_file(dest.to_path_buf(), rdrs);
+                    }
+                    continue;
+                }
+                undersized += 1;
+                let num_packets = rdrs[0].meta.num_packets();
+                match opts.undersized {
+                    UndersizedGranules::Skip => {
+                        warn!(
+                            %collection,
+                            %granule_id,
+                            "skipping granule with too few packets ({num_packets})"
+                        );
+                    }
+                    UndersizedGranules::Quarantine => {
+                        // Quarantined granules are never rotated into files with other granules
+                        let outdir = dest.join("quarantine");
+                        if !opts.dry_run {
+                            if let Err(err) = std::fs::create_dir_all(&outdir) {
+                                error!("failed to create quarantine dir {outdir:?}: {err}");
+                                continue;
+                            }
+                        }
+                        warn!(
+                            %collection,
+                            %granule_id,
+                            "quarantining granule with too few packets ({num_packets})"
+                        );
+                        write_file(outdir, rdrs);
+                    }
                 }
             }
+            for rdrs in rotator.finish() {
+                write_file(dest.to_path_buf(), rdrs);
+            }
+            summary.undersized_granules = undersized;
+            (summary, manifest)
         });
+
+        let (packets, dropped, quarantined, duplicates, pruned, before_base_time, times, aborted) =
+            collect_handle.join().expect("collector thread panicked");
+        let (mut summary, manifest) = write_handle.join().expect("writer thread panicked");
+        summary.packets = packets;
+        summary.packet_times = times;
+        summary.dropped_packets = dropped;
+        summary.quarantined_packets = quarantined;
+        summary.duplicate_packets = duplicates;
+        summary.pruned_packed_granules = pruned;
+        summary.before_base_time_packets = before_base_time;
+        (summary, manifest, aborted)
     });
+    summary.elapsed = started.elapsed();
+    if let Some(err) = aborted {
+        for line in summary.to_string().lines() {
+            info!("summary: {line}");
+        }
+        if let Some(mut manifest) = manifest.filter(|m| !m.files.is_empty()) {
+            manifest.aborted = Some(err.to_string());
+            let path = manifest.write(dest)?;
+            warn!(
+                "wrote manifest of {} files written before aborting to {path:?}",
+                manifest.files.len()
+            );
+        }
+        return Err(err).context("aborting create");
+    }
 
-    Ok(())
+    if let Some(manifest) = manifest.filter(|_| opts.manifest) {
+        let path = manifest.write(dest)?;
+        info!(
+            "wrote manifest of {} files to {path:?}",
+            manifest.files.len()
+        );
+    }
+
+    Ok(summary)
 }
 
-pub fn merge<P: AsRef<Path>>(paths: &[P], dest: P) -> Result<()> {
-    let paths: Vec<PathBuf> = paths.iter().map(|p| p.as_ref().to_path_buf()).collect();
+/// Merge `paths` into `dest`. Inputs are read with [open_input], so compressed and S3 inputs
+/// are merged without first being copied locally.
+pub fn merge<P: AsRef<Path>>(paths: &[P], dest: P, timecode: &TimecodeSpec) -> Result<()> {
     let dest = dest.as_ref();
     let writer = BufWriter::new(
         File::create(dest).with_context(|| format!("creating merge dest file: {dest:?}"))?,
     );
-    Ok(jpss_merge(&paths, writer)?)
+    let open = |path: &P| open_input(path.as_ref()).map_err(std::io::Error::other);
+    Ok(jpss_merge_from(paths, open, timecode, writer)?)
+}
+
+/// Inputs that begin more than this many microseconds after all preceding inputs end are
+/// reported as likely mistakes, e.g., a file from a different day.
+const DISJOINT_INPUT_GAP: u64 = 86_400_000_000;
+
+/// Packet time range of an input file.
+#[derive(Debug, Clone)]
+struct InputSpan {
+    path: PathBuf,
+    first: Time,
+    last: Time,
+    packets: usize,
+}
+
+impl InputSpan {
+    /// Read the packet times from the input at `path`, or `None` if it has no packets with
+    /// valid times.
+    fn read(path: &Path, timecode: &TimecodeSpec) -> Result<Option<Self>> {
+        let reader = open_input(path).with_context(|| format!("opening {path:?}"))?;
+        let packets = decode_packets(reader).filter_map(Result::ok);
+        let groups = collect_groups(packets).filter_map(Result::ok);
+        let mut span: Option<Self> = None;
+        for (_, time) in PacketTimeIter::new(groups).with_timecode(timecode) {
+            match &mut span {
+                Some(span) => {
+                    if time < span.first {
+                        span.first = time.clone();
+                    }
+                    if time > span.last {
+                        span.last = time;
+                    }
+                    span.packets += 1;
+                }
+                None => {
+                    span = Some(Self {
+                        path: path.to_path_buf(),
+                        first: time.clone(),
+                        last: time,
+                        packets: 1,
+                    })
+                }
+            }
+        }
+        Ok(span)
+    }
+
+    fn is_duplicate(&self, other: &Self) -> bool {
+        self.first == other.first && self.last == other.last && self.packets == other.packets
+    }
+}
+
+/// Check the packet time ranges of `inputs` before they are merged, returning the paths of
+/// the inputs to process sorted by their first packet time.
+///
+/// Inputs with no packets, or with no packets within `pass_window`, are rejected with a
+/// warning. Inputs that appear to be duplicates of another input, i.e., with the same time
+/// range and number of packets, inputs whose time ranges overlap a preceding input, and
+/// inputs that begin more than [DISJOINT_INPUT_GAP] after all preceding inputs end are
+/// reported with a warning but still processed.
+///
+/// # Errors
+/// If an input cannot be read or no inputs remain.
+fn check_inputs(
+    inputs: &[PathBuf],
+    timecode: &TimecodeSpec,
+    pass_window: Option<&(Time, Time)>,
+) -> Result<Vec<PathBuf>> {
+    let fmt = "%Y-%m-%dT%H:%M:%SZ";
+    let mut spans: Vec<InputSpan> = Vec::default();
+    for input in inputs {
+        let Some(span) = InputSpan::read(input, timecode)? else {
+            warn!("rejecting input {input:?}; no packets with valid times");
+            continue;
+        };
+        debug!(
+            "input {input:?} packets={} first={} last={}",
+            span.packets,
+            span.first.format_utc(fmt),
+            span.last.format_utc(fmt)
+        );
+        if let Some((start, end)) = pass_window {
+            if span.last < *start || span.first > *end {
+                warn!(
+                    "rejecting input {input:?}; packets from {} to {} are outside the pass window {} to {}",
+                    span.first.format_utc(fmt),
+                    span.last.format_utc(fmt),
+                    start.format_utc(fmt),
+                    end.format_utc(fmt)
+                );
+                continue;
+            }
+        }
+        spans.push(span);
+    }
+    if spans.is_empty() {
+        bail!("no usable inputs");
+    }
+    spans.sort_by(|a, b| a.first.cmp(&b.first));
+
+    // Preceding input with the latest last packet time
+    let mut latest: Option<&InputSpan> = None;
+    for (idx, span) in spans.iter().enumerate() {
+        if let Some(other) = spans[..idx].iter().find(|s| s.is_duplicate(span)) {
+            warn!(
+                "input {:?} may be a duplicate of {:?}; both have {} packets from {} to {}",
+                span.path,
+                other.path,
+                span.packets,
+                span.first.format_utc(fmt),
+                span.last.format_utc(fmt)
+            );
+        } else if let Some(other) = latest.filter(|s| span.first <= s.last) {
+            let end = if span.last < other.last {
+                &span.last
+            } else {
+                &other.last
+            };
+            warn!(
+                "input {:?} overlaps {:?} from {} to {}; packets in both are merged",
+                span.path,
+                other.path,
+                span.first.format_utc(fmt),
+                end.format_utc(fmt)
+            );
+        }
+        if let Some(latest) = latest {
+            let gap = span.first.iet().saturating_sub(latest.last.iet());
+            if gap > DISJOINT_INPUT_GAP {
+                warn!(
+                    "input {:?} begins {:.1} hours after the preceding inputs end; check it is \
+                     the intended input",
+                    span.path,
+                    gap as f64 / 3_600_000_000.0
+                );
+            }
+        }
+        if latest.map_or(true, |latest| span.last > latest.last) {
+            latest = Some(span);
+        }
+    }
+
+    Ok(spans.into_iter().map(|s| s.path).collect())
 }
 
 pub fn create(
     satellite: Option<String>,
     config: Option<PathBuf>,
+    config_override: Option<PathBuf>,
     input: &[PathBuf],
     output: PathBuf,
+    opts: &CreateOptions,
+    writer: &dyn RdrWriter,
 ) -> Result<()> {
-    let config = match get_config(satellite, config) {
+    let config = match get_config(satellite, config, config_override) {
         Ok(Some(config)) => config,
         Ok(None) => bail!("No spacecraft configuration found"),
         Err(err) => bail!("Failed to lookup config: {err}"),
     };
+    if let Some(layout) = &opts.output_layout {
+        rdr::output_subdir(layout, &config.satellite.id, "", "", &Time::now())
+            .with_context(|| format!("invalid output layout {layout:?}"))?;
+    }
     for input in input {
-        if !input.exists() {
+        if !is_s3(input) && !input.exists() {
             bail!("Input does not exist: {input:?}");
         }
     }
 
+    if opts.batch {
+        return create_batch(&config, input, &output, opts, writer);
+    }
+    create_from(&config, input, output, opts, writer)
+}
+
+/// Output directory for `input` in batch mode; a subdirectory of `output` named for the input
+/// file name up to the first `.`, e.g., `P1570826VIIRSSCIENCEAS24001000000001.PDS.gz` is
+/// written to `output/P1570826VIIRSSCIENCEAS24001000000001`.
+pub fn batch_output_dir(output: &Path, input: &Path) -> PathBuf {
+    let name = input
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let stem = name
+        .split('.')
+        .next()
+        .filter(|s| !s.is_empty())
+        .unwrap_or("input");
+    output.join(stem)
+}
+
+/// Create RDRs from each of `inputs` independently, writing each input's outputs to its
+/// [batch_output_dir] with a summary logged for each input.
+///
+/// A failed input does not stop the remaining inputs from being processed, but results in an
+/// error once all inputs are done.
+fn create_batch(
+    config: &Config,
+    inputs: &[PathBuf],
+    output: &Path,
+    opts: &CreateOptions,
+    writer: &dyn RdrWriter,
+) -> Result<()> {
+    let mut outdirs: HashMap<PathBuf, &PathBuf> = HashMap::default();
+    for input in inputs {
+        let outdir = batch_output_dir(output, input);
+        if let Some(other) = outdirs.insert(outdir.clone(), input) {
+            bail!("inputs {other:?} and {input:?} would both be written to {outdir:?}");
+        }
+    }
+    if !opts.dry_run {
+        std::fs::create_dir_all(output).with_context(|| format!("creating {output:?}"))?;
+    }
+
+    let mut failed = Vec::default();
+    for (idx, input) in inputs.iter().enumerate() {
+        let span = info_span!("batch", input = %input.display());
+        let _guard = span.enter();
+        info!("processing input {} of {}", idx + 1, inputs.len());
+        let outdir = batch_output_dir(output, input);
+        if let Err(err) = create_from(config, std::slice::from_ref(input), outdir, opts, writer) {
+            error!("failed to create RDRs: {err:#}");
+            failed.push(input);
+        }
+    }
+    if !failed.is_empty() {
+        bail!(
+            "failed to create RDRs for {} of {} inputs: {failed:?}",
+            failed.len(),
+            inputs.len()
+        );
+    }
+    Ok(())
+}
+
+/// Create RDRs from `input`, merging multiple inputs if necessary.
+fn create_from(
+    config: &Config,
+    input: &[PathBuf],
+    output: PathBuf,
+    opts: &CreateOptions,
+    writer: &dyn RdrWriter,
+) -> Result<()> {
+    let checked;
+    let input = if input.len() > 1 || opts.pass_window.is_some() {
+        checked = check_inputs(input, &config.satellite.timecode, opts.pass_window.as_ref())?;
+        &checked[..]
+    } else {
+        input
+    };
+
     // Get single input, merging multiple inputs if necessary
     let mut tmpdir: Option<TempDir> = None;
     let input = if input.len() > 1 {
-        let dir = TempDir::new()?;
+        let dir = tmpdir.insert(TempDir::new()?);
         let dest = dir.path().join("merge.dat");
         info!(?input, ?dest, "merging inputs");
-        merge(input, dest.clone()).context("merging multiple inputs")?;
-        tmpdir = Some(dir);
+        merge(input, dest.clone(), &config.satellite.timecode)
+            .context("merging multiple inputs")?;
         dest
     } else {
         input[0].clone()
     };
-    let file = BufReader::new(File::open(input)?);
+    let file = open_input(&input).with_context(|| format!("opening {input:?}"))?;
     let packets = decode_packets(file).filter_map(Result::ok);
     let groups = collect_groups(packets).filter_map(Result::ok);
 
-    create_rdr(&config, groups, &output)?;
+    let summary = create_rdr(config, groups, &output, opts, writer)?;
+    for line in summary.to_string().lines() {
+        info!("summary: {line}");
+    }
 
     if let Some(dir) = tmpdir {
         debug!(dir = ?dir.path(), "removing tempdir");
@@ -169,3 +947,94 @@ pub fn create(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rdr::fixtures;
+
+    /// Granule ids of the primary granules in each file.
+    fn primary_ids(files: &[Vec<Rdr>]) -> Vec<Vec<String>> {
+        files
+            .iter()
+            .map(|rdrs| {
+                rdrs.iter()
+                    .filter(|r| r.meta.collection == rdrs[0].meta.collection)
+                    .map(|r| r.meta.id.clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rotator_disabled() {
+        let config = get_default("npp").unwrap().unwrap();
+        let granules = fixtures::viirs_granules(&config, 0, 3).unwrap();
+        let mut rotator = Rotator::new(Rotation::default());
+
+        for rdrs in granules {
+            let id = rdrs[0].meta.id.clone();
+            let files = rotator.push(rdrs);
+            assert_eq!(primary_ids(&files), vec![vec![id]]);
+        }
+        assert!(rotator.finish().is_empty());
+    }
+
+    #[test]
+    fn test_rotator_granules() {
+        let config = get_default("npp").unwrap().unwrap();
+        let granules = fixtures::viirs_granules(&config, 0, 5).unwrap();
+        let ids: Vec<String> = granules.iter().map(|r| r[0].meta.id.clone()).collect();
+        let mut rotator = Rotator::new(Rotation {
+            granules: Some(2),
+            window: None,
+        });
+
+        let mut files = Vec::default();
+        for (idx, rdrs) in granules.into_iter().enumerate() {
+            let complete = rotator.push(rdrs);
+            // a file completes with every second primary granule
+            assert_eq!(complete.len(), idx % 2, "granule {idx}");
+            files.extend(complete);
+        }
+        files.extend(rotator.finish());
+
+        assert_eq!(
+            primary_ids(&files),
+            vec![ids[0..2].to_vec(), ids[2..4].to_vec(), ids[4..5].to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_rotator_window() {
+        let config = get_default("npp").unwrap().unwrap();
+        let granules = fixtures::viirs_granules(&config, 0, 8).unwrap();
+        let window = Duration::from_secs(200);
+
+        // group the primary granules by the window containing their begin time
+        let mut expected: Vec<(u64, Vec<String>)> = Vec::default();
+        for rdrs in &granules {
+            let idx = Time::from(rdrs[0].meta.begin_time_iet).utc() / 200_000_000;
+            match expected.last_mut() {
+                Some((last, ids)) if *last == idx => ids.push(rdrs[0].meta.id.clone()),
+                _ => expected.push((idx, vec![rdrs[0].meta.id.clone()])),
+            }
+        }
+        assert!(expected.len() > 1, "granules should span multiple windows");
+
+        let mut rotator = Rotator::new(Rotation {
+            granules: None,
+            window: Some(window),
+        });
+        let mut files = Vec::default();
+        for rdrs in granules {
+            files.extend(rotator.push(rdrs));
+        }
+        // only the last window is pending
+        assert_eq!(files.len(), expected.len() - 1);
+        files.extend(rotator.finish());
+
+        let expected: Vec<Vec<String>> = expected.into_iter().map(|(_, ids)| ids).collect();
+        assert_eq!(primary_ids(&files), expected);
+    }
+}
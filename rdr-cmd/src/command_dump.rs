@@ -1,52 +1,151 @@
 use anyhow::{bail, Context, Result};
-use ccsds::spacepacket::decode_packets;
+use ccsds::spacepacket::{collect_groups, decode_packets};
 use hdf5::{File as H5File, Group};
-use rdr::{jpss_merge, ApidInfo, PacketTracker, StaticHeader, Time};
+use rdr::{
+    common_rdr_packets,
+    config::{get_default_for_platform, ApidSpec, Config},
+    construction_record_name, jpss_merge, read_attr_string, ConstructionRecord, FileSink,
+    GranuleMeta, Meta, PacketSink, PacketTimeIter, RawDatasetDetail, StaticHeader, Time,
+};
 use std::{
-    collections::HashMap,
+    collections::HashSet,
     fs::{self, File},
-    io::Write,
+    io::{BufWriter, Write},
     path::{Path, PathBuf},
 };
 use tempfile::TempDir;
 use tracing::{debug, info, trace, warn};
 
-const SUPPORTED_SENSORS: [&str; 4] = ["VIIRS", "CRIS", "ATMS", "OMPS"];
-
-enum DatasetType<'a> {
-    Science(&'a str),
-    Spacecraft(u16),
+/// Granule selection for [dump]. Empty criteria select all granules.
+#[derive(Debug, Default)]
+pub struct GranuleSelection {
+    /// N_Granule_ID values of granules to include
+    pub granule_ids: Vec<String>,
+    /// Include only granules that end after this time
+    pub start: Option<Time>,
+    /// Include only granules that begin before this time
+    pub end: Option<Time>,
+    /// Read granule metadata with [Meta::from_file_lenient] for files with missing or
+    /// incomplete `Data_Products` entries
+    pub lenient: bool,
 }
 
-// TODO:
-//  * Determine what OMPS L0 files should look like
-//  * Support DIAG, HK, DWELL, etc ...
-fn dataset_name(scid: u8, type_: &DatasetType, created: &Time) -> String {
-    let dstr = created.format_utc("%y%j%H%M%S");
-    match type_ {
-        DatasetType::Science(path) => {
-            if path.contains("VIIRS") {
-                format!("P{scid:03}0826VIIRSSCIENCEAS{dstr}001.PDS")
-            } else if path.contains("CRIS") {
-                format!("P{scid:03}1289CRISSCIENCEAAS{dstr}001.PDS")
-            } else if path.contains("ATMS") {
-                format!("P{scid:03}0515ATMSSCIENCEAAS{dstr}001.PDS")
-            } else if path.contains("OMPS") {
-                format!("P{scid:03}????OMPSSCIENCEAAS{dstr}001.PDS")
-            } else {
-                format!("{scid:03}-{dstr}.dat")
-            }
+impl GranuleSelection {
+    fn is_empty(&self) -> bool {
+        self.granule_ids.is_empty() && self.start.is_none() && self.end.is_none()
+    }
+
+    fn in_time_range(&self, granule: &GranuleMeta) -> bool {
+        self.start
+            .as_ref()
+            .map_or(true, |t| granule.end_time_iet > t.iet_micros())
+            && self
+                .end
+                .as_ref()
+                .map_or(true, |t| granule.begin_time_iet < t.iet_micros())
+    }
+
+    /// Paths of the `RawApplicationPackets` datasets in `input` for the selected granules.
+    ///
+    /// Granule ids identify sensor granules, so spacecraft diary granules are selected if they
+    /// overlap a selected sensor granule, i.e., the diary that would be packed with it.
+    ///
+    /// `config` is used to reconstruct granule metadata when lenient.
+    fn raw_datasets(&self, input: &Path, config: Option<&Config>) -> Result<HashSet<String>> {
+        let meta = if self.lenient {
+            Meta::from_file_lenient(input, config, RawDatasetDetail::Size)
+        } else {
+            Meta::from_file_with(input, RawDatasetDetail::Size)
         }
-        DatasetType::Spacecraft(apid) => {
-            format!("P{scid:03}{apid:04}AAAAAAAAAAAAAS{dstr}001.PDS")
+        .with_context(|| format!("reading {input:?}"))?;
+        let (diary, sensor): (Vec<&GranuleMeta>, Vec<&GranuleMeta>) = meta
+            .granules
+            .values()
+            .flatten()
+            .partition(|g| g.collection.contains("SPACECRAFT"));
+
+        let sensor: Vec<&GranuleMeta> = sensor
+            .into_iter()
+            .filter(|g| {
+                (self.granule_ids.is_empty() || self.granule_ids.contains(&g.id))
+                    && self.in_time_range(g)
+            })
+            .collect();
+        let diary = diary.into_iter().filter(|d| {
+            self.in_time_range(d)
+                && (self.granule_ids.is_empty() || sensor.iter().any(|g| d.is_packed_with(g)))
+        });
+
+        let mut datasets = HashSet::default();
+        for granule in sensor.iter().copied().chain(diary) {
+            debug!(collection = %granule.collection, granule_id = %granule.id, "selected");
+            if let Some(raw) = &granule.raw_dataset {
+                datasets.insert(raw.name.clone());
+            }
         }
+        Ok(datasets)
     }
 }
 
-const NO_PACKETS_RECEIVED: i32 = -1;
+/// Collections to dump, by short name. Empty criteria select all collections.
+#[derive(Debug, Default)]
+pub struct CollectionFilter {
+    /// Short names of collections to include, e.g., VIIRS-SCIENCE-RDR
+    pub include: Vec<String>,
+    /// Short names of collections to exclude
+    pub exclude: Vec<String>,
+}
+
+impl CollectionFilter {
+    fn matches(&self, short_name: &str) -> bool {
+        let listed = |names: &[String]| names.iter().any(|n| n.eq_ignore_ascii_case(short_name));
+        (self.include.is_empty() || listed(&self.include)) && !listed(&self.exclude)
+    }
+}
+
+/// APID and PDS dataset identifier of collections dumped to a single PDS file. All other
+/// collections are dumped to a PDS file per APID using the configured [ApidSpec::pds_id].
+fn collection_pds_id(short_name: &str) -> Option<(u16, &'static str)> {
+    match short_name {
+        "VIIRS-SCIENCE-RDR" => Some((826, "VIIRSSCIENCE")),
+        "CRIS-SCIENCE-RDR" => Some((1289, "CRISSCIENCE")),
+        "ATMS-SCIENCE-RDR" => Some((515, "ATMSSCIENCE")),
+        "ATMS-DIAGNOSTIC-RDR" => Some((524, "ATMSDWELL")),
+        "ATMS-TELEMETRY-RDR" => Some((536, "ATMSHSK")),
+        _ => None,
+    }
+}
+
+/// NASA Level-0 PDS file name for `apid`, where `id` is the PDS dataset identifier.
+fn dataset_name(scid: u8, apid: u16, id: &str, created: &Time) -> String {
+    let dstr = created.format_utc("%y%j%H%M%S");
+    let width = ApidSpec::MAX_PDS_ID_LEN;
+    format!("P{scid:03}{apid:04}{id:A<width$}S{dstr}001.PDS")
+}
+
+/// Dataset name for a single granule, i.e., [dataset_name] using the granule start as the
+/// creation time with the granule start and end appended.
+fn granule_dataset_name(scid: u8, apid: u16, id: &str, start: &Time, end: &Time) -> String {
+    let name = dataset_name(scid, apid, id, start);
+    let stem = name.strip_suffix(".PDS").unwrap_or(&name);
+    format!(
+        "{stem}_d{}_t{}_e{}.PDS",
+        start.format_utc("%Y%m%d"),
+        start.format_utc("%H%M%S"),
+        end.format_utc("%H%M%S"),
+    )
+}
 
-/// Dump the Common RDR Application Packets Storage to a file.
-fn dump_datasets_to(workdir: &Path, path: &str, group: &Group) -> Result<Vec<PathBuf>> {
+/// Dump the Common RDR Application Packets Storage for each granule dataset to a file,
+/// returning the file paths along with the granule static headers.
+///
+/// Only datasets in `selected` are dumped, if provided.
+fn dump_datasets_to(
+    workdir: &Path,
+    path: &str,
+    group: &Group,
+    selected: Option<&HashSet<String>>,
+) -> Result<Vec<(PathBuf, StaticHeader)>> {
     let mut files = Vec::default();
 
     for (idx, dataset) in group
@@ -55,11 +154,15 @@ fn dump_datasets_to(workdir: &Path, path: &str, group: &Group) -> Result<Vec<Pat
         .iter()
         .enumerate()
     {
+        if selected.is_some_and(|s| !s.contains(&dataset.name())) {
+            trace!("skipping unselected {}", dataset.name());
+            continue;
+        }
         let destpath = workdir
             .join(path.replace('/', "::"))
             .with_extension(format!("{idx}"));
         debug!("writing to {destpath:?}");
-        let mut file = File::create(&destpath).context("opening packet dest file")?;
+        let mut file = BufWriter::new(File::create(&destpath).context("opening packet dest file")?);
 
         // The whole common RDR as bytes
         let bytes = dataset.read_1d::<u8>().context("Reading data")?;
@@ -68,54 +171,36 @@ fn dump_datasets_to(workdir: &Path, path: &str, group: &Group) -> Result<Vec<Pat
         let header = StaticHeader::from_bytes(data).context("decoding static header")?;
         trace!("{header:?}");
 
-        let start = header.apid_list_offset as usize;
-        let end = start + ApidInfo::LEN * usize::try_from(header.num_apids)?;
-        let apids = ApidInfo::all_from_bytes(&data[start..end]).context("decoding apidlist")?;
-
-        debug!("{path} num_apids={}", apids.len());
-
-        for apid in &apids {
-            debug!(
-                "reading {}({}) pkts_received={}",
-                apid.name, apid.value, apid.pkts_received
-            );
-            trace!("{:?}", apid);
-
-            let mut tracker_offset = header.pkt_tracker_offset as usize
-                + apid.pkt_tracker_start_idx as usize * PacketTracker::LEN;
-            for _ in 0..apid.pkts_received {
-                let tracker = PacketTracker::from_bytes(&data[tracker_offset..])
-                    .context("decoding packet tracker")?;
-                trace!("{:?}", tracker);
-                tracker_offset += PacketTracker::LEN;
-                if tracker.offset == NO_PACKETS_RECEIVED {
-                    break;
-                }
-                let start = header.ap_storage_offset as usize + usize::try_from(tracker.offset)?;
-                let end = start + usize::try_from(tracker.size)?;
-                file.write_all(&data[start..end])?;
-            }
+        let packets = common_rdr_packets(data).context("decoding packets")?;
+        debug!("{path} num_packets={}", packets.len());
+        for pkt in &packets {
+            file.write_all(&pkt.data)?;
         }
+        file.flush()?;
 
-        files.push(destpath.clone());
+        files.push((destpath.clone(), header));
     }
 
     Ok(files)
 }
 
+/// Dump all granules in `group` to a single file named `name` in `workdir`.
 fn dump_group(
     workdir: &Path,
-    scid: u8,
     path: &str,
     group: &Group,
-    created: &Time,
+    name: &str,
+    selected: Option<&HashSet<String>>,
 ) -> Result<Option<PathBuf>> {
     info!("dumping {path} to {workdir:?}");
-    let files = dump_datasets_to(workdir, path, group)?;
+    let files: Vec<PathBuf> = dump_datasets_to(workdir, path, group, selected)?
+        .into_iter()
+        .map(|(fpath, _)| fpath)
+        .collect();
     if files.is_empty() {
         return Ok(None);
     }
-    let destpath = workdir.join(dataset_name(scid, &DatasetType::Science(path), created));
+    let destpath = workdir.join(name);
     debug!("merging {} files to {destpath:?}", files.len());
     let dest = File::create(&destpath).with_context(|| format!("Creating {destpath:?}"))?;
 
@@ -124,100 +209,333 @@ fn dump_group(
     Ok(Some(destpath))
 }
 
-fn get_spacecraft(path: &Path) -> u8 {
-    let path = path.to_string_lossy();
-    if path.contains("npp") {
-        157
-    } else if path.contains("j01") {
-        159
-    } else if path.contains("j02") {
-        177
-    } else if path.contains("j03") {
-        178
-    } else if path.contains("j04") {
-        179
-    } else {
-        0
+/// Dump each granule in `group` to its own file in `workdir`, named by `name(start, end)` for
+/// the granule start and end.
+fn dump_group_granules<F>(
+    workdir: &Path,
+    path: &str,
+    group: &Group,
+    name: F,
+    selected: Option<&HashSet<String>>,
+) -> Result<Vec<(PathBuf, Time, Time)>>
+where
+    F: Fn(&Time, &Time) -> String,
+{
+    info!("dumping {path} granules to {workdir:?}");
+    let mut granules = Vec::default();
+    for (fpath, header) in dump_datasets_to(workdir, path, group, selected)? {
+        let start = Time::from_iet(header.start_boundary);
+        let end = Time::from_iet(header.end_boundary);
+        let destpath = workdir.join(name(&start, &end));
+        debug!("merging {fpath:?} to {destpath:?}");
+        let dest = File::create(&destpath).with_context(|| format!("Creating {destpath:?}"))?;
+        // Merging a single file puts packets in time order
+        jpss_merge(&[fpath], dest).with_context(|| format!("Merging {destpath:?}"))?;
+        granules.push((destpath, start, end));
     }
+    Ok(granules)
 }
 
-pub fn split_spacecraft(fpath: &Path, scid: u8, created: &Time) -> Result<Vec<PathBuf>> {
-    let mut files: HashMap<u16, File> = HashMap::default();
-    let mut paths: Vec<PathBuf> = Vec::default();
+/// CCSDS spacecraft ids of satellites without a built-in config, by satellite id.
+const KNOWN_SCIDS: &[(&str, u8)] = &[("j04", 179)];
+
+/// Satellite id field of an IDPS style filename, e.g., `j01` for
+/// `RVIRS_j01_d20240101_t0000000_e0001000_b00001_c20240101000000000000_noac_ops.h5`.
+fn filename_satid(path: &Path) -> Option<String> {
+    let fname = path.file_name()?.to_string_lossy();
+    let satid = fname.split('_').nth(1)?;
+    (satid.len() == 3).then(|| satid.to_lowercase())
+}
+
+/// Config of the satellite for the RDR `file` at `path`; `config` if provided, otherwise
+/// the default config matching the file's `Platform_Short_Name` or the satellite id in its
+/// filename, if any.
+fn get_config(file: &H5File, path: &Path, config: Option<&Config>) -> Result<Option<Config>> {
+    if let Some(config) = config {
+        return Ok(Some(config.clone()));
+    }
+    let platform = read_attr_string(file, "Platform_Short_Name")?;
+    if let Some(config) = get_default_for_platform(&platform)? {
+        return Ok(Some(config));
+    }
+    if let Some(config) = filename_satid(path)
+        .map(|satid| get_default_for_platform(&satid))
+        .transpose()?
+        .flatten()
+    {
+        return Ok(Some(config));
+    }
+    warn!("no config for platform {platform:?}; no PDS ids will be used in file names");
+    Ok(None)
+}
+
+/// CCSDS spacecraft id from `config`, or from [KNOWN_SCIDS] by the satellite id in the
+/// filename `path`, or 0 if the satellite or its SCID is unknown.
+fn get_spacecraft(config: Option<&Config>, path: &Path) -> u8 {
+    if let Some(scid) = config.and_then(Config::scid) {
+        return scid;
+    }
+    let satid = config
+        .map(|c| c.satellite.id.clone())
+        .or_else(|| filename_satid(path));
+    let known = satid.as_deref().and_then(|satid| {
+        KNOWN_SCIDS
+            .iter()
+            .find(|(id, _)| *id == satid)
+            .map(|(_, scid)| *scid)
+    });
+    known.unwrap_or_else(|| {
+        warn!("no scid known for {satid:?}; using SCID 0 in file names");
+        0
+    })
+}
+
+/// The configured [ApidSpec::pds_id] for `apid` of the product with `short_name`, or an empty
+/// id, i.e., all padding, if not configured.
+fn pds_id<'a>(config: Option<&'a Config>, short_name: &str, apid: u16) -> &'a str {
+    config
+        .and_then(|c| {
+            c.products
+                .iter()
+                .filter(|p| p.short_name == short_name)
+                .flat_map(|p| &p.apids)
+                .find(|a| a.num == apid)
+                .and_then(|a| a.pds_id.as_deref())
+        })
+        .unwrap_or_default()
+}
+
+/// Split the `short_name` packets in `fpath` into a file per APID, where `name` provides the
+/// file name for an APID.
+pub fn split_apids<F>(fpath: &Path, short_name: &str, name: F) -> Result<Vec<PathBuf>>
+where
+    F: Fn(u16) -> String + Send,
+{
+    let dir = fpath.parent().unwrap_or(Path::new("."));
+    let mut sink = FileSink::per_apid(dir, move |_, apid| name(apid));
 
     for packet in decode_packets(&File::open(fpath)?) {
         let packet = match packet {
             Ok(p) => p,
             Err(err) => bail!("error while reading packets: {err}"),
         };
+        sink.write(short_name, &packet)?;
+    }
 
-        let dest = files.entry(packet.header.apid).or_insert_with(|| {
-            let sc_path = fpath.with_file_name(dataset_name(
-                scid,
-                &DatasetType::Spacecraft(packet.header.apid),
-                created,
-            ));
-            debug!("creating {sc_path:?}!");
-            paths.push(sc_path.clone());
-            File::create(&sc_path).expect("could not create destination")
-        });
+    Ok(sink.finish()?)
+}
+
+fn move_to_cwd(fpath: &Path) -> Result<PathBuf> {
+    let dest = PathBuf::from(fpath.file_name().expect("dumped files will have names"));
+    fs::rename(fpath, &dest).with_context(|| format!("renaming {fpath:?} to {dest:?}"))?;
+    info!(path = ?dest, "wrote {dest:?}");
+    Ok(dest)
+}
+
+/// Write the NASA construction record for the PDS data file at `fpath` alongside it, returning
+/// the path written. Packet times are decoded using the timecode from `config`, if available,
+/// otherwise the default CDS timecode.
+fn write_construction_record(
+    fpath: &Path,
+    scid: u8,
+    config: Option<&Config>,
+    created: &Time,
+) -> Result<PathBuf> {
+    let name = fpath
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let Some(cr_name) = construction_record_name(&name) else {
+        bail!("{fpath:?} is not a PDS data file");
+    };
+    let pds_id = cr_name.strip_suffix(".PDS").unwrap_or(&cr_name);
+    let mut cr = ConstructionRecord::new(pds_id, scid, created.clone());
+    cr.add_file(&name);
 
-        dest.write_all(&packet.data)?;
+    let file = File::open(fpath).with_context(|| format!("opening {fpath:?}"))?;
+    let groups = collect_groups(decode_packets(file).filter_map(Result::ok)).filter_map(Result::ok);
+    let mut packets = PacketTimeIter::new(groups);
+    if let Some(config) = config {
+        packets = packets.with_timecode(&config.satellite.timecode);
+    }
+    for (pkt, time) in packets {
+        cr.add_packet(&time, &pkt);
+    }
+    if cr.num_gaps() > 0 {
+        warn!("{} packet sequence gaps in {fpath:?}", cr.num_gaps());
     }
 
-    Ok(paths)
+    let dest = fpath.with_file_name(&cr_name);
+    cr.write(&dest)
+        .with_context(|| format!("writing construction record {dest:?}"))?;
+    info!(path = ?dest, "wrote {dest:?}");
+    Ok(dest)
 }
 
-pub fn dump(input: &Path, spacecraft: bool) -> Result<()> {
+/// Short names of the `/All_Data/<short_name>_All` RDR collections in `file` matching
+/// `filter`, sorted by short name.
+fn discover_collections(file: &H5File, filter: &CollectionFilter) -> Result<Vec<String>> {
+    let all_data = file.group("All_Data").context("opening /All_Data")?;
+    let mut collections: Vec<String> = all_data
+        .member_names()
+        .context("listing /All_Data groups")?
+        .into_iter()
+        .filter_map(|name| name.strip_suffix("_All").map(str::to_string))
+        .filter(|short_name| short_name.ends_with("-RDR") && filter.matches(short_name))
+        .collect();
+    collections.sort();
+    Ok(collections)
+}
+
+/// Dump the packets of the RDR collections in `input`, optionally excluding spacecraft
+/// collections, to PDS files in the current directory.
+///
+/// All `/All_Data/*-RDR_All` collections matching `collections` are dumped. Collections with
+/// a well-known PDS dataset identifier, e.g., VIIRS-SCIENCE-RDR, are written to a single PDS
+/// file. Others, e.g., spacecraft and OMPS, are written to a PDS file per APID named using the
+/// APID `pds_id` from the config for the file's platform.
+///
+/// If `per_granule` is true a PDS file is written for each granule, rather than a single PDS
+/// file for all granules, with the granule start and end encoded in the file name.
+///
+/// Only the packets of the granules matching `selection` are dumped, e.g., to regenerate the
+/// Level-0 for a single granule.
+///
+/// If `construction_records` is true a NASA construction record is also written for each PDS
+/// file. See [ConstructionRecord].
+///
+/// If `config` is `None` the default config for the file's platform is used, if any.
+pub fn dump(
+    config: Option<&Config>,
+    input: &Path,
+    spacecraft: bool,
+    per_granule: bool,
+    selection: &GranuleSelection,
+    collections: &CollectionFilter,
+    construction_records: bool,
+) -> Result<()> {
     if !input.is_file() {
         bail!("Failed to open {input:?}");
     }
-    let scid = get_spacecraft(input);
+    let selected = if selection.is_empty() {
+        None
+    } else {
+        let datasets = selection.raw_datasets(input, config)?;
+        if datasets.is_empty() {
+            bail!("no granules in {input:?} match selection");
+        }
+        info!("dumping {} selected granules", datasets.len());
+        Some(datasets)
+    };
     let workdir = TempDir::new()?;
     let created = Time::now();
 
     let file = H5File::open(input).context("Opening input")?;
+    let config = get_config(&file, input, config).context("determining satellite config")?;
+    let config = config.as_ref();
+    let scid = get_spacecraft(config, input);
+    let output = |fpath: &Path| -> Result<()> {
+        let dest = move_to_cwd(fpath)?;
+        if construction_records {
+            write_construction_record(&dest, scid, config, &created)?;
+        }
+        Ok(())
+    };
 
-    let mut groups = Vec::default();
-    for sensor in SUPPORTED_SENSORS {
-        let path = format!("All_Data/{sensor}-SCIENCE-RDR_All");
-        groups.push(path);
-    }
-    if spacecraft {
-        groups.push("All_Data/SPACECRAFT-DIARY-RDR_All".to_string());
+    let short_names = discover_collections(&file, collections)?;
+    if short_names.is_empty() {
+        warn!("no RDR collections in {input:?} match the collection filter");
     }
+    for short_name in &short_names {
+        let short_name = short_name.as_str();
+        if !spacecraft && short_name.contains("SPACECRAFT") {
+            debug!("skipping {short_name}");
+            continue;
+        }
+        let group_path = format!("All_Data/{short_name}_All");
+        let group = file
+            .group(&group_path)
+            .with_context(|| format!("opening {group_path}"))?;
+        let single = collection_pds_id(short_name);
 
-    for group_path in groups {
-        debug!("trying to dump {group_path}");
-        if let Ok(group) = file.group(&group_path) {
-            let dat_path = match dump_group(workdir.path(), scid, &group_path, &group, &created)? {
-                Some(p) => p,
-                None => {
-                    warn!("no data found for {group_path}");
+        if per_granule {
+            let name = |start: &Time, end: &Time| match single {
+                Some((apid, id)) => granule_dataset_name(scid, apid, id, start, end),
+                None => format!("{short_name}.{}.dat", start.iet()),
+            };
+            let granules =
+                dump_group_granules(workdir.path(), &group_path, &group, name, selected.as_ref())?;
+            if granules.is_empty() {
+                warn!("no data found for {group_path}");
+            }
+            for (dat_path, start, end) in granules {
+                if single.is_some() {
+                    output(&dat_path)?;
                     continue;
                 }
-            };
-
-            if spacecraft && group_path.contains("SPACECRAFT") {
-                debug!("splitting {dat_path:?} into separate spacecraft files");
-                let files = split_spacecraft(&dat_path, scid, &created)
-                    .context("splitting spacecraft files")?;
-                for fpath in files {
-                    let dest = fpath.file_name().expect("split files will have names");
-                    fs::rename(&fpath, dest)
-                        .with_context(|| format!("renaming {dat_path:?} to {dest:?}"))?;
-                    info!("wrote {dest:?}");
+                let name = |apid| {
+                    let id = pds_id(config, short_name, apid);
+                    granule_dataset_name(scid, apid, id, &start, &end)
+                };
+                for fpath in split_apids(&dat_path, short_name, name)
+                    .with_context(|| format!("splitting {short_name} files"))?
+                {
+                    output(&fpath)?;
                 }
-            } else {
-                let dest = dat_path.file_name().expect("dumped files will have names");
-                fs::rename(&dat_path, dest)
-                    .with_context(|| format!("renaming {dat_path:?} to {dest:?}"))?;
-                info!("wrote {dest:?}");
             }
-        } else {
-            debug!("Failed to open {group_path}, assuming it does not exist");
+            continue;
+        }
+
+        let name = match single {
+            Some((apid, id)) => dataset_name(scid, apid, id, &created),
+            None => format!("{short_name}.dat"),
+        };
+        let Some(dat_path) = dump_group(
+            workdir.path(),
+            &group_path,
+            &group,
+            &name,
+            selected.as_ref(),
+        )?
+        else {
+            warn!("no data found for {group_path}");
+            continue;
+        };
+
+        if single.is_some() {
+            output(&dat_path)?;
+            continue;
+        }
+        debug!("splitting {dat_path:?} into separate {short_name} files");
+        let name = |apid| {
+            let id = pds_id(config, short_name, apid);
+            dataset_name(scid, apid, id, &created)
+        };
+        let files = split_apids(&dat_path, short_name, name)
+            .with_context(|| format!("splitting {short_name} files"))?;
+        for fpath in files {
+            output(&fpath)?;
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rdr::config::get_default;
+
+    #[test]
+    fn test_get_spacecraft() {
+        let j03 = get_default("j03").unwrap().unwrap();
+        let path = Path::new(
+            "RVIRS_j04_d20240101_t0000000_e0001000_b00001_c20240101000000000000_noac_ops.h5",
+        );
+
+        assert_eq!(filename_satid(path).as_deref(), Some("j04"));
+        assert_eq!(get_spacecraft(Some(&j03), path), 178);
+        assert_eq!(get_spacecraft(None, path), 179);
+        assert_eq!(get_spacecraft(None, Path::new("input.h5")), 0);
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::{Context, Result};
-use hdf5::types::FixedAscii;
-use rdr::CommonRdr;
+use rdr::{config::Config, read_attr_string, CommonRdr, Meta, RawDatasetDetail};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use std::fs::{write, File};
 use std::path::{Path, PathBuf};
 use tracing::{debug, warn};
@@ -11,26 +12,27 @@ pub struct ExtractedOutput {
     pub short_name: String,
 }
 
-pub fn extract<I: AsRef<Path>, O: AsRef<Path>>(
-    input: I,
-    outdir: O,
-    short_name: Option<String>,
-    granule_id: Option<String>,
-) -> Result<Vec<ExtractedOutput>> {
-    let mut outputs = Vec::default();
-
-    let outdir = outdir.as_ref();
-    std::fs::create_dir_all(outdir).with_context(|| format!("creating direcotry {outdir:?}"))?;
+/// A RawApplicationPackets dataset in an RDR file.
+pub struct GranuleDataset {
+    /// Full h5 path to the RawApplicationPackets dataset
+    pub path: String,
+    pub granule_id: String,
+    pub short_name: String,
+}
 
-    let file = hdf5::File::open(&input)
-        .with_context(|| format!("failed to open {:?}", input.as_ref().to_path_buf()))?;
+/// Get all the RawApplicationPackets datasets in `file`, optionally only for `short_name`.
+pub fn granule_datasets(
+    file: &hdf5::File,
+    short_name: Option<&str>,
+) -> Result<Vec<GranuleDataset>> {
+    let mut granules = Vec::default();
 
     let all_data = file.group("All_Data").context("failed to open /All_Data")?;
     for group in all_data
         .groups()
         .context("failed to get /All_Data groups")?
     {
-        if let Some(short_name) = short_name.as_ref() {
+        if let Some(short_name) = short_name {
             if !group.name().ends_with(&format!("{short_name}_All")) {
                 debug!("skipping group {}", group.name());
                 continue;
@@ -50,39 +52,185 @@ pub fn extract<I: AsRef<Path>, O: AsRef<Path>>(
                 warn!("failed to parse short name from {dataset_path}");
                 continue;
             }
-            let id = get_granule_id(&file, &dataset_path)?;
+            let granule_id = get_granule_id(file, &dataset_path)?;
+            granules.push(GranuleDataset {
+                path: dataset_path,
+                granule_id,
+                short_name,
+            });
+        }
+    }
 
-            if let Some(granule_id) = granule_id.as_ref() {
-                if id != *granule_id {
-                    debug!("skipping granule {short_name} {id}");
-                    continue;
-                }
-            }
+    Ok(granules)
+}
+
+/// Get the RawApplicationPackets datasets in the file at `path` like [granule_datasets], but
+/// with granule ids from [Meta::from_file_lenient] so files with missing or incomplete
+/// `Data_Products` entries can be read. Granule metadata is reconstructed using `config`, or
+/// the default config for the file's platform if `None`.
+pub fn lenient_granule_datasets(
+    path: &Path,
+    config: Option<&Config>,
+    short_name: Option<&str>,
+) -> Result<Vec<GranuleDataset>> {
+    let meta = Meta::from_file_lenient(path, config, RawDatasetDetail::Size)
+        .with_context(|| format!("reading {path:?}"))?;
+    let mut granules = Vec::default();
+    for (name, granule_metas) in meta.granules {
+        if short_name.is_some_and(|s| s != name) {
+            debug!("skipping collection {name}");
+            continue;
+        }
+        for granule in granule_metas {
+            let Some(raw) = granule.raw_dataset.filter(|r| r.size > 0) else {
+                warn!("no data for {name} granule {}", granule.id);
+                continue;
+            };
+            granules.push(GranuleDataset {
+                path: raw.name,
+                granule_id: granule.id,
+                short_name: name.clone(),
+            });
+        }
+    }
+    Ok(granules)
+}
+
+/// Summary of the packets for a single APID in a granule, for comparing extractions without
+/// comparing the raw data.
+#[derive(Debug, Clone, Serialize)]
+pub struct ApidSummary {
+    pub name: String,
+    pub apid: u32,
+    pub num_packets: usize,
+    pub first_sequence: Option<i32>,
+    pub last_sequence: Option<i32>,
+    /// First packet time as IET microseconds
+    pub first_obs_time: Option<i64>,
+    /// Last packet time as IET microseconds
+    pub last_obs_time: Option<i64>,
+    /// Hex encoded SHA-256 of the APID's packet bytes in tracker order
+    pub sha256: String,
+}
 
-            // read entire common rdr data bytes
-            let arr = dataset
-                .read_1d::<u8>()
-                .with_context(|| format!("reading {}", dataset.name()))?;
-            let Some(data) = arr.as_slice() else {
-                warn!("invalid array format for {short_name}");
+/// Summarize the packets for each APID in the Common RDR `data`.
+fn apid_summaries(common: &CommonRdr, data: &[u8]) -> Vec<ApidSummary> {
+    let storage = common.static_header.ap_storage_offset as usize;
+    let mut summaries = Vec::default();
+    for info in &common.apid_list {
+        let start = info.pkt_tracker_start_idx as usize;
+        let end = start + info.pkts_received as usize;
+        let trackers = common
+            .packet_trackers
+            .get(start..end)
+            .unwrap_or_default()
+            .iter()
+            // Negative offsets indicate fill for missing packets
+            .filter(|t| t.offset >= 0);
+        let mut hasher = Sha256::new();
+        let mut summary = ApidSummary {
+            name: info.name.clone(),
+            apid: info.value,
+            num_packets: 0,
+            first_sequence: None,
+            last_sequence: None,
+            first_obs_time: None,
+            last_obs_time: None,
+            sha256: String::default(),
+        };
+        for tracker in trackers {
+            let offset = storage + tracker.offset as usize;
+            let Some(bytes) = data.get(offset..offset + tracker.size.max(0) as usize) else {
+                warn!("{}: packet tracker beyond data: {tracker:?}", info.name);
                 continue;
             };
+            hasher.update(bytes);
+            summary.num_packets += 1;
+            summary
+                .first_sequence
+                .get_or_insert(tracker.sequence_number);
+            summary.first_obs_time.get_or_insert(tracker.obs_time);
+            summary.last_sequence = Some(tracker.sequence_number);
+            summary.last_obs_time = Some(tracker.obs_time);
+        }
+        summary.sha256 = format!("{:x}", hasher.finalize());
+        summaries.push(summary);
+    }
+    summaries
+}
+
+/// Extract JSON content; the Common RDR structures along with per-APID summaries.
+#[derive(Serialize)]
+struct ExtractedJson<'a> {
+    #[serde(flatten)]
+    common: &'a CommonRdr,
+    apids: Vec<ApidSummary>,
+}
+
+pub fn extract<I: AsRef<Path>, O: AsRef<Path>>(
+    config: Option<&Config>,
+    input: I,
+    outdir: O,
+    short_name: Option<String>,
+    granule_id: Option<String>,
+    lenient: bool,
+) -> Result<Vec<ExtractedOutput>> {
+    let mut outputs = Vec::default();
 
-            let common_rdr = CommonRdr::from_bytes(data)?;
-            let fpfx = format!("{short_name}_{id}");
-            let fpath = outdir.join(format!("{fpfx}.json"));
-            let file = File::create(&fpath).with_context(|| format!("creating {fpath:?}"))?;
-            serde_json::to_writer_pretty(&file, &common_rdr)?;
+    let outdir = outdir.as_ref();
+    std::fs::create_dir_all(outdir).with_context(|| format!("creating direcotry {outdir:?}"))?;
 
-            let fpath = outdir.join(format!("{fpfx}.dat"));
-            write(&fpath, data).with_context(|| format!("writing {fpath:?}"))?;
+    let file = hdf5::File::open(&input)
+        .with_context(|| format!("failed to open {:?}", input.as_ref().to_path_buf()))?;
 
-            outputs.push(ExtractedOutput {
-                path: fpath,
-                granule_id: id,
-                short_name,
-            });
+    let granules = if lenient {
+        lenient_granule_datasets(input.as_ref(), config, short_name.as_deref())?
+    } else {
+        granule_datasets(&file, short_name.as_deref())?
+    };
+    for granule in granules {
+        let GranuleDataset {
+            path,
+            granule_id: id,
+            short_name,
+        } = granule;
+        if let Some(granule_id) = granule_id.as_ref() {
+            if id != *granule_id {
+                debug!("skipping granule {short_name} {id}");
+                continue;
+            }
         }
+
+        // read entire common rdr data bytes
+        let dataset = file
+            .dataset(&path)
+            .with_context(|| format!("opening dataset {path}"))?;
+        let arr = dataset
+            .read_1d::<u8>()
+            .with_context(|| format!("reading {}", dataset.name()))?;
+        let Some(data) = arr.as_slice() else {
+            warn!("invalid array format for {short_name}");
+            continue;
+        };
+
+        let common_rdr = CommonRdr::from_bytes(data)?;
+        let fpfx = format!("{short_name}_{id}");
+        let fpath = outdir.join(format!("{fpfx}.json"));
+        let file = File::create(&fpath).with_context(|| format!("creating {fpath:?}"))?;
+        let json = ExtractedJson {
+            apids: apid_summaries(&common_rdr, data),
+            common: &common_rdr,
+        };
+        serde_json::to_writer_pretty(&file, &json)?;
+
+        let fpath = outdir.join(format!("{fpfx}.dat"));
+        write(&fpath, data).with_context(|| format!("writing {fpath:?}"))?;
+
+        outputs.push(ExtractedOutput {
+            path: fpath,
+            granule_id: id,
+            short_name,
+        });
     }
 
     Ok(outputs)
@@ -100,11 +248,6 @@ fn get_granule_id(file: &hdf5::File, dataset_path: &str) -> Result<String> {
     let dataset = file
         .dataset(&path)
         .with_context(|| format!("opening dataset {path}"))?;
-    let attr = dataset
-        .attr("N_Granule_ID")
-        .context("getting attr {path}:N_Granule_ID")?;
-    Ok(attr
-        .read_2d::<FixedAscii<20>>()
-        .context("reading attr {path}:N_Granule_ID")?[[0, 0]]
-    .to_string())
+    read_attr_string(&dataset, "N_Granule_ID")
+        .with_context(|| format!("reading attr {path}:N_Granule_ID"))
 }
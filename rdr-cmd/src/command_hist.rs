@@ -0,0 +1,201 @@
+use anyhow::{Context, Result};
+use ccsds::spacepacket::{collect_groups, decode_packets, Apid, Packet};
+use rdr::{config::Config, dump_packets, open_packet_file, Meta, PacketTimeIter, Time};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{stdout, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+use tracing::{debug, warn};
+
+use crate::util::is_rdr;
+
+/// Output format for [hist].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistFormat {
+    /// Newline delimited JSON, one object per bin and APID
+    Json,
+    /// CSV with a header row, one row per bin and APID
+    Csv,
+}
+
+const CSV_HEADER: &str = "start,start_utc,apid,packets,bytes";
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Counts {
+    packets: u64,
+    bytes: u64,
+}
+
+/// Histogram record for a single APID in a single time bin.
+#[derive(Debug, Serialize)]
+struct Record {
+    start: String,
+    start_utc: u64,
+    apid: Apid,
+    packets: u64,
+    bytes: u64,
+}
+
+impl Record {
+    fn write<W: Write>(&self, format: HistFormat, mut writer: W) -> Result<()> {
+        match format {
+            HistFormat::Json => serde_json::to_writer(&mut writer, self)?,
+            HistFormat::Csv => write!(
+                writer,
+                "{},{},{},{},{}",
+                self.start, self.start_utc, self.apid, self.packets, self.bytes
+            )?,
+        }
+        writeln!(writer)?;
+        Ok(())
+    }
+}
+
+/// The packets in `input`, either the packets of all collections of an RDR, or the packets of
+/// a level-0 file.
+fn input_packets(input: &Path) -> Result<Box<dyn Iterator<Item = Packet>>> {
+    if !is_rdr(input) {
+        let file = open_packet_file(input).with_context(|| format!("opening {input:?}"))?;
+        return Ok(Box::new(decode_packets(file).filter_map(Result::ok)));
+    }
+
+    let meta = Meta::from_file(input).with_context(|| format!("reading {input:?}"))?;
+    let mut short_names: Vec<String> = meta.granules.into_keys().collect();
+    short_names.sort();
+    let file = hdf5::File::open(input).with_context(|| format!("opening {input:?}"))?;
+    let mut collections = Vec::default();
+    for short_name in short_names {
+        debug!("reading {short_name} packets");
+        let packets = dump_packets(&file, &short_name)
+            .with_context(|| format!("reading {short_name} packets"))?;
+        collections.push(packets.filter_map(move |pkt| match pkt {
+            Ok(pkt) => Some(pkt),
+            Err(err) => {
+                warn!("skipping {short_name} granule: {err}");
+                None
+            }
+        }));
+    }
+    Ok(Box::new(collections.into_iter().flatten()))
+}
+
+/// Write per-APID packet and byte counts for `input`, an RDR or level-0 file, in time bins of
+/// size `bin` to `output`, or stdout if `None`.
+///
+/// Bins start at multiples of `bin` since the Unix epoch, in UTC microseconds, so bins align
+/// with UTC times, and only bins containing packets are output, in time order. Each output bin
+/// has a record for every APID so dropouts within the data show as zero counts. Packet times
+/// are decoded using the timecode from `config`; packets whose time cannot be decoded are not
+/// counted.
+pub fn hist(
+    config: &Config,
+    input: &Path,
+    bin: Duration,
+    format: HistFormat,
+    output: Option<&Path>,
+) -> Result<()> {
+    let bin = u64::try_from(bin.as_micros())
+        .ok()
+        .filter(|b| *b > 0)
+        .context("bin size must be between 1 microsecond and u64::MAX microseconds")?;
+
+    let groups = collect_groups(input_packets(input)?).filter_map(Result::ok);
+    let mut apids: BTreeSet<Apid> = BTreeSet::default();
+    let mut bins: BTreeMap<u64, BTreeMap<Apid, Counts>> = BTreeMap::default();
+    for (pkt, time) in PacketTimeIter::new(groups).with_timecode(&config.satellite.timecode) {
+        let start = time.utc() / bin * bin;
+        let counts = bins
+            .entry(start)
+            .or_default()
+            .entry(pkt.header.apid)
+            .or_default();
+        counts.packets += 1;
+        counts.bytes += pkt.data.len() as u64;
+        apids.insert(pkt.header.apid);
+    }
+
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).with_context(|| format!("creating {path:?}"))?,
+        )),
+        None => Box::new(stdout()),
+    };
+    if format == HistFormat::Csv {
+        writeln!(writer, "{CSV_HEADER}")?;
+    }
+    if bins.is_empty() {
+        warn!("no packets with valid times in {input:?}");
+    }
+    for (start, counts) in &bins {
+        for apid in &apids {
+            let Counts { packets, bytes } = counts.get(apid).copied().unwrap_or_default();
+            Record {
+                start: Time::from_utc(*start).format_utc("%Y-%m-%dT%H:%M:%S.%fZ"),
+                start_utc: *start,
+                apid: *apid,
+                packets,
+                bytes,
+            }
+            .write(format, &mut writer)?;
+        }
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rdr::{config::get_default, fixtures};
+
+    #[test]
+    fn test_hist() {
+        let config = get_default("npp").unwrap().unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        // 3 packets, then 3 more after a gap of more than a bin
+        let start = fixtures::granule_time(&config, 0).unwrap();
+        let start = Time::from_utc(start.utc() / 10_000_000 * 10_000_000);
+        let mut data = fixtures::viirs_packets(&start, 3);
+        data.extend(fixtures::viirs_packets(
+            &Time::from_utc(start.utc() + 60_000_000),
+            3,
+        ));
+        let input = dir.path().join("input.dat");
+        std::fs::write(&input, &data).unwrap();
+        let output = dir.path().join("hist.csv");
+
+        hist(
+            &config,
+            &input,
+            Duration::from_secs(10),
+            HistFormat::Csv,
+            Some(&output),
+        )
+        .unwrap();
+
+        let content = std::fs::read_to_string(&output).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        // Only the 2 bins with data, aligned to UTC
+        assert_eq!(
+            lines[1..],
+            [
+                format!(
+                    "{},{},826,3,66",
+                    start.format_utc("%Y-%m-%dT%H:%M:%S.%fZ"),
+                    start.utc()
+                ),
+                format!(
+                    "{},{},826,3,66",
+                    Time::from_utc(start.utc() + 60_000_000).format_utc("%Y-%m-%dT%H:%M:%S.%fZ"),
+                    start.utc() + 60_000_000
+                ),
+            ]
+        );
+    }
+}
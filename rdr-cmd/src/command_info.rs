@@ -1,14 +1,15 @@
 use anyhow::Result;
 use std::{collections::HashMap, path::Path};
 
-use rdr::{GranuleMeta, Meta};
+use rdr::{GranuleMeta, Meta, RawDatasetDetail};
 
 pub fn info<P: AsRef<Path>>(
     input: P,
     short_name: Option<String>,
     granule_id: Option<String>,
+    detail: RawDatasetDetail,
 ) -> Result<()> {
-    let mut meta = Meta::from_file(input)?;
+    let mut meta = Meta::from_file_with(input, detail)?;
 
     if let Some(short_name) = short_name {
         meta.products.retain(|s, _| *s == short_name);
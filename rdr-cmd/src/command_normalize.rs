@@ -0,0 +1,13 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use tracing::info;
+
+pub fn normalize(input: &Path) -> Result<()> {
+    let changed = rdr::normalize_rdr(input).with_context(|| format!("normalizing {input:?}"))?;
+    if changed == 0 {
+        info!("{input:?} granule indexes already in time order");
+    } else {
+        info!("renumbered {changed} granules in {input:?}");
+    }
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use anyhow::{bail, Context, Result};
+use rdr::{
+    config::{get_default_for_platform, Config},
+    Meta, Time,
+};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Filename fields to set rather than keep from the current filename.
+#[derive(Debug, Clone, Default)]
+pub struct RenameOptions {
+    /// Origin, e.g., `noaa`; only the first 3 characters are used
+    pub origin: Option<String>,
+    /// IDPS mode, e.g., `ops`
+    pub mode: Option<String>,
+    pub created: Option<Time>,
+    /// Print the mapping rather than rename
+    pub dry_run: bool,
+}
+
+/// The `c<created>`, origin, and mode fields of an IDPS style filename, e.g.,
+/// `c20200101121314000000`, `noac`, and `ops`.
+fn idps_fields(fname: &str) -> Option<(&str, &str, &str)> {
+    let stem = fname.strip_suffix(".h5")?;
+    let mut fields = stem.rsplitn(4, '_');
+    let mode = fields.next()?;
+    let origin = fields.next()?;
+    let created = fields.next()?;
+    fields.next()?;
+    let digits = created.strip_prefix('c')?;
+    if digits.len() != 20 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((created, origin, mode))
+}
+
+/// Replace the created, origin, and mode fields of the IDPS style filename `fname`.
+fn with_idps_fields(fname: &str, created: &str, origin: &str, mode: &str) -> String {
+    let stem = fname.strip_suffix(".h5").unwrap_or(fname);
+    let rest = stem.rsplitn(4, '_').nth(3).unwrap_or(stem);
+    format!("{rest}_{created}_{origin}_{mode}.h5")
+}
+
+/// The canonical filename for the RDR at `path`. Fields not set in `opts` are kept from the
+/// current filename if it is IDPS style, otherwise they are from `config` and the current time.
+fn canonical_name(path: &Path, config: Option<&Config>, opts: &RenameOptions) -> Result<String> {
+    let meta = Meta::from_file(path).with_context(|| format!("reading {path:?}"))?;
+    let default;
+    let config = match config {
+        Some(config) => config,
+        None => {
+            let Some(config) = get_default_for_platform(&meta.platform)? else {
+                bail!("no config for platform {:?} of {path:?}", meta.platform);
+            };
+            default = config;
+            &default
+        }
+    };
+
+    let created = opts.created.clone().unwrap_or_else(Time::now);
+    let fname = meta.filename(
+        config,
+        opts.origin.as_deref().unwrap_or(&config.origin),
+        opts.mode.as_deref().unwrap_or(&config.mode),
+        &created,
+    )?;
+
+    let current = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let (Some(old), Some(new)) = (idps_fields(&current), idps_fields(&fname)) else {
+        return Ok(fname);
+    };
+    Ok(with_idps_fields(
+        &fname,
+        if opts.created.is_some() { new.0 } else { old.0 },
+        if opts.origin.is_some() { new.1 } else { old.1 },
+        if opts.mode.is_some() { new.2 } else { old.2 },
+    ))
+}
+
+/// Rename each of `inputs`, in place, to the canonical IDPS filename generated from its
+/// metadata. If `config` is `None` the default config for each file's platform is used.
+pub fn rename(config: Option<&Config>, inputs: &[PathBuf], opts: &RenameOptions) -> Result<()> {
+    let mut renames = Vec::default();
+    for path in inputs {
+        let dest = path.with_file_name(canonical_name(path, config, opts)?);
+        if &dest == path {
+            info!("{path:?} already has the canonical name");
+            continue;
+        }
+        if dest.exists() || renames.iter().any(|(_, d)| d == &dest) {
+            bail!("cannot rename {path:?}; {dest:?} already exists");
+        }
+        renames.push((path, dest));
+    }
+
+    for (path, dest) in renames {
+        if opts.dry_run {
+            println!("{} -> {}", path.display(), dest.display());
+            continue;
+        }
+        std::fs::rename(path, &dest).with_context(|| format!("renaming {path:?} to {dest:?}"))?;
+        info!("renamed {path:?} to {dest:?}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rdr::{config::get_default, create_rdr, fixtures};
+
+    fn fixture_file(dir: &Path, config: &Config) -> PathBuf {
+        let short_name = fixtures::product(config).unwrap().short_name.clone();
+        let rdrs: Vec<_> = fixtures::viirs_granules(config, 0, 2)
+            .unwrap()
+            .into_iter()
+            .map(|mut rdrs| rdrs.remove(0))
+            .collect();
+        let path = dir.join("input.h5");
+        let meta = Meta::from_products(&[short_name], config).unwrap();
+        create_rdr(&path, meta, &rdrs).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_rename() {
+        let config = get_default("npp").unwrap().unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = fixture_file(dir.path(), &config);
+        let opts = RenameOptions {
+            created: Some(Time::from_utc(1_577_880_794_000_000)),
+            ..Default::default()
+        };
+        let expected = Meta::from_file(&path)
+            .unwrap()
+            .filename(
+                &config,
+                &config.origin,
+                &config.mode,
+                opts.created.as_ref().unwrap(),
+            )
+            .unwrap();
+
+        rename(
+            Some(&config),
+            &[path.clone()],
+            &RenameOptions {
+                dry_run: true,
+                ..opts.clone()
+            },
+        )
+        .unwrap();
+        assert!(path.exists(), "dry run should not rename");
+
+        rename(Some(&config), &[path.clone()], &opts).unwrap();
+        let dest = dir.path().join(&expected);
+        assert!(!path.exists());
+        assert!(dest.exists(), "expected {expected}");
+
+        // Already canonical, with the created, origin, and mode fields kept
+        rename(Some(&config), &[dest.clone()], &RenameOptions::default()).unwrap();
+        assert!(dest.exists());
+    }
+
+    #[test]
+    fn test_rename_keeps_idps_fields() {
+        let config = get_default("npp").unwrap().unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = fixture_file(dir.path(), &config);
+        let created = Time::from_utc(1_577_880_794_000_000);
+        let fname = Meta::from_file(&path)
+            .unwrap()
+            .filename(&config, "xxx", "dev", &created)
+            .unwrap();
+        let idps = dir.path().join(&fname);
+        std::fs::rename(&path, &idps).unwrap();
+
+        let name = canonical_name(
+            &idps,
+            Some(&config),
+            &RenameOptions {
+                mode: Some("ops".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let (created, origin, mode) = idps_fields(&name).unwrap();
+        let (old_created, old_origin, _) = idps_fields(&fname).unwrap();
+        assert_eq!(created, old_created);
+        assert_eq!(origin, old_origin);
+        assert_eq!(mode, "ops");
+    }
+}
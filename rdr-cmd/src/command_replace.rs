@@ -0,0 +1,48 @@
+use anyhow::{bail, Context, Result};
+use rdr::config::Config;
+use std::path::Path;
+use tracing::info;
+
+use crate::command_backfill::for_each_granule;
+
+/// Replace the granules with `granule_id` in the RDR `target`, in place, with the granules
+/// with the same granule id from `input`, e.g., to repair a single bad granule.
+///
+/// `input` may be an RDR, i.e., a file with a `.h5` extension, or a level-0 file, which is
+/// collected into granules using `config`. Only collections already in `target` are replaced,
+/// limited to `short_name` if provided.
+pub fn replace(
+    config: &Config,
+    target: &Path,
+    input: &Path,
+    granule_id: &str,
+    short_name: Option<&str>,
+) -> Result<()> {
+    let meta = rdr::Meta::from_file(target).with_context(|| format!("reading {target:?}"))?;
+    let filter = |g: &rdr::GranuleMeta| {
+        g.id == granule_id
+            && !short_name.is_some_and(|s| s != g.collection)
+            && meta.granules.contains_key(&g.collection)
+    };
+
+    let mut replaced = 0;
+    for_each_granule(config, input, filter, |rdr| {
+        let idx = rdr::replace_rdr_granule(target, &rdr).with_context(|| {
+            format!(
+                "replacing {} granule {granule_id} in {target:?}",
+                rdr.meta.collection
+            )
+        })?;
+        info!(
+            collection = %rdr.meta.collection,
+            granule_id = %granule_id,
+            "replaced granule {idx} in {target:?}"
+        );
+        replaced += 1;
+        Ok(())
+    })?;
+    if replaced == 0 {
+        bail!("no granule {granule_id} in {input:?} for a collection in {target:?}");
+    }
+    Ok(())
+}
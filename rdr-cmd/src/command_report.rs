@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use rdr::{
+    config::{get_default_for_platform, Config},
+    ContinuityReport, Meta, TimeSkewReport,
+};
+use serde::Serialize;
+use std::{collections::BTreeMap, io::Write, path::PathBuf};
+use tracing::{info, warn};
+
+/// JSON report document; the continuity report fields plus per-file time skew, if checked.
+#[derive(Serialize)]
+struct Report<'a> {
+    #[serde(flatten)]
+    continuity: &'a ContinuityReport,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    time_skew: BTreeMap<PathBuf, TimeSkewReport>,
+}
+
+/// Expand `paths` to RDR files, where directories are expanded to the `.h5` files they
+/// contain. Files are sorted by path.
+fn rdr_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::default();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        for entry in std::fs::read_dir(path).with_context(|| format!("reading {path:?}"))? {
+            let fpath = entry?.path();
+            if fpath.is_file() && fpath.extension().is_some_and(|ext| ext == "h5") {
+                files.push(fpath);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Report the gaps and overlaps in the granule timeline of each collection across all the
+/// RDRs in `paths`, writing the report as JSON to `writer`.
+///
+/// If `time_skew` is true each file is also checked for packets outside of their granule's
+/// time window, see [TimeSkewReport], with the results included in the report by file path.
+/// Granule padding is from `config`, if provided, otherwise from the default config for each
+/// file's platform.
+///
+/// Files that cannot be read are skipped with a warning.
+pub fn report<W: Write>(
+    config: Option<&Config>,
+    paths: &[PathBuf],
+    time_skew: bool,
+    writer: W,
+) -> Result<ContinuityReport> {
+    let mut metas: Vec<(PathBuf, Meta)> = Vec::default();
+    for path in rdr_files(paths)? {
+        match Meta::from_file(&path) {
+            Ok(meta) => metas.push((path, meta)),
+            Err(err) => warn!("failed to read {path:?}; skipping: {err}"),
+        }
+    }
+    info!("read metadata from {} files", metas.len());
+
+    let mut skews = BTreeMap::default();
+    if time_skew {
+        for (path, meta) in &metas {
+            let default;
+            let config = match config {
+                Some(config) => Some(config),
+                None => {
+                    default = get_default_for_platform(&meta.platform)?;
+                    default.as_ref()
+                }
+            };
+            match TimeSkewReport::from_file(path, config) {
+                Ok(skew) => {
+                    if !skew.is_within_window() {
+                        for line in skew.to_string().lines() {
+                            warn!("time skew {path:?}: {line}");
+                        }
+                    }
+                    skews.insert(path.clone(), skew);
+                }
+                Err(err) => warn!("failed to check time skew for {path:?}; skipping: {err}"),
+            }
+        }
+    }
+
+    let report = ContinuityReport::new(metas.iter().map(|(p, m)| (p.as_path(), m)));
+    serde_json::to_writer_pretty(
+        writer,
+        &Report {
+            continuity: &report,
+            time_skew: skews,
+        },
+    )?;
+    Ok(report)
+}
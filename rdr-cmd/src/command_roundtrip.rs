@@ -0,0 +1,260 @@
+use anyhow::{Context, Result};
+use ccsds::spacepacket::{collect_groups, decode_packets, Apid};
+use hdf5::File;
+use rdr::{config::Config, open_packet_file, CommonRdr, Meta, PacketTimeIter};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info, warn};
+
+/// Maximum number of missing packets to log per collection.
+const MAX_MISSING_LOGGED: u64 = 10;
+
+/// Roundtrip results for a single collection.
+#[derive(Debug, Default)]
+pub struct CollectionRoundtrip {
+    pub granules: usize,
+    /// Number of packets in the RDR collection
+    pub rdr_packets: usize,
+    /// Number of input packets within the time range of a collection granule
+    pub covered: u64,
+    /// Number of covered input packets not found byte-identical in the collection
+    pub missing: u64,
+}
+
+/// Results of comparing the packets in an RDR against the packets it was created from.
+#[derive(Debug, Default)]
+pub struct RoundtripReport {
+    /// Collection short name to results
+    pub collections: BTreeMap<String, CollectionRoundtrip>,
+    /// Number of input packets read
+    pub input_packets: u64,
+}
+
+impl RoundtripReport {
+    /// Total number of covered input packets that were not found in the RDR.
+    #[must_use]
+    pub fn missing(&self) -> u64 {
+        self.collections.values().map(|c| c.missing).sum()
+    }
+}
+
+impl Display for RoundtripReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "input_packets={}", self.input_packets)?;
+        for (short_name, c) in &self.collections {
+            writeln!(
+                f,
+                "{short_name}: granules={} rdr_packets={} covered={} missing={}",
+                c.granules, c.rdr_packets, c.covered, c.missing
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// SHA-256 digest of a packet's bytes.
+type PacketDigest = [u8; 32];
+
+fn packet_digest(data: &[u8]) -> PacketDigest {
+    Sha256::digest(data).into()
+}
+
+/// Digests of the packets in a single RDR granule.
+///
+/// Only digests are kept so memory use does not scale with the size of the packets.
+struct GranulePackets {
+    /// Granule start boundary IET, inclusive
+    begin: u64,
+    /// Granule end boundary IET, exclusive
+    end: u64,
+    digests: HashSet<PacketDigest>,
+}
+
+/// Packets from an RDR collection, grouped by granule.
+struct CollectionPackets {
+    apids: HashSet<Apid>,
+    granules: Vec<GranulePackets>,
+}
+
+impl CollectionPackets {
+    /// The granule whose time range contains `iet`, if any.
+    fn granule(&self, iet: u64) -> Option<&GranulePackets> {
+        self.granules.iter().find(|g| g.begin <= iet && iet < g.end)
+    }
+
+    fn num_packets(&self) -> usize {
+        self.granules.iter().map(|g| g.digests.len()).sum()
+    }
+}
+
+/// Read packet digests for each `RawApplicationPackets` dataset for the collection.
+fn read_rdr_packets(file: &File, short_name: &str) -> Result<Vec<GranulePackets>> {
+    let group = file
+        .group(&format!("All_Data/{short_name}_All"))
+        .with_context(|| format!("opening All_Data group for {short_name}"))?;
+    let mut granules = Vec::default();
+    for name in group.member_names()? {
+        if !name.starts_with("RawApplicationPackets_") {
+            continue;
+        }
+        let data = group
+            .dataset(&name)
+            .and_then(|ds| ds.read_raw::<u8>())
+            .with_context(|| format!("reading {short_name}/{name}"))?;
+        let common = CommonRdr::from_bytes(&data)
+            .with_context(|| format!("decoding {short_name}/{name}"))?;
+        let storage = common.static_header.ap_storage_offset as usize;
+        let mut digests = HashSet::default();
+        for tracker in &common.packet_trackers {
+            // Negative offsets indicate fill for missing packets
+            let (Ok(offset), Ok(size)) = (
+                usize::try_from(tracker.offset),
+                usize::try_from(tracker.size),
+            ) else {
+                continue;
+            };
+            let Some(bytes) = data.get(storage + offset..storage + offset + size) else {
+                warn!(
+                    "{short_name}/{name}: packet tracker offset={offset} size={size} out of bounds"
+                );
+                continue;
+            };
+            digests.insert(packet_digest(bytes));
+        }
+        granules.push(GranulePackets {
+            begin: common.static_header.start_boundary,
+            end: common.static_header.end_boundary,
+            digests,
+        });
+    }
+    Ok(granules)
+}
+
+/// Verify that every packet in `inputs` within the time range of a granule in `rdr` is
+/// present, byte-identical, in that granule's collection.
+///
+/// Input packets are assigned to a collection using the product APIDs in `config` and to a
+/// granule using the granule begin and end times, so packets outside of the granules in the
+/// RDR are not checked. Each input packet must be found in the granule covering its time.
+///
+/// Only a digest of each RDR packet is kept in memory, and input packets are streamed.
+pub fn verify_roundtrip(
+    config: &Config,
+    inputs: &[PathBuf],
+    rdr: &Path,
+) -> Result<RoundtripReport> {
+    let meta = Meta::from_file(rdr).with_context(|| format!("reading {rdr:?}"))?;
+    let file = File::open(rdr).with_context(|| format!("opening {rdr:?}"))?;
+
+    let mut report = RoundtripReport::default();
+    let mut collections: BTreeMap<String, CollectionPackets> = BTreeMap::default();
+    for (short_name, granules) in &meta.granules {
+        if granules.is_empty() {
+            continue;
+        }
+        let Some(product) = config.products.iter().find(|p| p.short_name == *short_name) else {
+            warn!("{short_name}: no product config; skipping");
+            continue;
+        };
+        let packets = CollectionPackets {
+            apids: product.apids.iter().map(|a| a.num).collect(),
+            granules: read_rdr_packets(&file, short_name)?,
+        };
+        debug!("{short_name}: read {} packets", packets.num_packets());
+        report.collections.insert(
+            short_name.clone(),
+            CollectionRoundtrip {
+                granules: granules.len(),
+                rdr_packets: packets.num_packets(),
+                ..Default::default()
+            },
+        );
+        collections.insert(short_name.clone(), packets);
+    }
+
+    for input in inputs {
+        info!("checking packets from {input:?}");
+        let reader = open_packet_file(input).with_context(|| format!("opening {input:?}"))?;
+        let packets = decode_packets(reader).filter_map(Result::ok);
+        let groups = collect_groups(packets).filter_map(Result::ok);
+        for (pkt, time) in PacketTimeIter::new(groups).with_timecode(&config.satellite.timecode) {
+            report.input_packets += 1;
+            let iet = time.iet();
+            let mut digest = None;
+            for (short_name, collection) in &collections {
+                if !collection.apids.contains(&pkt.header.apid) {
+                    continue;
+                }
+                let Some(granule) = collection.granule(iet) else {
+                    continue;
+                };
+                let result = report
+                    .collections
+                    .get_mut(short_name)
+                    .expect("report has all collections");
+                result.covered += 1;
+                let digest = digest.get_or_insert_with(|| packet_digest(&pkt.data));
+                if !granule.digests.contains(digest) {
+                    result.missing += 1;
+                    if result.missing <= MAX_MISSING_LOGGED {
+                        warn!(
+                            "{short_name}: missing packet apid={} seq={} iet={iet} from {input:?}",
+                            pkt.header.apid, pkt.header.sequence_id
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rdr::{config::get_default, create_rdr, fixtures};
+
+    #[test]
+    fn test_verify_roundtrip() {
+        let config = get_default("npp").unwrap().unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let short_name = fixtures::product(&config).unwrap().short_name.clone();
+        let rdrs: Vec<_> = fixtures::viirs_granules(&config, 0, 2)
+            .unwrap()
+            .into_iter()
+            .map(|mut rdrs| rdrs.remove(0))
+            .collect();
+        let rdr = dir.path().join("rdr.h5");
+        let meta = Meta::from_products(&[short_name.clone()], &config).unwrap();
+        create_rdr(&rdr, meta, &rdrs).unwrap();
+
+        let mut data = Vec::default();
+        for num in 0..2 {
+            let time = fixtures::granule_time(&config, num).unwrap();
+            data.extend(fixtures::viirs_packets(&time, 5));
+        }
+        let input = dir.path().join("input.dat");
+        std::fs::write(&input, &data).unwrap();
+
+        let report = verify_roundtrip(&config, &[input.clone()], &rdr).unwrap();
+        assert_eq!(report.input_packets, 10);
+        let collection = &report.collections[&short_name];
+        assert_eq!(collection.granules, 2);
+        assert_eq!(collection.rdr_packets, 10);
+        assert_eq!(collection.covered, 10);
+        assert_eq!(report.missing(), 0);
+
+        // Change the filler of the last packet so it no longer matches
+        let last = data.len() - 1;
+        data[last] ^= 0xff;
+        std::fs::write(&input, &data).unwrap();
+        let report = verify_roundtrip(&config, &[input], &rdr).unwrap();
+        assert_eq!(report.collections[&short_name].covered, 10);
+        assert_eq!(report.missing(), 1);
+    }
+}
@@ -0,0 +1,127 @@
+use anyhow::{bail, Context, Result};
+use rdr::{config::Config, filename_with_orbit, GranuleMeta, Meta, Orbits};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+use crate::command_subset::{file_config, subset, subset_by, Selection};
+
+/// Split the multi-collection RDR `input` into a new RDR in `outdir` for each collection.
+///
+/// Granules and file attributes are preserved. Collections without granules are not written.
+/// Returns the paths of the new files. If `config` is not provided, the default config for the
+/// file's Platform_Short_Name is used.
+pub fn split(config: Option<&Config>, input: &Path, outdir: &Path) -> Result<Vec<PathBuf>> {
+    let meta = Meta::from_file(input).with_context(|| format!("reading {input:?}"))?;
+    let mut short_names: Vec<&String> = meta
+        .granules
+        .iter()
+        .filter(|(_, granules)| !granules.is_empty())
+        .map(|(short_name, _)| short_name)
+        .collect();
+    short_names.sort();
+    if short_names.is_empty() {
+        bail!("no granules in {input:?}");
+    }
+
+    let mut outputs = Vec::default();
+    for short_name in short_names {
+        let selection = Selection {
+            short_names: vec![short_name.clone()],
+            ..Default::default()
+        };
+        let fpath = subset(config, input, outdir, &selection)
+            .with_context(|| format!("splitting {short_name} from {input:?}"))?;
+        info!(collection = %short_name, path = ?fpath, "wrote {short_name} to {fpath:?}");
+        outputs.push(fpath);
+    }
+    Ok(outputs)
+}
+
+/// Split the RDR `input` into a new RDR in `outdir` for each orbit, using `orbits`, or the
+/// configured satellite orbits if not provided, for orbit boundaries.
+///
+/// Primary granules are assigned to the orbit containing their begin time. Packed granules,
+/// e.g., spacecraft diary, are included in every orbit with a primary granule they overlap, so
+/// each file has the packed data for its granules. Packed granules not overlapping any primary
+/// granule are assigned by begin time. File names include the orbit number. Returns the paths
+/// of the new files. If `config` is not provided, the default config for the file's
+/// Platform_Short_Name is used.
+pub fn split_orbits(
+    config: Option<&Config>,
+    input: &Path,
+    outdir: &Path,
+    orbits: Option<Orbits>,
+) -> Result<Vec<PathBuf>> {
+    let meta = Meta::from_file(input).with_context(|| format!("reading {input:?}"))?;
+    let config = file_config(config, &meta)?;
+    let Some(orbits) = orbits.or(config.satellite.orbits) else {
+        bail!(
+            "no orbit information for {}; provide a TLE",
+            config.satellite.id
+        );
+    };
+
+    let packed_ids: HashSet<&String> = config.rdrs.iter().flat_map(|r| &r.packed_with).collect();
+    let packed_short_names: HashSet<&String> = config
+        .products
+        .iter()
+        .filter(|p| packed_ids.contains(&p.product_id))
+        .map(|p| &p.short_name)
+        .collect();
+    let (packed, primary): (Vec<&GranuleMeta>, Vec<&GranuleMeta>) = meta
+        .granules
+        .values()
+        .flatten()
+        .partition(|g| packed_short_names.contains(&g.collection));
+    if packed.is_empty() && primary.is_empty() {
+        bail!("no granules in {input:?}");
+    }
+
+    // orbit number to the short name and granule id of its granules
+    let mut selected: BTreeMap<u64, HashSet<(String, String)>> = BTreeMap::default();
+    let key = |g: &GranuleMeta| (g.collection.clone(), g.id.clone());
+    for gran in &primary {
+        let orbit = orbits.orbit_number(gran.begin_time_iet);
+        selected.entry(orbit).or_default().insert(key(gran));
+    }
+    for gran in &packed {
+        let mut overlapped = false;
+        for prim in &primary {
+            if gran.is_packed_with(prim) {
+                let orbit = orbits.orbit_number(prim.begin_time_iet);
+                selected.entry(orbit).or_default().insert(key(gran));
+                overlapped = true;
+            }
+        }
+        if !overlapped {
+            if !primary.is_empty() {
+                warn!(
+                    collection = %gran.collection,
+                    granule_id = %gran.id,
+                    "packed granule does not overlap any primary granule"
+                );
+            }
+            let orbit = orbits.orbit_number(gran.begin_time_iet);
+            selected.entry(orbit).or_default().insert(key(gran));
+        }
+    }
+
+    let mut outputs = Vec::default();
+    for (orbit, granules) in selected {
+        let fpath = subset_by(Some(&config), input, outdir, |g| granules.contains(&key(g)))
+            .with_context(|| format!("splitting orbit {orbit} from {input:?}"))?;
+        let fname = fpath
+            .file_name()
+            .context("subset output has no file name")?
+            .to_string_lossy();
+        let dest = fpath.with_file_name(filename_with_orbit(&fname, orbit));
+        std::fs::rename(&fpath, &dest)
+            .with_context(|| format!("renaming {fpath:?} to {dest:?}"))?;
+        info!(orbit, path = ?dest, "wrote orbit {orbit} to {dest:?}");
+        outputs.push(dest);
+    }
+    Ok(outputs)
+}
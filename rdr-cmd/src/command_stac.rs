@@ -0,0 +1,39 @@
+use anyhow::{Context, Result};
+use rdr::{Meta, StacItem};
+use std::{io::Write, path::Path};
+
+/// Write STAC Items for each of `inputs` to `writer` as newline delimited JSON.
+///
+/// `href_prefix`, if provided, is joined with the input file name for the asset href, otherwise
+/// the input path is used.
+pub fn stac<W: Write>(
+    inputs: &[impl AsRef<Path>],
+    per_granule: bool,
+    href_prefix: Option<&str>,
+    mut writer: W,
+) -> Result<()> {
+    for input in inputs {
+        let input = input.as_ref();
+        let meta = Meta::from_file(input).with_context(|| format!("reading {input:?}"))?;
+        let name = input
+            .file_name()
+            .context("input has no file name")?
+            .to_string_lossy();
+        let href = match href_prefix {
+            Some(prefix) => format!("{}/{name}", prefix.trim_end_matches('/')),
+            None => input.to_string_lossy().to_string(),
+        };
+
+        let items = if per_granule {
+            StacItem::from_granules(&meta, &href)
+        } else {
+            let id = name.strip_suffix(".h5").unwrap_or(&name);
+            StacItem::from_file(id, &meta, &href).into_iter().collect()
+        };
+        for item in items {
+            serde_json::to_writer(&mut writer, &item)?;
+            writeln!(writer)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use anyhow::{bail, Context, Result};
+use rdr::{
+    config::{get_default_for_platform, Config},
+    GranuleMeta, Meta, Rdr, Time,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info};
+
+use crate::command_create::rdr_filename_meta;
+use crate::command_extract::granule_datasets;
+
+/// Granule selection criteria for [subset]. Empty criteria select everything.
+#[derive(Debug, Default)]
+pub struct Selection {
+    /// Collection short names to include
+    pub short_names: Vec<String>,
+    /// Granule ids to include
+    pub granule_ids: Vec<String>,
+    /// Include only granules that end after this time
+    pub start: Option<Time>,
+    /// Include only granules that begin before this time
+    pub end: Option<Time>,
+}
+
+impl Selection {
+    fn contains(&self, granule: &GranuleMeta) -> bool {
+        (self.short_names.is_empty() || self.short_names.contains(&granule.collection))
+            && (self.granule_ids.is_empty() || self.granule_ids.contains(&granule.id))
+            && self
+                .start
+                .as_ref()
+                .map_or(true, |t| granule.end_time_iet > t.iet_micros())
+            && self
+                .end
+                .as_ref()
+                .map_or(true, |t| granule.begin_time_iet < t.iet_micros())
+    }
+}
+
+/// `config`, if provided, otherwise the default config for the Platform_Short_Name in `meta`.
+pub fn file_config(config: Option<&Config>, meta: &Meta) -> Result<Config> {
+    if let Some(config) = config {
+        return Ok(config.clone());
+    }
+    get_default_for_platform(&meta.platform)?
+        .with_context(|| format!("no config for platform {}", meta.platform))
+}
+
+/// Copy the granules in `input` matching `selection` to a new RDR in `outdir`.
+///
+/// Granule indexes and `_Aggr` attributes are recomputed for the new file. Returns the path
+/// of the new file. If `config` is not provided, the default config for the file's
+/// Platform_Short_Name is used.
+pub fn subset(
+    config: Option<&Config>,
+    input: &Path,
+    outdir: &Path,
+    selection: &Selection,
+) -> Result<PathBuf> {
+    subset_by(config, input, outdir, |granule| selection.contains(granule))
+}
+
+/// Copy the granules in `input` for which `filter` returns true to a new RDR in `outdir`.
+///
+/// See [subset].
+pub fn subset_by<F>(
+    config: Option<&Config>,
+    input: &Path,
+    outdir: &Path,
+    filter: F,
+) -> Result<PathBuf>
+where
+    F: Fn(&GranuleMeta) -> bool,
+{
+    let meta = Meta::from_file(input).with_context(|| format!("reading {input:?}"))?;
+    let config = file_config(config, &meta)?;
+
+    // short name and granule id to granule metadata for selected granules
+    let mut selected: HashMap<(String, String), GranuleMeta> = HashMap::default();
+    for granule in meta.granules.values().flatten() {
+        if filter(granule) {
+            selected.insert(
+                (granule.collection.clone(), granule.id.clone()),
+                granule.clone(),
+            );
+        }
+    }
+    if selected.is_empty() {
+        bail!("no granules in {input:?} match selection");
+    }
+
+    let file = hdf5::File::open(input).with_context(|| format!("opening {input:?}"))?;
+    let mut rdrs: Vec<Rdr> = Vec::default();
+    for dataset in granule_datasets(&file, None)? {
+        let key = (dataset.short_name, dataset.granule_id);
+        let Some(granule) = selected.remove(&key) else {
+            continue;
+        };
+        let Some(product) = config
+            .products
+            .iter()
+            .find(|p| p.short_name == granule.collection)
+        else {
+            bail!("no product configured for {}", granule.collection);
+        };
+        debug!(collection = %key.0, granule_id = %key.1, "copying {}", dataset.path);
+        let data = file
+            .dataset(&dataset.path)
+            .and_then(|ds| ds.read_raw::<u8>())
+            .with_context(|| format!("reading {}", dataset.path))?;
+        rdrs.push(Rdr {
+            meta: granule,
+            product_id: product.product_id.clone(),
+            data,
+        });
+    }
+    if rdrs.is_empty() {
+        bail!("no granule data found in {input:?} for selection");
+    }
+
+    // Put the science granules first so file names use the primary product
+    rdrs.sort_by_key(|r| {
+        (
+            !r.meta.collection.contains("SCIENCE"),
+            r.meta.begin_time_iet,
+        )
+    });
+    let (start, end, product_ids) = rdr_filename_meta(&rdrs, config.time_from(&rdrs[0].product_id));
+    let fpath = outdir.join(rdr::filename(
+        &config.satellite.id,
+        &config.origin,
+        &config.mode,
+        &Time::now(),
+        &start,
+        &end,
+        &product_ids,
+    ));
+
+    let mut out_meta = meta;
+    out_meta.created = Time::now();
+    out_meta
+        .products
+        .retain(|short_name, _| rdrs.iter().any(|r| &r.meta.collection == short_name));
+    out_meta.granules.clear();
+
+    rdr::create_rdr(&fpath, out_meta, &rdrs).with_context(|| format!("writing {fpath:?}"))?;
+    info!(path = ?fpath, "wrote {} granules to {fpath:?}", rdrs.len());
+    Ok(fpath)
+}
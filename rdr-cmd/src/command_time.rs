@@ -0,0 +1,61 @@
+use anyhow::{anyhow, bail, Context, Result};
+use rdr::{config::Config, get_granule_start, Time};
+
+const UTC_FMT: &str = "%Y-%m-%dT%H:%M:%S.%fZ";
+
+/// Parse `value` as IET microseconds, a granule id for the configured satellite, or a UTC
+/// time string.
+fn parse_value(config: &Config, value: &str) -> Result<Time> {
+    let sat = &config.satellite;
+    if let Ok(iet) = value.parse::<u64>() {
+        return Ok(Time::from_iet(iet));
+    }
+    if value
+        .to_uppercase()
+        .starts_with(&sat.granule_id_prefix().to_uppercase())
+    {
+        let iet = sat.granule_id_to_iet(value)?;
+        return Ok(Time::from(iet));
+    }
+    value
+        .parse::<Time>()
+        .map_err(|e| anyhow!("{value:?} is not IET, a granule id, or a UTC time: {e}"))
+}
+
+/// Print the IET, UTC, and granule id for `value`, which may be any of IET microseconds, a
+/// granule id, or a UTC time.
+///
+/// If `product_id` is provided, the granule containing the time and its time range are also
+/// printed.
+pub fn time(config: &Config, product_id: Option<&str>, value: &str) -> Result<()> {
+    let sat = &config.satellite;
+    let time = parse_value(config, value)?;
+    let iet = time.iet_micros();
+    if iet < sat.base_time {
+        bail!("{value:?} is before the {} base time", sat.id);
+    }
+
+    println!("iet:           {iet}");
+    println!("utc:           {}", time.format_utc(UTC_FMT));
+    let Some(product_id) = product_id else {
+        println!("granule_id:    {}", sat.granule_id(iet)?);
+        return Ok(());
+    };
+
+    let product = config
+        .products
+        .iter()
+        .find(|p| p.product_id == product_id)
+        .with_context(|| format!("no product {product_id} configured for {}", sat.id))?;
+    let start = Time::from(get_granule_start(iet, product.gran_len, sat.base_time));
+    let end = Time::from(start.iet_micros() + product.gran_len);
+    println!("granule_id:    {}", sat.granule_id(start.iet_micros())?);
+    println!(
+        "granule_start: {} {}",
+        start.iet(),
+        start.format_utc(UTC_FMT)
+    );
+    println!("granule_end:   {} {}", end.iet(), end.format_utc(UTC_FMT));
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use anyhow::{bail, Context, Result};
+use rdr::{config::Config, verify_rdr, Meta, TimeSkewReport};
+use std::{collections::BTreeSet, path::Path};
+use tracing::{info, warn};
+
+/// Validate the RDR at `input`, failing if it is not a valid RDR, if primary granules lack
+/// packed granule coverage required by `config`, or if any granule has packets with times
+/// outside of its static header time window.
+pub fn validate(config: &Config, input: &Path) -> Result<()> {
+    let granules = verify_rdr(input).with_context(|| format!("verifying {input:?}"))?;
+    info!("verified {granules} granules in {input:?}");
+
+    let meta = Meta::from_file(input).with_context(|| format!("reading {input:?}"))?;
+    let coverage = meta.packed_coverage(config);
+    for line in coverage.to_string().lines() {
+        if coverage.is_complete() {
+            info!("packed coverage: {line}");
+        } else {
+            warn!("packed coverage: {line}");
+        }
+    }
+
+    let skew = TimeSkewReport::from_file(input, Some(config))
+        .with_context(|| format!("checking packet times in {input:?}"))?;
+    for line in skew.to_string().lines() {
+        if skew.is_within_window() {
+            info!("time skew: {line}");
+        } else {
+            warn!("time skew: {line}");
+        }
+    }
+    if !skew.is_within_window() {
+        let apids: BTreeSet<u32> = skew
+            .granules
+            .iter()
+            .flat_map(|g| g.apids.iter().map(|a| a.apid))
+            .collect();
+        bail!(
+            "{} granules have packets outside of their time window for APIDs {apids:?}",
+            skew.granules.len()
+        );
+    }
+    if !coverage.is_complete() {
+        bail!(
+            "{} primary granules lack packed coverage and {} packed granules are misaligned",
+            coverage.gaps.len(),
+            coverage.misaligned.len()
+        );
+    }
+    Ok(())
+}
@@ -1,21 +1,45 @@
 mod command_aggr;
+mod command_apids;
+mod command_attr;
+mod command_backfill;
+mod command_catmeta;
+mod command_completions;
 mod command_create;
 mod command_deaggr;
 mod command_dump;
 mod command_extract;
+mod command_hist;
 mod command_info;
+mod command_normalize;
+mod command_rename;
+mod command_replace;
+mod command_report;
+mod command_roundtrip;
+mod command_split;
+mod command_stac;
+mod command_subset;
+mod command_time;
+mod command_validate;
+mod manifest;
+mod staging;
+mod util;
 
 use anyhow::{bail, Context, Result};
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
 use std::{
     io::{stderr, stdout, Write},
     path::PathBuf,
+    time::Duration,
 };
 use tempfile::TempDir;
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
-use rdr::config::get_default_content;
+use rdr::{
+    config::get_default_content, BaseTimePolicy, BlobWriter, DuplicatePolicy, EmptyCollections,
+    FileOptions, Hdf5Writer, LibverBounds, OrphanPolicy, RawDatasetDetail, RdrWriter, StorageOrder,
+    Time, TimeBounds,
+};
 
 fn version() -> &'static str {
     concat!(
@@ -44,10 +68,236 @@ struct Cli {
     #[arg(short, long, default_value = "info")]
     logging: String,
 
+    /// Log output format. The json format includes structured fields, such as collection,
+    /// granule id, and file path, suitable for log aggregation.
+    #[arg(long, value_name = "format", default_value = "text")]
+    log_format: LogFormat,
+
+    /// Serve Prometheus metrics for packet collection and file writing at this address,
+    /// e.g., 0.0.0.0:9000, for the duration of the command.
+    #[cfg(feature = "prometheus")]
+    #[arg(long, value_name = "addr")]
+    metrics_addr: Option<std::net::SocketAddr>,
+
     #[command(subcommand)]
     commands: Commands,
 }
 
+/// Output format for created RDRs.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum OutputFormat {
+    /// JPSS HDF5 RDR files
+    H5,
+    /// A directory per RDR containing raw Common RDR granule data and JSON metadata
+    Blob,
+}
+
+/// Handling of duplicate packets, from any input, within a granule.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Duplicates {
+    /// Keep all packets, including duplicates
+    Keep,
+    /// Drop duplicates, keeping the first packet seen
+    KeepFirst,
+    /// Replace previously seen packets with their duplicate, unless their granule is
+    /// already complete, in which case the duplicate is dropped
+    KeepLast,
+}
+
+impl From<Duplicates> for DuplicatePolicy {
+    fn from(value: Duplicates) -> Self {
+        match value {
+            Duplicates::Keep => DuplicatePolicy::Keep,
+            Duplicates::KeepFirst => DuplicatePolicy::KeepFirst,
+            Duplicates::KeepLast => DuplicatePolicy::KeepLast,
+        }
+    }
+}
+
+/// Handling of multiple versions of the same granule when aggregating.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Versions {
+    /// Aggregate all versions
+    Keep,
+    /// Fail if any granule has multiple versions
+    Error,
+    /// Aggregate only the version with the latest creation date/time
+    Latest,
+}
+
+impl From<Versions> for crate::command_aggr::VersionPolicy {
+    fn from(value: Versions) -> Self {
+        match value {
+            Versions::Keep => Self::Keep,
+            Versions::Error => Self::Error,
+            Versions::Latest => Self::Latest,
+        }
+    }
+}
+
+/// Handling of time gaps between aggregated granules.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ContiguityArg {
+    /// Fail without writing the output
+    Error,
+    /// Log the gaps and list them in the report
+    Warn,
+}
+
+impl From<ContiguityArg> for crate::command_aggr::Contiguity {
+    fn from(value: ContiguityArg) -> Self {
+        match value {
+            ContiguityArg::Error => Self::Error,
+            ContiguityArg::Warn => Self::Warn,
+        }
+    }
+}
+
+/// Order of packets in the granule application packet storage.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum StorageOrderArg {
+    /// The order packets were received
+    Arrival,
+    /// Packet time order
+    Time,
+    /// APID order, then packet time order within each APID
+    ApidThenTime,
+}
+
+impl From<StorageOrderArg> for StorageOrder {
+    fn from(value: StorageOrderArg) -> Self {
+        match value {
+            StorageOrderArg::Arrival => StorageOrder::Arrival,
+            StorageOrderArg::Time => StorageOrder::Time,
+            StorageOrderArg::ApidThenTime => StorageOrder::ApidThenTime,
+        }
+    }
+}
+
+/// Handling of packed granules, e.g., diary, not packed with any primary granule.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Orphans {
+    /// Drop with a log entry
+    Drop,
+    /// Write each as its own file
+    Standalone,
+    /// Pack with the primary granule nearest in time
+    Nearest,
+}
+
+impl From<Orphans> for OrphanPolicy {
+    fn from(value: Orphans) -> Self {
+        match value {
+            Orphans::Drop => OrphanPolicy::Drop,
+            Orphans::Standalone => OrphanPolicy::Standalone,
+            Orphans::Nearest => OrphanPolicy::Nearest,
+        }
+    }
+}
+
+/// Handling of packets with times before the spacecraft base time.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum BeforeBaseTime {
+    /// Drop, counting them in the summary
+    Skip,
+    /// Add to the first granule as if at the base time
+    Clamp,
+    /// Abort on the first such packet
+    Fail,
+}
+
+impl From<BeforeBaseTime> for BaseTimePolicy {
+    fn from(value: BeforeBaseTime) -> Self {
+        match value {
+            BeforeBaseTime::Skip => BaseTimePolicy::Skip,
+            BeforeBaseTime::Clamp => BaseTimePolicy::Clamp,
+            BeforeBaseTime::Fail => BaseTimePolicy::Fail,
+        }
+    }
+}
+
+/// Handling of granules with fewer packets than the configured minimum.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Undersized {
+    /// Write to a `quarantine` directory in the output directory
+    Quarantine,
+    /// Do not write
+    Skip,
+}
+
+impl From<Undersized> for crate::command_create::UndersizedGranules {
+    fn from(value: Undersized) -> Self {
+        match value {
+            Undersized::Quarantine => Self::Quarantine,
+            Undersized::Skip => Self::Skip,
+        }
+    }
+}
+
+/// HDF5 library version bounds for created files.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Libver {
+    /// Earliest possible object formats; the HDF5 default
+    Earliest,
+    /// HDF5 1.8 object formats, for readers linked against HDF5 1.8
+    V18,
+    /// HDF5 1.10 object formats
+    V110,
+    /// Latest object formats supported by the linked HDF5 library
+    Latest,
+}
+
+impl From<Libver> for LibverBounds {
+    fn from(value: Libver) -> Self {
+        match value {
+            Libver::Earliest => LibverBounds::Earliest,
+            Libver::V18 => LibverBounds::V18,
+            Libver::V110 => LibverBounds::V110,
+            Libver::Latest => LibverBounds::Latest,
+        }
+    }
+}
+
+/// Output format for cat-meta.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum CatMetaFormat {
+    /// Newline delimited JSON
+    Json,
+    Csv,
+}
+
+impl From<CatMetaFormat> for crate::command_catmeta::CatFormat {
+    fn from(value: CatMetaFormat) -> Self {
+        match value {
+            CatMetaFormat::Json => crate::command_catmeta::CatFormat::Json,
+            CatMetaFormat::Csv => crate::command_catmeta::CatFormat::Csv,
+        }
+    }
+}
+
+/// Output format for hist.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum HistOutputFormat {
+    /// Newline delimited JSON
+    Json,
+    Csv,
+}
+
+impl From<HistOutputFormat> for crate::command_hist::HistFormat {
+    fn from(value: HistOutputFormat) -> Self {
+        match value {
+            HistOutputFormat::Json => crate::command_hist::HistFormat::Json,
+            HistOutputFormat::Csv => crate::command_hist::HistFormat::Csv,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
 fn parse_valid_satellite(sat: &str) -> Result<String, String> {
     let valid_satellites = ["npp", "j01", "j02", "j03"];
     if valid_satellites.contains(&sat) {
@@ -57,17 +307,158 @@ fn parse_valid_satellite(sat: &str) -> Result<String, String> {
     }
 }
 
+fn parse_granule_version(s: &str) -> Result<String, String> {
+    rdr::validate_granule_version(s)
+        .map(|()| s.to_string())
+        .map_err(|_| "expected an uppercase letter followed by a number 1-9, e.g., A1".to_string())
+}
+
+fn parse_time(s: &str) -> Result<Time, String> {
+    s.parse::<Time>().map_err(|e| format!("invalid time: {e}"))
+}
+
+fn parse_created(s: &str) -> Result<Time, String> {
+    if s == "now" {
+        return Ok(Time::now());
+    }
+    parse_time(s)
+}
+
+/// Packet time sanity bounds. Packets with times outside these bounds, e.g., due to a corrupt
+/// timecode, are quarantined and counted rather than added to a granule.
+#[derive(Args)]
+struct TimeBoundsArgs {
+    /// Minimum acceptable packet time, e.g., 2024-01-01T00:00:00Z
+    #[arg(long, value_name = "time", value_parser=parse_time)]
+    min_packet_time: Option<Time>,
+
+    /// Maximum acceptable packet time, e.g., 2024-01-02T00:00:00Z
+    #[arg(long, value_name = "time", value_parser=parse_time)]
+    max_packet_time: Option<Time>,
+
+    /// Maximum acceptable difference in seconds between a packet time and the time of the
+    /// previously accepted packet.
+    #[arg(long, value_name = "seconds")]
+    max_packet_delta: Option<u64>,
+
+    /// Number of consecutive packets that must agree on a time outside of
+    /// --max-packet-delta before it is accepted as following a data gap. 0 never accepts it.
+    #[arg(long, value_name = "count", default_value_t = TimeBounds::DEFAULT_REANCHOR_AFTER)]
+    packet_reanchor_after: usize,
+}
+
+impl From<TimeBoundsArgs> for TimeBounds {
+    fn from(args: TimeBoundsArgs) -> Self {
+        TimeBounds {
+            min: args.min_packet_time,
+            max: args.max_packet_time,
+            max_delta: args.max_packet_delta.map(|secs| secs * 1_000_000),
+            reanchor_after: args.packet_reanchor_after,
+        }
+    }
+}
+
+/// HDF5 file creation properties. Only apply to the h5 format.
+#[derive(Args)]
+struct FileOptionsArgs {
+    /// Reserve a userblock of this many bytes at the start of the file; 0 or a power of 2 of
+    /// at least 512.
+    #[arg(long, value_name = "bytes")]
+    userblock: Option<u64>,
+
+    /// Raw data chunk cache size in bytes.
+    #[arg(long, value_name = "bytes")]
+    chunk_cache: Option<usize>,
+
+    /// Restrict the HDF5 object formats used, e.g., v18 to keep files readable by software
+    /// linked against HDF5 1.8.
+    #[arg(long, value_name = "version")]
+    libver: Option<Libver>,
+
+    /// Build files with at most this many bytes of granule data in memory and write each to
+    /// disk in a single write, reducing filesystem overhead for many small files.
+    #[arg(long, value_name = "bytes")]
+    memory_image: Option<usize>,
+}
+
+impl From<FileOptionsArgs> for FileOptions {
+    fn from(args: FileOptionsArgs) -> Self {
+        let mut opts = FileOptions::default();
+        if let Some(size) = args.userblock {
+            opts = opts.with_userblock(size);
+        }
+        if let Some(nbytes) = args.chunk_cache {
+            // HDF5 default number of slots and preemption policy
+            opts = opts.with_chunk_cache(521, nbytes, 0.75);
+        }
+        if let Some(libver) = args.libver {
+            opts = opts.with_libver_bounds(libver.into());
+        }
+        if let Some(max_size) = args.memory_image {
+            opts = opts.with_memory_image(max_size);
+        }
+        opts
+    }
+}
+
+#[derive(Subcommand)]
+enum AttrCommands {
+    /// Print attribute values selected by an object path and attribute name.
+    ///
+    /// Selectors are an object path, `@`, and an attribute name, e.g.,
+    /// `/Data_Products/VIIRS-SCIENCE-RDR@N_Collection_Short_Name`, or just a path whose last
+    /// component is the attribute name, e.g., `/Mission_Name`. Path components and the name may
+    /// contain `*` and `?` wildcards, e.g., `/Data_Products/*/*_Gran_*@N_Granule_ID`.
+    ///
+    /// A single attribute prints its value as JSON typed by the attribute datatype. Multiple
+    /// attributes, or any selector with wildcards, print a JSON object of `<path>@<name>` to
+    /// value.
+    Get {
+        /// RDR file
+        #[arg(value_name = "file")]
+        input: PathBuf,
+        /// Attribute selector
+        #[arg(value_name = "path")]
+        selector: String,
+        /// Print a single attribute's string values without JSON quoting, one per line.
+        #[arg(long)]
+        raw: bool,
+    },
+    /// List the attributes of a group or dataset, one per line with tab separated name, type,
+    /// shape, and JSON value.
+    Ls {
+        /// RDR file
+        #[arg(value_name = "file")]
+        input: PathBuf,
+        /// Group or dataset path
+        #[arg(value_name = "group", default_value = "/")]
+        path: String,
+    },
+}
+
 #[derive(Args)]
-#[group(multiple = false, required = true)]
 struct Configs {
     /// Use the built-in default configuration for this satellite id; one of npp, j01, j02, or j03.
-    #[arg(short, long, value_name = "name", value_parser=parse_valid_satellite)]
+    #[arg(
+        short,
+        long,
+        value_name = "name",
+        value_parser = parse_valid_satellite,
+        required_unless_present = "config",
+        conflicts_with = "config"
+    )]
     satellite: Option<String>,
 
     /// YAML decode configuration file to use, rather than a embeded default config. See the
     /// config subcommand to view embeded configuration.
     #[arg(short, long, value_name = "path")]
     config: Option<PathBuf>,
+
+    /// Partial YAML configuration applied on top of the satellite default or config file,
+    /// e.g., to change a single product's gran_len or add an APID. Products, RDRs, and APIDs
+    /// are matched by product_id, product, and num respectively.
+    #[arg(long, value_name = "path")]
+    config_override: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -82,34 +473,385 @@ enum Commands {
         #[command(flatten)]
         configs: Configs,
 
-        /// Output directory.
+        /// Output directory, or an S3 URL prefix, e.g., s3://bucket/prefix, if built with the
+        /// s3 feature. Outputs for S3 are written to a local temporary directory and uploaded
+        /// once all files are created.
         #[arg(short, long, value_name = "path", default_value = "output")]
         output: PathBuf,
 
+        /// Write files to subdirectories of the output directory using this layout, creating
+        /// them as needed, e.g., `{satid}/{short_name}/{yyyymmdd}`. Supports the {satid},
+        /// {short_name}, {product_id}, {yyyy}, {mm}, {dd}, {yyyymmdd}, and {doy} placeholders,
+        /// with dates from the file start time. Overrides the config `output_layout`.
+        #[arg(long, value_name = "template")]
+        output_layout: Option<String>,
+
+        /// Spill granule packet data to temporary files in this directory rather than keeping
+        /// it in memory. Useful for large backfills on machines with limited memory.
+        #[arg(long, value_name = "path")]
+        spill_dir: Option<PathBuf>,
+
+        /// Output format.
+        #[arg(long, value_name = "format", default_value = "h5")]
+        format: OutputFormat,
+
+        #[command(flatten)]
+        time_bounds: TimeBoundsArgs,
+
+        #[command(flatten)]
+        file_options: FileOptionsArgs,
+
+        /// Seconds to keep packed granules, e.g., SPACECRAFT-DIARY, after they can no longer
+        /// be packed with the oldest incomplete primary granule. Bounds memory when creating
+        /// from long streams; increase for inputs with out of order primary data.
+        #[arg(long, value_name = "seconds", default_value_t = 300)]
+        packed_retention: u64,
+
+        /// Order of packets in each granule's application packet storage, for consumers that
+        /// require a particular layout.
+        #[arg(long, value_name = "order", default_value = "arrival")]
+        storage_order: StorageOrderArg,
+
+        /// How to handle packed granules, e.g., SPACECRAFT-DIARY, that are not packed with any
+        /// primary granule, such as diary granules at the edges of a pass without science data.
+        #[arg(long, value_name = "policy", default_value = "drop")]
+        orphans: Orphans,
+
+        /// How to handle packets with times before the spacecraft base time, which cannot be
+        /// in any granule, e.g., vendor test data with epoch-zero timestamps.
+        #[arg(long, value_name = "policy", default_value = "skip")]
+        before_base_time: BeforeBaseTime,
+
+        /// Write packed products that have no granules overlapping the primary granule as empty
+        /// collections, with an aggregate granule count of 0, rather than omitting them. Only
+        /// applies to the h5 format.
+        #[arg(long)]
+        write_empty_collections: bool,
+
+        /// Write each file with a `.inprogress` suffix and rename it once it is completely
+        /// written, so pollers that ignore `.inprogress` files never open a partial file. Only
+        /// applies to the h5 format.
+        #[arg(long)]
+        rename_on_complete: bool,
+
+        /// How to handle duplicate packets, i.e., packets with the same APID, sequence id, and
+        /// time, such as from replayed downlinks or the same pass received at multiple
+        /// stations and provided as separate inputs.
+        #[arg(long, value_name = "policy", default_value = "keep-first")]
+        duplicates: Duplicates,
+
+        /// How to handle granules with fewer packets than the product's configured
+        /// `min_packets`, e.g., nearly-empty granules at the edges of a pass.
+        #[arg(long, value_name = "action", default_value = "quarantine")]
+        undersized: Undersized,
+
+        /// N_Granule_Version for all granules, an uppercase letter followed by a number 1-9,
+        /// e.g., A2.
+        #[arg(long, value_name = "version", default_value = "A1", value_parser=parse_granule_version)]
+        granule_version: String,
+
+        /// Bump the version of granules that already exist in the output directory, e.g., A1 to
+        /// A2, following IDPS conventions, rather than writing another granule with the same
+        /// version. Existing files in the output directory are read to find granule versions.
+        #[arg(long)]
+        bump_versions: bool,
+
+        /// Process each input independently, e.g., a file per pass, rather than merging all
+        /// inputs. Outputs for each input are written to a subdirectory of the output directory
+        /// named for the input file, and a summary is logged for each input. Remaining inputs
+        /// are still processed if one fails.
+        #[arg(long)]
+        batch: bool,
+
+        /// Write the granules of this many primary granules, with their packed granules, to
+        /// each file rather than a file per primary granule. Files have aggregate attributes
+        /// for the granules they contain.
+        #[arg(long, value_name = "n", value_parser = clap::value_parser!(u64).range(1..))]
+        rotate_granules: Option<u64>,
+
+        /// Start a new file when a primary granule begins in a different UTC time window of
+        /// this many seconds, e.g., 3600 for hourly files. May be combined with
+        /// --rotate-granules. Files are written once complete, i.e., when the next window
+        /// starts or all input is processed.
+        #[arg(long, value_name = "seconds", value_parser = clap::value_parser!(u64).range(1..))]
+        rotate_window: Option<u64>,
+
+        /// Abort if the times of more than this many packet groups cannot be decoded, e.g.,
+        /// because the input is not packet data or uses a different timecode. Groups with
+        /// undecodable times are skipped with a warning; by default there is no limit.
+        #[arg(long, value_name = "n")]
+        max_time_errors: Option<usize>,
+
+        /// Start of the expected time range of the input data, e.g., the start of a pass, such
+        /// as 2024-01-01T00:00:00Z. Inputs with no packets between --pass-start and --pass-end
+        /// are rejected before processing, e.g., so a wrong file does not produce granules
+        /// from another day.
+        #[arg(long, value_name = "time", value_parser=parse_time, requires = "pass_end")]
+        pass_start: Option<Time>,
+
+        /// End of the expected time range of the input data. See --pass-start.
+        #[arg(long, value_name = "time", value_parser=parse_time, requires = "pass_start")]
+        pass_end: Option<Time>,
+
+        /// Write run-manifest.json to the output directory listing the files written with
+        /// their collections, granule ids, time ranges, sizes, and SHA-256 checksums. With
+        /// --batch a manifest is written to each input's output directory. A manifest of the
+        /// files written before an abort, e.g., see --max-time-errors, is always written.
+        #[arg(long, conflicts_with = "dry_run")]
+        manifest: bool,
+
+        /// Re-open and verify each file after it is written, i.e., check attributes, Common
+        /// RDR headers and packet trackers, and that datasets are readable. Files failing
+        /// verification are removed. Only supported for the h5 format.
+        #[arg(long)]
+        verify: bool,
+
+        /// Collect granules and print the files and granules, with time ranges and packet
+        /// counts, that would be written, without writing anything.
+        #[arg(long, conflicts_with = "verify")]
+        dry_run: bool,
+
         /// One or more packet data file.
         ///
         /// The input will be merged before processing and need not be in any particular order.
+        /// Multiple inputs are checked before merging, with a warning for inputs that appear
+        /// to be duplicates or that are far apart in time.
+        /// Gzip (.gz) compressed inputs are decompressed transparently, as are zstd (.zst)
+        /// compressed inputs if built with the zstd feature. Inputs may be S3 URLs, e.g.,
+        /// s3://bucket/key, if built with the s3 feature, which are downloaded to a local
+        /// temporary directory before processing.
         #[arg(value_name = "path")]
         input: Vec<PathBuf>,
     },
     /// Dump raw spacepacket data to Level-0 PDS files.
     ///
-    /// Level-0 PDS files will follow the NASA Level-0 naming conventions.
+    /// Level-0 PDS files will follow the NASA Level-0 naming conventions. All RDR collections
+    /// in the file are dumped unless filtered using --include or --exclude.
     Dump {
+        /// Use the built-in default configuration for this satellite id rather than the default
+        /// for the file's Platform_Short_Name.
+        #[arg(
+            short,
+            long,
+            value_name = "name",
+            value_parser = parse_valid_satellite,
+            conflicts_with = "config"
+        )]
+        satellite: Option<String>,
+        /// YAML decode configuration file to use rather than the default for the file's
+        /// Platform_Short_Name.
+        #[arg(short, long, value_name = "path")]
+        config: Option<PathBuf>,
         /// RDR file to dump
         #[arg(value_name = "path")]
         input: PathBuf,
+        /// Write a PDS file for each granule, rather than for all granules, with the granule
+        /// start and end times encoded in the file name.
+        #[arg(long)]
+        per_granule: bool,
+        /// N_Granule_ID of a granule to dump. May be repeated. Spacecraft diary granules
+        /// overlapping the selected granules are also dumped.
+        #[arg(short, long = "granule-id", value_name = "id")]
+        granule_ids: Vec<String>,
+        /// Dump only granules ending after this time, e.g., 2024-01-01T00:00:00Z
+        #[arg(long, value_name = "time", value_parser=parse_time)]
+        start: Option<Time>,
+        /// Dump only granules beginning before this time, e.g., 2024-01-01T00:00:00Z
+        #[arg(long, value_name = "time", value_parser=parse_time)]
+        end: Option<Time>,
+        /// Read files with missing or incomplete Data_Products entries, reconstructing granule
+        /// metadata from the Common RDR data in All_Data.
+        #[arg(long)]
+        lenient: bool,
+        /// Dump only this collection, e.g., VIIRS-SCIENCE-RDR. May be repeated.
+        #[arg(long, value_name = "short_name")]
+        include: Vec<String>,
+        /// Do not dump this collection, e.g., SPACECRAFT-DIARY-RDR. May be repeated.
+        #[arg(long, value_name = "short_name")]
+        exclude: Vec<String>,
+        /// Also write a NASA construction record, i.e., the PDS file with sequence number 000,
+        /// for each PDS file with packet counts, sequence gaps, and first and last packet times.
+        #[arg(long)]
+        construction_records: bool,
     },
     /// Aggregate multiple RDRs into a single aggregated RDR.
     Aggr {
-        /// One or more RDR file to include in the output. At least one RDR is required.
+        /// One or more RDR file to include in the output. At least one RDR is required. Inputs
+        /// may be S3 URLs, e.g., s3://bucket/key, if built with the s3 feature.
         #[arg(value_name = "paths")]
         inputs: Vec<PathBuf>,
+        /// Output directory, or an S3 URL prefix, e.g., s3://bucket/prefix, if built with the
+        /// s3 feature.
+        #[arg(short, long, value_name = "path", default_value = ".")]
+        output: PathBuf,
         /// Persistent working directory.
         ///
         /// If not specified a temporary directory is used that will be deleted before exit.
+        /// With --extract, extracted granules are recorded in the working directory so that
+        /// re-running with the same working directory resumes rather than re-extracting.
         #[arg(short, long)]
         workdir: Option<PathBuf>,
+        /// Extract granules to the working directory before aggregating rather than copying
+        /// granule data directly from the input files.
+        #[arg(long)]
+        extract: bool,
+        /// How to handle multiple versions of the same granule, i.e., the same granule id with
+        /// different creation times, such as from reprocessing. Superseded granules are listed
+        /// in the report.
+        #[arg(long, value_name = "policy", default_value = "keep")]
+        versions: Versions,
+        /// Re-open and verify the output after it is written, failing if it is invalid.
+        #[arg(long)]
+        verify: bool,
+        /// Read inputs and print the file and granules that would be written, without writing.
+        #[arg(long, conflicts_with = "verify")]
+        dry_run: bool,
+        /// Read files with missing or incomplete Data_Products entries, reconstructing granule
+        /// metadata from the Common RDR data in All_Data.
+        #[arg(long)]
+        lenient: bool,
+        /// Write run-manifest.json to the output directory listing the output file with its
+        /// collections, granule ids, time ranges, size, and SHA-256.
+        #[arg(long, conflicts_with = "dry_run")]
+        manifest: bool,
+        /// Set each granule's N_Software_Version to this software's version, recording the
+        /// version that produced the granule in its N_Source_Software_Version attribute.
+        /// Granules already re-stamped keep their original source version.
+        #[arg(long)]
+        restamp_software: bool,
+        /// Check that the granules of each collection are contiguous in time, e.g., for
+        /// deliveries that must not have gaps, reporting the ids of any missing granules. With
+        /// `error`, the default if no value is given, aggregation fails without writing the
+        /// output if there are gaps; with `warn` gaps are logged and listed in the report.
+        #[arg(
+            long,
+            value_name = "action",
+            num_args = 0..=1,
+            default_missing_value = "error"
+        )]
+        require_contiguous: Option<ContiguityArg>,
+    },
+    /// Report the APIDs in a spacepacket/level-0 file.
+    ///
+    /// Lists packet counts, first and last packet times, and the configured product, if any,
+    /// for each APID. Useful for determining why `create` did not produce the expected output.
+    Apids {
+        #[command(flatten)]
+        configs: Configs,
+
+        /// Spacepacket/level-0 file. Gzip and zstd compressed inputs are supported as for
+        /// `create`.
+        #[arg(value_name = "path")]
+        input: PathBuf,
+    },
+    /// Report per-APID packet and byte counts in time bins, e.g., to plot data rates and
+    /// find dropouts around a pass.
+    ///
+    /// Bins are aligned to UTC and only bins with packets are output. There is a record for
+    /// each APID in each output bin, including APIDs with no packets in that bin.
+    Hist {
+        #[command(flatten)]
+        configs: Configs,
+
+        /// RDR (.h5) or spacepacket/level-0 file. Gzip and zstd compressed level-0 inputs are
+        /// supported as for `create`.
+        #[arg(value_name = "path")]
+        input: PathBuf,
+        /// Bin size in seconds
+        #[arg(short, long, value_name = "seconds", default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+        bin: u64,
+        #[arg(short, long, value_name = "format", default_value = "csv")]
+        format: HistOutputFormat,
+        /// Output file. Output is written to stdout if not provided.
+        #[arg(short, long, value_name = "path")]
+        output: Option<PathBuf>,
+    },
+    /// Insert granules missing from an aggregated RDR, in place, e.g., when a late granule
+    /// arrives, rather than re-aggregating.
+    ///
+    /// Granules are taken from RDR (.h5) or level-0 inputs. Only granules for collections in
+    /// the aggregate that overlap its time range, and that are not already in it by granule
+    /// id, are inserted. Granule datasets are renumbered into time order and the aggregate
+    /// attributes updated. Aggregates with IDPS style names are renamed to reflect their new
+    /// time range.
+    Backfill {
+        #[command(flatten)]
+        configs: Configs,
+        /// Aggregated RDR to insert granules into
+        #[arg(value_name = "aggregate")]
+        target: PathBuf,
+        /// RDR or level-0 files containing the granules to insert
+        #[arg(value_name = "paths", required = true)]
+        inputs: Vec<PathBuf>,
+        /// Print the granules that would be inserted without modifying the aggregate.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Replace a granule in an RDR, in place, e.g., to repair a single bad granule without
+    /// regenerating the file.
+    ///
+    /// The replacement is the granule with the same granule id taken from an RDR (.h5) or
+    /// level-0 input. The granule keeps its dataset index, the aggregate attributes are
+    /// updated, and all other content is unchanged.
+    Replace {
+        #[command(flatten)]
+        configs: Configs,
+        /// RDR containing the granule to replace
+        #[arg(value_name = "target")]
+        target: PathBuf,
+        /// RDR or level-0 file containing the replacement granule
+        #[arg(value_name = "path")]
+        input: PathBuf,
+        /// N_Granule_ID of the granule to replace
+        #[arg(short, long, value_name = "id")]
+        granule_id: String,
+        /// Only replace the granule for this collection, e.g., VIIRS-SCIENCE-RDR, rather than
+        /// every collection with a granule with the granule id.
+        #[arg(long, value_name = "short_name")]
+        short_name: Option<String>,
+    },
+    /// Rename RDRs, in place, to the canonical IDPS filename generated from their metadata,
+    /// e.g., to fix files with incorrect times or product ids in their names.
+    ///
+    /// Unless provided, the created, origin, and mode fields are kept from the current filename
+    /// if it is an IDPS style filename.
+    Rename {
+        /// Use the built-in default configuration for this satellite id rather than the default
+        /// for each file's Platform_Short_Name.
+        #[arg(
+            short,
+            long,
+            value_name = "name",
+            value_parser = parse_valid_satellite,
+            conflicts_with = "config"
+        )]
+        satellite: Option<String>,
+        /// YAML decode configuration file to use rather than the default for each file's
+        /// Platform_Short_Name.
+        #[arg(short, long, value_name = "path")]
+        config: Option<PathBuf>,
+        /// One or more RDR files to rename
+        #[arg(value_name = "paths", required = true)]
+        inputs: Vec<PathBuf>,
+        /// Origin to use for the origin field; only the first 3 characters are used.
+        #[arg(long, value_name = "origin")]
+        origin: Option<String>,
+        /// IDPS mode to use for the mode field, e.g., ops or dev.
+        #[arg(long, value_name = "mode")]
+        mode: Option<String>,
+        /// Creation time to use for the created field, e.g., 2024-01-01T00:00:00Z, or now.
+        #[arg(long, value_name = "time", value_parser = parse_created)]
+        created: Option<Time>,
+        /// Print the current and canonical filenames rather than renaming.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Renumber the granules of each collection in an RDR, in place, so granule dataset
+    /// indexes are in time order.
+    Normalize {
+        /// RDR file to normalize
+        #[arg(value_name = "path")]
+        input: PathBuf,
     },
     /// Deaggregate an aggregated RDR.
     ///
@@ -121,13 +863,81 @@ enum Commands {
         #[arg(value_name = "path")]
         input: PathBuf,
     },
+    /// Validate an RDR's structure, packed granule coverage, and packet times.
+    ///
+    /// Checks that each granule is a valid Common RDR, that primary granules, e.g., science,
+    /// have all packed granules, e.g., SPACECRAFT-DIARY, required by the configured packing
+    /// rules, and that all packet tracker observation times are within the granule's static
+    /// header start and end boundaries, widened by any configured padding. Exits with an error
+    /// if the RDR is invalid, coverage is incomplete, or packets are outside their window.
+    Validate {
+        #[command(flatten)]
+        configs: Configs,
+        /// RDR file to validate
+        #[arg(value_name = "path")]
+        input: PathBuf,
+    },
+    /// Verify an RDR contains every packet from the level-0 inputs it was created from.
+    ///
+    /// Packets from the RDR are compared to input packets within the time range of the RDR
+    /// granules for the collection with the packet's APID. Each such input packet must be
+    /// present and byte-identical in the RDR. Exits with an error if any are missing.
+    VerifyRoundtrip {
+        #[command(flatten)]
+        configs: Configs,
+        /// RDR created from the inputs
+        #[arg(short, long, value_name = "path")]
+        rdr: PathBuf,
+        /// One or more packet data file the RDR was created from.
+        #[arg(value_name = "path", required = true)]
+        inputs: Vec<PathBuf>,
+    },
+    /// Convert between IET microseconds, UTC times, and granule ids.
+    ///
+    /// The value may be IET microseconds, e.g., 2112504394000000, a granule id, e.g.,
+    /// NPP004144851600, or a UTC time, e.g., 2024-01-01T00:00:00Z.
+    Time {
+        #[command(flatten)]
+        configs: Configs,
+        /// Product id, e.g., RVIRS, used to determine the granule containing the time
+        #[arg(short, long, value_name = "id")]
+        product: Option<String>,
+        /// Value to convert
+        #[arg(value_name = "value")]
+        value: String,
+    },
     /// Output the default configuration.
+    ///
+    /// With --effective, output the configuration actually used for a satellite or config
+    /// file and any override, after merging and validation and including defaulted values.
     Config {
         /// Satellite to show the config for
-        #[arg(value_name = "sat", value_parser=parse_valid_satellite)]
-        satellite: String,
+        #[arg(
+            value_name = "sat",
+            value_parser = parse_valid_satellite,
+            required_unless_present = "config",
+            conflicts_with = "config"
+        )]
+        satellite: Option<String>,
+        /// YAML config file to show the effective config for, rather than a satellite default.
+        #[arg(short, long, value_name = "path", requires = "effective")]
+        config: Option<PathBuf>,
+        /// Partial YAML configuration applied on top of the satellite default or config file.
+        /// See the create command.
+        #[arg(long, value_name = "path", requires = "effective")]
+        config_override: Option<PathBuf>,
+        /// Output the merged and validated configuration rather than the embedded default.
+        #[arg(long)]
+        effective: bool,
+    },
+    /// Read arbitrary group and dataset attributes.
+    Attr {
+        #[command(subcommand)]
+        command: AttrCommands,
     },
     /// Generate JSON containing file and dataset attributes and values.
+    ///
+    /// Use --sizes or --checksums to help spot zero-length or truncated granules.
     Info {
         #[arg(value_name = "path")]
         input: PathBuf,
@@ -135,12 +945,180 @@ enum Commands {
         short_name: Option<String>,
         #[arg(short, long)]
         granule_id: Option<String>,
+        /// Include the byte size of each granule's RawApplicationPackets dataset.
+        #[arg(long)]
+        sizes: bool,
+        /// Include the byte size and a breakdown of header, APID list, packet tracker, and AP
+        /// storage bytes computed from the static header of each granule's
+        /// RawApplicationPackets dataset. This requires reading all packet data.
+        #[arg(long)]
+        layout: bool,
+        /// Include the byte size, layout, and a CRC-32 of each granule's RawApplicationPackets
+        /// dataset. This requires reading all packet data.
+        #[arg(long)]
+        checksums: bool,
+    },
+    /// Copy selected collections and/or granules from an RDR into a new RDR.
+    ///
+    /// Selection options may be combined, in which case granules must match all of them. With
+    /// no selection options all granules are copied.
+    Subset {
+        /// Use the built-in default configuration for this satellite id rather than the default
+        /// for the file's Platform_Short_Name.
+        #[arg(
+            short = 'S',
+            long,
+            value_name = "name",
+            value_parser = parse_valid_satellite,
+            conflicts_with = "config"
+        )]
+        satellite: Option<String>,
+        /// YAML decode configuration file to use rather than the default for the file's
+        /// Platform_Short_Name.
+        #[arg(short, long, value_name = "path")]
+        config: Option<PathBuf>,
+        /// RDR file to subset
+        #[arg(value_name = "path")]
+        input: PathBuf,
+        /// Collection short name to include, e.g., VIIRS-SCIENCE-RDR. May be repeated.
+        #[arg(short, long = "short-name", value_name = "name")]
+        short_names: Vec<String>,
+        /// Granule id to include. May be repeated.
+        #[arg(short, long = "granule-id", value_name = "id")]
+        granule_ids: Vec<String>,
+        /// Include only granules ending after this time, e.g., 2024-01-01T00:00:00Z
+        #[arg(long, value_name = "time", value_parser=parse_time)]
+        start: Option<Time>,
+        /// Include only granules beginning before this time, e.g., 2024-01-01T00:00:00Z
+        #[arg(long, value_name = "time", value_parser=parse_time)]
+        end: Option<Time>,
+        /// Output directory
+        #[arg(short, long, value_name = "path", default_value = ".")]
+        outdir: PathBuf,
+    },
+    /// Report granule continuity across a set of RDRs, e.g., a day of per-pass files.
+    ///
+    /// Granules for each collection from all files are stitched into a single timeline and
+    /// gaps and overlaps, both within and between files, are reported as JSON.
+    Report {
+        /// Use the built-in default configuration for this satellite id rather than the default
+        /// for each file's Platform_Short_Name when checking --time-skew.
+        #[arg(
+            short,
+            long,
+            value_name = "name",
+            value_parser = parse_valid_satellite,
+            conflicts_with = "config"
+        )]
+        satellite: Option<String>,
+        /// YAML decode configuration file to use rather than the default for each file's
+        /// Platform_Short_Name when checking --time-skew.
+        #[arg(short, long, value_name = "path")]
+        config: Option<PathBuf>,
+        /// RDR files, or directories containing RDR (.h5) files
+        #[arg(value_name = "paths", required = true)]
+        paths: Vec<PathBuf>,
+        /// Also check each file for packets with times outside of their granule's static
+        /// header time window, reporting the granules and APIDs by file.
+        #[arg(long)]
+        time_skew: bool,
+    },
+    /// Catalog the granules of all RDRs in a directory as JSON or CSV.
+    ///
+    /// A record is output for each granule including the path of the file it is in. Files are
+    /// read in parallel, and an interrupted run writing to --output can be continued with
+    /// --resume.
+    CatMeta {
+        /// Directory containing RDR files
+        #[arg(value_name = "dir")]
+        dir: PathBuf,
+        /// Include files in subdirectories
+        #[arg(short, long)]
+        recursive: bool,
+        /// Glob file names must match, e.g., RVIRS*.h5
+        #[arg(short, long, value_name = "glob", default_value = "*.h5")]
+        pattern: String,
+        #[arg(short, long, value_name = "format", default_value = "json")]
+        format: CatMetaFormat,
+        /// Output file. Output is written to stdout if not provided.
+        #[arg(short, long, value_name = "path")]
+        output: Option<PathBuf>,
+        /// Skip files already in --output from a previous run and append to it rather than
+        /// overwriting it.
+        #[arg(long, requires = "output")]
+        resume: bool,
+    },
+    /// Split a multi-collection RDR, e.g., RCRIS-RNSCA, into a new RDR per collection.
+    ///
+    /// Granules and file attributes are preserved. With --per-orbit, split into a new RDR per
+    /// orbit instead, each with the orbit's granules and the packed granules, e.g., diary,
+    /// overlapping them.
+    Split {
+        /// Use the built-in default configuration for this satellite id rather than the default
+        /// for the file's Platform_Short_Name.
+        #[arg(
+            short,
+            long,
+            value_name = "name",
+            value_parser = parse_valid_satellite,
+            conflicts_with = "config"
+        )]
+        satellite: Option<String>,
+        /// YAML decode configuration file to use rather than the default for the file's
+        /// Platform_Short_Name.
+        #[arg(short, long, value_name = "path")]
+        config: Option<PathBuf>,
+        /// RDR file to split
+        #[arg(value_name = "path")]
+        input: PathBuf,
+        /// Output directory
+        #[arg(short, long, value_name = "path", default_value = ".")]
+        outdir: PathBuf,
+        /// Split per orbit rather than per collection.
+        #[arg(long)]
+        per_orbit: bool,
+        /// TLE file used to compute orbit boundaries. Required if the satellite config does
+        /// not provide orbits.
+        #[arg(long, value_name = "path", requires = "per_orbit")]
+        tle: Option<PathBuf>,
+    },
+    /// Generate STAC Items, as newline delimited JSON, for RDR files.
+    ///
+    /// By default an item is generated per file, spanning all granules in the file.
+    Stac {
+        /// One or more RDR files
+        #[arg(value_name = "paths")]
+        inputs: Vec<PathBuf>,
+        /// Generate an item per granule rather than per file.
+        #[arg(long)]
+        per_granule: bool,
+        /// URL prefix for asset hrefs, e.g., https://example.com/rdrs. The file name is appended
+        /// to the prefix. If not provided the input path is used.
+        #[arg(long, value_name = "url")]
+        href_prefix: Option<String>,
     },
     /// Extracts Common RDR metadata and data structures.
     ///
     /// This will produce a JSON file containing the RDR data structure metadata, i.e., everything
     /// but the AP storage, and a data file containing all of the Common RDR structure raw data.
+    ///
+    /// The JSON also includes, for each APID, the first and last packet sequence numbers and
+    /// times and a SHA-256 of the packet bytes so extractions can be compared by diffing JSON.
     Extract {
+        /// Use the built-in default configuration for this satellite id rather than the default
+        /// for the file's Platform_Short_Name when --lenient.
+        #[arg(
+            short = 'S',
+            long,
+            value_name = "name",
+            value_parser = parse_valid_satellite,
+            conflicts_with = "config"
+        )]
+        satellite: Option<String>,
+        /// YAML decode configuration file to use rather than the default for the file's
+        /// Platform_Short_Name when --lenient.
+        #[arg(short, long, value_name = "path")]
+        config: Option<PathBuf>,
         #[arg(value_name = "path")]
         input: PathBuf,
         #[arg(short, long)]
@@ -150,40 +1128,274 @@ enum Commands {
         /// Directory for extracted artifacts
         #[arg(short, long)]
         outdir: Option<PathBuf>,
+        /// Read files with missing or incomplete Data_Products entries, reconstructing granule
+        /// metadata from the Common RDR data in All_Data.
+        #[arg(long)]
+        lenient: bool,
+    },
+    /// Output a shell completion script.
+    ///
+    /// For example, for bash: rdr completions bash > /etc/bash_completion.d/rdr
+    Completions {
+        /// Shell to generate the completion script for
+        #[arg(value_name = "shell")]
+        shell: clap_complete::Shell,
+    },
+    /// Generate man pages for rdr and each subcommand.
+    #[command(hide = true)]
+    Manpage {
+        /// Output directory
+        #[arg(short, long, value_name = "path", default_value = ".")]
+        outdir: PathBuf,
     },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    tracing_subscriber::fmt()
+    let subscriber = tracing_subscriber::fmt()
         .with_target(false)
         .with_writer(stderr)
-        .with_ansi(false)
-        .without_time()
-        .with_env_filter(EnvFilter::new(cli.logging))
-        .init();
+        .with_env_filter(EnvFilter::new(cli.logging));
+    match cli.log_format {
+        LogFormat::Text => subscriber.with_ansi(false).without_time().init(),
+        LogFormat::Json => subscriber
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .init(),
+    }
+
+    #[cfg(feature = "prometheus")]
+    if let Some(addr) = cli.metrics_addr {
+        metrics_exporter_prometheus::PrometheusBuilder::new()
+            .with_http_listener(addr)
+            .install()
+            .context("installing prometheus exporter")?;
+        rdr::metrics::describe();
+        info!("serving metrics at http://{addr}/metrics");
+    }
 
-    info!("hdf5 version={}", env!("H5_VERSION"));
+    let build_info = rdr::build_info();
+    info!(
+        "hdf5 version={} linkage={}",
+        build_info.hdf5_runtime_version, build_info.hdf5_linkage
+    );
+    if !build_info.hdf5_matches() {
+        warn!(
+            "hdf5 runtime version {} does not match build version {}",
+            build_info.hdf5_runtime_version, build_info.hdf5_version
+        );
+    }
 
     match cli.commands {
         Commands::Create {
             configs,
             input,
             output,
+            output_layout,
+            spill_dir,
+            format,
+            time_bounds,
+            file_options,
+            packed_retention,
+            storage_order,
+            orphans,
+            before_base_time,
+            duplicates,
+            undersized,
+            granule_version,
+            bump_versions,
+            batch,
+            rotate_granules,
+            rotate_window,
+            max_time_errors,
+            pass_start,
+            pass_end,
+            manifest,
+            verify,
+            dry_run,
+            write_empty_collections,
+            rename_on_complete,
+        } => {
+            if verify && matches!(format, OutputFormat::Blob) {
+                bail!("--verify is only supported for the h5 format");
+            }
+            let empty = if write_empty_collections {
+                EmptyCollections::Write
+            } else {
+                EmptyCollections::Omit
+            };
+            let writer: Box<dyn RdrWriter> = match format {
+                _ if dry_run => Box::new(crate::command_create::DryRunWriter),
+                OutputFormat::H5 => Box::new(
+                    Hdf5Writer::default()
+                        .with_empty_collections(empty)
+                        .with_file_options(file_options.into())
+                        .with_rename_on_complete(rename_on_complete),
+                ),
+                OutputFormat::Blob => Box::new(BlobWriter),
+            };
+            // Inputs are streamed by create, so only the output is staged
+            let mut staging = crate::staging::Staging::default();
+            let local_output = staging.output_dir(&output)?;
+            crate::command_create::create(
+                configs.satellite,
+                configs.config,
+                configs.config_override,
+                &input,
+                local_output,
+                &crate::command_create::CreateOptions {
+                    spill_dir,
+                    time_bounds: time_bounds.into(),
+                    duplicates: duplicates.into(),
+                    undersized: undersized.into(),
+                    verify,
+                    dry_run,
+                    packed_retention: Some(Duration::from_secs(packed_retention)),
+                    storage_order: storage_order.into(),
+                    orphans: orphans.into(),
+                    output_layout,
+                    granule_version: Some(granule_version),
+                    bump_versions,
+                    batch,
+                    manifest,
+                    rotation: crate::command_create::Rotation {
+                        granules: rotate_granules.map(|n| n as usize),
+                        window: rotate_window.map(Duration::from_secs),
+                    },
+                    max_time_errors,
+                    pass_window: pass_start.zip(pass_end),
+                    base_time_policy: before_base_time.into(),
+                },
+                writer.as_ref(),
+            )?;
+            if !dry_run {
+                staging.upload(&output)?;
+            }
+            staging.close()?;
+        }
+        Commands::Dump {
+            satellite,
+            config,
+            input,
+            per_granule,
+            granule_ids,
+            start,
+            end,
+            lenient,
+            include,
+            exclude,
+            construction_records,
         } => {
-            crate::command_create::create(configs.satellite, configs.config, &input, output)?;
+            let selection = crate::command_dump::GranuleSelection {
+                granule_ids,
+                start,
+                end,
+                lenient,
+            };
+            let collections = crate::command_dump::CollectionFilter { include, exclude };
+            let config = if satellite.is_some() || config.is_some() {
+                let Some(config) = crate::command_create::get_config(satellite, config, None)?
+                else {
+                    bail!("No spacecraft configuration found");
+                };
+                Some(config)
+            } else {
+                None
+            };
+            crate::command_dump::dump(
+                config.as_ref(),
+                &input,
+                true,
+                per_granule,
+                &selection,
+                &collections,
+                construction_records,
+            )?;
+        }
+        Commands::Validate { configs, input } => {
+            let Some(config) = crate::command_create::get_config(
+                configs.satellite,
+                configs.config,
+                configs.config_override,
+            )?
+            else {
+                bail!("No spacecraft configuration found");
+            };
+            crate::command_validate::validate(&config, &input)?;
         }
-        Commands::Dump { input } => {
-            crate::command_dump::dump(&input, true)?;
+        Commands::VerifyRoundtrip {
+            configs,
+            rdr,
+            inputs,
+        } => {
+            let Some(config) = crate::command_create::get_config(
+                configs.satellite,
+                configs.config,
+                configs.config_override,
+            )?
+            else {
+                bail!("No spacecraft configuration found");
+            };
+            let report = crate::command_roundtrip::verify_roundtrip(&config, &inputs, &rdr)?;
+            for line in report.to_string().lines() {
+                info!("roundtrip: {line}");
+            }
+            let missing = report.missing();
+            if missing > 0 {
+                bail!("{missing} input packets missing from {rdr:?}");
+            }
         }
-        Commands::Config { satellite } => {
-            let Some(content) = get_default_content(&satellite) else {
-                bail!("no config for {satellite}");
+        Commands::Time {
+            configs,
+            product,
+            value,
+        } => {
+            let Some(config) = crate::command_create::get_config(
+                configs.satellite,
+                configs.config,
+                configs.config_override,
+            )?
+            else {
+                bail!("No spacecraft configuration found");
             };
-            stdout().write_all(content.as_bytes())?;
+            crate::command_time::time(&config, product.as_deref(), &value)?;
+        }
+        Commands::Config {
+            satellite,
+            config,
+            config_override,
+            effective,
+        } => {
+            if effective {
+                let Some(config) =
+                    crate::command_create::get_config(satellite, config, config_override)?
+                else {
+                    bail!("No spacecraft configuration found");
+                };
+                stdout().write_all(config.to_yaml()?.as_bytes())?;
+            } else {
+                let satellite = satellite.expect("satellite is required without --config");
+                let Some(content) = get_default_content(&satellite) else {
+                    bail!("no config for {satellite}");
+                };
+                stdout().write_all(content.as_bytes())?;
+            }
         }
-        Commands::Aggr { inputs, workdir } => {
+        Commands::Aggr {
+            inputs,
+            output,
+            workdir,
+            extract,
+            versions,
+            verify,
+            dry_run,
+            lenient,
+            manifest,
+            restamp_software,
+            require_contiguous,
+        } => {
             if inputs.is_empty() {
                 bail!("No inputs specified");
             }
@@ -196,12 +1408,150 @@ fn main() -> Result<()> {
                     tmpdir.as_ref().unwrap().path()
                 }
             };
-            let fpath = crate::command_aggr::aggreggate(&inputs, workdir)?;
-            info!("saved {fpath:?}");
+            // HDF5 inputs must be local files, so they are downloaded rather than streamed
+            let mut staging = crate::staging::Staging::default();
+            let inputs = staging.inputs(&inputs)?;
+            let local_output = staging.output_dir(&output)?;
+            let report = crate::command_aggr::aggreggate(
+                &inputs,
+                workdir,
+                &local_output,
+                extract,
+                versions.into(),
+                verify,
+                dry_run,
+                lenient,
+                manifest,
+                restamp_software,
+                require_contiguous.map(Into::into),
+            )?;
+            for line in report.to_string().lines() {
+                info!("report: {line}");
+            }
+            if !report.is_complete() {
+                warn!("aggregation is incomplete; some inputs or granules were skipped");
+            }
+            if !dry_run {
+                staging.upload(&output)?;
+            }
+            staging.close()?;
             if let Some(tmpdir) = tmpdir {
                 tmpdir.close().context("removing tmpdir")?;
             }
         }
+        Commands::Apids { configs, input } => {
+            let Some(config) = crate::command_create::get_config(
+                configs.satellite,
+                configs.config,
+                configs.config_override,
+            )?
+            else {
+                bail!("No spacecraft configuration found");
+            };
+            crate::command_apids::apids(&config, &input)?;
+        }
+        Commands::Hist {
+            configs,
+            input,
+            bin,
+            format,
+            output,
+        } => {
+            let Some(config) = crate::command_create::get_config(
+                configs.satellite,
+                configs.config,
+                configs.config_override,
+            )?
+            else {
+                bail!("No spacecraft configuration found");
+            };
+            crate::command_hist::hist(
+                &config,
+                &input,
+                Duration::from_secs(bin),
+                format.into(),
+                output.as_deref(),
+            )?;
+        }
+        Commands::Backfill {
+            configs,
+            target,
+            inputs,
+            dry_run,
+        } => {
+            let Some(config) = crate::command_create::get_config(
+                configs.satellite,
+                configs.config,
+                configs.config_override,
+            )?
+            else {
+                bail!("No spacecraft configuration found");
+            };
+            crate::command_backfill::backfill(&config, &target, &inputs, dry_run)?;
+        }
+        Commands::Attr { command } => match command {
+            AttrCommands::Get {
+                input,
+                selector,
+                raw,
+            } => crate::command_attr::attr_get(&input, &selector, raw)?,
+            AttrCommands::Ls { input, path } => crate::command_attr::attr_ls(&input, &path)?,
+        },
+        Commands::Replace {
+            configs,
+            target,
+            input,
+            granule_id,
+            short_name,
+        } => {
+            let Some(config) = crate::command_create::get_config(
+                configs.satellite,
+                configs.config,
+                configs.config_override,
+            )?
+            else {
+                bail!("No spacecraft configuration found");
+            };
+            crate::command_replace::replace(
+                &config,
+                &target,
+                &input,
+                &granule_id,
+                short_name.as_deref(),
+            )?;
+        }
+        Commands::Rename {
+            satellite,
+            config,
+            inputs,
+            origin,
+            mode,
+            created,
+            dry_run,
+        } => {
+            let config = if satellite.is_some() || config.is_some() {
+                let Some(config) = crate::command_create::get_config(satellite, config, None)?
+                else {
+                    bail!("No spacecraft configuration found");
+                };
+                Some(config)
+            } else {
+                None
+            };
+            crate::command_rename::rename(
+                config.as_ref(),
+                &inputs,
+                &crate::command_rename::RenameOptions {
+                    origin,
+                    mode,
+                    created,
+                    dry_run,
+                },
+            )?;
+        }
+        Commands::Normalize { input } => {
+            crate::command_normalize::normalize(&input)?;
+        }
         Commands::Deagg { .. } => {
             unimplemented!()
         }
@@ -209,17 +1559,160 @@ fn main() -> Result<()> {
             input,
             short_name,
             granule_id,
+            sizes,
+            layout,
+            checksums,
         } => {
-            crate::command_info::info(input, short_name, granule_id)?;
+            let detail = if checksums {
+                RawDatasetDetail::Checksum
+            } else if layout {
+                RawDatasetDetail::Layout
+            } else if sizes {
+                RawDatasetDetail::Size
+            } else {
+                RawDatasetDetail::None
+            };
+            crate::command_info::info(input, short_name, granule_id, detail)?;
+        }
+        Commands::Subset {
+            satellite,
+            config,
+            input,
+            short_names,
+            granule_ids,
+            start,
+            end,
+            outdir,
+        } => {
+            let selection = crate::command_subset::Selection {
+                short_names,
+                granule_ids,
+                start,
+                end,
+            };
+            let config = if satellite.is_some() || config.is_some() {
+                let Some(config) = crate::command_create::get_config(satellite, config, None)?
+                else {
+                    bail!("No spacecraft configuration found");
+                };
+                Some(config)
+            } else {
+                None
+            };
+            crate::command_subset::subset(config.as_ref(), &input, &outdir, &selection)?;
+        }
+        Commands::Report {
+            satellite,
+            config,
+            paths,
+            time_skew,
+        } => {
+            let config = if satellite.is_some() || config.is_some() {
+                let Some(config) = crate::command_create::get_config(satellite, config, None)?
+                else {
+                    bail!("No spacecraft configuration found");
+                };
+                Some(config)
+            } else {
+                None
+            };
+            let report =
+                crate::command_report::report(config.as_ref(), &paths, time_skew, stdout())?;
+            for line in report.to_string().lines() {
+                info!("report: {line}");
+            }
+        }
+        Commands::CatMeta {
+            dir,
+            recursive,
+            pattern,
+            format,
+            output,
+            resume,
+        } => {
+            crate::command_catmeta::cat_meta(
+                &dir,
+                recursive,
+                &pattern,
+                format.into(),
+                output.as_deref(),
+                resume,
+            )?;
+        }
+        Commands::Split {
+            satellite,
+            config,
+            input,
+            outdir,
+            per_orbit,
+            tle,
+        } => {
+            let config = if satellite.is_some() || config.is_some() {
+                let Some(config) = crate::command_create::get_config(satellite, config, None)?
+                else {
+                    bail!("No spacecraft configuration found");
+                };
+                Some(config)
+            } else {
+                None
+            };
+            if per_orbit {
+                let orbits = match tle {
+                    Some(path) => {
+                        let tle = std::fs::read_to_string(&path)
+                            .with_context(|| format!("reading {path:?}"))?;
+                        Some(
+                            rdr::Orbits::from_tle(&tle)
+                                .with_context(|| format!("parsing TLE {path:?}"))?,
+                        )
+                    }
+                    None => None,
+                };
+                crate::command_split::split_orbits(config.as_ref(), &input, &outdir, orbits)?;
+            } else {
+                crate::command_split::split(config.as_ref(), &input, &outdir)?;
+            }
+        }
+        Commands::Stac {
+            inputs,
+            per_granule,
+            href_prefix,
+        } => {
+            crate::command_stac::stac(&inputs, per_granule, href_prefix.as_deref(), stdout())?;
         }
         Commands::Extract {
+            satellite,
+            config,
             input,
             short_name,
             granule_id,
             outdir,
+            lenient,
         } => {
+            let config = if satellite.is_some() || config.is_some() {
+                let Some(config) = crate::command_create::get_config(satellite, config, None)?
+                else {
+                    bail!("No spacecraft configuration found");
+                };
+                Some(config)
+            } else {
+                None
+            };
             let outdir = outdir.unwrap_or(std::env::current_dir()?);
-            crate::command_extract::extract(input, outdir, short_name, granule_id)?;
+            crate::command_extract::extract(
+                config.as_ref(),
+                input,
+                outdir,
+                short_name,
+                granule_id,
+                lenient,
+            )?;
+        }
+        Commands::Completions { shell } => {
+            crate::command_completions::completions(&mut Cli::command(), shell, stdout())?;
+        }
+        Commands::Manpage { outdir } => {
+            crate::command_completions::manpages(Cli::command(), &outdir)?;
         }
     }
 
@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use rdr::{GranuleMeta, IetMicros, Time};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+/// File name of the manifest written alongside outputs.
+pub const MANIFEST_NAME: &str = "run-manifest.json";
+
+const TIME_FMT: &str = "%Y-%m-%dT%H:%M:%S.%fZ";
+
+/// Hex encoded SHA-256 of the contents of the file at `path`.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("opening {path:?}"))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).with_context(|| format!("reading {path:?}"))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// A granule in a [ManifestFile].
+#[derive(Debug, Clone, Serialize)]
+pub struct FileGranule {
+    pub id: String,
+    pub version: String,
+    pub begin: String,
+    pub end: String,
+    pub begin_time_iet: IetMicros,
+    pub end_time_iet: IetMicros,
+}
+
+impl From<&GranuleMeta> for FileGranule {
+    fn from(meta: &GranuleMeta) -> Self {
+        Self {
+            id: meta.id.clone(),
+            version: meta.version.clone(),
+            begin: Time::from(meta.begin_time_iet).format_utc(TIME_FMT),
+            end: Time::from(meta.end_time_iet).format_utc(TIME_FMT),
+            begin_time_iet: meta.begin_time_iet,
+            end_time_iet: meta.end_time_iet,
+        }
+    }
+}
+
+/// A file produced by a run.
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestFile {
+    pub name: String,
+    /// Path relative to the manifest
+    pub path: PathBuf,
+    /// Size in bytes; 0 for outputs that are not regular files, e.g., blob directories
+    pub size: u64,
+    /// Hex encoded SHA-256 of the file contents, if a regular file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    pub begin_time_iet: Option<IetMicros>,
+    pub end_time_iet: Option<IetMicros>,
+    /// Collection short name to granules, in time order
+    pub collections: BTreeMap<String, Vec<FileGranule>>,
+}
+
+impl ManifestFile {
+    /// Describe the output at `path` containing `granules`, reading the file to compute its
+    /// checksum. `path` is recorded relative to `dir`, the directory containing the manifest.
+    pub fn new<'a>(
+        dir: &Path,
+        path: &Path,
+        granules: impl IntoIterator<Item = &'a GranuleMeta>,
+    ) -> Result<Self> {
+        let (size, sha256) = if path.is_file() {
+            let size = path
+                .metadata()
+                .with_context(|| format!("reading metadata for {path:?}"))?
+                .len();
+            (size, Some(sha256_file(path)?))
+        } else {
+            (0, None)
+        };
+
+        let mut collections: BTreeMap<String, Vec<FileGranule>> = BTreeMap::default();
+        let (mut begin, mut end): (Option<IetMicros>, Option<IetMicros>) = (None, None);
+        for granule in granules {
+            begin = Some(begin.map_or(granule.begin_time_iet, |t| t.min(granule.begin_time_iet)));
+            end = Some(end.map_or(granule.end_time_iet, |t| t.max(granule.end_time_iet)));
+            collections
+                .entry(granule.collection.clone())
+                .or_default()
+                .push(granule.into());
+        }
+        for granules in collections.values_mut() {
+            granules.sort_by_key(|g| g.begin_time_iet);
+        }
+
+        Ok(Self {
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: path.strip_prefix(dir).unwrap_or(path).to_path_buf(),
+            size,
+            sha256,
+            begin_time_iet: begin,
+            end_time_iet: end,
+            collections,
+        })
+    }
+}
+
+/// Machine-readable record of the files produced by a single `create` or `aggr` run, so
+/// downstream processing can be driven from the manifest rather than directory scans.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunManifest {
+    /// Command that produced the files, e.g., create
+    pub command: String,
+    pub created: String,
+    /// Why the run aborted, if it did; the files are those written before the abort
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aborted: Option<String>,
+    pub files: Vec<ManifestFile>,
+}
+
+impl RunManifest {
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            created: Time::now().format_utc(TIME_FMT),
+            aborted: None,
+            files: Vec::default(),
+        }
+    }
+
+    /// Write the manifest as JSON to [MANIFEST_NAME] in `dir`, returning the path written.
+    pub fn write(&self, dir: &Path) -> Result<PathBuf> {
+        let path = dir.join(MANIFEST_NAME);
+        let file = File::create(&path).with_context(|| format!("creating {path:?}"))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)
+            .with_context(|| format!("writing {path:?}"))?;
+        Ok(path)
+    }
+}
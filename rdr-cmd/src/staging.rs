@@ -0,0 +1,276 @@
+use anyhow::{bail, Context, Result};
+use rdr::{open_packet_file, open_packet_reader, Compression};
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+use tempfile::TempDir;
+use tracing::{debug, info};
+
+use self::s3::S3;
+
+const S3_PREFIX: &str = "s3://";
+
+/// True if `path` is an S3 URL, e.g., `s3://bucket/key`.
+pub fn is_s3(path: &Path) -> bool {
+    path.to_str().is_some_and(|p| p.starts_with(S3_PREFIX))
+}
+
+/// Split an S3 URL into bucket and key.
+fn parse_s3(path: &Path) -> Result<(String, String)> {
+    let url = path.to_str().context("invalid S3 URL")?;
+    let rest = url
+        .strip_prefix(S3_PREFIX)
+        .with_context(|| format!("not an S3 URL: {url}"))?;
+    let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+    if bucket.is_empty() {
+        bail!("S3 URL has no bucket: {url}");
+    }
+    Ok((bucket.to_string(), key.trim_end_matches('/').to_string()))
+}
+
+/// Open the packet input `input` for reading, decompressing it if necessary. S3 inputs are
+/// streamed rather than staged, so inputs that are read once from start to finish need no
+/// local space. See [open_packet_file].
+pub fn open_input(input: &Path) -> Result<Box<dyn Read + Send>> {
+    if !is_s3(input) {
+        return Ok(open_packet_file(input)?);
+    }
+    let (bucket, key) = parse_s3(input)?;
+    debug!("streaming {input:?}");
+    let reader = S3::new()?
+        .reader(&bucket, &key)
+        .with_context(|| format!("reading {input:?}"))?;
+    Ok(open_packet_reader(reader, Compression::from_path(&key))?)
+}
+
+/// Stages S3 inputs and outputs through a local temporary directory, so commands can read and
+/// write S3 URLs as if they were local paths. Use [open_input] instead for inputs that can be
+/// streamed.
+///
+/// Nothing is staged for local paths, which are used as is.
+#[derive(Default)]
+pub struct Staging {
+    dir: Option<TempDir>,
+    s3: Option<S3>,
+}
+
+impl Staging {
+    fn dir(&mut self) -> Result<PathBuf> {
+        if self.dir.is_none() {
+            self.dir = Some(TempDir::new().context("creating staging dir")?);
+        }
+        Ok(self.dir.as_ref().expect("set above").path().to_path_buf())
+    }
+
+    fn s3(&mut self) -> Result<&S3> {
+        if self.s3.is_none() {
+            self.s3 = Some(S3::new()?);
+        }
+        Ok(self.s3.as_ref().expect("set above"))
+    }
+
+    /// Local paths for `inputs`, downloading S3 inputs to the staging directory. Downloaded
+    /// inputs keep their file names.
+    pub fn inputs(&mut self, inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        let mut local = Vec::default();
+        for (idx, input) in inputs.iter().enumerate() {
+            if !is_s3(input) {
+                local.push(input.clone());
+                continue;
+            }
+            let (bucket, key) = parse_s3(input)?;
+            let name = key
+                .rsplit('/')
+                .next()
+                .filter(|n| !n.is_empty())
+                .with_context(|| format!("S3 input has no file name: {input:?}"))?;
+            // Separate directory per input in case inputs share a name
+            let dir = self.dir()?.join("inputs").join(idx.to_string());
+            std::fs::create_dir_all(&dir).with_context(|| format!("creating {dir:?}"))?;
+            let dest = dir.join(name);
+            info!("downloading {input:?} to {dest:?}");
+            self.s3()?
+                .download(&bucket, &key, &dest)
+                .with_context(|| format!("downloading {input:?}"))?;
+            local.push(dest);
+        }
+        Ok(local)
+    }
+
+    /// Local directory to write outputs for `output`; `output` itself if it is local,
+    /// otherwise a staging directory to be uploaded with [Staging::upload].
+    pub fn output_dir(&mut self, output: &Path) -> Result<PathBuf> {
+        if !is_s3(output) {
+            return Ok(output.to_path_buf());
+        }
+        parse_s3(output)?;
+        let dir = self.dir()?.join("output");
+        std::fs::create_dir_all(&dir).with_context(|| format!("creating {dir:?}"))?;
+        Ok(dir)
+    }
+
+    /// Upload all files in the staging output directory to the S3 `output` prefix, keeping
+    /// their paths relative to the output directory. Does nothing if `output` is local.
+    pub fn upload(&mut self, output: &Path) -> Result<()> {
+        if !is_s3(output) {
+            return Ok(());
+        }
+        let (bucket, prefix) = parse_s3(output)?;
+        let dir = self.output_dir(output)?;
+        let mut dirs = vec![dir.clone()];
+        let mut num_files = 0;
+        while let Some(cur) = dirs.pop() {
+            for entry in std::fs::read_dir(&cur).with_context(|| format!("reading {cur:?}"))? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let rel = path.strip_prefix(&dir).expect("path is in output dir");
+                let rel = rel.to_string_lossy().replace('\\', "/");
+                let key = if prefix.is_empty() {
+                    rel
+                } else {
+                    format!("{prefix}/{rel}")
+                };
+                debug!("uploading {path:?} to s3://{bucket}/{key}");
+                self.s3()?
+                    .upload(&path, &bucket, &key)
+                    .with_context(|| format!("uploading {path:?} to s3://{bucket}/{key}"))?;
+                num_files += 1;
+            }
+        }
+        info!("uploaded {num_files} files to {output:?}");
+        Ok(())
+    }
+
+    /// Remove the staging directory, if one was created.
+    pub fn close(self) -> Result<()> {
+        if let Some(dir) = self.dir {
+            debug!(dir = ?dir.path(), "removing staging dir");
+            dir.close().context("removing staging dir")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "s3")]
+mod s3 {
+    use anyhow::{Context, Result};
+    use aws_sdk_s3::{primitives::ByteStream, Client};
+    use std::{io::Read, path::Path, pin::Pin};
+    use tokio::{io::AsyncRead, runtime::Runtime};
+    use tokio_util::io::SyncIoBridge;
+
+    /// Blocking S3 client using the standard AWS credential and region configuration, e.g.,
+    /// `AWS_PROFILE` or `AWS_REGION`.
+    ///
+    /// Objects are uploaded with a single request, limiting uploads to 5GB.
+    pub struct S3 {
+        rt: Runtime,
+        client: Client,
+    }
+
+    /// Blocking reader for the body of an S3 object, see [S3::reader].
+    pub struct S3Reader {
+        body: SyncIoBridge<Pin<Box<dyn AsyncRead + Send>>>,
+        // Drives the body, so dropped after it
+        _rt: Runtime,
+    }
+
+    impl Read for S3Reader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.body.read(buf)
+        }
+    }
+
+    impl S3 {
+        pub fn new() -> Result<Self> {
+            // A worker thread drives reads for S3Reader, which block on the runtime handle
+            let rt = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .enable_all()
+                .build()
+                .context("creating S3 runtime")?;
+            let config = rt.block_on(aws_config::load_defaults(
+                aws_config::BehaviorVersion::latest(),
+            ));
+            Ok(Self {
+                rt,
+                client: Client::new(&config),
+            })
+        }
+
+        /// Read the object `key` as it is downloaded rather than downloading it first.
+        pub fn reader(self, bucket: &str, key: &str) -> Result<S3Reader> {
+            let resp = self
+                .rt
+                .block_on(self.client.get_object().bucket(bucket).key(key).send())?;
+            let body: Pin<Box<dyn AsyncRead + Send>> = Box::pin(resp.body.into_async_read());
+            Ok(S3Reader {
+                body: SyncIoBridge::new_with_handle(body, self.rt.handle().clone()),
+                _rt: self.rt,
+            })
+        }
+
+        pub fn download(&self, bucket: &str, key: &str, dest: &Path) -> Result<()> {
+            self.rt.block_on(async {
+                let resp = self
+                    .client
+                    .get_object()
+                    .bucket(bucket)
+                    .key(key)
+                    .send()
+                    .await?;
+                let mut body = resp.body.into_async_read();
+                let mut file = tokio::fs::File::create(dest)
+                    .await
+                    .with_context(|| format!("creating {dest:?}"))?;
+                tokio::io::copy(&mut body, &mut file).await?;
+                Ok(())
+            })
+        }
+
+        pub fn upload(&self, src: &Path, bucket: &str, key: &str) -> Result<()> {
+            self.rt.block_on(async {
+                let body = ByteStream::from_path(src).await?;
+                self.client
+                    .put_object()
+                    .bucket(bucket)
+                    .key(key)
+                    .body(body)
+                    .send()
+                    .await?;
+                Ok(())
+            })
+        }
+    }
+}
+
+#[cfg(not(feature = "s3"))]
+mod s3 {
+    use anyhow::{bail, Result};
+    use std::{io::Empty, path::Path};
+
+    /// Placeholder that cannot be created without the s3 feature.
+    pub enum S3 {}
+
+    impl S3 {
+        pub fn new() -> Result<Self> {
+            bail!("S3 URLs are not supported; rdr must be built with the s3 feature")
+        }
+
+        pub fn reader(self, _bucket: &str, _key: &str) -> Result<Empty> {
+            match self {}
+        }
+
+        pub fn download(&self, _bucket: &str, _key: &str, _dest: &Path) -> Result<()> {
+            match *self {}
+        }
+
+        pub fn upload(&self, _src: &Path, _bucket: &str, _key: &str) -> Result<()> {
+            match *self {}
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! Helpers shared by multiple commands.
+use std::path::Path;
+
+/// True if `path` is an RDR, rather than level-0, input based on its `.h5` extension.
+pub fn is_rdr(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("h5"))
+}
+
+/// Simple shell style glob match of `name` against `pattern` supporting `*` and `?`.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position in pattern after the last `*`, and the name position it matched up to
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p + 1, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p;
+            n = star_n + 1;
+            star = Some((star_p, n));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_rdr() {
+        assert!(is_rdr(Path::new("dir/RVIRS_npp_d20200101.h5")));
+        assert!(is_rdr(Path::new("RVIRS.H5")));
+        assert!(!is_rdr(Path::new("P1570826VIIRSSCIENCEAS.PDS")));
+        assert!(!is_rdr(Path::new("h5")));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.h5", "RVIRS_npp.h5"));
+        assert!(glob_match("RVIRS*.h5", "RVIRS_npp.h5"));
+        assert!(glob_match("R?IRS*", "RVIRS_npp.h5"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*_*_*.h5", "a_b_c_d.h5"));
+        assert!(!glob_match("*.h5", "RVIRS_npp.h5.tmp"));
+        assert!(!glob_match("RCRIS*", "RVIRS_npp.h5"));
+        assert!(!glob_match("?", ""));
+    }
+}
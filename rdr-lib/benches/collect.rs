@@ -0,0 +1,60 @@
+//! Benchmark collecting granules from a level-0 file, e.g., a VIIRS pass.
+//!
+//! The input is not included in the repository, so provide one using `RDR_BENCH_INPUT` and,
+//! optionally, the satellite id using `RDR_BENCH_SAT` (default npp):
+//! ```sh
+//! RDR_BENCH_INPUT=P1570826VIIRSSCIENCEAS24001000000001.PDS cargo bench --bench collect
+//! ```
+//!
+//! To compare against the collector before `ProductIndex`, copy the first version of this
+//! file and its `[[bench]]` entry onto the parent of the commit that added both; it only uses
+//! API that exists there.
+use std::{env::var, path::PathBuf, time::Instant};
+
+use ccsds::spacepacket::{collect_groups, decode_packets};
+use rdr::{config::get_default, open_packet_file, Collector, PacketTimeIter};
+
+const ITERATIONS: u32 = 3;
+
+fn main() {
+    let Ok(input) = var("RDR_BENCH_INPUT").map(PathBuf::from) else {
+        eprintln!("RDR_BENCH_INPUT not set, skipping");
+        return;
+    };
+    let satid = var("RDR_BENCH_SAT").unwrap_or_else(|_| "npp".to_string());
+    let config = get_default(&satid)
+        .expect("loading config")
+        .expect("no config for satellite");
+
+    let mut best = f64::MAX;
+    for _ in 0..ITERATIONS {
+        let file = open_packet_file(&input).expect("opening input");
+        let packets = decode_packets(file).filter_map(Result::ok);
+        let groups = collect_groups(packets).filter_map(Result::ok);
+
+        let started = Instant::now();
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+        let (mut num_packets, mut num_granules) = (0usize, 0usize);
+        for (pkt, pkt_time) in PacketTimeIter::new(groups).with_timecode(&config.satellite.timecode)
+        {
+            num_packets += 1;
+            if let Ok(Some(rdrs)) = collector.add(&pkt_time, pkt) {
+                num_granules += rdrs.len();
+            }
+        }
+        num_granules += collector
+            .finish()
+            .expect("finishing")
+            .iter()
+            .map(Vec::len)
+            .sum::<usize>();
+        let elapsed = started.elapsed().as_secs_f64();
+        best = best.min(elapsed);
+        println!(
+            "packets={num_packets} granules={num_granules} elapsed={elapsed:.3}s packets/s={:.0}",
+            num_packets as f64 / elapsed
+        );
+    }
+    println!("best of {ITERATIONS}: {best:.3}s");
+}
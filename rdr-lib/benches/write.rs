@@ -0,0 +1,48 @@
+//! Benchmark writing an aggregated RDR, e.g., to measure granule attribute writing.
+//!
+//! Granules are synthetic VIIRS granules from the fixtures module. The number of granules
+//! defaults to 240 and can be set using `RDR_BENCH_GRANULES`:
+//! ```sh
+//! cargo bench --features fixtures --bench write
+//! ```
+use std::{env::var, time::Instant};
+
+use rdr::{config::get_default, create_rdr, fixtures, Meta};
+
+const ITERATIONS: u32 = 5;
+
+fn main() {
+    let num_granules: u64 = var("RDR_BENCH_GRANULES")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(240);
+    let config = get_default("npp")
+        .expect("loading config")
+        .expect("no config for satellite");
+    let rdrs: Vec<_> = fixtures::viirs_granules(&config, 0, num_granules)
+        .expect("collecting granules")
+        .into_iter()
+        .flatten()
+        .collect();
+    let short_name = fixtures::product(&config)
+        .expect("fixture product")
+        .short_name
+        .clone();
+    let meta = Meta::from_products(&[short_name], &config).expect("creating meta");
+    let dir = tempfile::tempdir().expect("creating temp dir");
+
+    let mut best = f64::MAX;
+    for num in 0..ITERATIONS {
+        let path = dir.path().join(format!("bench{num}.h5"));
+        let started = Instant::now();
+        create_rdr(&path, meta.clone(), &rdrs).expect("writing rdr");
+        let elapsed = started.elapsed().as_secs_f64();
+        best = best.min(elapsed);
+        println!(
+            "granules={} elapsed={elapsed:.3}s granules/s={:.0}",
+            rdrs.len(),
+            rdrs.len() as f64 / elapsed
+        );
+    }
+    println!("best of {ITERATIONS}: {best:.3}s");
+}
@@ -1,13 +1,94 @@
-use std::env::var_os;
+use std::env::{var, var_os, vars};
 use std::error::Error;
-use std::fs::copy;
+use std::fs::{copy, read_to_string};
 use std::path::{Path, PathBuf};
 
 fn main() -> Result<(), Box<dyn Error>> {
     include_default_configs()?;
+    println!("cargo:rustc-env=H5_VERSION={}", h5version());
+    println!("cargo:rustc-env=RDR_FEATURES={}", features().join(","));
+    link_hdf5();
     Ok(())
 }
 
+/// Additional linking required by HDF5 depending on how it's linked.
+fn link_hdf5() {
+    // Location of a system HDF5 used by hdf5-sys when not statically linking
+    println!("cargo:rerun-if-env-changed=HDF5_DIR");
+    let linkage = if var_os("CARGO_FEATURE_HDF5_STATIC").is_some() {
+        "static"
+    } else {
+        "dynamic"
+    };
+    println!("cargo:rustc-env=H5_LINKAGE={linkage}");
+
+    // A static HDF5 on Windows uses the shell path API, which is not linked by default
+    if linkage == "static" && var("CARGO_CFG_TARGET_OS").as_deref() == Ok("windows") {
+        println!("cargo:rustc-link-lib=shlwapi");
+    }
+}
+
+/// Names of all enabled cargo features, other than `default`, sorted.
+///
+/// Cargo only provides the feature names upper-cased with `-` replaced by `_`, so they are
+/// converted back assuming feature names are lower case.
+fn features() -> Vec<String> {
+    let mut features: Vec<String> = vars()
+        .filter_map(|(name, _)| {
+            name.strip_prefix("CARGO_FEATURE_")
+                .map(|f| f.to_lowercase().replace('_', "-"))
+        })
+        .filter(|f| f != "default")
+        .collect();
+    features.sort();
+    features
+}
+
+/// Version of the HDF5 library linked, from the metadata hdf5-sys provides to crates that
+/// depend on it, rather than by loading the library in the build script, which would report
+/// the host rather than the target library when cross-compiling.
+///
+/// The exact version is read from the headers hdf5-sys built with. Otherwise, hdf5-sys only
+/// provides a `DEP_HDF5_VERSION_<major>_<minor>_<micro>` variable for each version from a
+/// fixed list that the library is at least, so the newest of those is used as a lower bound.
+fn h5version() -> String {
+    if let Some(version) = var_os("DEP_HDF5_INCLUDE")
+        .and_then(|dir| read_to_string(Path::new(&dir).join("H5public.h")).ok())
+        .and_then(|header| header_version(&header))
+    {
+        return version;
+    }
+    vars()
+        .filter_map(|(name, _)| {
+            let version = name.strip_prefix("DEP_HDF5_VERSION_")?;
+            let parts: Vec<u32> = version.split('_').filter_map(|v| v.parse().ok()).collect();
+            <[u32; 3]>::try_from(parts).ok()
+        })
+        .max()
+        .map_or_else(
+            || "unknown".to_string(),
+            |[major, minor, micro]| format!(">={major}.{minor}.{micro}"),
+        )
+}
+
+/// HDF5 version from the `H5_VERS_*` defines in the contents of `H5public.h`.
+fn header_version(header: &str) -> Option<String> {
+    let define = |name: &str| {
+        header.lines().find_map(|line| {
+            let mut words = line.split_whitespace();
+            (words.next() == Some("#define") && words.next() == Some(name))
+                .then(|| words.next()?.parse::<u32>().ok())
+                .flatten()
+        })
+    };
+    Some(format!(
+        "{}.{}.{}",
+        define("H5_VERS_MAJOR")?,
+        define("H5_VERS_MINOR")?,
+        define("H5_VERS_RELEASE")?
+    ))
+}
+
 fn etc_path(name: &str) -> PathBuf {
     Path::new(env!("CARGO_MANIFEST_DIR")).join("etc").join(name)
 }
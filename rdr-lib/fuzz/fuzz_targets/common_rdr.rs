@@ -0,0 +1,11 @@
+//! Decode arbitrary data as a Common RDR granule, as read from a RawApplicationPackets
+//! dataset. Errors are expected; panics are not.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = rdr::CommonRdr::from_bytes(data);
+    let _ = rdr::validate_common_rdr(data);
+    let _ = rdr::common_rdr_packets(data);
+});
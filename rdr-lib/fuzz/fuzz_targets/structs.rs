@@ -0,0 +1,12 @@
+//! Decode arbitrary data as the individual Common RDR structures.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rdr::{ApidInfo, PacketTracker, StaticHeader};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = StaticHeader::from_bytes(data);
+    let _ = ApidInfo::from_bytes(data);
+    let _ = ApidInfo::all_from_bytes(data);
+    let _ = PacketTracker::from_bytes(data);
+});
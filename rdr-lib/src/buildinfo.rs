@@ -0,0 +1,65 @@
+use serde::Serialize;
+
+/// Library build and runtime environment information.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    /// Version of this library
+    pub version: &'static str,
+    /// HDF5 version this library was built against
+    pub hdf5_version: &'static str,
+    /// HDF5 version of the library in use at runtime
+    pub hdf5_runtime_version: String,
+    /// How HDF5 is linked, i.e., static or dynamic
+    pub hdf5_linkage: &'static str,
+    /// Cargo features enabled for this library
+    pub features: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    /// Returns true if the runtime HDF5 version matches the version built against.
+    #[must_use]
+    pub fn hdf5_matches(&self) -> bool {
+        self.hdf5_version == self.hdf5_runtime_version
+    }
+}
+
+/// Get the library [BuildInfo].
+///
+/// Embedding applications can use this to report their environment and to refuse to run
+/// against an HDF5 runtime that does not match the one built against.
+#[must_use]
+pub fn build_info() -> BuildInfo {
+    let (major, minor, patch) = hdf5::library_version();
+    // Set by the build script from the features cargo enabled
+    let features = env!("RDR_FEATURES")
+        .split(',')
+        .filter(|f| !f.is_empty())
+        .collect();
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        hdf5_version: env!("H5_VERSION"),
+        hdf5_runtime_version: format!("{major}.{minor}.{patch}"),
+        hdf5_linkage: env!("H5_LINKAGE"),
+        features,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info() {
+        let info = build_info();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(info.hdf5_matches(), "{info:?}");
+        for (feature, enabled) in [
+            ("hdf5-static", cfg!(feature = "hdf5-static")),
+            ("zstd", cfg!(feature = "zstd")),
+            ("fixtures", cfg!(feature = "fixtures")),
+            ("metrics", cfg!(feature = "metrics")),
+        ] {
+            assert_eq!(info.features.contains(&feature), enabled, "{feature}");
+        }
+    }
+}
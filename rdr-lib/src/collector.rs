@@ -1,106 +1,740 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use ccsds::spacepacket::{Apid, Packet, PacketGroup, TimecodeDecoder};
-use tracing::{trace, warn};
+use ccsds::spacepacket::{decode_packets, Apid, Packet, PacketGroup, TimecodeDecoder};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, trace, warn};
 
 use crate::{
-    config::{ProductSpec, RdrSpec, SatSpec},
+    config::{ProductIndex, ProductSpec, RdrSpec, SatSpec, TimecodeSpec},
     error::Result,
     get_granule_start,
-    rdr::Rdr,
-    Error, RdrData, RdrError, Time,
+    metrics::{self, DropReason},
+    rdr::{is_packed_overlap, Rdr},
+    storage::PacketStorage,
+    DuplicatePolicy, Error, GranuleMeta, IetMicros, PacketTimeStats, RateLimitedWarnings, RdrData,
+    RdrError, StorageOrder, Time,
 };
 
+/// Packets for a single open granule in a [CollectorSnapshot].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GranuleSnapshot {
+    pub product_id: String,
+    /// Granule start time
+    pub granule_time: IetMicros,
+    /// True for a packed product granule, false for a primary granule
+    pub packed: bool,
+    /// True for a packed granule already packed with a primary granule, i.e., not an orphan.
+    /// See [OrphanPolicy].
+    #[serde(default)]
+    pub used: bool,
+    /// Time and bytes of every packet added to the granule in the order they were added,
+    /// including any later replaced by a duplicate.
+    pub packets: Vec<(IetMicros, Vec<u8>)>,
+    /// Number of padding packets for each apid. See [crate::config::ProductSpec::padding].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub padding: BTreeMap<Apid, u32>,
+}
+
+/// Serializable state of the open granules and counters of a [Collector], e.g., so a
+/// streaming service can restart without losing partially filled granules. See
+/// [Collector::snapshot] and [Collector::restore].
+///
+/// Collector settings, such as the [DuplicatePolicy], are not included and come from the
+/// collector a snapshot is restored to.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CollectorSnapshot {
+    pub granules: Vec<GranuleSnapshot>,
+    /// Pre-roll packets held for primary granules not yet created
+    #[serde(default)]
+    pub preroll: Vec<GranuleSnapshot>,
+    /// Time of the last packet accepted
+    pub last_time: Option<IetMicros>,
+    /// Time and count of consecutive packets that may re-anchor [TimeBounds::max_delta]
+    #[serde(default)]
+    pub reanchor: Option<(IetMicros, usize)>,
+    pub quarantined: usize,
+    pub duplicates_found: usize,
+    pub packed_pruned: usize,
+    #[serde(default)]
+    pub orphaned: usize,
+    #[serde(default)]
+    pub before_base_time: usize,
+    /// Standalone orphan RDRs not yet taken, for [OrphanPolicy::Standalone]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub standalone: Vec<Vec<Rdr>>,
+    /// Orphan RDRs to pack with the next primary granule, for [OrphanPolicy::Nearest]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nearest: Vec<Rdr>,
+    /// Completed RDRs not yet taken. See [Collector::take_completed].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub completed: Vec<Vec<Rdr>>,
+}
+
+/// Computes extra granule level attributes, such as an ascending/descending or day/night flag,
+/// for the granules produced by a [Collector]. See [Collector::with_annotator].
+///
+/// Implemented for functions and closures taking a [GranuleMeta], so a simple flag can be
+/// provided without a dedicated type, while something like a TLE-based orbit calculator can
+/// implement it directly.
+pub trait GranuleAnnotator: Send + Sync {
+    /// Extra attribute names and values to add to the granule dataset attributes for `meta`.
+    ///
+    /// # Errors
+    /// Implementation specific. Errors are logged and the granule is produced without extra
+    /// attributes.
+    fn annotate(&self, meta: &GranuleMeta) -> Result<Vec<(String, String)>>;
+}
+
+impl<F> GranuleAnnotator for F
+where
+    F: Fn(&GranuleMeta) -> Result<Vec<(String, String)>> + Send + Sync,
+{
+    fn annotate(&self, meta: &GranuleMeta) -> Result<Vec<(String, String)>> {
+        self(meta)
+    }
+}
+
+/// Sanity bounds on packet times.
+///
+/// A corrupt timecode can decode to a time far in the past or future which would otherwise
+/// create bogus granules. Packets with times outside these bounds are quarantined by the
+/// [Collector] rather than added to a granule.
+#[derive(Debug, Clone)]
+pub struct TimeBounds {
+    /// Minimum acceptable packet time
+    pub min: Option<Time>,
+    /// Maximum acceptable packet time
+    pub max: Option<Time>,
+    /// Maximum acceptable difference, in microseconds, from the previously accepted packet time
+    pub max_delta: Option<u64>,
+    /// Number of consecutive packets outside of `max_delta`, each within `max_delta` of the one
+    /// before it, after which the new times are accepted as following a real data gap rather
+    /// than being corrupt. The packets before the one that re-anchors are still quarantined.
+    /// Zero never re-anchors.
+    pub reanchor_after: usize,
+}
+
+impl Default for TimeBounds {
+    fn default() -> Self {
+        Self {
+            min: None,
+            max: None,
+            max_delta: None,
+            reanchor_after: Self::DEFAULT_REANCHOR_AFTER,
+        }
+    }
+}
+
+impl TimeBounds {
+    pub const DEFAULT_REANCHOR_AFTER: usize = 5;
+
+    /// Returns true if `time` is within bounds, where `prev` is the previously accepted time,
+    /// if any.
+    #[must_use]
+    pub fn contains(&self, time: &Time, prev: Option<&Time>) -> bool {
+        self.within_limits(time) && self.within_delta(time, prev)
+    }
+
+    /// Returns true if `time` is within the `min` and `max` bounds.
+    fn within_limits(&self, time: &Time) -> bool {
+        !(self.min.as_ref().is_some_and(|min| time < min)
+            || self.max.as_ref().is_some_and(|max| time > max))
+    }
+
+    /// Returns true if `time` is within `max_delta` of `prev`.
+    fn within_delta(&self, time: &Time, prev: Option<&Time>) -> bool {
+        match (self.max_delta, prev) {
+            (Some(max_delta), Some(prev)) => time.iet().abs_diff(prev.iet()) <= max_delta,
+            _ => true,
+        }
+    }
+}
+
+/// How packed granules, e.g., SPACECRAFT-DIARY, that are never packed with a primary granule
+/// are handled, e.g., diary granules from the edges of a pass without covering science data.
+///
+/// A packed granule is orphaned when it is pruned (see [Collector::with_packed_retention]) or
+/// collection finishes without it overlapping any primary granule.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OrphanPolicy {
+    /// Drop orphaned granules, logging each one
+    #[default]
+    Drop,
+    /// Produce each orphaned granule as its own RDR without a primary granule. See
+    /// [Collector::take_orphans].
+    Standalone,
+    /// Pack orphaned granules with the primary granule nearest in time. Orphans are dropped
+    /// if there are no primary granules.
+    Nearest,
+}
+
+/// Handling of packets with times before the spacecraft `base_time`, which cannot belong to any
+/// granule, e.g., test data from instrument vendors with epoch-zero timestamps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BaseTimePolicy {
+    /// Drop the packet, counting it. See [Collector::num_before_base_time].
+    #[default]
+    Skip,
+    /// Add the packet to the first granule, as if its time was the base time
+    Clamp,
+    /// Return an [RdrError::InvalidGranuleStart] error for the packet
+    Fail,
+}
+
 /// Collects individual product Rdr data.
 pub struct Collector {
     sat: SatSpec,
-    /// Maps the promary RDR products ids to the ids of products they're packed with
-    primary_ids: HashMap<String, Vec<String>>,
-    /// ids of all packed products we're collecting
-    packed_ids: HashSet<String>,
-    /// Maps product_id to spec
-    products: HashMap<String, ProductSpec>,
-    /// Maps apids to product_id. If a packet apid is not in this map it cannot be added
-    ids: HashMap<Apid, String>,
+    /// Index of all products. Products are referred to by their position in the index. If a
+    /// packet apid is not in the index it cannot be added.
+    index: ProductIndex,
+    /// Index positions of the primary RDR products
+    primary_products: HashSet<usize>,
+    /// Index positions of all packed products we're collecting
+    packed_products: HashSet<usize>,
 
     /// Maps product and RDR granule time to an RDR
-    primary: HashMap<(String, Time), RdrData>,
+    primary: HashMap<(usize, Time), RdrData>,
     /// Maps packed product and RDR granule time to an RDR
-    packed: HashMap<(String, Time), RdrData>,
+    packed: HashMap<(usize, Time), RdrData>,
+
+    /// If set, granule packet data is spilled to files in this directory rather than being
+    /// kept in memory.
+    spill_dir: Option<PathBuf>,
+    /// Duplicate packet policy for all collected granules
+    duplicates: DuplicatePolicy,
+    /// Application packet storage order for all collected granules
+    storage_order: StorageOrder,
+
+    time_bounds: TimeBounds,
+    /// Time of the last packet accepted, used for [TimeBounds::max_delta]
+    last_time: Option<Time>,
+    /// Time of the last packet quarantined only for [TimeBounds::max_delta], and the number
+    /// of consecutive such packets agreeing with it; see [TimeBounds::reanchor_after]
+    reanchor: Option<(Time, usize)>,
+    /// Number of packets quarantined due to being out of [TimeBounds]
+    quarantined: usize,
+    /// Number of duplicate packets handled by the [DuplicatePolicy]
+    duplicates_found: usize,
+    /// How long packed granules are kept after they can no longer overlap the oldest open
+    /// primary granule
+    packed_retention: Duration,
+    /// Number of packed granules pruned
+    packed_pruned: usize,
+    /// Packed granules that have been packed with at least one primary granule
+    packed_used: HashSet<(usize, Time)>,
+    orphans: OrphanPolicy,
+    /// Number of orphaned packed granules
+    orphaned: usize,
+    base_time_policy: BaseTimePolicy,
+    /// Number of packets with times before the spacecraft base time
+    before_base_time: usize,
+    /// Standalone orphan RDRs not yet taken, for [OrphanPolicy::Standalone]
+    standalone: Vec<Vec<Rdr>>,
+    /// Orphan RDRs to pack with the next primary granule, for [OrphanPolicy::Nearest]
+    nearest: Vec<Rdr>,
+    /// RDRs completed by [Collector::add] but not yet taken, see [Collector::take_completed]
+    completed: Vec<Vec<Rdr>>,
+    /// Pre-roll packets for primary granules not yet created. See [ProductSpec::padding].
+    preroll: HashMap<(usize, Time), Vec<(Time, Packet)>>,
+    /// Apid, sequence id, and time of the packets in the most recently completed primary
+    /// granules, so duplicates arriving after their granule is complete, e.g., from another
+    /// station's input, are dropped rather than creating the granule again.
+    completed_keys: HashMap<(usize, Time), HashSet<(Apid, u16, u64)>>,
+    warnings: RateLimitedWarnings,
+    annotator: Option<Box<dyn GranuleAnnotator>>,
+}
+
+fn new_rdr_data(
+    sat: &SatSpec,
+    product: &ProductSpec,
+    time: &Time,
+    spill_dir: Option<&Path>,
+    duplicates: DuplicatePolicy,
+    order: StorageOrder,
+) -> Result<RdrData> {
+    let storage = match spill_dir {
+        Some(dir) => PacketStorage::spill_in(dir)?,
+        None => PacketStorage::memory(),
+    };
+    Ok(RdrData::with_storage(sat, product, time, storage)
+        .with_duplicate_policy(duplicates)
+        .with_storage_order(order))
+}
+
+/// Decode a packet of the snapshot `granule`.
+fn snapshot_packet(granule: &GranuleSnapshot, bytes: &[u8]) -> Result<Packet> {
+    let Some(pkt) = decode_packets(bytes).next() else {
+        return Err(Error::RdrError(RdrError::Invalid(format!(
+            "empty snapshot packet for {} granule {}",
+            granule.product_id, granule.granule_time
+        ))));
+    };
+    Ok(pkt?)
 }
 
 impl Collector {
+    /// Default for [Collector::with_packed_retention].
+    pub const DEFAULT_PACKED_RETENTION: Duration = Duration::from_secs(300);
+
     #[must_use]
     pub fn new(sat: SatSpec, rdrs: &[RdrSpec], products: &[ProductSpec]) -> Self {
         let mut collector = Collector {
             sat,
-            primary_ids: HashMap::default(),
-            packed_ids: HashSet::default(),
-            products: HashMap::default(),
-            ids: HashMap::default(),
+            index: ProductIndex::new(products),
+            primary_products: HashSet::default(),
+            packed_products: HashSet::default(),
             primary: HashMap::default(),
             packed: HashMap::default(),
+            spill_dir: None,
+            duplicates: DuplicatePolicy::default(),
+            storage_order: StorageOrder::default(),
+            time_bounds: TimeBounds::default(),
+            last_time: None,
+            reanchor: None,
+            quarantined: 0,
+            duplicates_found: 0,
+            packed_retention: Self::DEFAULT_PACKED_RETENTION,
+            packed_pruned: 0,
+            packed_used: HashSet::default(),
+            orphans: OrphanPolicy::default(),
+            orphaned: 0,
+            base_time_policy: BaseTimePolicy::default(),
+            before_base_time: 0,
+            standalone: Vec::default(),
+            nearest: Vec::default(),
+            completed: Vec::default(),
+            preroll: HashMap::default(),
+            completed_keys: HashMap::default(),
+            warnings: RateLimitedWarnings::default(),
+            annotator: None,
         };
 
-        for product in products {
-            collector
-                .products
-                .insert(product.product_id.clone(), product.clone());
-            for apid in &product.apids {
-                collector.ids.insert(apid.num, product.product_id.clone());
+        for rdr in rdrs {
+            let Some(idx) = collector.index.position(&rdr.product) else {
+                warn!("no product spec for rdr product {}", rdr.product);
+                continue;
+            };
+            collector.primary_products.insert(idx);
+            for prod_id in &rdr.packed_with {
+                if let Some(idx) = collector.index.position(prod_id) {
+                    collector.packed_products.insert(idx);
+                }
             }
         }
 
+        collector
+    }
+
+    /// Spill granule packet data to temporary files in `dir` rather than keeping it in memory.
+    ///
+    /// This bounds memory use when collecting large numbers of granules at the cost of
+    /// additional IO. The spill files are removed once the granule data is dropped.
+    #[must_use]
+    pub fn with_spill_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.spill_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set how duplicate packets within a granule are handled. See [DuplicatePolicy].
+    #[must_use]
+    pub fn with_duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Set the order of packets in the application packet storage of collected granules. See
+    /// [StorageOrder].
+    #[must_use]
+    pub fn with_storage_order(mut self, order: StorageOrder) -> Self {
+        self.storage_order = order;
+        self
+    }
+
+    /// Quarantine packets with times outside of `bounds` rather than adding them to a granule.
+    ///
+    /// Note that when using [TimeBounds::max_delta] the first packet is always accepted, and
+    /// the accepted time re-anchors after [TimeBounds::reanchor_after] consecutive packets agree
+    /// on a new time, e.g., after a data gap longer than `max_delta`.
+    #[must_use]
+    pub fn with_time_bounds(mut self, bounds: TimeBounds) -> Self {
+        self.time_bounds = bounds;
+        self
+    }
+
+    /// Keep packed granules for `retention` after they can no longer overlap the oldest open
+    /// primary granule before pruning them.
+    ///
+    /// Packed granules are otherwise kept for the life of the collector, so this bounds memory
+    /// for long-running collection. Packed packets arriving for a pruned granule create a new
+    /// granule. A longer retention tolerates more out of order primary data.
+    #[must_use]
+    pub fn with_packed_retention(mut self, retention: Duration) -> Self {
+        self.packed_retention = retention;
+        self
+    }
+
+    /// Set extra granule attributes for every produced granule using `annotator`.
+    ///
+    /// Extra attributes are written to the granule dataset as fixed length ASCII strings and
+    /// must not have the same name as a standard granule attribute.
+    #[must_use]
+    pub fn with_annotator<A: GranuleAnnotator + 'static>(mut self, annotator: A) -> Self {
+        self.annotator = Some(Box::new(annotator));
+        self
+    }
+
+    fn annotate(&self, rdrs: &mut [Rdr]) {
+        let Some(annotator) = &self.annotator else {
+            return;
+        };
         for rdr in rdrs {
-            collector
-                .primary_ids
-                .insert(rdr.product.clone(), rdr.packed_with.clone());
-            for prod_id in &rdr.packed_with {
-                collector.packed_ids.insert(prod_id.clone());
+            match annotator.annotate(&rdr.meta) {
+                Ok(attrs) => rdr.meta.extra_attrs.extend(attrs),
+                Err(err) => warn!(
+                    "failed to annotate granule {} {}: {err}",
+                    rdr.meta.collection, rdr.meta.id
+                ),
             }
         }
+    }
 
-        collector
+    /// Capture the packets of all open granules, along with the collector counters and any
+    /// completed or orphan RDRs not yet taken or packed.
+    ///
+    /// Granules are in time order. Spilled packet data is read back into the snapshot.
+    ///
+    /// # Errors
+    /// If reading spilled packet data fails.
+    pub fn snapshot(&self) -> Result<CollectorSnapshot> {
+        let mut granules = Vec::default();
+        for (packed, datas) in [(false, &self.primary), (true, &self.packed)] {
+            for ((idx, time), data) in datas {
+                let mut packets = Vec::default();
+                for stored in data.ap_storage.packets() {
+                    let mut buf = Vec::with_capacity(stored.size);
+                    data.ap_storage
+                        .read_into(std::slice::from_ref(stored), &mut buf)?;
+                    packets.push((stored.time, buf));
+                }
+                granules.push(GranuleSnapshot {
+                    product_id: self.index.get(*idx).product_id.clone(),
+                    granule_time: time.iet_micros(),
+                    packed,
+                    used: packed && self.packed_used.contains(&(*idx, time.clone())),
+                    packets,
+                    padding: data
+                        .apid_list
+                        .iter()
+                        .filter(|(_, info)| info.pkts_padding > 0)
+                        .map(|(apid, info)| (*apid, info.pkts_padding))
+                        .collect(),
+                });
+            }
+        }
+        let mut preroll: Vec<GranuleSnapshot> = self
+            .preroll
+            .iter()
+            .map(|((idx, time), packets)| GranuleSnapshot {
+                product_id: self.index.get(*idx).product_id.clone(),
+                granule_time: time.iet_micros(),
+                packed: false,
+                used: false,
+                packets: packets
+                    .iter()
+                    .map(|(time, pkt)| (time.iet_micros(), pkt.data.clone()))
+                    .collect(),
+                padding: BTreeMap::default(),
+            })
+            .collect();
+        preroll
+            .sort_by(|a, b| (a.granule_time, &a.product_id).cmp(&(b.granule_time, &b.product_id)));
+        granules.sort_by(|a, b| {
+            (a.granule_time, a.packed, &a.product_id).cmp(&(
+                b.granule_time,
+                b.packed,
+                &b.product_id,
+            ))
+        });
+
+        Ok(CollectorSnapshot {
+            granules,
+            preroll,
+            last_time: self.last_time.as_ref().map(Time::iet_micros),
+            reanchor: self
+                .reanchor
+                .as_ref()
+                .map(|(time, count)| (time.iet_micros(), *count)),
+            quarantined: self.quarantined,
+            duplicates_found: self.duplicates_found,
+            packed_pruned: self.packed_pruned,
+            orphaned: self.orphaned,
+            before_base_time: self.before_base_time,
+            standalone: self.standalone.clone(),
+            nearest: self.nearest.clone(),
+            completed: self.completed.clone(),
+        })
+    }
+
+    /// Restore the open granules, counters, and completed and orphan RDRs from `snapshot`,
+    /// replacing any current state.
+    ///
+    /// Packets are re-added to their granules in their original order, so duplicate handling
+    /// and packet storage are the same as for the collector the snapshot was taken from, given
+    /// the same collector settings.
+    ///
+    /// # Errors
+    /// If a snapshot granule is for a product this collector is not collecting or a packet
+    /// cannot be decoded or added.
+    pub fn restore(&mut self, snapshot: CollectorSnapshot) -> Result<()> {
+        let mut primary = HashMap::default();
+        let mut packed = HashMap::default();
+        let mut packed_used = HashSet::default();
+        for granule in snapshot.granules {
+            let idx = self
+                .index
+                .position(&granule.product_id)
+                .filter(|idx| {
+                    if granule.packed {
+                        self.packed_products.contains(idx)
+                    } else {
+                        self.primary_products.contains(idx)
+                    }
+                })
+                .ok_or_else(|| {
+                    Error::RdrError(RdrError::Invalid(format!(
+                        "snapshot granule for product {} not being collected",
+                        granule.product_id
+                    )))
+                })?;
+            let gran_time = Time::from(granule.granule_time);
+            let mut data = new_rdr_data(
+                &self.sat,
+                self.index.get(idx),
+                &gran_time,
+                self.spill_dir.as_deref(),
+                self.duplicates,
+                self.storage_order,
+            )?;
+            for (time, bytes) in &granule.packets {
+                data.add_packet(&Time::from(*time), snapshot_packet(&granule, bytes)?)?;
+            }
+            for (apid, count) in granule.padding {
+                if let Some(info) = data.apid_list.get_mut(&apid) {
+                    info.pkts_padding = count;
+                }
+            }
+            let key = (idx, gran_time);
+            if granule.packed {
+                if granule.used {
+                    packed_used.insert(key.clone());
+                }
+                packed.insert(key, data);
+            } else {
+                primary.insert(key, data);
+            }
+        }
+
+        let mut preroll: HashMap<(usize, Time), Vec<(Time, Packet)>> = HashMap::default();
+        for granule in snapshot.preroll {
+            let Some(idx) = self
+                .index
+                .position(&granule.product_id)
+                .filter(|idx| self.primary_products.contains(idx))
+            else {
+                return Err(Error::RdrError(RdrError::Invalid(format!(
+                    "snapshot pre-roll for product {} not being collected",
+                    granule.product_id
+                ))));
+            };
+            let packets = granule
+                .packets
+                .iter()
+                .map(|(time, bytes)| Ok((Time::from(*time), snapshot_packet(&granule, bytes)?)))
+                .collect::<Result<Vec<_>>>()?;
+            preroll.insert((idx, Time::from(granule.granule_time)), packets);
+        }
+
+        self.primary = primary;
+        self.packed = packed;
+        self.preroll = preroll;
+        self.packed_used = packed_used;
+        self.last_time = snapshot.last_time.map(Time::from);
+        self.reanchor = snapshot
+            .reanchor
+            .map(|(time, count)| (Time::from(time), count));
+        self.quarantined = snapshot.quarantined;
+        self.duplicates_found = snapshot.duplicates_found;
+        self.packed_pruned = snapshot.packed_pruned;
+        self.orphaned = snapshot.orphaned;
+        self.before_base_time = snapshot.before_base_time;
+        self.standalone = snapshot.standalone;
+        self.nearest = snapshot.nearest;
+        self.completed = snapshot.completed;
+        Ok(())
+    }
+
+    /// Set how packed granules that are never packed with a primary granule are handled. See
+    /// [OrphanPolicy].
+    #[must_use]
+    pub fn with_orphan_policy(mut self, policy: OrphanPolicy) -> Self {
+        self.orphans = policy;
+        self
+    }
+
+    /// Number of packed granules orphaned so far. See [OrphanPolicy].
+    #[must_use]
+    pub fn num_orphaned(&self) -> usize {
+        self.orphaned
+    }
+
+    /// Set how packets with times before the spacecraft base time are handled. See
+    /// [BaseTimePolicy].
+    #[must_use]
+    pub fn with_base_time_policy(mut self, policy: BaseTimePolicy) -> Self {
+        self.base_time_policy = policy;
+        self
+    }
+
+    /// Number of packets for configured apids with times before the spacecraft base time so
+    /// far, which were skipped, clamped, or failed according to the [BaseTimePolicy].
+    #[must_use]
+    pub fn num_before_base_time(&self) -> usize {
+        self.before_base_time
+    }
+
+    /// Take the standalone RDRs produced for orphaned packed granules since the last call when
+    /// using [OrphanPolicy::Standalone]. Orphans are produced as packed granules are pruned,
+    /// so this should be called after [Collector::add]. Any not taken are returned by
+    /// [Collector::finish].
+    pub fn take_orphans(&mut self) -> Vec<Vec<Rdr>> {
+        std::mem::take(&mut self.standalone)
+    }
+
+    fn orphan(&mut self, key: (usize, Time), data: &RdrData) {
+        self.orphaned += 1;
+        let short_name = &self.index.get(key.0).short_name;
+        if self.orphans == OrphanPolicy::Drop {
+            warn!(
+                "dropping {short_name} granule {:?} not packed with any primary granule",
+                key.1
+            );
+            return;
+        }
+        let mut rdr = match data.compile() {
+            Ok(rdr) => rdr,
+            Err(err) => {
+                warn!(
+                    "failed to compile orphaned {short_name} granule {:?}: {err}",
+                    key.1
+                );
+                return;
+            }
+        };
+        self.annotate(std::slice::from_mut(&mut rdr));
+        match self.orphans {
+            OrphanPolicy::Standalone => self.standalone.push(vec![rdr]),
+            _ => self.nearest.push(rdr),
+        }
+    }
+
+    /// Number of packed granules pruned. See [Collector::with_packed_retention].
+    #[must_use]
+    pub fn num_packed_pruned(&self) -> usize {
+        self.packed_pruned
+    }
+
+    /// Remove packed granules that end more than the packed retention before the oldest open
+    /// primary granule and therefore can no longer be packed with any primary granule.
+    fn prune_packed(&mut self) {
+        let Some(oldest) = self.primary.keys().map(|(_, time)| time.iet()).min() else {
+            return;
+        };
+        let retention = u64::try_from(self.packed_retention.as_micros()).unwrap_or(u64::MAX);
+        let expired: Vec<(usize, Time)> = self
+            .packed
+            .keys()
+            .filter(|(idx, time)| {
+                let end = time.iet() + self.index.get(*idx).gran_len.micros();
+                end.saturating_add(retention) <= oldest
+            })
+            .cloned()
+            .collect();
+        if expired.is_empty() {
+            return;
+        }
+        trace!(
+            "pruning {} packed granules ending before {oldest}",
+            expired.len()
+        );
+        self.packed_pruned += expired.len();
+        for key in expired {
+            let data = self.packed.remove(&key).expect("expired key exists");
+            if !self.packed_used.remove(&key) {
+                self.orphan(key, &data);
+            }
+        }
+    }
+
+    /// Number of packets quarantined because their time was outside the configured
+    /// [TimeBounds].
+    #[must_use]
+    pub fn num_quarantined(&self) -> usize {
+        self.quarantined
+    }
+
+    /// Number of duplicate packets, e.g., from the same downlink received at multiple
+    /// stations, that were dropped or replaced according to the [DuplicatePolicy].
+    #[must_use]
+    pub fn num_duplicates(&self) -> usize {
+        self.duplicates_found
     }
 
     /// Get all overlapping configured packed products.
     ///
     /// This is all granules where the packet granule start is within its granule length of
-    /// the start of the primary granule start and less than the primary granule end.
-    fn overlapping_packed_rdrs(&self, rdr: &Rdr) -> Result<Vec<Rdr>> {
-        let primary_gran_start = rdr.meta.begin_time_iet as i64;
-        let primary_gran_end = rdr.meta.end_time_iet as i64;
-        let mut packed = Vec::default();
-
-        for packed_id in &self.packed_ids {
-            let packed_product = self.products.get(packed_id).expect("spec for existing id");
-            let Ok(packed_gran_len) = i64::try_from(packed_product.gran_len) else {
-                return Err(Error::ConfigInvalid(
-                    "gran_len cannot be convert to i64".to_string(),
-                ));
-            };
+    /// the start of the primary granule start and less than the primary granule end. See
+    /// [is_packed_overlap].
+    fn overlapping_packed_rdrs(&mut self, rdr: &Rdr) -> Result<Vec<Rdr>> {
+        let primary_gran_start = rdr.meta.begin_time_iet;
+        let primary_gran_end = rdr.meta.end_time_iet;
+        let mut keys: Vec<(usize, Time)> = Vec::default();
 
-            for ((_, packed_time), data) in &self.packed {
-                let packed_gran_start = packed_time.iet() as i64;
-
-                if packed_gran_start > primary_gran_start - packed_gran_len
-                    && packed_gran_start < primary_gran_end
-                {
-                    let rdr = match data.compile() {
-                        Ok(r) => r,
-                        Err(err) => {
-                            warn!("failed to compile rdr data: {err}");
-                            continue;
-                        }
-                    };
-                    packed.push(rdr);
+        for packed_idx in &self.packed_products {
+            let packed_product = self.index.get(*packed_idx);
+
+            for (idx, packed_time) in self.packed.keys() {
+                if idx != packed_idx {
+                    continue;
+                }
+                if is_packed_overlap(
+                    primary_gran_start,
+                    primary_gran_end,
+                    packed_time.iet_micros(),
+                    packed_product.gran_len,
+                ) {
+                    keys.push((*idx, packed_time.clone()));
                 }
             }
         }
+        let overlapping: Vec<&RdrData> = keys.iter().map(|key| &self.packed[key]).collect();
+        let packed: Vec<Rdr> = overlapping
+            .par_iter()
+            .filter_map(|data| {
+                data.compile()
+                    .inspect_err(|err| warn!("failed to compile rdr data: {err}"))
+                    .ok()
+            })
+            .collect();
         trace!(
             "{} overlapping granules for start={primary_gran_start} end={primary_gran_end}",
             packed.len()
         );
+        self.packed_used.extend(keys);
         Ok(packed)
     }
 
@@ -110,92 +744,318 @@ impl Collector {
     /// The current primary granule can never be complete because we may not yet have all the
     /// overlapping packed data, so only the second to last granule is checked.
     ///
+    /// If the packet APID is shared by multiple products the packet is added to each of them.
+    /// See [Collector::take_completed].
+    ///
     /// # Errors
-    /// If the RDR granule time computed from the packet time is invalid for the spacecraft
-    /// configuration.
+    /// If the packet time is before the spacecraft base time when using [BaseTimePolicy::Fail],
+    /// or the RDR granule time computed from the packet time is otherwise invalid for the
+    /// spacecraft configuration.
     pub fn add(&mut self, pkt_time: &Time, pkt: Packet) -> Result<Option<Vec<Rdr>>> {
-        // The the product for this packet's apid
-        let Some(prod_id) = self.ids.get(&pkt.header.apid) else {
+        metrics::packet_added();
+        let zult = self.add_packet(pkt_time, pkt);
+        if zult.is_err() {
+            metrics::packet_dropped(DropReason::Invalid);
+        }
+        // Packed granules also expire while no primary granule completes, e.g., during a gap in
+        // primary data
+        self.prune_packed();
+        metrics::open_granules(self.primary.len() + self.packed.len());
+        zult
+    }
+
+    fn add_packet(&mut self, pkt_time: &Time, pkt: Packet) -> Result<Option<Vec<Rdr>>> {
+        // The products for this packet's apid; usually one, but an apid may be shared
+        let products: Vec<usize> = self
+            .index
+            .positions_for_apid(pkt.header.apid)
+            .filter(|idx| self.primary_products.contains(idx) || self.packed_products.contains(idx))
+            .collect();
+        let Some((last, rest)) = products.split_last() else {
+            metrics::packet_dropped(DropReason::NoProduct);
+            return Ok(None);
+        };
+
+        // Packets for unconfigured apids are dropped above, so are not counted here
+        let clamped;
+        let pkt_time = if pkt_time.iet_micros() < self.sat.base_time {
+            self.before_base_time += 1;
+            match self.base_time_policy {
+                BaseTimePolicy::Skip => {
+                    self.warnings.warn(
+                        "skipping packet with time before base time",
+                        format_args!("apid={} time={pkt_time:?}", pkt.header.apid),
+                    );
+                    metrics::packet_dropped(DropReason::BeforeBaseTime);
+                    return Ok(None);
+                }
+                BaseTimePolicy::Clamp => {
+                    clamped = Time::from(self.sat.base_time);
+                    &clamped
+                }
+                BaseTimePolicy::Fail => {
+                    return Err(Error::RdrError(RdrError::InvalidGranuleStart(
+                        pkt_time.iet(),
+                    )));
+                }
+            }
+        } else {
+            pkt_time
+        };
+
+        if !self.time_bounds.contains(pkt_time, self.last_time.as_ref())
+            && !self.reanchors(pkt_time)
+        {
+            self.warnings.warn(
+                "quarantining packet with out of bounds time",
+                format_args!("apid={} time={pkt_time:?}", pkt.header.apid),
+            );
+            self.quarantined += 1;
+            metrics::packet_dropped(DropReason::OutOfBounds);
             return Ok(None);
+        }
+        self.last_time = Some(pkt_time.clone());
+        self.reanchor = None;
+
+        // Each product gets its own copy of the packet, but a packet is only counted once as a
+        // duplicate
+        let mut duplicate = false;
+        let mut completed = Vec::default();
+        for idx in rest {
+            let (dup, rdrs) = self.add_to_product(*idx, pkt_time, pkt.clone())?;
+            duplicate |= dup;
+            completed.extend(rdrs);
+        }
+        let (dup, rdrs) = self.add_to_product(*last, pkt_time, pkt)?;
+        duplicate |= dup;
+        completed.extend(rdrs);
+        if duplicate {
+            self.duplicates_found += 1;
+            metrics::packet_dropped(DropReason::Duplicate);
+        }
+
+        let mut completed = completed.into_iter();
+        let first = completed.next();
+        self.completed.extend(completed);
+        Ok(first)
+    }
+
+    /// Take the RDRs completed by [Collector::add] in addition to the one it returned. This only
+    /// happens when an APID is shared by multiple primary products, so a single packet can
+    /// complete more than one RDR. Any not taken are returned by [Collector::finish].
+    pub fn take_completed(&mut self) -> Vec<Vec<Rdr>> {
+        std::mem::take(&mut self.completed)
+    }
+
+    /// Returns true if `time`, which is out of [TimeBounds::max_delta] of the last accepted time
+    /// but otherwise in bounds, is the [TimeBounds::reanchor_after]th consecutive time to agree
+    /// with the one before it, so it should be accepted as following a real gap.
+    fn reanchors(&mut self, time: &Time) -> bool {
+        let bounds = &self.time_bounds;
+        if bounds.reanchor_after == 0 || !bounds.within_limits(time) {
+            return false;
+        }
+        let count = match &self.reanchor {
+            Some((prev, count)) if bounds.within_delta(time, Some(prev)) => count + 1,
+            _ => 1,
         };
-        let product = self.products.get(prod_id).expect("spec for existing id");
+        if count < bounds.reanchor_after {
+            self.reanchor = Some((time.clone(), count));
+            return false;
+        }
+        self.warnings.warn(
+            "re-anchoring packet time bounds after a gap",
+            format_args!("time={time:?} last={:?}", self.last_time),
+        );
+        true
+    }
+
+    /// Keep the packet keys of the completed primary granule `key` for duplicate detection,
+    /// forgetting those of earlier granules of the same product. Granules are completed in
+    /// order, so duplicates are only expected for the most recently completed granule.
+    fn remember_completed(&mut self, key: (usize, Time), data: &RdrData) {
+        if self.duplicates == DuplicatePolicy::Keep {
+            return;
+        }
+        self.completed_keys.retain(|(idx, _), _| *idx != key.0);
+        self.completed_keys
+            .insert(key, data.packet_keys().copied().collect());
+    }
+
+    /// Add a packet to the granule for product `prod_idx`, returning whether it was a duplicate
+    /// and the completed primary RDR, if any.
+    fn add_to_product(
+        &mut self,
+        prod_idx: usize,
+        pkt_time: &Time,
+        pkt: Packet,
+    ) -> Result<(bool, Option<Vec<Rdr>>)> {
+        let is_primary = self.primary_products.contains(&prod_idx);
+        let product = self.index.get(prod_idx);
 
         // The granule time this packet belongs to, i.e., the one it gets added to
-        let gran_time = Time::from_iet(get_granule_start(
-            pkt_time.iet(),
+        let gran_time = Time::from(get_granule_start(
+            pkt_time.iet_micros(),
             product.gran_len,
             self.sat.base_time,
         ));
-        if gran_time.iet() < self.sat.base_time {
+        if gran_time.iet_micros() < self.sat.base_time {
             return Err(Error::RdrError(RdrError::InvalidGranuleStart(
                 gran_time.iet(),
             )));
         }
 
         // If this packet is for a primary product RDR add it to the primary collection
-        let key = (product.product_id.clone(), gran_time.clone());
-        if self.primary_ids.contains_key(prod_id) {
-            {
-                let data = self.primary.entry(key).or_insert_with(|| {
-                    trace!(
-                        "new primary granule product_id={} granule={:?}",
-                        product.product_id,
-                        gran_time,
+        let key = (prod_idx, gran_time.clone());
+        if is_primary {
+            if self.completed_keys.get(&key).is_some_and(|keys| {
+                keys.contains(&(pkt.header.apid, pkt.header.sequence_id, pkt_time.iet()))
+            }) {
+                trace!(
+                    "duplicate packet for completed granule apid={} seq={} time={pkt_time:?}",
+                    pkt.header.apid,
+                    pkt.header.sequence_id
+                );
+                return Ok((true, None));
+            }
+
+            // Packets near a granule boundary are also added to the adjacent granule
+            if let Some(padding) = product.padding {
+                let offset = pkt_time.iet() - gran_time.iet();
+                if offset < padding.post {
+                    // Post-roll for the previous granule, if it is still being collected
+                    let prev = (
+                        prod_idx,
+                        Time::from(gran_time.iet_micros() - product.gran_len),
                     );
-                    RdrData::new(&self.sat, product, &gran_time)
-                });
-                data.add_packet(pkt_time, pkt)?;
+                    if let Some(data) = self.primary.get_mut(&prev) {
+                        data.add_padding_packet(pkt_time, pkt.clone())?;
+                    }
+                }
+                if product.gran_len.micros() - offset <= padding.pre {
+                    // Pre-roll for the next granule, held until the granule is created so a
+                    // granule is never created only from padding
+                    let next = (
+                        prod_idx,
+                        Time::from(gran_time.iet_micros() + product.gran_len),
+                    );
+                    match self.primary.get_mut(&next) {
+                        Some(data) => data.add_padding_packet(pkt_time, pkt.clone())?,
+                        None => self
+                            .preroll
+                            .entry(next)
+                            .or_default()
+                            .push((pkt_time.clone(), pkt.clone())),
+                    }
+                }
             }
 
+            let duplicate = {
+                let data = match self.primary.entry(key) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        trace!(
+                            "new primary granule product_id={} granule={:?}",
+                            product.product_id,
+                            gran_time,
+                        );
+                        let mut data = new_rdr_data(
+                            &self.sat,
+                            product,
+                            &gran_time,
+                            self.spill_dir.as_deref(),
+                            self.duplicates,
+                            self.storage_order,
+                        )?;
+                        if let Some(packets) = self.preroll.remove(entry.key()) {
+                            for (time, pkt) in packets {
+                                data.add_padding_packet(&time, pkt)?;
+                            }
+                        }
+                        // Pre-roll for earlier granules that were never created is dropped
+                        self.preroll
+                            .retain(|(idx, time), _| *idx != prod_idx || *time > gran_time);
+                        entry.insert(data)
+                    }
+                };
+                let before = data.num_duplicates();
+                data.add_packet(pkt_time, pkt)?;
+                data.num_duplicates() > before
+            };
+
             // If the second to last primary granule exists we assume it has had a chance to get
-            // any overlapping packed products it may need, so we consider it "complete".
+            // any overlapping packed products it may need, so we consider it "complete". With
+            // padding, completion waits one more granule so post-roll packets arriving out of
+            // order, e.g., from another apid, are not lost.
+            let behind = if product.padding.is_some() { 3 } else { 2 };
             let second_to_last_key = (
-                product.product_id.clone(),
-                Time::from_iet(gran_time.iet() - product.gran_len * 2),
+                prod_idx,
+                Time::from(gran_time.iet_micros() - product.gran_len * behind),
             );
             if let Some(data) = self.primary.remove(&second_to_last_key) {
+                self.remember_completed(second_to_last_key, &data);
                 let rdr = match data.compile() {
                     Ok(r) => r,
                     Err(err) => {
                         warn!("failed to compile rdr data: {err}");
-                        return Ok(None);
+                        return Ok((duplicate, None));
                     }
                 };
                 let packed = self.overlapping_packed_rdrs(&rdr)?;
+                self.prune_packed();
                 let mut rdrs = vec![rdr];
                 rdrs.extend_from_slice(&packed);
-                Ok(Some(rdrs))
+                self.annotate(&mut rdrs);
+                // Orphans pruned above end before the remaining open primary granules, so
+                // this is the nearest primary granule
+                rdrs.append(&mut self.nearest);
+                Ok((duplicate, Some(rdrs)))
             } else {
-                Ok(None)
+                Ok((duplicate, None))
             }
         } else {
-            assert!(self.packed_ids.contains(&product.product_id));
-            // FIXME: Figure out how to clean up packed products
-            let data = self.packed.entry(key).or_insert_with(|| {
-                trace!(
-                    "new packed granule product_id={} time={:?}",
-                    product.product_id,
-                    gran_time,
-                );
-                RdrData::new(&self.sat, product, &gran_time)
-            });
+            let data = match self.packed.entry(key) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    trace!(
+                        "new packed granule product_id={} time={:?}",
+                        product.product_id,
+                        gran_time,
+                    );
+                    entry.insert(new_rdr_data(
+                        &self.sat,
+                        product,
+                        &gran_time,
+                        self.spill_dir.as_deref(),
+                        self.duplicates,
+                        self.storage_order,
+                    )?)
+                }
+            };
+            let before = data.num_duplicates();
             data.add_packet(pkt_time, pkt)?;
-            Ok(None)
+            Ok((data.num_duplicates() > before, None))
         }
     }
 
     pub fn finish(mut self) -> Result<Vec<Vec<Rdr>>> {
-        let mut keys: Vec<(String, Time)> = self.primary.keys().map(|k| (*k).clone()).collect();
+        let mut keys: Vec<(usize, Time)> = self.primary.keys().cloned().collect();
         keys.sort_by(|a, b| a.1.cmp(&b.1));
 
+        // Compile the remaining primary granules in parallel, keeping them in time order
+        let datas: Vec<RdrData> = keys
+            .iter()
+            .map(|key| {
+                self.primary
+                    .remove(key)
+                    .expect("exists because we created keys above")
+            })
+            .collect();
+        let compiled: Vec<Result<Rdr>> = datas.par_iter().map(RdrData::compile).collect();
+
         let mut finished = Vec::default();
-        for (pid, time) in &keys {
-            let key = (pid.clone(), time.clone());
-            let data = self
-                .primary
-                .remove(&key)
-                .expect("exists because we created keys above");
-            let rdr = match data.compile() {
+        for rdr in compiled {
+            let rdr = match rdr {
                 Ok(r) => r,
                 Err(err) => {
                     warn!("failed to compile rdr data: {err}");
@@ -206,14 +1066,50 @@ impl Collector {
             let packed = self.overlapping_packed_rdrs(&rdr)?;
             let mut rdrs = vec![rdr];
             rdrs.extend_from_slice(&packed);
+            self.annotate(&mut rdrs);
             finished.push(rdrs);
         }
 
+        // Any packed granules never packed with a primary granule are orphans
+        let mut orphans: Vec<((usize, Time), RdrData)> = self
+            .packed
+            .drain()
+            .filter(|(key, _)| !self.packed_used.contains(key))
+            .collect();
+        orphans.sort_by(|((_, a), _), ((_, b), _)| a.cmp(b));
+        for (key, data) in orphans {
+            self.orphan(key, &data);
+        }
+        if self.orphaned > 0 {
+            debug!("{} orphaned packed granules", self.orphaned);
+        }
+        for rdr in std::mem::take(&mut self.nearest) {
+            let begin = rdr.meta.begin_time_iet;
+            let nearest = finished
+                .iter_mut()
+                .min_by_key(|rdrs| rdrs[0].meta.begin_time_iet.abs_diff(begin));
+            match nearest {
+                Some(rdrs) => rdrs.push(rdr),
+                None => warn!(
+                    "dropping {} granule {} with no primary granule to pack with",
+                    rdr.meta.collection, rdr.meta.id
+                ),
+            }
+        }
+        finished.append(&mut self.completed);
+        finished.append(&mut self.standalone);
+        finished.sort_by_key(|rdrs| rdrs[0].meta.begin_time_iet);
+
         Ok(finished)
     }
 }
 
 /// Iterator that produces tuples of `Packet` and their time.
+///
+/// Packets in a group get the time of the first packet in the group. Groups whose time cannot
+/// be decoded are skipped with a warning and counted in [PacketTimeIter::stats]. Iteration
+/// stops early if more groups than allowed by [PacketTimeIter::with_max_time_errors] are
+/// skipped; use [PacketTimeIter::check] after iterating to detect this.
 pub struct PacketTimeIter<P>
 where
     P: Iterator<Item = PacketGroup>,
@@ -221,6 +1117,9 @@ where
     time_decoder: TimecodeDecoder,
     groups: P,
     cache: VecDeque<(Packet, Time)>,
+    warnings: RateLimitedWarnings,
+    stats: PacketTimeStats,
+    max_time_errors: Option<usize>,
 }
 
 impl<P> PacketTimeIter<P>
@@ -230,12 +1129,51 @@ where
     pub fn new(groups: P) -> Self {
         PacketTimeIter {
             cache: VecDeque::default(),
-            time_decoder: TimecodeDecoder::new(ccsds::timecode::Format::Cds {
-                num_day: 2,
-                num_submillis: 2,
-            }),
+            time_decoder: TimecodeSpec::default().decoder(),
             groups,
+            warnings: RateLimitedWarnings::default(),
+            stats: PacketTimeStats::default(),
+            max_time_errors: None,
+        }
+    }
+
+    /// Stop iterating once more than `max` groups have been skipped because their time could
+    /// not be decoded, e.g., for input that is not the expected format. By default there is no
+    /// limit.
+    #[must_use]
+    pub fn with_max_time_errors(mut self, max: usize) -> Self {
+        self.max_time_errors = Some(max);
+        self
+    }
+
+    fn exceeded_time_errors(&self) -> bool {
+        self.max_time_errors
+            .is_some_and(|max| self.stats.groups_dropped > max)
+    }
+
+    /// Check whether iteration was stopped early due to time decode failures. See
+    /// [PacketTimeIter::with_max_time_errors].
+    ///
+    /// # Errors
+    /// [Error::TooManyTimeErrors] if more groups than allowed were skipped.
+    pub fn check(&self) -> Result<()> {
+        if self.exceeded_time_errors() {
+            return Err(Error::TooManyTimeErrors(self.stats.groups_dropped));
         }
+        Ok(())
+    }
+
+    /// Statistics for the groups read so far, e.g., to report after iteration.
+    #[must_use]
+    pub fn stats(&self) -> &PacketTimeStats {
+        &self.stats
+    }
+
+    /// Decode packet times using `timecode` rather than the default JPSS timecode.
+    #[must_use]
+    pub fn with_timecode(mut self, timecode: &TimecodeSpec) -> Self {
+        self.time_decoder = timecode.decoder();
+        self
     }
 }
 
@@ -246,7 +1184,10 @@ where
     type Item = (Packet, Time);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.cache.is_empty() {
+        while self.cache.is_empty() {
+            if self.exceeded_time_errors() {
+                return None;
+            }
             let group = self.groups.next()?;
             assert!(
                 !group.packets.is_empty(),
@@ -254,10 +1195,13 @@ where
             );
             let first = &group.packets[0];
             let Ok(epoch) = self.time_decoder.decode(first) else {
-                warn!("failed to decode time from {:?}", first);
-                return None;
+                self.warnings
+                    .warn("failed to decode packet time", format_args!("{first:?}"));
+                self.stats.drop_group(group.packets.len());
+                continue;
             };
             let time = Time::from_epoch(epoch);
+            self.stats.add_group(group.packets.len(), &time);
 
             for pkt in group.packets {
                 self.cache.push_back((pkt, time.clone()));
@@ -266,3 +1210,894 @@ where
         self.cache.pop_front()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicates_across_inputs() {
+        let config = crate::config::get_default("npp").unwrap().unwrap();
+        // apid 826 w/ secondary header, unsegmented, 4 bytes of user data
+        let mut dat: Vec<u8> = Vec::default();
+        for seq in [1u8, 2] {
+            dat.extend_from_slice(&[0x0b, 0x3a, 0xc0, seq, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa]);
+        }
+        let packets: Vec<Packet> = ccsds::spacepacket::decode_packets(&dat[..])
+            .filter_map(|p| p.ok())
+            .collect();
+        let time = Time::from(config.satellite.base_time + 1_000_000);
+
+        // The same packets as if from 2 stations
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+        for pkt in packets.iter().chain(packets.iter()) {
+            collector.add(&time, pkt.clone()).unwrap();
+        }
+        assert_eq!(collector.num_duplicates(), 2);
+        let rdrs = collector.finish().unwrap();
+        let num_packets = rdrs[0][0].meta.packet_type_count.iter().sum::<u32>();
+        assert_eq!(num_packets, 2);
+
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products)
+                .with_duplicate_policy(DuplicatePolicy::Keep);
+        for pkt in packets.iter().chain(packets.iter()) {
+            collector.add(&time, pkt.clone()).unwrap();
+        }
+        assert_eq!(collector.num_duplicates(), 0);
+
+        // The second input's packets arrive after the first input's granule is complete
+        let first =
+            crate::fixtures::viirs_packets(&crate::fixtures::granule_time(&config, 0).unwrap(), 2);
+        let mut dat = first.clone();
+        for num in 1..3 {
+            dat.extend(crate::fixtures::viirs_packets(
+                &crate::fixtures::granule_time(&config, num).unwrap(),
+                1,
+            ));
+        }
+        dat.extend(first);
+        let groups = ccsds::spacepacket::collect_groups(
+            ccsds::spacepacket::decode_packets(&dat[..]).filter_map(|p| p.ok()),
+        )
+        .filter_map(|g| g.ok());
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+        let mut rdrs = Vec::default();
+        for (pkt, time) in PacketTimeIter::new(groups).with_timecode(&config.satellite.timecode) {
+            rdrs.extend(collector.add(&time, pkt).unwrap());
+        }
+        assert_eq!(rdrs.len(), 1, "first granule should be complete");
+        assert_eq!(collector.num_duplicates(), 2);
+        rdrs.extend(collector.finish().unwrap());
+        let times: Vec<IetMicros> = rdrs.iter().map(|r| r[0].meta.begin_time_iet).collect();
+        assert_eq!(
+            times.len(),
+            3,
+            "granule created again from duplicates: {times:?}"
+        );
+    }
+
+    #[test]
+    fn test_shared_apid() {
+        // apid 826 shared by the primary RVIRS and RATMD and the packed RNSCA
+        let overlay = "
+products:
+  - product_id: RATMD
+    apids:
+      - { num: 826, name: ENG, max_expected: 1 }
+  - product_id: RNSCA
+    apids:
+      - { num: 826, name: ENG, max_expected: 1 }
+";
+        let config = crate::config::Config::with_overrides(
+            crate::config::get_default_content("npp").unwrap(),
+            overlay,
+        )
+        .unwrap();
+        let mut dat: Vec<u8> = Vec::default();
+        for seq in [1u8, 2] {
+            dat.extend_from_slice(&[0x0b, 0x3a, 0xc0, seq, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa]);
+        }
+        let packets: Vec<Packet> = ccsds::spacepacket::decode_packets(&dat[..])
+            .filter_map(|p| p.ok())
+            .collect();
+        let time = Time::from(config.satellite.base_time + 1_000_000);
+
+        // Each packet twice, as if from 2 stations
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+        for pkt in packets.iter().chain(packets.iter()) {
+            collector.add(&time, pkt.clone()).unwrap();
+        }
+        // duplicates are counted per packet, not per product
+        assert_eq!(collector.num_duplicates(), 2);
+        assert!(collector.take_completed().is_empty());
+
+        let produced = collector.finish().unwrap();
+        assert_eq!(produced.len(), 2, "expected a set for each primary product");
+        let num_packets = |rdr: &Rdr| rdr.meta.packet_type_count.iter().sum::<u32>();
+        let viirs = produced
+            .iter()
+            .find(|rdrs| rdrs[0].product_id == "RVIRS")
+            .unwrap();
+        assert_eq!(viirs.len(), 2);
+        assert_eq!(viirs[1].product_id, "RNSCA");
+        assert_eq!(num_packets(&viirs[0]), 2);
+        assert_eq!(num_packets(&viirs[1]), 2);
+        let atmd = produced
+            .iter()
+            .find(|rdrs| rdrs[0].product_id == "RATMD")
+            .unwrap();
+        assert_eq!(atmd.len(), 1);
+        assert_eq!(num_packets(&atmd[0]), 2);
+    }
+
+    #[test]
+    fn test_formula_granule_ids() {
+        let overlay = "
+satellite:
+  granule_id: { scheme: formula, prefix: GW1, resolution: 1000000, width: 10 }
+";
+        let config = crate::config::Config::with_overrides(
+            crate::config::get_default_content("npp").unwrap(),
+            overlay,
+        )
+        .unwrap();
+
+        let produced = crate::fixtures::viirs_granules(&config, 0, 2).unwrap();
+
+        assert_eq!(produced.len(), 2);
+        for rdrs in &produced {
+            let meta = &rdrs[0].meta;
+            assert_eq!(
+                meta.id,
+                config.satellite.granule_id(meta.begin_time_iet).unwrap()
+            );
+            assert!(meta.id.starts_with("GW1"), "{}", meta.id);
+            assert!(meta.reference_id.contains(&meta.id));
+        }
+    }
+
+    #[test]
+    fn test_configured_granule_meta() {
+        let overlay = "
+satellite:
+  orbits: { ascending_node: 1698019234000000, period: 6085000000, number: 500 }
+products:
+  - product_id: RVIRS
+    instrument: VIIRS-X
+    document_ref: CDFCB-X.pdf
+";
+        let config = crate::config::Config::with_overrides(
+            crate::config::get_default_content("npp").unwrap(),
+            overlay,
+        )
+        .unwrap();
+
+        let produced = crate::fixtures::viirs_granules(&config, 0, 1).unwrap();
+
+        let meta = &produced[0][0].meta;
+        assert_eq!(meta.instrument, "VIIRS-X");
+        assert_eq!(meta.jpss_doc, "CDFCB-X.pdf");
+        assert_eq!(meta.orbit_number, 500);
+    }
+
+    #[test]
+    fn test_snapshot_restore_completed() {
+        // apid 826 shared by the primary RVIRS and RATMD, with the same granule length so a
+        // single packet completes a granule of each
+        let overlay = "
+products:
+  - product_id: RATMD
+    gran_len: 85350000
+    apids:
+      - { num: 826, name: ENG, max_expected: 1 }
+";
+        let config = crate::config::Config::with_overrides(
+            crate::config::get_default_content("npp").unwrap(),
+            overlay,
+        )
+        .unwrap();
+        let viirs =
+            decode_packets(&[0x0b, 0x3a, 0xc0, 0x01, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa][..])
+                .next()
+                .unwrap()
+                .unwrap();
+        let viirs_len = 85_350_000;
+
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+        let mut produced = Vec::default();
+        for num in 0..3 {
+            let time = Time::from(config.satellite.base_time + num * viirs_len + 1_000_000);
+            produced.extend(collector.add(&time, viirs.clone()).unwrap());
+        }
+        assert_eq!(produced.len(), 1);
+
+        let snapshot = collector.snapshot().unwrap();
+        assert_eq!(snapshot.completed.len(), 1);
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let snapshot: CollectorSnapshot = serde_json::from_str(&json).unwrap();
+        let mut restored = Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+        restored.restore(snapshot.clone()).unwrap();
+        assert_eq!(restored.snapshot().unwrap(), snapshot);
+
+        let completed = restored.take_completed();
+        assert_eq!(completed, collector.take_completed());
+        assert_eq!(completed.len(), 1);
+        assert_ne!(completed[0][0].product_id, produced[0][0].product_id);
+    }
+
+    #[test]
+    fn test_prune_packed() {
+        let config = crate::config::get_default("npp").unwrap().unwrap();
+        let base_time = config.satellite.base_time;
+        // apid 11 (diary) and 826 (viirs) w/ secondary header, unsegmented
+        let diary = ccsds::spacepacket::decode_packets(
+            &[0x08, 0x0b, 0xc0, 0x01, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa][..],
+        )
+        .next()
+        .unwrap()
+        .unwrap();
+        let viirs = ccsds::spacepacket::decode_packets(
+            &[0x0b, 0x3a, 0xc0, 0x01, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa][..],
+        )
+        .next()
+        .unwrap()
+        .unwrap();
+        let viirs_len = 85_350_000;
+
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products)
+                .with_packed_retention(Duration::from_secs(120));
+        collector
+            .add(&Time::from(base_time + 1_000_000), diary.clone())
+            .unwrap();
+        // diary packet within retention of the first primary granule
+        let recent = base_time + 10 * viirs_len;
+        collector
+            .add(&Time::from(recent - 30_000_000), diary)
+            .unwrap();
+        assert_eq!(collector.packed.len(), 2);
+
+        // 3 primary granules so the first is complete
+        for num in 10..13 {
+            let time = Time::from(base_time + num * viirs_len + 1_000_000);
+            collector.add(&time, viirs.clone()).unwrap();
+        }
+        assert_eq!(collector.num_packed_pruned(), 1);
+        assert_eq!(collector.packed.len(), 1);
+    }
+
+    #[test]
+    fn test_prune_packed_on_add() {
+        let config = crate::config::get_default("npp").unwrap().unwrap();
+        let base_time = config.satellite.base_time;
+        // apid 11 (diary) and 826 (viirs) w/ secondary header, unsegmented
+        let diary = ccsds::spacepacket::decode_packets(
+            &[0x08, 0x0b, 0xc0, 0x01, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa][..],
+        )
+        .next()
+        .unwrap()
+        .unwrap();
+        let viirs = ccsds::spacepacket::decode_packets(
+            &[0x0b, 0x3a, 0xc0, 0x01, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa][..],
+        )
+        .next()
+        .unwrap()
+        .unwrap();
+        let viirs_len = 85_350_000;
+
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products)
+                .with_packed_retention(Duration::from_secs(120));
+        // A single open primary granule that never completes
+        collector
+            .add(&Time::from(base_time + 10 * viirs_len), viirs)
+            .unwrap();
+        collector
+            .add(&Time::from(base_time + 10 * viirs_len), diary.clone())
+            .unwrap();
+        assert_eq!(collector.packed.len(), 1);
+
+        // Diary granules well before the open primary granule are pruned as they are added
+        collector
+            .add(&Time::from(base_time + 1_000_000), diary)
+            .unwrap();
+        assert_eq!(collector.num_packed_pruned(), 1);
+        assert_eq!(collector.packed.len(), 1);
+    }
+
+    #[test]
+    fn test_orphan_policy() {
+        let config = crate::config::get_default("npp").unwrap().unwrap();
+        let base_time = config.satellite.base_time;
+        // apid 11 (diary) and 826 (viirs) w/ secondary header, unsegmented
+        let diary =
+            decode_packets(&[0x08, 0x0b, 0xc0, 0x01, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa][..])
+                .next()
+                .unwrap()
+                .unwrap();
+        let viirs =
+            decode_packets(&[0x0b, 0x3a, 0xc0, 0x01, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa][..])
+                .next()
+                .unwrap()
+                .unwrap();
+        let viirs_len = 85_350_000;
+
+        // Diary granule long before the science granules, so it is pruned as an orphan when
+        // the first science packet is added. Returns all produced sets of RDRs.
+        let collect = |policy: OrphanPolicy| {
+            let mut collector =
+                Collector::new(config.satellite.clone(), &config.rdrs, &config.products)
+                    .with_packed_retention(Duration::from_secs(120))
+                    .with_orphan_policy(policy);
+            let mut produced = Vec::default();
+            collector
+                .add(&Time::from(base_time + 1_000_000), diary.clone())
+                .unwrap();
+            for num in 10..13 {
+                let time = Time::from(base_time + num * viirs_len + 1_000_000);
+                produced.extend(collector.add(&time, viirs.clone()).unwrap());
+                produced.extend(collector.take_orphans());
+            }
+            assert_eq!(collector.num_orphaned(), 1);
+            produced.extend(collector.finish().unwrap());
+            produced
+        };
+        let product_ids = |rdrs: &[Rdr]| {
+            rdrs.iter()
+                .map(|r| r.product_id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let produced = collect(OrphanPolicy::Drop);
+        assert_eq!(produced.len(), 3);
+        assert!(produced.iter().all(|rdrs| product_ids(rdrs) == ["RVIRS"]));
+
+        let produced = collect(OrphanPolicy::Standalone);
+        assert_eq!(produced.len(), 4);
+        assert_eq!(product_ids(&produced[0]), ["RNSCA"]);
+
+        let produced = collect(OrphanPolicy::Nearest);
+        assert_eq!(produced.len(), 3);
+        assert_eq!(product_ids(&produced[0]), ["RVIRS", "RNSCA"]);
+
+        // orphaned at finish with no primary granules
+        for (policy, expected) in [
+            (OrphanPolicy::Drop, 0),
+            (OrphanPolicy::Standalone, 1),
+            (OrphanPolicy::Nearest, 0),
+        ] {
+            let mut collector =
+                Collector::new(config.satellite.clone(), &config.rdrs, &config.products)
+                    .with_orphan_policy(policy);
+            collector
+                .add(&Time::from(base_time + 1_000_000), diary.clone())
+                .unwrap();
+            assert_eq!(collector.finish().unwrap().len(), expected, "{policy:?}");
+        }
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let config = crate::config::get_default("npp").unwrap().unwrap();
+        let base_time = config.satellite.base_time;
+        // apid 11 (diary) and 826 (viirs) w/ secondary header, unsegmented
+        let mut dat: Vec<u8> = Vec::default();
+        dat.extend_from_slice(&[0x08, 0x0b, 0xc0, 0x01, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa]);
+        for seq in [1u8, 2, 2] {
+            dat.extend_from_slice(&[0x0b, 0x3a, 0xc0, seq, 0x00, 0x03, 0xbb, 0xbb, 0xbb, seq]);
+        }
+        let packets: Vec<Packet> = decode_packets(&dat[..]).filter_map(|p| p.ok()).collect();
+        let time = Time::from(base_time + 1_000_000);
+
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+        for pkt in packets {
+            collector.add(&time, pkt).unwrap();
+        }
+        let snapshot = collector.snapshot().unwrap();
+        assert_eq!(snapshot.granules.len(), 2);
+        assert!(!snapshot.granules[0].packed);
+        assert_eq!(snapshot.granules[0].packets.len(), 2);
+        assert!(snapshot.granules[1].packed);
+        assert_eq!(snapshot.duplicates_found, 1);
+
+        // serializable and restores the same state
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let snapshot: CollectorSnapshot = serde_json::from_str(&json).unwrap();
+        let mut restored = Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+        restored.restore(snapshot.clone()).unwrap();
+        assert_eq!(restored.snapshot().unwrap(), snapshot);
+        assert_eq!(restored.num_duplicates(), 1);
+
+        let expected = collector.finish().unwrap();
+        let rdrs = restored.finish().unwrap();
+        assert_eq!(rdrs.len(), expected.len());
+        assert_eq!(rdrs[0].len(), expected[0].len());
+        for (rdr, expected) in rdrs[0].iter().zip(expected[0].iter()) {
+            assert_eq!(rdr.product_id, expected.product_id);
+            assert_eq!(rdr.meta.packet_type_count, expected.meta.packet_type_count);
+            assert_eq!(rdr.data.len(), expected.data.len());
+        }
+
+        let mut bad = CollectorSnapshot::default();
+        bad.granules.push(GranuleSnapshot {
+            product_id: "NOT-A-PRODUCT".to_string(),
+            granule_time: base_time,
+            packed: false,
+            used: false,
+            packets: Vec::default(),
+            padding: BTreeMap::default(),
+        });
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+        assert!(collector.restore(bad).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_restore_orphans() {
+        let config = crate::config::get_default("npp").unwrap().unwrap();
+        let base_time = config.satellite.base_time;
+        // apid 11 (diary) and 826 (viirs) w/ secondary header, unsegmented
+        let diary =
+            decode_packets(&[0x08, 0x0b, 0xc0, 0x01, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa][..])
+                .next()
+                .unwrap()
+                .unwrap();
+        let viirs =
+            decode_packets(&[0x0b, 0x3a, 0xc0, 0x01, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa][..])
+                .next()
+                .unwrap()
+                .unwrap();
+        let viirs_len = 85_350_000;
+
+        // Diary granule orphaned when the science packet is added, then snapshot and restored
+        let restored = |policy: OrphanPolicy| {
+            let new = || {
+                Collector::new(config.satellite.clone(), &config.rdrs, &config.products)
+                    .with_packed_retention(Duration::from_secs(120))
+                    .with_orphan_policy(policy)
+            };
+            let mut collector = new();
+            collector
+                .add(&Time::from(base_time + 1_000_000), diary.clone())
+                .unwrap();
+            collector
+                .add(&Time::from(base_time + 10 * viirs_len), viirs.clone())
+                .unwrap();
+            assert_eq!(collector.num_orphaned(), 1);
+
+            let snapshot = collector.snapshot().unwrap();
+            let json = serde_json::to_string(&snapshot).unwrap();
+            let snapshot: CollectorSnapshot = serde_json::from_str(&json).unwrap();
+            let mut restored = new();
+            restored.restore(snapshot.clone()).unwrap();
+            assert_eq!(restored.snapshot().unwrap(), snapshot);
+            (snapshot, restored)
+        };
+
+        let (snapshot, mut collector) = restored(OrphanPolicy::Standalone);
+        assert_eq!(snapshot.standalone.len(), 1);
+        assert!(snapshot.nearest.is_empty());
+        let orphans = collector.take_orphans();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0][0].product_id, "RNSCA");
+        assert_eq!(
+            orphans[0][0].meta.begin.iet_micros(),
+            orphans[0][0].meta.begin_time_iet
+        );
+
+        let (snapshot, collector) = restored(OrphanPolicy::Nearest);
+        assert!(snapshot.standalone.is_empty());
+        assert_eq!(snapshot.nearest.len(), 1);
+        let rdrs = collector.finish().unwrap();
+        assert_eq!(rdrs.len(), 1);
+        let product_ids: Vec<&str> = rdrs[0].iter().map(|r| r.product_id.as_str()).collect();
+        assert_eq!(product_ids, ["RVIRS", "RNSCA"]);
+    }
+
+    #[test]
+    fn test_annotator() {
+        let config = crate::config::get_default("npp").unwrap().unwrap();
+        let viirs = ccsds::spacepacket::decode_packets(
+            &[0x0b, 0x3a, 0xc0, 0x01, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa][..],
+        )
+        .next()
+        .unwrap()
+        .unwrap();
+        let time = Time::from(config.satellite.base_time + 1_000_000);
+
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products)
+                .with_annotator(|meta: &GranuleMeta| -> Result<Vec<(String, String)>> {
+                    Ok(vec![(
+                        "Ascending_Descending".to_string(),
+                        format!("Ascending-{}", meta.orbit_number),
+                    )])
+                });
+        collector.add(&time, viirs).unwrap();
+        let rdrs = collector.finish().unwrap();
+        let meta = &rdrs[0][0].meta;
+        assert_eq!(
+            meta.extra_attrs
+                .get("Ascending_Descending")
+                .map(String::as_str),
+            Some("Ascending-1")
+        );
+
+        // extra attributes are written and read back
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("test.h5");
+        let short_names = vec![meta.collection.clone()];
+        let file_meta = crate::Meta::from_products(&short_names, &config).unwrap();
+        crate::create_rdr(&path, file_meta, &rdrs[0][..1]).unwrap();
+        let file_meta = crate::Meta::from_file(&path).unwrap();
+        let granule = &file_meta.granules[&meta.collection][0];
+        assert_eq!(granule.extra_attrs, meta.extra_attrs);
+    }
+
+    #[test]
+    fn test_viirs_scans() {
+        let config = crate::config::get_default("npp").unwrap().unwrap();
+        let start = crate::fixtures::granule_time(&config, 0).unwrap();
+        let mut dat = crate::fixtures::viirs_packets(&start, 4);
+        // The first 2 packets become science packets for the same scan and the remaining
+        // engineering packets get distinct values where the scan number would be.
+        for (num, pkt) in dat.chunks_mut(22).enumerate() {
+            if num < 2 {
+                pkt[..2].copy_from_slice(&(0x0800u16 | 800).to_be_bytes());
+            } else {
+                pkt[19] = num as u8;
+            }
+        }
+
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+        for pkt in decode_packets(&dat[..]).filter_map(|p| p.ok()) {
+            collector.add(&start, pkt).unwrap();
+        }
+        let rdrs = collector.finish().unwrap();
+        assert_eq!(
+            rdrs[0][0].meta.scans,
+            Some(crate::viirs::ScanCounts {
+                num_scans: 1,
+                incomplete_scans: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_packet_time_iter_skips_invalid_times() {
+        let mut dat: Vec<u8> = Vec::default();
+        // apid 826 w/ secondary header, unsegmented, with an 8 byte CDS timecode
+        dat.extend_from_slice(&[0x0b, 0x3a, 0xc0, 0x01, 0x00, 0x07]);
+        dat.extend_from_slice(&[0x55, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        // too short for a timecode
+        dat.extend_from_slice(&[0x0b, 0x3a, 0xc0, 0x02, 0x00, 0x01, 0x00, 0x00]);
+        // 1s after the first
+        dat.extend_from_slice(&[0x0b, 0x3a, 0xc0, 0x03, 0x00, 0x07]);
+        dat.extend_from_slice(&[0x55, 0xf0, 0x00, 0x00, 0x03, 0xe8, 0x00, 0x00]);
+        let groups =
+            ccsds::spacepacket::collect_groups(decode_packets(&dat[..]).filter_map(|p| p.ok()))
+                .filter_map(|g| g.ok());
+
+        let mut iter = PacketTimeIter::new(groups);
+        let seqs: Vec<u16> = iter.by_ref().map(|(p, _)| p.header.sequence_id).collect();
+
+        assert_eq!(
+            seqs,
+            vec![1, 3],
+            "iteration should continue past the invalid time"
+        );
+        let stats = iter.stats();
+        assert_eq!(stats.groups, 3);
+        assert_eq!(stats.packets, 2);
+        assert_eq!(stats.groups_dropped, 1);
+        assert_eq!(stats.packets_dropped, 1);
+        assert_eq!(
+            stats.last_time_iet.unwrap() - stats.first_time_iet.unwrap(),
+            1_000_000
+        );
+        assert!(iter.check().is_ok());
+
+        let groups =
+            ccsds::spacepacket::collect_groups(decode_packets(&dat[..]).filter_map(|p| p.ok()))
+                .filter_map(|g| g.ok());
+        let mut iter = PacketTimeIter::new(groups).with_max_time_errors(0);
+        assert_eq!(
+            iter.by_ref().count(),
+            1,
+            "should stop at the first invalid time"
+        );
+        assert!(matches!(iter.check(), Err(Error::TooManyTimeErrors(1))));
+    }
+
+    #[test]
+    fn test_omps_granule_lengths() {
+        let config = crate::config::get_default("npp").unwrap().unwrap();
+        let product = |id: &str| {
+            config
+                .products
+                .iter()
+                .find(|p| p.product_id == id)
+                .unwrap()
+                .clone()
+        };
+        let (nadir, limb) = (product("RONPS"), product("ROLPS"));
+        assert_eq!(nadir.instrument(), limb.instrument());
+        assert_ne!(nadir.gran_len, limb.gran_len);
+
+        // apid 561 (nadir) and 562 (limb) w/ secondary header, unsegmented
+        let packet = |apid: u8| {
+            decode_packets(&[0x0a, apid, 0xc0, 0x01, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa][..])
+                .next()
+                .unwrap()
+                .unwrap()
+        };
+        let (np, lp) = (packet(0x31), packet(0x32));
+
+        // Interleaved nadir and limb packets every 5s spanning several granules of each
+        let start = config.satellite.base_time + nadir.gran_len * 100;
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+        let mut produced = Vec::default();
+        let num = 40;
+        for idx in 0..num {
+            let time = Time::from(start + crate::GranLen::from_micros(idx * 5_000_000));
+            produced.extend(collector.add(&time, np.clone()).unwrap());
+            produced.extend(collector.add(&time, lp.clone()).unwrap());
+        }
+        produced.extend(collector.finish().unwrap());
+
+        for spec in [&nadir, &limb] {
+            let granules: Vec<&Rdr> = produced
+                .iter()
+                .map(|rdrs| &rdrs[0])
+                .filter(|rdr| rdr.product_id == spec.product_id)
+                .collect();
+            assert!(granules.len() > 1, "{}", spec.product_id);
+            for rdr in &granules {
+                let meta = &rdr.meta;
+                assert_eq!(meta.instrument, "OMPS");
+                assert_eq!(
+                    meta.end_time_iet - meta.begin_time_iet,
+                    spec.gran_len.micros()
+                );
+                let offset = meta.begin_time_iet - config.satellite.base_time;
+                assert_eq!(offset % spec.gran_len.micros(), 0, "{}", spec.product_id);
+            }
+            let num_packets: u64 = granules.iter().map(|r| r.meta.num_packets()).sum();
+            assert_eq!(num_packets, num, "{}", spec.product_id);
+        }
+    }
+
+    #[test]
+    fn test_padding() {
+        let base = crate::config::get_default_content("npp").unwrap();
+        let overlay = "
+products:
+  - product_id: RVIRS
+    padding: { pre: 1000000, post: 1000000 }
+";
+        let config = crate::config::Config::with_overrides(base, overlay).unwrap();
+        let viirs = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let first = config.satellite.base_time + viirs.gran_len * 10;
+        let dat = crate::fixtures::viirs_packets(&Time::from(first), 3);
+        let packets: Vec<Packet> = ccsds::spacepacket::decode_packets(&dat[..])
+            .filter_map(|p| p.ok())
+            .collect();
+        let times = [
+            // pre-roll for the second granule
+            first + viirs.gran_len - crate::GranLen::from_micros(500_000),
+            // post-roll for the first granule
+            first + viirs.gran_len + crate::GranLen::from_micros(500_000),
+            // no padding
+            first + viirs.gran_len * 2 + crate::GranLen::from_micros(5_000_000),
+        ];
+
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+        let mut rdrs = Vec::default();
+        for (pkt, time) in packets.into_iter().zip(times) {
+            rdrs.extend(collector.add(&Time::from(time), pkt).unwrap());
+        }
+        rdrs.extend(collector.finish().unwrap());
+        let primary: Vec<&Rdr> = rdrs.iter().map(|r| &r[0]).collect();
+
+        assert_eq!(primary.len(), 3);
+        assert_eq!(primary[0].meta.num_packets(), 2);
+        assert_eq!(primary[0].meta.padding_packets, Some(1));
+        assert_eq!(primary[1].meta.num_packets(), 2);
+        assert_eq!(primary[1].meta.padding_packets, Some(1));
+        assert_eq!(primary[2].meta.num_packets(), 1);
+        assert_eq!(primary[2].meta.padding_packets, None);
+        assert_eq!(
+            primary[0].meta.padding_window(),
+            Some(crate::config::GranulePadding {
+                pre: 1_000_000,
+                post: 1_000_000
+            })
+        );
+
+        // padding is persisted with the granule
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("test.h5");
+        let meta = crate::Meta::from_products(&[viirs.short_name.clone()], &config).unwrap();
+        crate::create_rdr(&path, meta, &[primary[0].clone()]).unwrap();
+        let meta = crate::Meta::from_file(&path).unwrap();
+        let granule = &meta.granules[&viirs.short_name][0];
+        assert_eq!(granule.padding_packets, Some(1));
+        assert_eq!(granule.padding_window(), primary[0].meta.padding_window());
+    }
+
+    #[test]
+    fn test_padding_out_of_order_and_snapshot() {
+        let base = crate::config::get_default_content("npp").unwrap();
+        let overlay = "
+products:
+  - product_id: RVIRS
+    padding: { pre: 1000000, post: 1000000 }
+";
+        let config = crate::config::Config::with_overrides(base, overlay).unwrap();
+        let viirs = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let first = config.satellite.base_time + viirs.gran_len * 10;
+        let dat = crate::fixtures::viirs_packets(&Time::from(first), 6);
+        let mut packets = ccsds::spacepacket::decode_packets(&dat[..]).map(Result::unwrap);
+        let secs = crate::GranLen::from_micros;
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+        let mut rdrs = Vec::default();
+        for time in [
+            first + secs(5_000_000),
+            first + viirs.gran_len + secs(5_000_000),
+            first + viirs.gran_len * 2 + secs(5_000_000),
+            // post-roll for the first granule arriving after the third granule started
+            first + viirs.gran_len + secs(500_000),
+            // pre-roll for the fourth granule, which does not exist yet
+            first + viirs.gran_len * 3 - secs(500_000),
+        ] {
+            let pkt = packets.next().unwrap();
+            rdrs.extend(collector.add(&Time::from(time), pkt).unwrap());
+        }
+
+        let snapshot = collector.snapshot().unwrap();
+        assert_eq!(snapshot.preroll.len(), 1);
+        assert_eq!(snapshot.preroll[0].packets.len(), 1);
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let mut restored = Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+        restored
+            .restore(serde_json::from_str(&json).unwrap())
+            .unwrap();
+        assert_eq!(restored.snapshot().unwrap(), snapshot);
+
+        let time = first + viirs.gran_len * 3 + secs(5_000_000);
+        rdrs.extend(
+            restored
+                .add(&Time::from(time), packets.next().unwrap())
+                .unwrap(),
+        );
+        rdrs.extend(restored.finish().unwrap());
+        let primary: Vec<&Rdr> = rdrs.iter().map(|r| &r[0]).collect();
+
+        assert_eq!(primary.len(), 4);
+        assert_eq!(primary[0].meta.padding_packets, Some(1));
+        assert_eq!(primary[3].meta.padding_packets, Some(1));
+        assert_eq!(primary[3].meta.num_packets(), 2);
+    }
+
+    #[test]
+    fn test_base_time_policy() {
+        let config = crate::config::get_default("npp").unwrap().unwrap();
+        let base_time = config.satellite.base_time;
+        let viirs = decode_packets(&crate::fixtures::viirs_packets(&Time::from(base_time), 1)[..])
+            .next()
+            .unwrap()
+            .unwrap();
+        // same packet with an unconfigured apid
+        let mut data = crate::fixtures::viirs_packets(&Time::from(base_time), 1);
+        data[..2].copy_from_slice(&(0x0800u16 | 2000).to_be_bytes());
+        let unconfigured = decode_packets(&data[..]).next().unwrap().unwrap();
+        let early = Time::from_iet(base_time.0 - 1_000_000);
+        let collector = |policy: BaseTimePolicy| {
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products)
+                .with_base_time_policy(policy)
+        };
+
+        let mut skip = collector(BaseTimePolicy::Skip);
+        assert!(skip.add(&early, viirs.clone()).unwrap().is_none());
+        assert!(skip.add(&early, unconfigured.clone()).unwrap().is_none());
+        assert_eq!(
+            skip.num_before_base_time(),
+            1,
+            "unconfigured apids are not counted"
+        );
+        assert!(skip.finish().unwrap().is_empty());
+
+        let mut clamp = collector(BaseTimePolicy::Clamp);
+        assert!(clamp.add(&early, viirs.clone()).unwrap().is_none());
+        assert_eq!(clamp.num_before_base_time(), 1);
+        let produced = clamp.finish().unwrap();
+        assert_eq!(produced.len(), 1);
+        assert_eq!(produced[0][0].meta.begin_time_iet, base_time);
+        assert_eq!(produced[0][0].meta.num_packets(), 1);
+
+        let mut fail = collector(BaseTimePolicy::Fail);
+        assert!(
+            fail.add(&early, unconfigured).unwrap().is_none(),
+            "unconfigured apids are dropped rather than failing"
+        );
+        assert!(matches!(
+            fail.add(&early, viirs),
+            Err(Error::RdrError(RdrError::InvalidGranuleStart(_)))
+        ));
+        assert_eq!(fail.num_before_base_time(), 1);
+    }
+
+    #[test]
+    fn test_time_bounds() {
+        let bounds = TimeBounds {
+            min: Some(Time::from_iet(1_000)),
+            max: Some(Time::from_iet(10_000)),
+            max_delta: Some(2_000),
+            ..Default::default()
+        };
+
+        assert!(bounds.contains(&Time::from_iet(5_000), None));
+        assert!(!bounds.contains(&Time::from_iet(999), None));
+        assert!(!bounds.contains(&Time::from_iet(10_001), None));
+        assert!(bounds.contains(&Time::from_iet(5_000), Some(&Time::from_iet(7_000))));
+        assert!(!bounds.contains(&Time::from_iet(5_000), Some(&Time::from_iet(7_001))));
+        assert!(TimeBounds::default().contains(&Time::from_iet(0), None));
+    }
+
+    #[test]
+    fn test_time_bounds_reanchor_after_gap() {
+        let config = crate::config::get_default("npp").unwrap().unwrap();
+        let start = crate::fixtures::granule_time(&config, 10).unwrap();
+        let gap = Time::from_iet(start.iet() + 3_600_000_000);
+        let mut collector =
+            Collector::new(config.satellite.clone(), &config.rdrs, &config.products)
+                .with_time_bounds(TimeBounds {
+                    max_delta: Some(10_000_000),
+                    reanchor_after: 3,
+                    ..Default::default()
+                });
+        let add = |collector: &mut Collector, time: &Time, count: usize| {
+            let dat = crate::fixtures::viirs_packets(time, count);
+            for pkt in decode_packets(&dat[..]).map(Result::unwrap) {
+                let time =
+                    Time::from_iet(time.iet() + u64::from(pkt.header.sequence_id) * 1_000_000);
+                collector.add(&time, pkt).unwrap();
+            }
+        };
+
+        add(&mut collector, &start, 2);
+        // a single corrupt time does not re-anchor
+        add(
+            &mut collector,
+            &Time::from_iet(start.iet() + 7_200_000_000),
+            1,
+        );
+        add(&mut collector, &Time::from_iet(start.iet() + 2_000_000), 1);
+        assert_eq!(collector.num_quarantined(), 1);
+
+        // the first 2 packets after a real gap are quarantined, the rest accepted
+        add(&mut collector, &gap, 5);
+        assert_eq!(collector.num_quarantined(), 3);
+        let rdrs = collector.finish().unwrap();
+        let packets: u64 = rdrs.iter().map(|r| r[0].meta.num_packets()).sum();
+        assert_eq!(packets, 3 + 3);
+    }
+}
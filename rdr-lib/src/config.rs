@@ -1,11 +1,24 @@
-use std::{collections::HashSet, fs::File, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    path::PathBuf,
+};
 
-use ccsds::spacepacket::Apid;
-use serde::Deserialize;
+use ccsds::{
+    spacepacket::{Apid, TimecodeDecoder},
+    timecode::Format,
+};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use tracing::warn;
 
-use crate::error::{Error, Result};
+use crate::{
+    error::{Error, Result},
+    writer::file_attr_len,
+    GranLen, IetMicros, Orbits,
+};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SatSpec {
     /// Satellite id, e.g., npp, j01, etc ...
     pub id: String,
@@ -28,19 +41,209 @@ pub struct SatSpec {
     /// |JPSS-4    |1698019234000000|
     /// |GCOM-W1   |1715904034000000|
     /// |GOSAT-GW  |1767225635000000|
-    pub base_time: u64,
+    pub base_time: IetMicros,
     /// Mission, e.g., S-NPP/JPSS
     pub mission: String,
+    /// CCSDS spacecraft id (SCID), e.g., 157 for SNPP, used in PDS file names. See
+    /// [Config::scid].
+    #[serde(default)]
+    pub scid: Option<u8>,
+    /// Format of the timecode in packet secondary headers. Defaults to the JPSS CDS format.
+    #[serde(default)]
+    pub timecode: TimecodeSpec,
+    /// Orbit reference used to compute orbit numbers and boundaries, e.g., for granule orbit
+    /// numbers or when splitting files per orbit. See [crate::Orbits::from_tle] to create one
+    /// from a TLE.
+    #[serde(default)]
+    pub orbits: Option<Orbits>,
+    /// Scheme used to generate `N_Granule_ID` values. Defaults to the JPSS scheme.
+    #[serde(default)]
+    pub granule_id: GranuleIdSpec,
+}
+
+impl SatSpec {
+    /// The `N_Granule_ID` for a granule starting at `iet` using the configured
+    /// [GranuleIdSpec].
+    ///
+    /// # Errors
+    /// If `iet` is before the satellite base time.
+    pub fn granule_id(&self, iet: IetMicros) -> Result<String> {
+        let (resolution, width) = self.granule_id.number_format();
+        crate::rdr::format_granule_id(
+            &self.granule_id_prefix(),
+            self.base_time,
+            iet,
+            resolution,
+            width,
+        )
+    }
+
+    /// The granule start time for the `N_Granule_ID` `id`; the inverse of
+    /// [SatSpec::granule_id].
+    ///
+    /// # Errors
+    /// If `id` is not a valid granule id for the configured [GranuleIdSpec].
+    pub fn granule_id_to_iet(&self, id: &str) -> Result<IetMicros> {
+        let (resolution, _) = self.granule_id.number_format();
+        crate::rdr::parse_granule_id(&self.granule_id_prefix(), self.base_time, id, resolution)
+    }
+
+    /// Prefix of the granule ids for this satellite.
+    #[must_use]
+    pub fn granule_id_prefix(&self) -> String {
+        match &self.granule_id {
+            GranuleIdSpec::Formula {
+                prefix: Some(prefix),
+                ..
+            } => prefix.clone(),
+            _ => self.short_name.to_uppercase(),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Scheme used to generate `N_Granule_ID` values from granule start times, for missions that
+/// do not use the JPSS convention.
+///
+/// Configured using a `scheme` tag, e.g., for ids of whole seconds since the base time:
+/// ```yaml
+/// granule_id:
+///   scheme: formula
+///   prefix: GW1
+///   resolution: 1000000
+///   width: 10
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "scheme", rename_all = "lowercase")]
+pub enum GranuleIdSpec {
+    /// The uppercase satellite short name followed by the 12 digit number of tenths of a
+    /// second since the mission base time. See [crate::granule_id].
+    #[default]
+    Jpss,
+    /// `prefix`, or the uppercase satellite short name if not set, followed by the number of
+    /// `resolution` microseconds since the mission base time zero padded to `width` digits.
+    Formula {
+        #[serde(default)]
+        prefix: Option<String>,
+        resolution: u64,
+        width: usize,
+    },
+}
+
+impl GranuleIdSpec {
+    /// Microseconds per unit and number of digits of the granule id number.
+    fn number_format(&self) -> (u64, usize) {
+        match self {
+            Self::Jpss => (
+                crate::rdr::JPSS_GRANULE_ID_RESOLUTION,
+                crate::rdr::JPSS_GRANULE_ID_WIDTH,
+            ),
+            Self::Formula {
+                resolution, width, ..
+            } => (*resolution, *width),
+        }
+    }
+}
+
+/// Packet secondary header timecode format.
+///
+/// Configured using a `format` tag, e.g.,
+/// ```yaml
+/// timecode:
+///   format: cds
+///   num_day: 2
+///   num_submillis: 2
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "format", rename_all = "lowercase")]
+pub enum TimecodeSpec {
+    /// CCSDS Day Segmented timecode with the number of day and sub-millisecond bytes
+    Cds {
+        num_day: usize,
+        num_submillis: usize,
+    },
+    /// CCSDS Unsegmented timecode with the number of coarse and fine bytes, and an optional
+    /// multiplier to convert fine time to nanoseconds
+    Cuc {
+        num_coarse: usize,
+        num_fine: usize,
+        #[serde(default)]
+        fine_mult: Option<f32>,
+    },
+}
+
+impl Default for TimecodeSpec {
+    /// The JPSS timecode; CDS with a 2 byte day and 2 bytes of sub-milliseconds.
+    fn default() -> Self {
+        TimecodeSpec::Cds {
+            num_day: 2,
+            num_submillis: 2,
+        }
+    }
+}
+
+impl TimecodeSpec {
+    #[must_use]
+    pub fn format(&self) -> Format {
+        match *self {
+            TimecodeSpec::Cds {
+                num_day,
+                num_submillis,
+            } => Format::Cds {
+                num_day,
+                num_submillis,
+            },
+            TimecodeSpec::Cuc {
+                num_coarse,
+                num_fine,
+                fine_mult,
+            } => Format::Cuc {
+                num_coarse,
+                num_fine,
+                fine_mult,
+            },
+        }
+    }
+
+    /// Create a decoder for packet times in this format.
+    #[must_use]
+    pub fn decoder(&self) -> TimecodeDecoder {
+        TimecodeDecoder::new(self.format())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ApidSpec {
     pub num: Apid,
     pub name: String,
     pub max_expected: usize,
+    /// Dataset identifier used in NASA Level-0 PDS file names for this APID, e.g.,
+    /// `OMPSNPSCIENCE`, for sensors dumped to a PDS file per APID. At most
+    /// [ApidSpec::MAX_PDS_ID_LEN] uppercase alphanumeric characters.
+    #[serde(default)]
+    pub pds_id: Option<String>,
+}
+
+impl ApidSpec {
+    /// Maximum length of [ApidSpec::pds_id]; shorter ids are padded in PDS file names.
+    pub const MAX_PDS_ID_LEN: usize = 13;
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Pre and post-roll windows, configured as microseconds, around the nominal granule time
+/// range. Packets within `pre` of the end of a granule are also added to the following
+/// granule and packets within `post` of the start of a granule are also added to the previous
+/// granule, e.g., for SDR algorithms that reconstruct scans at granule edges.
+///
+/// ```yaml
+/// padding: { pre: 1780000, post: 1780000 }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GranulePadding {
+    pub pre: u64,
+    pub post: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ProductSpec {
     /// The product identifier, e.g., RVIRS, RNSCA, etc...
     ///
@@ -52,24 +255,139 @@ pub struct ProductSpec {
     pub short_name: String,
     /// Data type, e.g., SCIENCE, DIARY, etc ...
     pub type_id: String,
-    pub gran_len: u64,
+    /// Granule length, configured as microseconds.
+    pub gran_len: GranLen,
     pub apids: Vec<ApidSpec>,
+    /// Minimum number of packets for a granule to be considered usable, e.g., to keep
+    /// nearly-empty granules from the edges of a pass out of normal output. 0 disables the check.
+    #[serde(default)]
+    pub min_packets: u64,
+    /// Instrument_Short_Name attribute value, if different from `sensor`.
+    #[serde(default)]
+    pub instrument: Option<String>,
+    /// N_JPSS_Document_Ref granule attribute value. See CDFCB-X.
+    #[serde(default)]
+    pub document_ref: Option<String>,
+    /// N_Dataset_Type_Tag product attribute value, if not the default RDR.
+    #[serde(default)]
+    pub dataset_type: Option<String>,
+    /// Reserve [ApidSpec::max_expected] packet trackers for every APID regardless of the
+    /// packets received, writing fill trackers for the unused entries, as IDPS does for
+    /// SPACECRAFT-DIARY.
+    #[serde(default)]
+    pub reserve_trackers: bool,
+    /// Add packets from around the nominal granule time range to primary granules. Padding
+    /// packets are counted in [crate::ApidInfo::pkts_padding].
+    #[serde(default)]
+    pub padding: Option<GranulePadding>,
 }
 
 impl ProductSpec {
+    /// Instrument short name for product attributes; the `instrument` override if set,
+    /// otherwise `sensor`.
+    #[must_use]
+    pub fn instrument(&self) -> &str {
+        self.instrument.as_deref().unwrap_or(&self.sensor)
+    }
+
+    /// Get the spec for `apid`.
+    ///
+    /// This is a linear scan of the product apids; use a [ProductIndex] for lookups in hot
+    /// paths.
     #[must_use]
     pub fn get_apid(&self, apid: Apid) -> Option<ApidSpec> {
-        // FIXME: make this more efficient
-        for spec in &self.apids {
-            if spec.num == apid {
-                return Some(spec.clone());
+        self.apids.iter().find(|spec| spec.num == apid).cloned()
+    }
+}
+
+/// Prepared index of [ProductSpec]s providing constant time lookups by APID and product id.
+///
+/// Products are identified by their position in the index, which can be used as a cheap key
+/// rather than the product id. An APID may be shared by multiple products, e.g., diagnostic
+/// streams, in which case lookups that return a single product return the first in config
+/// order.
+#[derive(Debug, Clone, Default)]
+pub struct ProductIndex {
+    products: Vec<ProductSpec>,
+    /// Maps product_id to product index
+    ids: HashMap<String, usize>,
+    /// Maps APID to product indexes and index of the APID in each product's apids
+    apids: HashMap<Apid, Vec<(usize, usize)>>,
+}
+
+impl ProductIndex {
+    #[must_use]
+    pub fn new(products: &[ProductSpec]) -> Self {
+        let mut index = Self::default();
+        for (idx, product) in products.iter().enumerate() {
+            index.ids.insert(product.product_id.clone(), idx);
+            for (apid_idx, apid) in product.apids.iter().enumerate() {
+                index
+                    .apids
+                    .entry(apid.num)
+                    .or_default()
+                    .push((idx, apid_idx));
             }
+            index.products.push(product.clone());
         }
-        None
+        index
+    }
+
+    /// Index of the product with `product_id`.
+    #[must_use]
+    pub fn position(&self, product_id: &str) -> Option<usize> {
+        self.ids.get(product_id).copied()
+    }
+
+    /// Index of the first product `apid` belongs to.
+    #[must_use]
+    pub fn position_for_apid(&self, apid: Apid) -> Option<usize> {
+        self.positions_for_apid(apid).next()
+    }
+
+    /// Indexes of all products `apid` belongs to, in config order.
+    pub fn positions_for_apid(&self, apid: Apid) -> impl Iterator<Item = usize> + '_ {
+        self.apids
+            .get(&apid)
+            .into_iter()
+            .flatten()
+            .map(|(idx, _)| *idx)
+    }
+
+    /// Product at index `idx`.
+    ///
+    /// # Panics
+    /// If `idx` is not a valid index
+    #[must_use]
+    pub fn get(&self, idx: usize) -> &ProductSpec {
+        &self.products[idx]
+    }
+
+    /// Product with `product_id`.
+    #[must_use]
+    pub fn product(&self, product_id: &str) -> Option<&ProductSpec> {
+        self.position(product_id).map(|idx| &self.products[idx])
+    }
+
+    /// Product and apid spec for `apid`, for the first product `apid` belongs to.
+    #[must_use]
+    pub fn apid(&self, apid: Apid) -> Option<(&ProductSpec, &ApidSpec)> {
+        self.apids
+            .get(&apid)
+            .and_then(|v| v.first())
+            .map(|(idx, apid_idx)| {
+                let product = &self.products[*idx];
+                (product, &product.apids[*apid_idx])
+            })
+    }
+
+    #[must_use]
+    pub fn products(&self) -> &[ProductSpec] {
+        &self.products
     }
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct RdrSpec {
     /// Data product id.
     ///
@@ -77,10 +395,155 @@ pub struct RdrSpec {
     pub product: String,
     #[serde(default)]
     pub packed_with: Vec<String>,
+    /// Product id of the granules that determine output file start and end times; either
+    /// `product` or one of `packed_with`. Defaults to the `SCIENCE` collections, or `product`
+    /// if there are none, e.g., for diary or housekeeping only outputs.
+    #[serde(default)]
+    pub time_from: Option<String>,
+}
+
+/// Length of the `N_Granule_ID` attribute, the maximum length of a granule id.
+const MAX_GRANULE_ID_LEN: usize = 15;
+
+/// Maximum configurable length of file level string attributes.
+pub const MAX_FILE_ATTR_LEN: usize = 64;
+
+/// How string attribute values longer than the attribute's fixed length are written.
+///
+/// ```yaml
+/// attr_truncation: error
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttrTruncation {
+    /// Truncate the value to the attribute length and log a warning
+    #[default]
+    Warn,
+    /// Fail rather than write a truncated value
+    Error,
+}
+
+/// How non-ASCII characters are handled in configured values that are written as string
+/// attributes, e.g., `origin`, `distributor`, or the satellite `mission`. String attributes are
+/// ASCII so such values would otherwise fail when writing.
+///
+/// ```yaml
+/// non_ascii: replace
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NonAscii {
+    /// Fail when loading the config, naming the config key with the invalid value
+    #[default]
+    Error,
+    /// Replace non-ASCII characters with `?` and log a warning
+    Replace,
+}
+
+impl NonAscii {
+    /// Check the `value` of config `key` is ASCII, replacing non-ASCII characters if
+    /// [NonAscii::Replace].
+    fn apply(self, key: &str, value: &mut String) -> Result<()> {
+        if value.is_ascii() {
+            return Ok(());
+        }
+        match self {
+            Self::Error => Err(Error::ConfigInvalid(format!(
+                "{key} must only contain ASCII characters, got {value:?}"
+            ))),
+            Self::Replace => {
+                let sanitized: String = value
+                    .chars()
+                    .map(|c| if c.is_ascii() { c } else { '?' })
+                    .collect();
+                warn!("replaced non-ASCII characters in {key} {value:?} with {sanitized:?}");
+                *value = sanitized;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Fixed string lengths of the file level attributes. Longer values are handled according to
+/// the configured [AttrTruncation].
+///
+/// The defaults are the JPSS lengths from CDFCB-X Vol 2, the same lengths used for all other
+/// attributes, which are not configurable. Missions with longer names may increase them, up
+/// to [MAX_FILE_ATTR_LEN], e.g.,
+/// ```yaml
+/// attr_lengths:
+///   mission: 32
+///   platform: 6
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FileAttrLengths {
+    /// `Distributor` length
+    pub distributor: usize,
+    /// `Mission_Name` length
+    pub mission: usize,
+    /// `Platform_Short_Name` length
+    pub platform: usize,
+    /// `N_Dataset_Source` length
+    pub dataset_source: usize,
+}
+
+impl Default for FileAttrLengths {
+    fn default() -> Self {
+        Self {
+            distributor: file_attr_len("Distributor"),
+            mission: file_attr_len("Mission_Name"),
+            platform: file_attr_len("Platform_Short_Name"),
+            dataset_source: file_attr_len("N_Dataset_Source"),
+        }
+    }
+}
+
+impl FileAttrLengths {
+    fn validate(&self) -> Result<()> {
+        for (name, len) in [
+            ("distributor", self.distributor),
+            ("mission", self.mission),
+            ("platform", self.platform),
+            ("dataset_source", self.dataset_source),
+        ] {
+            if !(1..=MAX_FILE_ATTR_LEN).contains(&len) {
+                return Err(Error::ConfigInvalid(format!(
+                    "attr_lengths {name} must be between 1 and {MAX_FILE_ATTR_LEN}, got {len}"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Granule lengths by RDR product id. See CDFCB-X Volume I.
+///
+/// Keyed by product id rather than a configured field, e.g., `sensor`, so a misconfigured
+/// product cannot select a different expected length.
+const KNOWN_GRAN_LENS: &[(&str, GranLen)] = &[
+    ("RVIRS", GranLen::from_micros(85_350_000)),
+    ("RCRIS", GranLen::from_micros(31_997_000)),
+    ("RATMS", GranLen::from_micros(31_997_000)),
+    ("RATMD", GranLen::from_micros(31_997_000)),
+    ("RATMT", GranLen::from_micros(31_997_000)),
+    ("RONPS", GranLen::from_micros(37_405_000)),
+    ("ROTCS", GranLen::from_micros(37_405_000)),
+    ("ROLPS", GranLen::from_micros(37_437_000)),
+    ("RNSCA", GranLen::from_micros(20_000_000)),
+];
+
+/// The known granule length for the RDR product `product_id`, if any.
+#[must_use]
+pub fn known_gran_len(product_id: &str) -> Option<GranLen> {
+    KNOWN_GRAN_LENS
+        .iter()
+        .find(|(id, _)| *id == product_id)
+        .map(|(_, len)| *len)
 }
 
 // Per-satellite RDR configuration
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
     pub origin: String,
     pub mode: String,
@@ -88,14 +551,143 @@ pub struct Config {
     pub satellite: SatSpec,
     pub products: Vec<ProductSpec>,
     pub rdrs: Vec<RdrSpec>,
+    /// Lengths of the file level string attributes, e.g., `Mission_Name`, written using
+    /// `distributor` and the satellite `mission` and `short_name`.
+    #[serde(default)]
+    pub attr_lengths: FileAttrLengths,
+    /// How string attribute values longer than their attribute length are handled.
+    #[serde(default)]
+    pub attr_truncation: AttrTruncation,
+    /// How non-ASCII characters in values written as string attributes are handled.
+    #[serde(default)]
+    pub non_ascii: NonAscii,
+    /// Layout of output directories relative to the output directory, e.g.,
+    /// `{satid}/{short_name}/{yyyymmdd}`. See [crate::output_subdir] for placeholders. Files
+    /// are written directly to the output directory if not set.
+    #[serde(default)]
+    pub output_layout: Option<String>,
 }
 
 impl Config {
-    fn validate(self) -> Result<Self> {
-        // Make sure products only specify valid packed products
+    /// Check, or sanitize according to [Config::non_ascii], the values written as string
+    /// attributes so non-ASCII values fail when loading rather than when writing.
+    fn check_ascii(&mut self) -> Result<()> {
+        let policy = self.non_ascii;
+        policy.apply("origin", &mut self.origin)?;
+        policy.apply("mode", &mut self.mode)?;
+        policy.apply("distributor", &mut self.distributor)?;
+        policy.apply("satellite id", &mut self.satellite.id)?;
+        policy.apply("satellite short_name", &mut self.satellite.short_name)?;
+        policy.apply("satellite mission", &mut self.satellite.mission)?;
+        if let GranuleIdSpec::Formula {
+            prefix: Some(prefix),
+            ..
+        } = &mut self.satellite.granule_id
+        {
+            policy.apply("satellite granule_id prefix", prefix)?;
+        }
+        for product in &mut self.products {
+            let id = product.product_id.clone();
+            policy.apply(&format!("product {id} short_name"), &mut product.short_name)?;
+            policy.apply(&format!("product {id} type_id"), &mut product.type_id)?;
+            policy.apply(&format!("product {id} sensor"), &mut product.sensor)?;
+            for (name, value) in [
+                ("instrument", &mut product.instrument),
+                ("document_ref", &mut product.document_ref),
+                ("dataset_type", &mut product.dataset_type),
+            ] {
+                if let Some(value) = value {
+                    policy.apply(&format!("product {id} {name}"), value)?;
+                }
+            }
+            for apid in &mut product.apids {
+                policy.apply(
+                    &format!("product {id} apid {} name", apid.num),
+                    &mut apid.name,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(mut self) -> Result<Self> {
+        self.check_ascii()?;
+        self.attr_lengths.validate()?;
+        if let Some(layout) = &self.output_layout {
+            crate::output_subdir(layout, "satid", "SHORT-NAME", "PID", &crate::Time::now())
+                .map_err(|_| Error::ConfigInvalid(format!("invalid output_layout {layout:?}")))?;
+        }
+        if let GranuleIdSpec::Formula {
+            resolution, width, ..
+        } = self.satellite.granule_id
+        {
+            let len = self.satellite.granule_id_prefix().len() + width;
+            if resolution == 0 || width == 0 || len > MAX_GRANULE_ID_LEN {
+                return Err(Error::ConfigInvalid(format!(
+                    "satellite granule_id resolution and width must be > 0 and ids must be at \
+                     most {MAX_GRANULE_ID_LEN} characters, got {len}"
+                )));
+            }
+        }
+        if self.satellite.orbits.is_some_and(|o| o.period == 0) {
+            return Err(Error::ConfigInvalid(
+                "satellite orbits period must be > 0".to_string(),
+            ));
+        }
+
+        // Make sure products only specify valid packed products. APIDs may be shared between
+        // products, but not repeated within one.
         let mut product_ids: HashSet<String> = HashSet::default();
         for product in &self.products {
             product_ids.insert(product.product_id.clone());
+            if product.gran_len.micros() == 0 {
+                return Err(Error::ConfigInvalid(format!(
+                    "product {} gran_len must be greater than 0",
+                    product.product_id
+                )));
+            }
+            if let Some(padding) = product.padding {
+                if padding.pre >= product.gran_len.micros()
+                    || padding.post >= product.gran_len.micros()
+                {
+                    return Err(Error::ConfigInvalid(format!(
+                        "product {} padding must be less than gran_len {}",
+                        product.product_id, product.gran_len
+                    )));
+                }
+            }
+            match known_gran_len(&product.product_id) {
+                Some(known) if known != product.gran_len => warn!(
+                    "product {} gran_len {} is unusual; expected {known}",
+                    product.product_id, product.gran_len
+                ),
+                _ => {}
+            }
+            let mut apids: HashSet<Apid> = HashSet::default();
+            for apid in &product.apids {
+                if !apids.insert(apid.num) {
+                    return Err(Error::ConfigInvalid(format!(
+                        "product {} has duplicate apid {}",
+                        product.product_id, apid.num
+                    )));
+                }
+                if let Some(id) = &apid.pds_id {
+                    if id.is_empty()
+                        || id.len() > ApidSpec::MAX_PDS_ID_LEN
+                        || !id
+                            .chars()
+                            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+                    {
+                        return Err(Error::ConfigInvalid(format!(
+                            "product {} apid {} pds_id must be 1 to {} uppercase alphanumeric \
+                             characters, got {id:?}",
+                            product.product_id,
+                            apid.num,
+                            ApidSpec::MAX_PDS_ID_LEN
+                        )));
+                    }
+                }
+            }
         }
         for rdr in &self.rdrs {
             for packed_id in &rdr.packed_with {
@@ -106,11 +698,45 @@ impl Config {
                     )));
                 }
             }
+            if let Some(time_from) = &rdr.time_from {
+                if *time_from != rdr.product && !rdr.packed_with.contains(time_from) {
+                    return Err(Error::ConfigInvalid(format!(
+                        "product {} time_from {} is not the product or a packed product",
+                        rdr.product, time_from
+                    )));
+                }
+            }
         }
 
         Ok(self)
     }
 
+    /// Product id of the granules that determine file times for RDRs with primary product
+    /// `product_id`, if configured. See [RdrSpec::time_from].
+    #[must_use]
+    pub fn time_from(&self, product_id: &str) -> Option<&str> {
+        self.rdrs
+            .iter()
+            .find(|r| r.product == product_id)
+            .and_then(|r| r.time_from.as_deref())
+    }
+
+    /// The configured CCSDS spacecraft id, if any.
+    #[must_use]
+    pub fn scid(&self) -> Option<u8> {
+        self.satellite.scid
+    }
+
+    /// The config as YAML, including defaulted values, i.e., the effective config. The YAML
+    /// can be loaded again, e.g., using [Config::with_path].
+    ///
+    /// # Errors
+    /// If the config cannot be serialized.
+    pub fn to_yaml(&self) -> Result<String> {
+        serde_yaml::to_string(self)
+            .map_err(|err| Error::ConfigInvalid(format!("serializing config: {err}")))
+    }
+
     pub fn with_path(fpath: &PathBuf) -> Result<Config> {
         let fin = File::open(fpath)?;
         let config: Config = serde_yaml::from_reader(fin)?;
@@ -122,6 +748,62 @@ impl Config {
         let config: Config = serde_yaml::from_str(dat)?;
         config.validate()
     }
+
+    /// Create a config from the YAML `base`, e.g., an embedded default from
+    /// [get_default_content], with the partial YAML config `overlay` applied on top.
+    ///
+    /// Mappings are merged recursively, with `overlay` values replacing `base` values. Lists
+    /// of products, RDRs, and APIDs are merged by their `product_id`, `product`, and `num`
+    /// respectively, so an overlay can change a single product or add an APID without
+    /// repeating the rest of the list. Any other list in `overlay` replaces the `base` list.
+    /// Removing entries requires a full config.
+    ///
+    /// # Errors
+    /// If either document is not valid YAML or the merged config is not valid.
+    pub fn with_overrides(base: &str, overlay: &str) -> Result<Config> {
+        let mut merged: Value = serde_yaml::from_str(base)?;
+        let overlay: Value = serde_yaml::from_str(overlay)?;
+        merge_yaml(&mut merged, overlay);
+        let config: Config = serde_yaml::from_value(merged)?;
+        config.validate()
+    }
+}
+
+/// Fields identifying entries in config lists that are merged by [Config::with_overrides].
+const MERGE_KEYS: &[&str] = &["product_id", "product", "num"];
+
+fn merge_key(value: &Value) -> Option<(&'static str, &Value)> {
+    let mapping = value.as_mapping()?;
+    MERGE_KEYS
+        .iter()
+        .find_map(|key| mapping.get(*key).map(|v| (*key, v)))
+}
+
+fn merge_yaml(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_yaml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Sequence(base), Value::Sequence(overlay))
+            if overlay.iter().all(|v| merge_key(v).is_some()) =>
+        {
+            for value in overlay {
+                let key = merge_key(&value);
+                match base.iter_mut().find(|v| merge_key(v) == key) {
+                    Some(existing) => merge_yaml(existing, value),
+                    None => base.push(value),
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
 }
 
 static NPP_CONFIG: &str = include_str!(concat!(env!("OUT_DIR"), "/npp.config.yaml"));
@@ -129,6 +811,9 @@ static J01_CONFIG: &str = include_str!(concat!(env!("OUT_DIR"), "/j01.config.yam
 static J02_CONFIG: &str = include_str!(concat!(env!("OUT_DIR"), "/j02.config.yaml"));
 static J03_CONFIG: &str = include_str!(concat!(env!("OUT_DIR"), "/j03.config.yaml"));
 
+/// Satellite ids with default configs.
+const DEFAULT_SATIDS: &[&str] = &["npp", "j01", "j02", "j03"];
+
 pub fn get_default_content(satid: &str) -> Option<&'static str> {
     match satid {
         "npp" => Some(NPP_CONFIG),
@@ -145,3 +830,445 @@ pub fn get_default(satid: &str) -> Result<Option<Config>> {
         None => Ok(None),
     }
 }
+
+/// The default config for the satellite with the `short_name`, e.g., the
+/// `Platform_Short_Name` of an RDR file, ignoring case.
+///
+/// Besides the configured short name this matches the short name as written to files, i.e.,
+/// truncated to [FileAttrLengths::platform], e.g., `JPS` for `JPSS-3`, and the satellite id,
+/// e.g., `j03`.
+pub fn get_default_for_platform(short_name: &str) -> Result<Option<Config>> {
+    for satid in DEFAULT_SATIDS {
+        if let Some(config) = get_default(satid)? {
+            let configured = &config.satellite.short_name;
+            let written: String = configured
+                .chars()
+                .take(config.attr_lengths.platform)
+                .collect();
+            if configured.eq_ignore_ascii_case(short_name)
+                || written.eq_ignore_ascii_case(short_name)
+                || config.satellite.id.eq_ignore_ascii_case(short_name)
+            {
+                return Ok(Some(config));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_configs() {
+        for satid in DEFAULT_SATIDS {
+            let config = get_default(satid)
+                .unwrap_or_else(|e| panic!("invalid default config for {satid}: {e}"))
+                .unwrap();
+            assert!(config.scid().is_some(), "{satid} missing scid");
+            let platform = get_default_for_platform(&config.satellite.short_name.to_lowercase())
+                .unwrap()
+                .unwrap();
+            assert_eq!(platform.satellite.id, *satid);
+            let written: String = config
+                .satellite
+                .short_name
+                .chars()
+                .take(config.attr_lengths.platform)
+                .collect();
+            for short_name in [written.as_str(), satid] {
+                let platform = get_default_for_platform(short_name).unwrap().unwrap();
+                assert_eq!(platform.satellite.id, *satid, "{short_name}");
+            }
+
+            for product_id in ["RATMS", "RATMD", "RATMT"] {
+                assert!(
+                    config.products.iter().any(|p| p.product_id == product_id),
+                    "{satid} missing product {product_id}"
+                );
+                assert!(
+                    config.rdrs.iter().any(|r| r.product == product_id),
+                    "{satid} missing rdr {product_id}"
+                );
+            }
+            for product in &config.products {
+                assert_eq!(
+                    known_gran_len(&product.product_id),
+                    Some(product.gran_len),
+                    "{satid} {} has unexpected gran_len",
+                    product.product_id
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_gran_len() {
+        let base = get_default_content("npp").unwrap();
+        assert_eq!(known_gran_len("RVIRS"), Some(GranLen::from_secs_f64(85.35)));
+        assert!(known_gran_len("bogus").is_none());
+
+        // unusual lengths are allowed, but zero is not
+        let overlay = "products: [{product_id: RVIRS, gran_len: 1000}]\n";
+        assert!(Config::with_overrides(base, overlay).is_ok());
+        let overlay = "products: [{product_id: RVIRS, gran_len: 0}]\n";
+        assert!(Config::with_overrides(base, overlay).is_err());
+    }
+
+    #[test]
+    fn test_timecode_spec() {
+        let config = get_default("npp").unwrap().unwrap();
+        assert_eq!(config.satellite.timecode, TimecodeSpec::default());
+
+        let spec: TimecodeSpec =
+            serde_yaml::from_str("format: cuc\nnum_coarse: 4\nnum_fine: 2\n").unwrap();
+        assert_eq!(
+            spec,
+            TimecodeSpec::Cuc {
+                num_coarse: 4,
+                num_fine: 2,
+                fine_mult: None
+            }
+        );
+        let spec: TimecodeSpec =
+            serde_yaml::from_str("format: cds\nnum_day: 2\nnum_submillis: 0\n").unwrap();
+        assert!(matches!(
+            spec.format(),
+            Format::Cds {
+                num_submillis: 0,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_attr_lengths() {
+        let config = get_default("npp").unwrap().unwrap();
+        assert_eq!(config.attr_lengths, FileAttrLengths::default());
+
+        assert_eq!(FileAttrLengths::default().mission, 20);
+        assert_eq!(config.attr_truncation, AttrTruncation::Warn);
+        let truncation: AttrTruncation = serde_yaml::from_str("error").unwrap();
+        assert_eq!(truncation, AttrTruncation::Error);
+
+        let lengths: FileAttrLengths = serde_yaml::from_str("platform: 6\n").unwrap();
+        assert_eq!(lengths.platform, 6);
+        assert_eq!(lengths.mission, FileAttrLengths::default().mission);
+
+        assert!(FileAttrLengths {
+            mission: MAX_FILE_ATTR_LEN + 1,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(FileAttrLengths {
+            platform: 0,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_with_overrides() {
+        let base = get_default_content("npp").unwrap();
+        let overlay = "
+distributor: arch
+products:
+  - product_id: RVIRS
+    gran_len: 1000
+  - product_id: RNSCA
+    apids:
+      - num: 11
+        max_expected: 10
+      - num: 99
+        name: TEST
+        max_expected: 1
+";
+        let default = get_default("npp").unwrap().unwrap();
+        let config = Config::with_overrides(base, overlay).unwrap();
+        assert_eq!(config.distributor, "arch");
+        assert_eq!(config.products.len(), default.products.len());
+        assert_eq!(config.rdrs, default.rdrs);
+
+        let viirs = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        assert_eq!(viirs.gran_len, GranLen::from_micros(1000));
+        assert_eq!(viirs.short_name, "VIIRS-SCIENCE-RDR");
+
+        let default_apids = &default
+            .products
+            .iter()
+            .find(|p| p.product_id == "RNSCA")
+            .unwrap()
+            .apids;
+        let apids = &config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RNSCA")
+            .unwrap()
+            .apids;
+        assert_eq!(apids.len(), default_apids.len() + 1);
+        let apid = apids.iter().find(|a| a.num == 11).unwrap();
+        assert_eq!(apid.max_expected, 10);
+        assert_eq!(
+            apid.name,
+            default_apids.iter().find(|a| a.num == 11).unwrap().name
+        );
+        assert!(apids.iter().any(|a| a.num == 99 && a.name == "TEST"));
+
+        // merged config is validated
+        let overlay = "
+rdrs:
+  - product: RVIRS
+    packed_with: [NOT-A-PRODUCT]
+";
+        assert!(Config::with_overrides(base, overlay).is_err());
+    }
+
+    #[test]
+    fn test_time_from() {
+        let base = get_default_content("npp").unwrap();
+        let config = get_default("npp").unwrap().unwrap();
+        assert_eq!(config.time_from("RVIRS"), None);
+
+        let overlay = "
+rdrs:
+  - product: RVIRS
+    packed_with: [RNSCA]
+    time_from: RNSCA
+";
+        let config = Config::with_overrides(base, overlay).unwrap();
+        assert_eq!(config.time_from("RVIRS"), Some("RNSCA"));
+        assert_eq!(config.time_from("RNSCA"), None);
+
+        let overlay = "
+rdrs:
+  - product: RVIRS
+    time_from: RCRIS
+";
+        assert!(Config::with_overrides(base, overlay).is_err());
+    }
+
+    #[test]
+    fn test_padding() {
+        let base = get_default_content("npp").unwrap();
+        let overlay = "
+products:
+  - product_id: RVIRS
+    padding: { pre: 1780000 }
+";
+        let config = Config::with_overrides(base, overlay).unwrap();
+        let viirs = config.products.iter().find(|p| p.product_id == "RVIRS");
+        assert_eq!(
+            viirs.unwrap().padding,
+            Some(GranulePadding {
+                pre: 1_780_000,
+                post: 0
+            })
+        );
+
+        let overlay = "
+products:
+  - product_id: RVIRS
+    padding: { post: 85350000 }
+";
+        assert!(Config::with_overrides(base, overlay).is_err());
+    }
+
+    #[test]
+    fn test_non_ascii() {
+        let base = get_default_content("npp").unwrap();
+
+        let err = Config::with_overrides(base, "distributor: \"arsc\u{e9}\"\n").unwrap_err();
+        assert!(err.to_string().contains("distributor"), "{err}");
+
+        let overlay = "
+non_ascii: replace
+origin: \"ssec\u{e9}\"
+products:
+  - product_id: RVIRS
+    sensor: \"VIIRS\u{2122}\"
+";
+        let config = Config::with_overrides(base, overlay).unwrap();
+        assert_eq!(config.origin, "ssec?");
+        let viirs = config.products.iter().find(|p| p.product_id == "RVIRS");
+        assert_eq!(viirs.unwrap().sensor, "VIIRS?");
+    }
+
+    #[test]
+    fn test_pds_id() {
+        let config = get_default("npp").unwrap().unwrap();
+        let omps = config
+            .products
+            .iter()
+            .filter(|p| p.instrument.as_deref() == Some("OMPS"));
+        for product in omps {
+            for apid in &product.apids {
+                assert!(apid.pds_id.is_some(), "no pds_id for {}", apid.num);
+            }
+        }
+
+        let base = get_default_content("npp").unwrap();
+        for id in ["", "omps", "OMPSNPSCIENCEX", "OMPS_NP"] {
+            let overlay = format!(
+                "
+products:
+  - product_id: RONPS
+    apids:
+      - {{ num: 561, name: NP, max_expected: 256, pds_id: \"{id}\" }}
+"
+            );
+            assert!(Config::with_overrides(base, &overlay).is_err(), "{id:?}");
+        }
+    }
+
+    #[test]
+    fn test_output_layout() {
+        let base = get_default_content("npp").unwrap();
+        assert!(get_default("npp").unwrap().unwrap().output_layout.is_none());
+
+        let config =
+            Config::with_overrides(base, "output_layout: '{satid}/{short_name}'\n").unwrap();
+        assert_eq!(
+            config.output_layout.as_deref(),
+            Some("{satid}/{short_name}")
+        );
+        assert!(Config::with_overrides(base, "output_layout: '{bogus}'\n").is_err());
+        assert!(Config::with_overrides(base, "output_layout: '../{satid}'\n").is_err());
+    }
+
+    #[test]
+    fn test_granule_id_scheme() {
+        let base = get_default_content("npp").unwrap();
+        let sat = get_default("npp").unwrap().unwrap().satellite;
+        assert_eq!(sat.granule_id, GranuleIdSpec::Jpss);
+        let iet = sat.base_time + 414_485_160_000_000;
+        assert_eq!(sat.granule_id(iet).unwrap(), "NPP004144851600");
+        assert_eq!(
+            sat.granule_id(iet).unwrap(),
+            crate::granule_id("NPP", sat.base_time, iet).unwrap()
+        );
+
+        let overlay = "
+satellite:
+  granule_id: { scheme: formula, prefix: GW1, resolution: 1000000, width: 10 }
+";
+        let sat = Config::with_overrides(base, overlay).unwrap().satellite;
+        let id = sat.granule_id(iet).unwrap();
+        assert_eq!(id, "GW10414485160");
+        assert_eq!(sat.granule_id_to_iet(&id).unwrap(), iet);
+        assert!(sat.granule_id_to_iet("NPP004144851600").is_err());
+
+        let overlay = "
+satellite:
+  granule_id: { scheme: formula, resolution: 1000000, width: 13 }
+";
+        assert!(Config::with_overrides(base, overlay).is_err(), "too long");
+        let overlay = "
+satellite:
+  granule_id: { scheme: formula, resolution: 0, width: 10 }
+";
+        assert!(
+            Config::with_overrides(base, overlay).is_err(),
+            "zero resolution"
+        );
+    }
+
+    #[test]
+    fn test_product_overrides() {
+        let dat = "
+product_id: RVIRS
+sensor: VIIRS
+short_name: VIIRS-SCIENCE-RDR
+type_id: SCIENCE
+gran_len: 85350000
+apids: []
+instrument: VIIRS-X
+document_ref: 474-00001-02-01_JPSS-CDFCB-X-Vol-II_0123B.pdf
+";
+        let product: ProductSpec = serde_yaml::from_str(dat).unwrap();
+        assert_eq!(product.instrument(), "VIIRS-X");
+        assert!(product.document_ref.is_some());
+        assert!(product.dataset_type.is_none());
+
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        assert_eq!(product.instrument(), product.sensor);
+    }
+
+    #[test]
+    fn test_to_yaml() {
+        for satid in DEFAULT_SATIDS {
+            let config = get_default(satid).unwrap().unwrap();
+            let yaml = config.to_yaml().unwrap();
+            // defaults are included
+            assert!(yaml.contains("attr_truncation: warn"), "{satid}");
+            assert!(yaml.contains("non_ascii: error"), "{satid}");
+
+            let reloaded = Config::with_data(&yaml).unwrap();
+            assert_eq!(reloaded.to_yaml().unwrap(), yaml, "{satid}");
+        }
+    }
+
+    #[test]
+    fn test_product_index() {
+        let config = get_default("npp").unwrap().unwrap();
+        let index = ProductIndex::new(&config.products);
+
+        let (product, apid) = index.apid(826).unwrap();
+        assert_eq!(product.product_id, "RVIRS");
+        assert_eq!(apid.name, "ENG");
+        let idx = index.position_for_apid(826).unwrap();
+        assert_eq!(index.get(idx).product_id, "RVIRS");
+        assert_eq!(index.position("RVIRS"), Some(idx));
+        assert_eq!(index.product("RVIRS").unwrap().product_id, "RVIRS");
+        assert!(index.apid(9999).is_none());
+        assert_eq!(index.positions_for_apid(826).collect::<Vec<_>>(), vec![idx]);
+        assert_eq!(index.positions_for_apid(9999).count(), 0);
+        assert_eq!(
+            product.get_apid(826).map(|a| a.name),
+            Some("ENG".to_string())
+        );
+    }
+
+    #[test]
+    fn test_shared_apids() {
+        let base = get_default_content("npp").unwrap();
+        let overlay = "
+products:
+  - product_id: RNSCA
+    apids:
+      - num: 826
+        name: ENG
+        max_expected: 1
+";
+        let config = Config::with_overrides(base, overlay).unwrap();
+        let index = ProductIndex::new(&config.products);
+        let ids: Vec<&str> = index
+            .positions_for_apid(826)
+            .map(|idx| index.get(idx).product_id.as_str())
+            .collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&"RVIRS"));
+        assert!(ids.contains(&"RNSCA"));
+        assert_eq!(
+            index.get(index.position_for_apid(826).unwrap()).product_id,
+            ids[0]
+        );
+
+        // apids may not be repeated within a product
+        let mut config = get_default("npp").unwrap().unwrap();
+        let product = &mut config.products[0];
+        product.apids.push(product.apids[0].clone());
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,343 @@
+//! Reading the packets contained in RDRs, e.g., to convert RDRs to Level-0.
+//!
+//! [dump_packets] iterates the packets for a collection and [PacketSink]s provide
+//! destinations for the packets, such as a single stream or a file per APID.
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use ccsds::spacepacket::{decode_packets, Apid, Packet};
+use hdf5::Dataset;
+use tracing::{debug, warn};
+
+use crate::{
+    error::{Error, Result},
+    CommonRdr,
+};
+
+/// Decode the packets from the Common RDR `data` for a single granule.
+///
+/// Packets are returned in APID list order, and in packet tracker order for each APID.
+/// Corrupt packets, i.e., those outside of the application packet storage or that cannot be
+/// decoded, are skipped with a warning so the rest of the granule's packets are not lost.
+///
+/// # Errors
+/// If `data` is not a valid Common RDR.
+pub fn common_rdr_packets(data: &[u8]) -> Result<Vec<Packet>> {
+    let common = CommonRdr::from_bytes(data)?;
+    let storage = common.static_header.ap_storage_offset as usize;
+    let mut packets = Vec::default();
+    for info in &common.apid_list {
+        let start = info.pkt_tracker_start_idx as usize;
+        let end = start + info.pkts_received as usize;
+        let Some(trackers) = common.packet_trackers.get(start..end) else {
+            return Err(Error::NotEnoughBytes("PacketTracker"));
+        };
+        for tracker in trackers {
+            // Negative offsets indicate fill for missing packets
+            let (Ok(offset), Ok(size)) = (
+                usize::try_from(tracker.offset),
+                usize::try_from(tracker.size),
+            ) else {
+                continue;
+            };
+            let Some(bytes) = data.get(storage + offset..storage + offset + size) else {
+                warn!(
+                    apid = info.value,
+                    offset, size, "skipping packet outside of application packet storage"
+                );
+                continue;
+            };
+            match decode_packets(bytes).next() {
+                Some(Ok(pkt)) if pkt.data.len() == size => packets.push(pkt),
+                Some(Ok(_)) | None => {
+                    warn!(
+                        apid = info.value,
+                        offset, size, "skipping packet with invalid length"
+                    );
+                }
+                Some(Err(err)) => {
+                    warn!(
+                        apid = info.value,
+                        offset, size, "skipping undecodable packet: {err}"
+                    );
+                }
+            }
+        }
+    }
+    Ok(packets)
+}
+
+/// Iterator over the packets for a collection. See [dump_packets].
+pub struct DumpPackets {
+    datasets: std::vec::IntoIter<Dataset>,
+    packets: std::vec::IntoIter<Packet>,
+}
+
+impl Iterator for DumpPackets {
+    type Item = Result<Packet>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(pkt) = self.packets.next() {
+                return Some(Ok(pkt));
+            }
+            let dataset = self.datasets.next()?;
+            debug!("reading packets from {}", dataset.name());
+            let packets = dataset
+                .read_raw::<u8>()
+                .map_err(Error::from)
+                .and_then(|data| common_rdr_packets(&data));
+            match packets {
+                Ok(packets) => self.packets = packets.into_iter(),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// Iterate the packets for the collection `short_name` in the RDR `file`.
+///
+/// Granules are read one at a time, in granule index order, so only a single granule is held
+/// in memory. Packets are in Common RDR storage order for each granule, so they are generally
+/// not in time order across APIDs; use [crate::jpss_merge] to time order packets.
+///
+/// # Errors
+/// If the collection does not exist in `file`.
+pub fn dump_packets(file: &hdf5::File, short_name: &str) -> Result<DumpPackets> {
+    let group = file.group(&format!("All_Data/{short_name}_All"))?;
+    let mut datasets: Vec<(usize, Dataset)> = Vec::default();
+    for name in group.member_names()? {
+        let Some(idx) = name
+            .strip_prefix("RawApplicationPackets_")
+            .and_then(|idx| idx.parse().ok())
+        else {
+            warn!("skipping unexpected dataset {short_name}/{name}");
+            continue;
+        };
+        datasets.push((idx, group.dataset(&name)?));
+    }
+    // Member names are in lexical order, e.g., _10 before _2
+    datasets.sort_by_key(|(idx, _)| *idx);
+    Ok(DumpPackets {
+        datasets: datasets
+            .into_iter()
+            .map(|(_, ds)| ds)
+            .collect::<Vec<_>>()
+            .into_iter(),
+        packets: Vec::default().into_iter(),
+    })
+}
+
+/// A destination for packets dumped from an RDR.
+pub trait PacketSink {
+    /// Write `pkt` from the collection `short_name`.
+    fn write(&mut self, short_name: &str, pkt: &Packet) -> Result<()>;
+
+    /// Flush all output, returning the paths of any files written.
+    fn finish(&mut self) -> Result<Vec<PathBuf>>;
+}
+
+/// [PacketSink] writing all packets to a single stream, e.g., a file or an in-memory buffer.
+pub struct StreamSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> StreamSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Consume the sink, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> PacketSink for StreamSink<W> {
+    fn write(&mut self, _short_name: &str, pkt: &Packet) -> Result<()> {
+        Ok(self.writer.write_all(&pkt.data)?)
+    }
+
+    fn finish(&mut self) -> Result<Vec<PathBuf>> {
+        self.writer.flush()?;
+        Ok(Vec::default())
+    }
+}
+
+type NameFn<'a> = Box<dyn Fn(&str, Apid) -> String + Send + 'a>;
+
+/// [PacketSink] writing packets to files in a directory, where the file for each packet is
+/// named by a user provided function, e.g., a file per collection or per APID.
+pub struct FileSink<'a> {
+    dir: PathBuf,
+    name: NameFn<'a>,
+    /// File name to writer
+    files: HashMap<String, BufWriter<File>>,
+    paths: Vec<PathBuf>,
+}
+
+impl<'a> FileSink<'a> {
+    /// Write a file per collection, i.e., per sensor, named by `name(short_name)`.
+    pub fn per_collection<P, F>(dir: P, name: F) -> Self
+    where
+        P: AsRef<Path>,
+        F: Fn(&str) -> String + Send + 'a,
+    {
+        Self::new(dir, Box::new(move |short_name, _| name(short_name)))
+    }
+
+    /// Write a file per APID, named by `name(short_name, apid)`.
+    pub fn per_apid<P, F>(dir: P, name: F) -> Self
+    where
+        P: AsRef<Path>,
+        F: Fn(&str, Apid) -> String + Send + 'a,
+    {
+        Self::new(dir, Box::new(name))
+    }
+
+    fn new<P: AsRef<Path>>(dir: P, name: NameFn<'a>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            name,
+            files: HashMap::default(),
+            paths: Vec::default(),
+        }
+    }
+}
+
+impl PacketSink for FileSink<'_> {
+    fn write(&mut self, short_name: &str, pkt: &Packet) -> Result<()> {
+        let name = (self.name)(short_name, pkt.header.apid);
+        let writer = match self.files.entry(name) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let path = self.dir.join(entry.key());
+                debug!("creating {path:?}");
+                let file = File::create(&path)?;
+                self.paths.push(path);
+                entry.insert(BufWriter::new(file))
+            }
+        };
+        Ok(writer.write_all(&pkt.data)?)
+    }
+
+    fn finish(&mut self) -> Result<Vec<PathBuf>> {
+        for writer in self.files.values_mut() {
+            writer.flush()?;
+        }
+        self.files.clear();
+        Ok(std::mem::take(&mut self.paths))
+    }
+}
+
+/// Write the packets for each collection in `short_names` from the RDR `file` to `sink`,
+/// returning the paths of any files written. Collections not in `file` are skipped.
+///
+/// # Errors
+/// On any error reading packets or writing to `sink`.
+pub fn dump_rdr<S: PacketSink>(
+    file: &hdf5::File,
+    short_names: &[&str],
+    sink: &mut S,
+) -> Result<Vec<PathBuf>> {
+    for short_name in short_names {
+        let Ok(packets) = dump_packets(file, short_name) else {
+            debug!("no collection {short_name}");
+            continue;
+        };
+        for pkt in packets {
+            sink.write(short_name, &pkt?)?;
+        }
+    }
+    sink.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::get_default, create_rdr, Meta, RdrData, Time};
+
+    #[test]
+    fn test_common_rdr_packets_skips_corrupt() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = crate::fixtures::product(&config).unwrap();
+        let time = crate::fixtures::granule_time(&config, 0).unwrap();
+        let dat = crate::fixtures::viirs_packets(&time, 3);
+        let mut rdr_data = RdrData::new(&config.satellite, product, &time);
+        for (secs, pkt) in decode_packets(&dat[..]).enumerate() {
+            let time = Time::from_iet(time.iet() + secs as u64 * 1_000_000);
+            rdr_data.add_packet(&time, pkt.unwrap()).unwrap();
+        }
+        let mut data = rdr_data.compile().unwrap().data;
+        let common = CommonRdr::from_bytes(&data).unwrap();
+        let storage = common.static_header.ap_storage_offset as usize;
+        let info = common
+            .apid_list
+            .iter()
+            .find(|a| a.value == u32::from(crate::fixtures::APID))
+            .unwrap();
+        let tracker = &common.packet_trackers[info.pkt_tracker_start_idx as usize + 1];
+        // Length of the second packet extends past the end of the data
+        let len = storage + tracker.offset as usize + 4;
+        data[len..len + 2].copy_from_slice(&[0xff, 0xff]);
+
+        let packets = common_rdr_packets(&data).unwrap();
+        let seqs: Vec<u16> = packets.iter().map(|p| p.header.sequence_id).collect();
+        assert_eq!(seqs, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_dump_packets() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let time = Time::from(config.satellite.base_time);
+
+        // two granules, each with a single packet
+        let mut rdrs = Vec::default();
+        for (num, seq) in [(0u64, 1u8), (1, 2)] {
+            let time = Time::from(time.iet_micros() + product.gran_len * num);
+            #[rustfmt::skip]
+            let dat = vec![
+                // apid 826, unsegmented, sequence id
+                0x0b, 0x3a, 0xc0, seq, 0x00, 0x01,
+                0x00, 0x00,
+            ];
+            let pkt = decode_packets(&dat[..]).next().unwrap().unwrap();
+            let mut rdr_data = RdrData::new(&config.satellite, product, &time);
+            rdr_data.add_packet(&time, pkt).unwrap();
+            rdrs.push(rdr_data.compile().unwrap());
+        }
+        let meta = Meta::from_products(&[product.short_name.clone()], &config).unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("test.h5");
+        create_rdr(&path, meta, &rdrs).unwrap();
+
+        let file = hdf5::File::open(&path).unwrap();
+        let packets: Vec<Packet> = dump_packets(&file, &product.short_name)
+            .unwrap()
+            .map(|p| p.unwrap())
+            .collect();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].header.sequence_id, 1);
+        assert_eq!(packets[1].header.sequence_id, 2);
+        assert!(dump_packets(&file, "NOT-A-COLLECTION").is_err());
+
+        let mut sink = StreamSink::new(Vec::default());
+        let paths = dump_rdr(&file, &[product.short_name.as_str()], &mut sink).unwrap();
+        assert!(paths.is_empty());
+        assert_eq!(sink.into_inner().len(), 16);
+
+        let mut sink = FileSink::per_apid(dir.path(), |_, apid| format!("{apid}.dat"));
+        let paths = dump_rdr(&file, &[product.short_name.as_str()], &mut sink).unwrap();
+        assert_eq!(paths, vec![dir.path().join("826.dat")]);
+        assert_eq!(std::fs::read(&paths[0]).unwrap().len(), 16);
+    }
+}
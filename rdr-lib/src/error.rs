@@ -33,6 +33,12 @@ pub enum Error {
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error("Unsupported compression: {0}")]
+    UnsupportedCompression(String),
+
     #[error("Config invalid: {0}")]
     ConfigInvalid(String),
     #[error("Failed to load config: {}", .source)]
@@ -43,6 +49,12 @@ pub enum Error {
     #[error("No config for {0}")]
     ConfigNotFound(String),
 
+    #[error("Invalid TLE: {0}")]
+    InvalidTle(String),
+
+    #[error("Too many packet time decode failures; {0} packet groups dropped")]
+    TooManyTimeErrors(usize),
+
     #[error(transparent)]
     RdrError(#[from] RdrError),
 
@@ -0,0 +1,150 @@
+//! Small synthetic inputs for examples and tests, enabled with the `fixtures` feature.
+//!
+//! The packets only have valid headers and timecodes; their user data is filler, so they are
+//! useful for exercising the library API but not for science processing.
+use ccsds::spacepacket::{collect_groups, decode_packets, Apid};
+
+use crate::{
+    config::{Config, ProductSpec},
+    error::{Error, Result},
+    Collector, PacketTimeIter, Rdr, Time,
+};
+
+/// Product id of the fixture packets.
+pub const PRODUCT_ID: &str = "RVIRS";
+
+/// Apid of the fixture packets, the VIIRS engineering apid.
+pub const APID: Apid = 826;
+
+/// Bytes of filler user data following the secondary header timecode in each packet.
+const FILLER_LEN: usize = 8;
+
+/// Days between the JPSS timecode epoch (Jan 1, 1958) and the Unix epoch.
+const UNIX_EPOCH_DAYS: u64 = 4383;
+
+/// JPSS CDS timecode for `time`; 2 byte day, 4 byte millisecond of day, and 2 byte
+/// microsecond of millisecond.
+fn cds_timecode(time: &Time) -> [u8; 8] {
+    let micros = time.utc();
+    let days = micros / 86_400_000_000 + UNIX_EPOCH_DAYS;
+    let millis = micros % 86_400_000_000 / 1000;
+    let submillis = micros % 1000;
+
+    let mut buf = [0u8; 8];
+    buf[..2].copy_from_slice(&(days as u16).to_be_bytes());
+    buf[2..6].copy_from_slice(&(millis as u32).to_be_bytes());
+    buf[6..].copy_from_slice(&(submillis as u16).to_be_bytes());
+    buf
+}
+
+/// Level-0 packet data, as found in a PDS or raw packet file, of `count` unsegmented [APID]
+/// packets, one per second starting at `start`.
+#[must_use]
+pub fn viirs_packets(start: &Time, count: usize) -> Vec<u8> {
+    let mut data = Vec::default();
+    for num in 0..count {
+        let time = Time::from_iet(start.iet() + num as u64 * 1_000_000);
+        let seq = (num % 0x4000) as u16;
+        // version 0, telemetry, secondary header, unsegmented
+        data.extend_from_slice(&(0x0800 | APID).to_be_bytes());
+        data.extend_from_slice(&(0xc000 | seq).to_be_bytes());
+        data.extend_from_slice(&((8 + FILLER_LEN - 1) as u16).to_be_bytes());
+        data.extend_from_slice(&cds_timecode(&time));
+        data.extend_from_slice(&[0xaa; FILLER_LEN]);
+    }
+    data
+}
+
+/// The [PRODUCT_ID] product in `config`.
+///
+/// # Errors
+/// If `config` does not contain the product.
+pub fn product(config: &Config) -> Result<&ProductSpec> {
+    config
+        .products
+        .iter()
+        .find(|p| p.product_id == PRODUCT_ID)
+        .ok_or_else(|| Error::ConfigInvalid(format!("no product {PRODUCT_ID}")))
+}
+
+/// Start time for packets in granule `num` after the configured base time.
+///
+/// Packets start in the middle of the granule, so packets for the following few seconds fall
+/// within the granule.
+///
+/// # Errors
+/// If `config` does not contain the [PRODUCT_ID] product.
+pub fn granule_time(config: &Config, num: u64) -> Result<Time> {
+    let gran_len = product(config)?.gran_len;
+    Ok(Time::from(
+        config.satellite.base_time + gran_len * num + gran_len.micros() / 2,
+    ))
+}
+
+/// Collect `num_granules` consecutive [PRODUCT_ID] granules, each containing 5 packets,
+/// starting with granule `first` after the configured base time.
+///
+/// Each item contains the primary granule followed by any packed granules.
+///
+/// # Errors
+/// If `config` does not contain the [PRODUCT_ID] product or the packets cannot be collected.
+pub fn viirs_granules(config: &Config, first: u64, num_granules: u64) -> Result<Vec<Vec<Rdr>>> {
+    let mut data = Vec::default();
+    for num in first..first + num_granules {
+        data.extend(viirs_packets(&granule_time(config, num)?, 5));
+    }
+    let groups =
+        collect_groups(decode_packets(&data[..]).filter_map(|p| p.ok())).filter_map(|g| g.ok());
+
+    let mut collector = Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+    let mut rdrs = Vec::default();
+    for (pkt, time) in PacketTimeIter::new(groups).with_timecode(&config.satellite.timecode) {
+        if let Some(completed) = collector.add(&time, pkt)? {
+            rdrs.push(completed);
+        }
+    }
+    rdrs.extend(collector.finish()?);
+    Ok(rdrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_default;
+
+    #[test]
+    fn test_viirs_packets() {
+        let config = get_default("npp").unwrap().unwrap();
+        let start = granule_time(&config, 0).unwrap();
+        let data = viirs_packets(&start, 3);
+
+        let packets: Vec<_> = decode_packets(&data[..]).map(|p| p.unwrap()).collect();
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[0].header.apid, APID);
+        assert_eq!(packets[2].header.sequence_id, 2);
+
+        let decoder = config.satellite.timecode.decoder();
+        let times: Vec<u64> = packets
+            .iter()
+            .map(|p| Time::from_epoch(decoder.decode(p).unwrap()).utc())
+            .collect();
+        assert_eq!(times[1] - times[0], 1_000_000);
+    }
+
+    #[test]
+    fn test_viirs_granules() {
+        let config = get_default("npp").unwrap().unwrap();
+        let gran_len = product(&config).unwrap().gran_len;
+        let rdrs = viirs_granules(&config, 2, 3).unwrap();
+
+        assert_eq!(rdrs.len(), 3);
+        for (granule, num) in rdrs.iter().zip(2u64..) {
+            assert_eq!(granule[0].product_id, PRODUCT_ID);
+            assert_eq!(
+                granule[0].meta.begin_time_iet,
+                config.satellite.base_time + gran_len * num
+            );
+            assert_eq!(granule[0].meta.num_packets(), 5);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::error::{Error, Result};
+
+/// Compression of a packet input file, determined by its file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// `.gz`
+    Gzip,
+    /// `.zst`; requires the `zstd` feature
+    Zstd,
+}
+
+impl Compression {
+    #[must_use]
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+/// Open a packet file for reading, transparently decompressing it if it is compressed.
+///
+/// See [Compression] for supported compression formats.
+///
+/// # Errors
+/// If the file cannot be opened or the compression format is not supported by this build.
+pub fn open_packet_file<P: AsRef<Path>>(path: P) -> Result<Box<dyn Read + Send>> {
+    let path = path.as_ref();
+    let file = File::open(path)?;
+    open_packet_reader(file, Compression::from_path(path)).map_err(|err| match err {
+        Error::UnsupportedCompression(msg) => {
+            Error::UnsupportedCompression(format!("{path:?}; {msg}"))
+        }
+        err => err,
+    })
+}
+
+/// Read packets from `reader`, decompressing them according to `compression`, e.g., to
+/// stream an input that is not a local file. See [open_packet_file].
+///
+/// # Errors
+/// If the compression format is not supported by this build.
+pub fn open_packet_reader<R: Read + Send + 'static>(
+    reader: R,
+    compression: Compression,
+) -> Result<Box<dyn Read + Send>> {
+    match compression {
+        Compression::None => Ok(Box::new(BufReader::new(reader))),
+        Compression::Gzip => Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader)))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(Box::new(BufReader::new(zstd::stream::read::Decoder::new(
+            reader,
+        )?))),
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd => Err(Error::UnsupportedCompression(
+            "zstd support requires the zstd feature".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+
+    use super::*;
+
+    #[test]
+    fn test_compression_from_path() {
+        assert_eq!(Compression::from_path("a.pds"), Compression::None);
+        assert_eq!(Compression::from_path("a.PDS.gz"), Compression::Gzip);
+        assert_eq!(Compression::from_path("a.dat.zst"), Compression::Zstd);
+    }
+
+    #[test]
+    fn test_open_gzip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("packets.dat.gz");
+        let expected: Vec<u8> = (0..=255).collect();
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Default::default());
+        encoder.write_all(&expected).unwrap();
+        encoder.finish().unwrap();
+
+        let mut zult = Vec::default();
+        open_packet_file(&path)
+            .unwrap()
+            .read_to_end(&mut zult)
+            .unwrap();
+
+        assert_eq!(zult, expected);
+    }
+
+    #[test]
+    fn test_open_packet_reader() {
+        let expected: Vec<u8> = (0..=255).collect();
+        let mut encoder = GzEncoder::new(Vec::default(), Default::default());
+        encoder.write_all(&expected).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut zult = Vec::default();
+        open_packet_reader(std::io::Cursor::new(compressed), Compression::Gzip)
+            .unwrap()
+            .read_to_end(&mut zult)
+            .unwrap();
+        assert_eq!(zult, expected);
+
+        let mut zult = Vec::default();
+        open_packet_reader(std::io::Cursor::new(expected.clone()), Compression::None)
+            .unwrap()
+            .read_to_end(&mut zult)
+            .unwrap();
+        assert_eq!(zult, expected);
+    }
+}
@@ -6,18 +6,238 @@
 //! Unfortunately, the document does not seem to be publicly available from an official source,
 //! but if you may have some luck if you search for CDFCB-X.
 //!
+//! # Examples
+//! The examples use synthetic packets from the `fixtures` module, which requires the
+//! `fixtures` feature, e.g., `cargo test --doc --features fixtures`.
+//!
+//! ## Create RDRs from packets
+//! Packets are collected into granules by a [Collector], then each granule, along with any
+//! granules packed with it, is written to its own file.
+//! ```
+//! # #[cfg(feature = "fixtures")]
+//! # fn main() -> rdr::Result<()> {
+//! use ccsds::spacepacket::{collect_groups, decode_packets};
+//! use rdr::{config::get_default, create_rdr, fixtures, Collector, Meta, PacketTimeIter};
+//!
+//! let config = get_default("npp")?.expect("npp is a default config");
+//! let data = fixtures::viirs_packets(&fixtures::granule_time(&config, 0)?, 5);
+//!
+//! let packets = decode_packets(&data[..]).filter_map(|p| p.ok());
+//! let groups = collect_groups(packets).filter_map(|g| g.ok());
+//! let mut collector = Collector::new(config.satellite.clone(), &config.rdrs, &config.products);
+//! let mut granules = Vec::default();
+//! for (pkt, time) in PacketTimeIter::new(groups).with_timecode(&config.satellite.timecode) {
+//!     if let Some(rdrs) = collector.add(&time, pkt)? {
+//!         granules.push(rdrs);
+//!     }
+//! }
+//! granules.extend(collector.finish()?);
+//!
+//! let dir = tempfile::TempDir::new()?;
+//! for rdrs in granules {
+//!     let short_names: Vec<String> = rdrs.iter().map(|r| r.meta.collection.clone()).collect();
+//!     let meta = Meta::from_products(&short_names, &config).expect("products are configured");
+//!     create_rdr(dir.path().join(format!("{}.h5", rdrs[0].meta.id)), meta, &rdrs)?;
+//! }
+//! # Ok(())
+//! # }
+//! # #[cfg(not(feature = "fixtures"))]
+//! # fn main() {}
+//! ```
+//!
+//! ## Aggregate granules
+//! Writing more than one granule for a collection creates an aggregate RDR, and
+//! [append_rdr_granule] adds a granule to an existing file.
+//! ```
+//! # #[cfg(feature = "fixtures")]
+//! # fn main() -> rdr::Result<()> {
+//! use rdr::{append_rdr_granule, config::get_default, create_rdr, fixtures, Meta};
+//!
+//! let config = get_default("npp")?.expect("npp is a default config");
+//! let short_name = fixtures::product(&config)?.short_name.clone();
+//! // Only the primary granules; the fixtures have no packed data
+//! let rdrs: Vec<_> = fixtures::viirs_granules(&config, 0, 3)?
+//!     .into_iter()
+//!     .map(|mut rdrs| rdrs.remove(0))
+//!     .collect();
+//!
+//! let dir = tempfile::TempDir::new()?;
+//! let path = dir.path().join("aggr.h5");
+//! let meta = Meta::from_products(&[short_name.clone()], &config).expect("product is configured");
+//! create_rdr(&path, meta, &rdrs[..2])?;
+//! let path = append_rdr_granule(&path, &rdrs[2])?;
+//!
+//! assert_eq!(Meta::from_file(&path)?.granules[&short_name].len(), 3);
+//! # Ok(())
+//! # }
+//! # #[cfg(not(feature = "fixtures"))]
+//! # fn main() {}
+//! ```
+//!
+//! ## Read metadata and extract Common RDRs
+//! [Meta] describes a file's global, collection, and granule metadata. The Common RDR for each
+//! granule is stored in a `RawApplicationPackets_<idx>` dataset and decoded with [CommonRdr],
+//! or decoded into packets with [common_rdr_packets].
+//! ```
+//! # #[cfg(feature = "fixtures")]
+//! # fn main() -> rdr::Result<()> {
+//! use rdr::{common_rdr_packets, config::get_default, create_rdr, fixtures, CommonRdr, Meta};
+//!
+//! let config = get_default("npp")?.expect("npp is a default config");
+//! let short_name = fixtures::product(&config)?.short_name.clone();
+//! let rdrs = fixtures::viirs_granules(&config, 0, 1)?.remove(0);
+//! let dir = tempfile::TempDir::new()?;
+//! let path = dir.path().join("rdr.h5");
+//! let meta = Meta::from_products(&[short_name.clone()], &config).expect("product is configured");
+//! create_rdr(&path, meta, &rdrs)?;
+//!
+//! let meta = Meta::from_file(&path)?;
+//! let granule = &meta.granules[&short_name][0];
+//! println!("{} {} {}", granule.id, granule.begin_time_iet, granule.end_time_iet);
+//!
+//! let file = hdf5::File::open(&path)?;
+//! let dataset = file.dataset(&format!("All_Data/{short_name}_All/RawApplicationPackets_0"))?;
+//! let data = dataset.read_raw::<u8>()?;
+//! let common = CommonRdr::from_bytes(&data)?;
+//! let eng = common
+//!     .apid_list
+//!     .iter()
+//!     .find(|a| a.value == u32::from(fixtures::APID))
+//!     .expect("fixture apid is configured");
+//! assert_eq!(eng.pkts_received, 5);
+//! assert_eq!(common_rdr_packets(&data)?.len(), 5);
+//! # Ok(())
+//! # }
+//! # #[cfg(not(feature = "fixtures"))]
+//! # fn main() {}
+//! ```
+mod buildinfo;
 mod collector;
+mod dump;
 mod error;
+mod input;
 mod merge;
+mod orbit;
+mod pds;
 mod rdr;
+mod stac;
+mod stats;
+mod storage;
 mod time;
+mod warnings;
 mod writer;
 
 pub mod config;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+pub mod metrics;
+pub mod viirs;
+
+pub mod prelude;
+
+// The public API is re-exported explicitly, rather than by glob, so adding a `pub` item to a
+// module does not silently extend it. See `test_exported_api` in the `tests` module below.
+pub use buildinfo::{build_info, BuildInfo};
+pub use collector::{
+    BaseTimePolicy, Collector, CollectorSnapshot, GranuleAnnotator, GranuleSnapshot, OrphanPolicy,
+    PacketTimeIter, TimeBounds,
+};
+pub use dump::{
+    common_rdr_packets, dump_packets, dump_rdr, DumpPackets, FileSink, PacketSink, StreamSink,
+};
+pub use error::{Error, RdrError, Result};
+pub use input::{open_packet_file, open_packet_reader, Compression};
+pub use merge::{jpss_merge, jpss_merge_from, jpss_merge_with};
+pub use orbit::Orbits;
+pub use pds::{construction_record_name, ConstructionRecord, PdsApid, PdsFile, PdsGap};
+pub use rdr::{
+    filename, filename_with_orbit, get_granule_start, granule_id, granule_id_to_iet,
+    granule_versions, list, next_granule_version, output_subdir, read_attr_string,
+    validate_common_rdr, validate_granule_version, AggrMeta, ApidInfo, ApidTimeSkew,
+    CollectionSummary, CommonRdr, DuplicatePolicy, GranuleMeta, Meta, PacketTracker, ProductMeta,
+    RawDatasetDetail, RawDatasetInfo, RawDatasetLayout, Rdr, RdrData, StaticHeader, StorageOrder,
+};
+pub use stac::{StacAsset, StacItem, StacProperties, HDF5_MEDIA_TYPE};
+pub use stats::{
+    AggrReport, CollectionStats, CollectionTimeline, ContinuityReport, CreateSummary,
+    Discontinuity, GranuleGap, GranuleTimeSkew, InputReport, InputStatus, MisalignedGranule,
+    PackedCoverage, PackedCoverageGap, PacketTimeStats, SkippedGranule, SupersededGranule,
+    TimeSkewReport,
+};
+pub use time::{GranLen, IetMicros, Time, UtcMicros};
+pub use warnings::RateLimitedWarnings;
+pub use writer::{
+    append_rdr_granule, create_rdr, create_rdr_with, in_progress_path, normalize_rdr,
+    replace_rdr_granule, verify_rdr, write_all_data_attrs, write_rdr_granule, write_rdr_meta,
+    BlobWriter, EmptyCollections, FileOptions, Hdf5Writer, LibverBounds, RdrWriter, WriteOptions,
+    IN_PROGRESS_SUFFIX,
+};
+
+#[cfg(test)]
+mod tests {
+    //! Compile-time checks of the public API. A failure here means a change is visible to
+    //! downstream users and needs a semver appropriate version bump.
+    use std::path::PathBuf;
+
+    use ccsds::spacepacket::{Packet, PacketGroup};
+
+    use crate::config::{ProductSpec, RdrSpec, SatSpec};
+
+    #[test]
+    fn test_prelude_api() {
+        use crate::prelude::*;
+
+        let _: fn(&str) -> crate::Result<Option<config::Config>> = config::get_default;
+        let _: fn(SatSpec, &[RdrSpec], &[ProductSpec]) -> Collector = Collector::new;
+        let _: fn(&mut Collector, &Time, Packet) -> crate::Result<Option<Vec<Rdr>>> =
+            Collector::add;
+        let _: fn(Collector) -> crate::Result<Vec<Vec<Rdr>>> = Collector::finish;
+        let _: fn(PathBuf, Meta, &[Rdr]) -> crate::Result<()> = create_rdr::<PathBuf>;
+        let _: fn(&[PathBuf], Vec<u8>) -> ccsds::Result<()> = jpss_merge::<Vec<u8>>;
+        let _: fn(PathBuf) -> crate::Result<Meta> = Meta::from_file::<PathBuf>;
+        let _: fn(u64) -> Time = Time::from_iet;
+        let _: fn() -> Time = Time::now;
+        let _: fn(&SatSpec, &ProductSpec, &Time) -> RdrData = RdrData::new;
+    }
 
-pub use collector::*;
-pub use error::*;
-pub use merge::*;
-pub use rdr::*;
-pub use time::*;
-pub use writer::*;
+    #[test]
+    fn test_exported_api() {
+        // Items outside the prelude that downstream users rely on
+        let _: fn(std::vec::IntoIter<PacketGroup>) -> crate::PacketTimeIter<_> =
+            crate::PacketTimeIter::new;
+        let _: fn(PathBuf, &crate::Rdr) -> crate::Result<PathBuf> =
+            crate::append_rdr_granule::<PathBuf>;
+        let _: Option<Box<dyn crate::RdrWriter>> = Some(Box::new(crate::Hdf5Writer::default()));
+        let _: fn(u64) -> crate::GranLen = crate::GranLen::from_micros;
+        let _ = crate::IetMicros(0);
+        let _: fn(&crate::GranuleMeta, &crate::GranuleMeta) -> bool =
+            crate::GranuleMeta::is_packed_with;
+        let _: std::result::Result<(), crate::Error> = Ok(());
+        let _ = crate::open_packet_file::<PathBuf>;
+        let _ = crate::open_packet_reader::<std::io::Empty>;
+        let _ = crate::common_rdr_packets;
+        let _ = crate::validate_common_rdr;
+        let _ = crate::read_attr_string;
+        let _ = crate::jpss_merge_with::<Vec<u8>>;
+        let _ = crate::jpss_merge_from::<PathBuf, std::fs::File, _, Vec<u8>>;
+        let _ = crate::normalize_rdr::<PathBuf>;
+        let _ = crate::verify_rdr::<PathBuf>;
+        let _ = crate::create_rdr_with::<PathBuf>;
+        let _ = crate::list::<PathBuf>;
+        let _ = crate::build_info;
+        let _ = crate::construction_record_name;
+        let _ = crate::ConstructionRecord::new;
+        let _: &str = crate::IN_PROGRESS_SUFFIX;
+        let _: &str = crate::HDF5_MEDIA_TYPE;
+        let _ = crate::CreateSummary::default();
+        let _ = crate::PacketTimeStats::default();
+        let _ = crate::TimeSkewReport::default();
+        let _ = crate::ApidTimeSkew::default();
+        let _ = crate::DuplicatePolicy::default();
+        let _ = crate::OrphanPolicy::default();
+        let _ = crate::BaseTimePolicy::default();
+        let _ = crate::EmptyCollections::default();
+        let _ = crate::WriteOptions::default();
+        let _ = crate::Compression::None;
+    }
+}
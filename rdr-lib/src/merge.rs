@@ -1,18 +1,178 @@
-use std::{io::Write, path::PathBuf};
+use std::{
+    collections::HashSet,
+    io::{self, Read, Write},
+    path::PathBuf,
+};
 
-use ccsds::spacepacket::{Merger, TimecodeDecoder};
+use ccsds::spacepacket::{collect_groups, decode_packets, Apid, Merger};
 use ccsds::Result;
+use tracing::{debug, warn};
+
+use crate::{config::TimecodeSpec, Time};
+
+/// Apids written first when several packets have the same time.
+const APID_ORDER: [Apid; 2] = [826, 821];
 
 /// Merge JPSS spacepacket files into `writer`.
 ///
 /// The merged output will be sorted by time and apid.
 pub fn jpss_merge<W: Write>(files: &[PathBuf], writer: W) -> Result<()> {
-    let time_decoder = TimecodeDecoder::new(ccsds::timecode::Format::Cds {
-        num_day: 2,
-        num_submillis: 2,
-    });
+    jpss_merge_with(files, &TimecodeSpec::default(), writer)
+}
 
-    Merger::new(files.to_vec(), time_decoder)
-        .with_apid_order(&[826, 821])
+/// Merge spacepacket files into `writer`, decoding packet times using `timecode`.
+///
+/// See [jpss_merge].
+pub fn jpss_merge_with<W: Write>(
+    files: &[PathBuf],
+    timecode: &TimecodeSpec,
+    writer: W,
+) -> Result<()> {
+    Merger::new(files.to_vec(), timecode.decoder())
+        .with_apid_order(&APID_ORDER)
         .merge(writer)
 }
+
+/// Location of a packet group in a merge input.
+struct GroupPtr {
+    input: usize,
+    offset: u64,
+    size: u64,
+    time: u64,
+    order: i32,
+}
+
+/// Sequential reader for a merge input that is re-opened to move backwards.
+struct InputCursor<R> {
+    reader: R,
+    pos: u64,
+}
+
+/// Merge spacepacket `inputs` into `writer`, like [jpss_merge_with], reading each input with
+/// `open`, e.g., to merge compressed or remote inputs without first copying them locally.
+///
+/// Each input is read once to index its packet groups and again to copy them to `writer`.
+/// Inputs are only read forwards; an input is re-opened if a group must be copied from
+/// before the current position, so inputs in time order, e.g., level-0 files, are read
+/// exactly twice. Groups with the same time, apid, and sequence id as a group from an
+/// earlier input are dropped as duplicates.
+///
+/// # Errors
+/// If an input cannot be opened or read, or on failure writing to `writer`.
+pub fn jpss_merge_from<I, R, F, W>(
+    inputs: &[I],
+    mut open: F,
+    timecode: &TimecodeSpec,
+    mut writer: W,
+) -> crate::Result<()>
+where
+    R: Read + Send,
+    F: FnMut(&I) -> io::Result<R>,
+    W: Write,
+{
+    let decoder = timecode.decoder();
+    let mut seen: HashSet<(Apid, u64, u16)> = HashSet::default();
+    let mut index: Vec<GroupPtr> = Vec::default();
+    for (idx, input) in inputs.iter().enumerate() {
+        let packets = decode_packets(open(input)?).filter_map(|p| p.ok());
+        for group in collect_groups(packets).filter_map(|g| g.ok()) {
+            let Some(first) = group.packets.first() else {
+                continue;
+            };
+            if !(first.is_first() || first.is_standalone()) {
+                warn!(header = ?first.header, "dropping bad group");
+                continue;
+            }
+            let Ok(epoch) = decoder.decode(first) else {
+                warn!(header = ?first.header, "timecode decode error; dropping group");
+                continue;
+            };
+            let time = Time::from_epoch(epoch).iet();
+            let apid = first.header.apid;
+            if !seen.insert((apid, time, first.header.sequence_id)) {
+                continue;
+            }
+            // the same ordering as [Merger::with_apid_order]
+            let order = APID_ORDER
+                .iter()
+                .position(|a| *a == apid)
+                .map_or(i32::from(apid), |pos| 4096 - pos as i32);
+            index.push(GroupPtr {
+                input: idx,
+                offset: first.offset as u64,
+                size: group.packets.iter().map(|p| p.data.len() as u64).sum(),
+                time,
+                order,
+            });
+        }
+    }
+    index.sort_by_key(|ptr| (ptr.time, ptr.order));
+
+    let mut cursors: Vec<Option<InputCursor<R>>> = inputs.iter().map(|_| None).collect();
+    let mut buf = Vec::default();
+    for ptr in &index {
+        let slot = &mut cursors[ptr.input];
+        if slot.as_ref().map_or(true, |cursor| cursor.pos > ptr.offset) {
+            if slot.is_some() {
+                debug!(input = ptr.input, "re-opening merge input");
+            }
+            *slot = Some(InputCursor {
+                reader: open(&inputs[ptr.input])?,
+                pos: 0,
+            });
+        }
+        let cursor = slot.as_mut().expect("merge input is open");
+        let skip = ptr.offset - cursor.pos;
+        if io::copy(&mut cursor.reader.by_ref().take(skip), &mut io::sink())? != skip {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        buf.resize(ptr.size as usize, 0);
+        cursor.reader.read_exact(&mut buf)?;
+        cursor.pos = ptr.offset + ptr.size;
+        writer.write_all(&buf)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{config::get_default, fixtures};
+
+    /// Bytes of each fixture packet; 6 byte header, 8 byte timecode, 8 bytes filler.
+    const PACKET_LEN: usize = 22;
+
+    #[test]
+    fn test_jpss_merge_from() {
+        let config = get_default("npp").unwrap().unwrap();
+        let data = fixtures::viirs_packets(&fixtures::granule_time(&config, 0).unwrap(), 15);
+        let packet = |idx: usize| &data[idx * PACKET_LEN..(idx + 1) * PACKET_LEN];
+
+        // overlapping inputs, the second with its packets in reverse order
+        let first = data[..10 * PACKET_LEN].to_vec();
+        let second: Vec<u8> = (5..15).rev().flat_map(packet).copied().collect();
+        let inputs = [first, second];
+
+        let mut opened = 0;
+        let mut merged = Vec::default();
+        jpss_merge_from(
+            &inputs,
+            |input| {
+                opened += 1;
+                Ok(Cursor::new(input.clone()))
+            },
+            &config.satellite.timecode,
+            &mut merged,
+        )
+        .unwrap();
+
+        assert_eq!(merged, data);
+        // once each to index, once to copy the first, and once for each of the 5 packets
+        // of the second not in the first
+        assert_eq!(opened, 2 + 1 + 5);
+    }
+}
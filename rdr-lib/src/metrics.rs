@@ -0,0 +1,92 @@
+//! Collection and writing metrics, recorded using the [metrics](https://docs.rs/metrics)
+//! facade when built with the `metrics` feature.
+//!
+//! Nothing is recorded until the host application installs a recorder, e.g., the
+//! `metrics-exporter-prometheus` exporter so metrics can be scraped by Prometheus. Without the
+//! `metrics` feature recording is a no-op.
+
+/// Counter of packets added to a [crate::Collector].
+pub const PACKETS: &str = "rdr_packets_total";
+/// Counter of packets not added to a granule, labeled with a `reason`. See [DropReason].
+pub const PACKETS_DROPPED: &str = "rdr_packets_dropped_total";
+/// Gauge of primary and packed granules currently being collected.
+pub const OPEN_GRANULES: &str = "rdr_open_granules";
+/// Counter of RDR files written.
+pub const RDRS_WRITTEN: &str = "rdr_rdrs_written_total";
+/// Counter of granule data bytes written.
+pub const BYTES_WRITTEN: &str = "rdr_bytes_written_total";
+
+/// Reason a packet was dropped, the `reason` label of [PACKETS_DROPPED].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    /// The packet APID is not configured for any collected product
+    NoProduct,
+    /// The packet time is outside the configured time bounds and it was quarantined
+    OutOfBounds,
+    /// The packet is a duplicate handled according to the duplicate policy
+    Duplicate,
+    /// The packet time is before the spacecraft base time and it was skipped
+    BeforeBaseTime,
+    /// The packet could not be added, e.g., due to an invalid granule time
+    Invalid,
+}
+
+impl DropReason {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NoProduct => "no_product",
+            Self::OutOfBounds => "out_of_bounds",
+            Self::Duplicate => "duplicate",
+            Self::BeforeBaseTime => "before_base_time",
+            Self::Invalid => "invalid",
+        }
+    }
+}
+
+/// Register descriptions for all metrics with the installed recorder. Call after installing a
+/// recorder so exporters include help text.
+pub fn describe() {
+    #[cfg(feature = "metrics")]
+    {
+        ::metrics::describe_counter!(PACKETS, "Packets added to a collector");
+        ::metrics::describe_counter!(PACKETS_DROPPED, "Packets not added to a granule");
+        ::metrics::describe_gauge!(OPEN_GRANULES, "Granules currently being collected");
+        ::metrics::describe_counter!(RDRS_WRITTEN, "RDR files written");
+        ::metrics::describe_counter!(
+            BYTES_WRITTEN,
+            ::metrics::Unit::Bytes,
+            "Granule data bytes written"
+        );
+    }
+}
+
+pub(crate) fn packet_added() {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(PACKETS).increment(1);
+}
+
+pub(crate) fn packet_dropped(reason: DropReason) {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(PACKETS_DROPPED, "reason" => reason.as_str()).increment(1);
+    #[cfg(not(feature = "metrics"))]
+    let _ = reason;
+}
+
+pub(crate) fn open_granules(num: usize) {
+    #[cfg(feature = "metrics")]
+    ::metrics::gauge!(OPEN_GRANULES).set(num as f64);
+    #[cfg(not(feature = "metrics"))]
+    let _ = num;
+}
+
+pub(crate) fn rdr_written(rdrs: &[crate::Rdr]) {
+    #[cfg(feature = "metrics")]
+    {
+        let bytes: usize = rdrs.iter().map(|r| r.data.len()).sum();
+        ::metrics::counter!(RDRS_WRITTEN).increment(1);
+        ::metrics::counter!(BYTES_WRITTEN).increment(bytes as u64);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = rdrs;
+}
@@ -0,0 +1,162 @@
+use hifitime::{Epoch, Unit};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    IetMicros, Time,
+};
+
+/// Orbit numbers and boundaries, where an orbit starts at an ascending node.
+///
+/// Boundaries are approximated from a single reference ascending node and a constant period,
+/// so they drift the further a time is from the reference. This is accurate enough to group
+/// granules by orbit for data within days of the reference, but it is not a propagator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Orbits {
+    /// Time of a reference ascending node, i.e., the start of orbit `number`.
+    pub ascending_node: IetMicros,
+    /// Orbital period in microseconds.
+    pub period: u64,
+    /// Orbit number starting at `ascending_node`.
+    pub number: u64,
+}
+
+/// Parse the trimmed TLE field at the 1-based inclusive columns `start` to `end`.
+fn tle_field<T: std::str::FromStr>(line: &str, start: usize, end: usize, name: &str) -> Result<T> {
+    line.get(start - 1..end)
+        .and_then(|s| s.trim().parse().ok())
+        .ok_or_else(|| Error::InvalidTle(format!("invalid {name}")))
+}
+
+impl Orbits {
+    /// Create from a two-line element set, optionally preceded by a name line.
+    ///
+    /// The reference ascending node is estimated from the epoch, argument of perigee, and mean
+    /// anomaly, the period from the mean motion, and the orbit number from the revolution
+    /// number at epoch.
+    ///
+    /// # Errors
+    /// If the element lines are missing or their fields cannot be parsed.
+    pub fn from_tle(tle: &str) -> Result<Self> {
+        let line = |num: &str| {
+            tle.lines()
+                .map(str::trim_end)
+                .find(|l| l.starts_with(num))
+                .ok_or_else(|| Error::InvalidTle(format!("missing line {}", num.trim())))
+        };
+        let (line1, line2) = (line("1 ")?, line("2 ")?);
+
+        let year: i32 = tle_field(line1, 19, 20, "epoch year")?;
+        let year = if year < 57 { 2000 + year } else { 1900 + year };
+        let day: f64 = tle_field(line1, 21, 32, "epoch day")?;
+        let arg_perigee: f64 = tle_field(line2, 35, 42, "argument of perigee")?;
+        let mean_anomaly: f64 = tle_field(line2, 44, 51, "mean anomaly")?;
+        let mean_motion: f64 = tle_field(line2, 53, 63, "mean motion")?;
+        let number: u64 = tle_field(line2, 64, 68, "revolution number")?;
+        if mean_motion <= 0.0 {
+            return Err(Error::InvalidTle(format!(
+                "invalid mean motion {mean_motion}"
+            )));
+        }
+
+        let epoch = Epoch::from_gregorian_utc_at_midnight(year, 1, 1) + Unit::Day * (day - 1.0);
+        let epoch = Time::from_epoch(epoch).iet_micros();
+        let period = (86_400_000_000.0 / mean_motion).round() as u64;
+        // Argument of latitude is the angle from the ascending node
+        let since_node = ((arg_perigee + mean_anomaly) % 360.0) / 360.0 * period as f64;
+
+        Ok(Self {
+            ascending_node: epoch - since_node.round() as u64,
+            period,
+            number,
+        })
+    }
+
+    /// Number of the orbit containing `time`.
+    #[must_use]
+    pub fn orbit_number(&self, time: IetMicros) -> u64 {
+        if time >= self.ascending_node {
+            self.number + (time - self.ascending_node) / self.period
+        } else {
+            let before = (self.ascending_node - time).div_ceil(self.period);
+            self.number.saturating_sub(before)
+        }
+    }
+
+    /// Start time, i.e., ascending node, of orbit `number`.
+    #[must_use]
+    pub fn orbit_start(&self, number: u64) -> IetMicros {
+        if number >= self.number {
+            self.ascending_node + (number - self.number) * self.period
+        } else {
+            self.ascending_node - (self.number - number) * self.period
+        }
+    }
+
+    /// End time of orbit `number`, i.e., the start of the next orbit.
+    #[must_use]
+    pub fn orbit_end(&self, number: u64) -> IetMicros {
+        self.orbit_start(number + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const TLE: &str = "SUOMI NPP
+1 37849U 11061A   24001.50000000  .00000000  00000-0  00000-0 0  9990
+2 37849  98.7000 100.0000 0001000  90.0000 270.0000 14.19500000123450";
+
+    #[test]
+    fn test_from_tle() {
+        let orbits = Orbits::from_tle(TLE).unwrap();
+
+        let epoch = Time::from_str("2024-01-01T12:00:00Z").unwrap().iet_micros();
+        // argument of latitude is 0, so epoch is the ascending node
+        assert_eq!(orbits.ascending_node, epoch);
+        assert_eq!(orbits.period, 6_086_650_229);
+        assert_eq!(orbits.number, 12345);
+    }
+
+    #[test]
+    fn test_from_tle_since_node() {
+        let tle = TLE.replace(" 90.0000 270.0000", " 45.0000  45.0000");
+        let orbits = Orbits::from_tle(&tle).unwrap();
+
+        let epoch = Time::from_str("2024-01-01T12:00:00Z").unwrap().iet_micros();
+        assert_eq!(epoch - orbits.ascending_node, orbits.period / 4);
+    }
+
+    #[test]
+    fn test_from_tle_invalid() {
+        assert!(Orbits::from_tle("").is_err());
+        assert!(Orbits::from_tle(&TLE.replace("14.19500000", "  .00000000")).is_err());
+        assert!(
+            Orbits::from_tle(TLE.lines().take(2).collect::<Vec<_>>().join("\n").as_str()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_orbit_number() {
+        let orbits = Orbits {
+            ascending_node: IetMicros(1_000_000),
+            period: 100,
+            number: 10,
+        };
+
+        assert_eq!(orbits.orbit_number(IetMicros(1_000_000)), 10);
+        assert_eq!(orbits.orbit_number(IetMicros(1_000_099)), 10);
+        assert_eq!(orbits.orbit_number(IetMicros(1_000_100)), 11);
+        assert_eq!(orbits.orbit_number(IetMicros(999_999)), 9);
+        assert_eq!(orbits.orbit_number(IetMicros(999_900)), 9);
+        assert_eq!(orbits.orbit_number(IetMicros(999_899)), 8);
+        assert_eq!(orbits.orbit_number(IetMicros(0)), 0);
+
+        assert_eq!(orbits.orbit_start(10), IetMicros(1_000_000));
+        assert_eq!(orbits.orbit_start(12), IetMicros(1_000_200));
+        assert_eq!(orbits.orbit_start(9), IetMicros(999_900));
+        assert_eq!(orbits.orbit_end(9), IetMicros(1_000_000));
+    }
+}
@@ -0,0 +1,497 @@
+//! NASA Level-0 Production Data Set (PDS) construction records.
+//!
+//! A construction record (CR) accompanies the data files of a PDS and describes the packets in
+//! them: the spacecraft and APIDs, packet and octet counts, first and last packet times, and
+//! packet sequence count gaps. Records are encoded using the EDOS PDS construction record
+//! layout (423-ICD-EDOS/EGS), where multi-byte values are big-endian and times are 8 byte CCSDS
+//! day segmented (CDS) times. Fields EDOS populates from ground station processing, such as
+//! fill and Reed-Solomon correction counts, are always zero, and the EDOS service header (ESH)
+//! times, i.e., ground receipt times, which are not available from the packets, are the packet
+//! times. The spacecraft clock (SCS) section is a single start and stop covering all packets.
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    path::Path,
+};
+
+use ccsds::spacepacket::{Apid, Packet};
+use hifitime::Unit;
+
+use crate::{
+    error::{Error, RdrError, Result},
+    Time,
+};
+
+/// Length of the PDS id field, i.e., a PDS file name without the `.PDS` extension.
+const PDS_ID_LEN: usize = 36;
+/// Length of the file name fields.
+const FILE_NAME_LEN: usize = 40;
+/// Days from the CDS epoch, 1958-01-01, to the Unix epoch.
+const CDS_UNIX_DAYS: i64 = 4383;
+const DAY_MICROS: i64 = 86_400_000_000;
+/// Modulus of the 14 bit CCSDS packet sequence count.
+const SEQ_MODULUS: u32 = 1 << 14;
+
+/// 8 byte CCSDS day segmented time, i.e., days since 1958-01-01, milliseconds of the day,
+/// and microseconds of the millisecond, as used for JPSS packet times.
+fn cds_bytes(time: &Time) -> [u8; 8] {
+    let micros = time.to_unix(Unit::Microsecond).round() as i64;
+    let days = micros.div_euclid(DAY_MICROS) + CDS_UNIX_DAYS;
+    let of_day = micros.rem_euclid(DAY_MICROS);
+    let mut buf = [0u8; 8];
+    buf[..2].copy_from_slice(&(days as u16).to_be_bytes());
+    buf[2..6].copy_from_slice(&((of_day / 1_000) as u32).to_be_bytes());
+    buf[6..].copy_from_slice(&((of_day % 1_000) as u16).to_be_bytes());
+    buf
+}
+
+/// Append `value` to `buf` as a fixed length, space padded, ASCII field of `len` bytes.
+fn put_str(buf: &mut Vec<u8>, value: &str, len: usize) {
+    let mut field = vec![b' '; len];
+    let bytes = value.as_bytes();
+    let n = bytes.len().min(len);
+    field[..n].copy_from_slice(&bytes[..n]);
+    buf.extend(field);
+}
+
+/// Count field of a single byte. There are at most a few dozen APIDs in a PDS, so larger
+/// counts are saturated.
+fn count_u8(len: usize) -> u8 {
+    u8::try_from(len).unwrap_or(u8::MAX)
+}
+
+/// Packets missing from the packet sequence of an APID.
+#[derive(Debug, Clone)]
+pub struct PdsGap {
+    /// Sequence count of the first missing packet
+    pub first_missing: u16,
+    /// Number of missing packets
+    pub missing: u32,
+    /// Time of the packet before the gap
+    pub pre_gap_time: Time,
+    /// Time of the packet after the gap
+    pub post_gap_time: Time,
+    /// Byte offset in the PDS of the packet before the gap
+    pub pre_gap_offset: u64,
+    /// Byte offset in the PDS of the packet after the gap
+    pub post_gap_offset: u64,
+}
+
+/// Packet summary for a single APID in a PDS.
+#[derive(Debug, Clone)]
+pub struct PdsApid {
+    pub scid: u8,
+    pub apid: Apid,
+    /// Byte offset in the PDS of the first packet
+    pub first_offset: u64,
+    pub first_time: Time,
+    pub last_time: Time,
+    pub packets: u32,
+    pub octets: u64,
+    pub gaps: Vec<PdsGap>,
+    last_seq: u16,
+    last_offset: u64,
+}
+
+/// A data file of a PDS and the first and last packet times of each APID in it.
+#[derive(Debug, Clone)]
+pub struct PdsFile {
+    pub name: String,
+    pub apids: BTreeMap<Apid, (Time, Time)>,
+}
+
+/// Construction record for a PDS. See the [module](self) docs.
+///
+/// Packets are added in the order they appear in the data files, starting each file with
+/// [ConstructionRecord::add_file].
+#[derive(Debug, Clone)]
+pub struct ConstructionRecord {
+    /// PDS id, i.e., the construction record file name without the `.PDS` extension
+    pub pds_id: String,
+    pub scid: u8,
+    /// Time the PDS was completed
+    pub created: Time,
+    pub apids: BTreeMap<Apid, PdsApid>,
+    pub files: Vec<PdsFile>,
+    /// Byte offset in the PDS of the next packet
+    offset: u64,
+}
+
+impl ConstructionRecord {
+    /// EDOS software version major and minor fields.
+    pub const VERSION: (u8, u8) = (1, 0);
+    /// Construction record type for a PDS, rather than an expedited data set.
+    pub const TYPE_PDS: u8 = 1;
+
+    #[must_use]
+    pub fn new(pds_id: &str, scid: u8, created: Time) -> Self {
+        Self {
+            pds_id: pds_id.to_string(),
+            scid,
+            created,
+            apids: BTreeMap::default(),
+            files: Vec::default(),
+            offset: 0,
+        }
+    }
+
+    /// Start the data file `name`. Packets added after this are recorded as in this file.
+    pub fn add_file(&mut self, name: &str) {
+        self.files.push(PdsFile {
+            name: name.to_string(),
+            apids: BTreeMap::default(),
+        });
+    }
+
+    /// Record `pkt`, with packet time `time`, as the next packet in the PDS.
+    pub fn add_packet(&mut self, time: &Time, pkt: &Packet) {
+        let (apid, seq) = (pkt.header.apid, pkt.header.sequence_id);
+        let len = pkt.data.len() as u64;
+        let offset = self.offset;
+        self.offset += len;
+
+        if let Some(file) = self.files.last_mut() {
+            file.apids
+                .entry(apid)
+                .and_modify(|(first, last)| {
+                    *first = first.clone().min(time.clone());
+                    *last = last.clone().max(time.clone());
+                })
+                .or_insert_with(|| (time.clone(), time.clone()));
+        }
+
+        let info = match self.apids.entry(apid) {
+            Entry::Vacant(entry) => {
+                entry.insert(PdsApid {
+                    scid: self.scid,
+                    apid,
+                    first_offset: offset,
+                    first_time: time.clone(),
+                    last_time: time.clone(),
+                    packets: 1,
+                    octets: len,
+                    gaps: Vec::default(),
+                    last_seq: seq,
+                    last_offset: offset,
+                });
+                return;
+            }
+            Entry::Occupied(entry) => entry.into_mut(),
+        };
+        let delta = (u32::from(seq) + SEQ_MODULUS - u32::from(info.last_seq)) % SEQ_MODULUS;
+        if delta > 1 {
+            info.gaps.push(PdsGap {
+                first_missing: ((u32::from(info.last_seq) + 1) % SEQ_MODULUS) as u16,
+                missing: delta - 1,
+                pre_gap_time: info.last_time.clone(),
+                post_gap_time: time.clone(),
+                pre_gap_offset: info.last_offset,
+                post_gap_offset: offset,
+            });
+        }
+        if *time < info.first_time {
+            info.first_time = time.clone();
+        }
+        if *time > info.last_time {
+            info.last_time = time.clone();
+        }
+        info.packets += 1;
+        info.octets += len;
+        info.last_seq = seq;
+        info.last_offset = offset;
+    }
+
+    /// Total number of packets for all APIDs.
+    #[must_use]
+    pub fn num_packets(&self) -> u64 {
+        self.apids.values().map(|a| u64::from(a.packets)).sum()
+    }
+
+    /// Total number of sequence count gaps for all APIDs.
+    #[must_use]
+    pub fn num_gaps(&self) -> usize {
+        self.apids.values().map(|a| a.gaps.len()).sum()
+    }
+
+    /// Encode using the binary construction record layout.
+    ///
+    /// The data files are preceded in the file list by the construction record itself, which
+    /// has no APIDs, as EDOS does.
+    ///
+    /// # Errors
+    /// If a count or the byte offset of an APID's first packet does not fit its field.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let int = |err| Error::RdrError(RdrError::IntError(err));
+        let first = self.apids.values().map(|a| &a.first_time).min();
+        let last = self.apids.values().map(|a| &a.last_time).max();
+        let (first, last) = (
+            first.map(cds_bytes).unwrap_or_default(),
+            last.map(cds_bytes).unwrap_or_default(),
+        );
+
+        let mut buf = Vec::default();
+        buf.extend([Self::VERSION.0, Self::VERSION.1, Self::TYPE_PDS, 0]);
+        put_str(&mut buf, &self.pds_id, PDS_ID_LEN);
+        // Test flag and spare
+        buf.extend([0u8; 10]);
+        // A single spacecraft clock start/stop covering all the packets
+        buf.extend(1u16.to_be_bytes());
+        buf.extend(first);
+        buf.extend(last);
+        // EDOS fill octets and packets with mismatched lengths
+        buf.extend(0u64.to_be_bytes());
+        buf.extend(0u32.to_be_bytes());
+        // Packet and ESH first and last times
+        buf.extend(first);
+        buf.extend(last);
+        buf.extend(first);
+        buf.extend(last);
+        // Reed-Solomon corrected packets
+        buf.extend(0u32.to_be_bytes());
+        buf.extend(
+            u32::try_from(self.num_packets())
+                .map_err(int)?
+                .to_be_bytes(),
+        );
+        buf.extend(self.offset.to_be_bytes());
+        buf.extend(u32::try_from(self.num_gaps()).map_err(int)?.to_be_bytes());
+        buf.extend(cds_bytes(&self.created));
+        buf.extend([0u8; 7]);
+
+        buf.push(count_u8(self.apids.len()));
+        for info in self.apids.values() {
+            buf.extend([0, info.scid]);
+            buf.extend(info.apid.to_be_bytes());
+            buf.extend(u32::try_from(info.first_offset).map_err(int)?.to_be_bytes());
+            // Spare and VCID
+            buf.extend([0u8; 4]);
+            buf.extend(u32::try_from(info.gaps.len()).map_err(int)?.to_be_bytes());
+            for gap in &info.gaps {
+                buf.extend(u32::from(gap.first_missing).to_be_bytes());
+                buf.extend(gap.missing.to_be_bytes());
+                buf.extend(gap.pre_gap_offset.to_be_bytes());
+                buf.extend(gap.post_gap_offset.to_be_bytes());
+                // Packet and ESH times
+                let (pre, post) = (cds_bytes(&gap.pre_gap_time), cds_bytes(&gap.post_gap_time));
+                buf.extend(pre);
+                buf.extend(post);
+                buf.extend(pre);
+                buf.extend(post);
+            }
+            // Packets with fill and packets with mismatched lengths
+            buf.extend(0u32.to_be_bytes());
+            buf.extend(0u32.to_be_bytes());
+            // Packet and ESH first and last times
+            let (first, last) = (cds_bytes(&info.first_time), cds_bytes(&info.last_time));
+            buf.extend(first);
+            buf.extend(last);
+            buf.extend(first);
+            buf.extend(last);
+            // Reed-Solomon corrected packets
+            buf.extend(0u32.to_be_bytes());
+            buf.extend(info.packets.to_be_bytes());
+            buf.extend(info.octets.to_be_bytes());
+            buf.extend([0u8; 8]);
+        }
+
+        buf.extend([0u8; 3]);
+        buf.push(count_u8(self.files.len() + 1));
+        put_str(&mut buf, &format!("{}.PDS", self.pds_id), FILE_NAME_LEN);
+        buf.extend([0u8; 4]);
+        for file in &self.files {
+            put_str(&mut buf, &file.name, FILE_NAME_LEN);
+            buf.extend([0u8; 3]);
+            buf.push(count_u8(file.apids.len()));
+            for (apid, (first, last)) in &file.apids {
+                buf.extend([0, self.scid]);
+                buf.extend(apid.to_be_bytes());
+                buf.extend(cds_bytes(first));
+                buf.extend(cds_bytes(last));
+                buf.extend([0u8; 4]);
+            }
+        }
+        Ok(buf)
+    }
+
+    /// Write the encoded construction record to `path`.
+    ///
+    /// # Errors
+    /// See [ConstructionRecord::to_bytes], or on IO error.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        Ok(std::fs::write(path, self.to_bytes()?)?)
+    }
+}
+
+/// The construction record file name for the PDS data file `name`, i.e., `name` with a file
+/// sequence number of `000` rather than `001`, or `None` if `name` is not a PDS data file name
+/// such as `P1570826VIIRSSCIENCEAS16001000000001.PDS`.
+#[must_use]
+pub fn construction_record_name(name: &str) -> Option<String> {
+    const SEQ: std::ops::Range<usize> = 33..36;
+    if !name.starts_with('P') || !name.ends_with(".PDS") || name.get(SEQ) != Some("001") {
+        return None;
+    }
+    Some(format!("{}000{}", &name[..SEQ.start], &name[SEQ.end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ccsds::spacepacket::decode_packets;
+
+    use super::*;
+
+    #[test]
+    fn test_cds_bytes() {
+        let time = Time::from_str("1970-01-02T00:00:01.002003Z").unwrap();
+        let buf = cds_bytes(&time);
+        assert_eq!(u16::from_be_bytes([buf[0], buf[1]]), 4384);
+        assert_eq!(u32::from_be_bytes([buf[2], buf[3], buf[4], buf[5]]), 1_002);
+        assert_eq!(u16::from_be_bytes([buf[6], buf[7]]), 3);
+    }
+
+    /// Reads big-endian fields of an encoded construction record.
+    struct Reader<'a>(&'a [u8]);
+
+    impl Reader<'_> {
+        fn take(&mut self, len: usize) -> &[u8] {
+            let (head, rest) = self.0.split_at(len);
+            self.0 = rest;
+            head
+        }
+        fn u8(&mut self) -> u8 {
+            self.take(1)[0]
+        }
+        fn u16(&mut self) -> u16 {
+            u16::from_be_bytes(self.take(2).try_into().unwrap())
+        }
+        fn u32(&mut self) -> u32 {
+            u32::from_be_bytes(self.take(4).try_into().unwrap())
+        }
+        fn u64(&mut self) -> u64 {
+            u64::from_be_bytes(self.take(8).try_into().unwrap())
+        }
+        fn time(&mut self) -> [u8; 8] {
+            self.take(8).try_into().unwrap()
+        }
+        fn str(&mut self, len: usize) -> String {
+            String::from_utf8(self.take(len).to_vec())
+                .unwrap()
+                .trim_end()
+                .to_string()
+        }
+    }
+
+    #[test]
+    fn test_construction_record() {
+        let start = Time::from_str("2016-01-01T00:00:00Z").unwrap();
+        // sequence counts 2 and 3 missing
+        let dat = crate::fixtures::viirs_packets(&start, 5);
+        let packets: Vec<Packet> = decode_packets(&dat[..])
+            .map(|p| p.unwrap())
+            .filter(|p| ![2, 3].contains(&p.header.sequence_id))
+            .collect();
+        let times: Vec<Time> = [0u64, 1, 4]
+            .iter()
+            .map(|secs| Time::from_iet(start.iet() + secs * 1_000_000))
+            .collect();
+        let pkt_len = packets[0].data.len() as u64;
+        let pds_id = "P1570826VIIRSSCIENCEAS16001000000000";
+        let created = Time::from_str("2016-01-01T01:00:00Z").unwrap();
+        let mut cr = ConstructionRecord::new(pds_id, 157, created.clone());
+        cr.add_file("P1570826VIIRSSCIENCEAS16001000000001.PDS");
+        for (pkt, time) in packets.iter().zip(&times) {
+            cr.add_packet(time, pkt);
+        }
+        assert_eq!(cr.num_packets(), 3);
+        assert_eq!(cr.num_gaps(), 1);
+
+        // Decode using the EDOS field layout
+        let bytes = cr.to_bytes().unwrap();
+        let mut r = Reader(&bytes);
+        let (first, last) = (cds_bytes(&times[0]), cds_bytes(&times[2]));
+        assert_eq!(
+            (r.u8(), r.u8(), r.u8()),
+            (1, 0, ConstructionRecord::TYPE_PDS)
+        );
+        r.take(1);
+        assert_eq!(r.str(PDS_ID_LEN), pds_id);
+        r.take(10);
+        assert_eq!(r.u16(), 1, "scs count");
+        assert_eq!((r.time(), r.time()), (first, last), "scs start/stop");
+        assert_eq!(
+            (r.u64(), r.u32()),
+            (0, 0),
+            "fill octets, mismatched lengths"
+        );
+        assert_eq!((r.time(), r.time()), (first, last), "packet times");
+        assert_eq!((r.time(), r.time()), (first, last), "esh times");
+        assert_eq!(r.u32(), 0, "rs corrected");
+        assert_eq!(r.u32(), 3, "packets");
+        assert_eq!(r.u64(), pkt_len * 3, "octets");
+        assert_eq!(r.u32(), 1, "gaps");
+        assert_eq!(r.time(), cds_bytes(&created));
+        r.take(7);
+
+        assert_eq!(r.u8(), 1, "apid count");
+        r.take(1);
+        assert_eq!((r.u8(), r.u16(), r.u32()), (157, 826, 0));
+        r.take(4);
+        assert_eq!(r.u32(), 1, "apid gaps");
+        assert_eq!((r.u32(), r.u32()), (2, 2), "first missing, missing");
+        assert_eq!((r.u64(), r.u64()), (pkt_len, pkt_len * 2), "gap offsets");
+        let gap = (cds_bytes(&times[1]), cds_bytes(&times[2]));
+        assert_eq!((r.time(), r.time()), gap, "gap packet times");
+        assert_eq!((r.time(), r.time()), gap, "gap esh times");
+        assert_eq!((r.u32(), r.u32()), (0, 0), "fill, mismatched lengths");
+        assert_eq!((r.time(), r.time()), (first, last), "apid packet times");
+        assert_eq!((r.time(), r.time()), (first, last), "apid esh times");
+        assert_eq!((r.u32(), r.u32(), r.u64()), (0, 3, pkt_len * 3));
+        r.take(8);
+
+        r.take(3);
+        assert_eq!(r.u8(), 2, "file count");
+        assert_eq!(r.str(FILE_NAME_LEN), format!("{pds_id}.PDS"));
+        r.take(3);
+        assert_eq!(r.u8(), 0, "construction record apids");
+        assert_eq!(
+            r.str(FILE_NAME_LEN),
+            "P1570826VIIRSSCIENCEAS16001000000001.PDS"
+        );
+        r.take(3);
+        assert_eq!(r.u8(), 1, "file apids");
+        r.take(1);
+        assert_eq!((r.u8(), r.u16()), (157, 826));
+        assert_eq!((r.time(), r.time()), (first, last));
+        r.take(4);
+        assert!(r.0.is_empty(), "{} unread bytes", r.0.len());
+    }
+
+    #[test]
+    fn test_construction_record_offset_overflow() {
+        let start = Time::from_str("2016-01-01T00:00:00Z").unwrap();
+        let dat = crate::fixtures::viirs_packets(&start, 1);
+        let pkt = decode_packets(&dat[..]).next().unwrap().unwrap();
+        let mut cr = ConstructionRecord::new("P1570826VIIRSSCIENCEAS16001000000000", 157, start);
+        cr.offset = u64::from(u32::MAX) + 1;
+        cr.add_packet(&Time::from_iet(0), &pkt);
+        assert!(cr.to_bytes().is_err());
+    }
+
+    #[test]
+    fn test_construction_record_name() {
+        assert_eq!(
+            construction_record_name("P1570826VIIRSSCIENCEAS16001000000001.PDS").as_deref(),
+            Some("P1570826VIIRSSCIENCEAS16001000000000.PDS")
+        );
+        assert_eq!(
+            construction_record_name(
+                "P1570826VIIRSSCIENCEAS16001000000001_d20160101_t000000_e000100.PDS"
+            )
+            .as_deref(),
+            Some("P1570826VIIRSSCIENCEAS16001000000000_d20160101_t000000_e000100.PDS")
+        );
+        assert!(construction_record_name("P1570826VIIRSSCIENCEAS16001000000000.PDS").is_none());
+        assert!(construction_record_name("157-16001000000.dat").is_none());
+    }
+}
@@ -0,0 +1,8 @@
+//! Commonly used types and functions.
+//!
+//! Covers collecting packets into granules, writing and reading RDR files, and loading
+//! configuration. Import everything with:
+//! ```
+//! use rdr::prelude::*;
+//! ```
+pub use crate::{config, create_rdr, jpss_merge, Collector, Meta, Rdr, RdrData, Time};
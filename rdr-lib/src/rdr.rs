@@ -1,16 +1,23 @@
 use ccsds::spacepacket::{Apid, Packet};
-use hdf5::{types::FixedAscii, Dataset, Group};
-use serde::Serialize;
+use hdf5::{
+    types::{FixedAscii, VarLenAscii},
+    Attribute, Dataset, Group, Location,
+};
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, VecDeque},
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::Display,
-    path::Path,
+    path::{Component, Path, PathBuf},
 };
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
 
 use crate::{
-    config::get_default,
+    config::get_default_for_platform,
     error::{Error, RdrError, Result},
+    storage::{PacketStorage, StoredPacket},
+    viirs::{self, ScanCounts},
+    GranLen, IetMicros, MisalignedGranule, PackedCoverage, PackedCoverageGap, RateLimitedWarnings,
     Time,
 };
 
@@ -52,36 +59,115 @@ macro_rules! to_str {
     };
 }
 
-use crate::config::{Config, ProductSpec, SatSpec};
+use crate::config::{
+    AttrTruncation, Config, FileAttrLengths, GranulePadding, ProductSpec, SatSpec,
+    MAX_FILE_ATTR_LEN,
+};
 
 /// Compute the RDR granule start time in IET microseconds.
 ///
 /// This is generated the spacecraft mission base time which seems to be based on when
 /// SNPP was launched and the same for the currently flying spacecraft.
-pub fn get_granule_start(iet: u64, gran_len: u64, base_time: u64) -> u64 {
+pub fn get_granule_start(iet: IetMicros, gran_len: GranLen, base_time: IetMicros) -> IetMicros {
     let seconds_since_base = iet - base_time;
     // granule number relative to base_time
-    let granule_number = seconds_since_base / gran_len;
-    // number of micro seconds since base_time
-    let ms = granule_number * gran_len;
+    let granule_number = seconds_since_base / gran_len.micros();
     // convert back to IET
-    ms + base_time
+    base_time + gran_len * granule_number
 }
 
-/// Compuate the value used for N_Granule_ID
+/// True if the packed granule starting at `packed_start` with length `packed_len` is packed
+/// with the primary granule spanning `primary_start` to `primary_end`.
+///
+/// This is the packing rule; a packed granule is included if its start is within its granule
+/// length of the primary granule start and less than the primary granule end.
+#[must_use]
+pub(crate) fn is_packed_overlap(
+    primary_start: IetMicros,
+    primary_end: IetMicros,
+    packed_start: IetMicros,
+    packed_len: GranLen,
+) -> bool {
+    packed_start + packed_len > primary_start && packed_start < primary_end
+}
+
+/// Compuate the value used for N_Granule_ID using the JPSS scheme; the uppercase satellite
+/// short name followed by the 12 digit number of tenths of a second since `base_time`.
+///
+/// See [SatSpec::granule_id] for ids using the configured scheme.
 ///
 /// # Errors
 /// If `rdr_iet` is less than the configured satellite base time
-pub fn granule_id(sat_short_name: &str, base_time: u64, rdr_iet: u64) -> Result<String> {
+pub fn granule_id(
+    sat_short_name: &str,
+    base_time: IetMicros,
+    rdr_iet: IetMicros,
+) -> Result<String> {
+    format_granule_id(
+        &sat_short_name.to_uppercase(),
+        base_time,
+        rdr_iet,
+        JPSS_GRANULE_ID_RESOLUTION,
+        JPSS_GRANULE_ID_WIDTH,
+    )
+}
+
+/// Compute the IET microseconds for a N_Granule_ID value; the inverse of [granule_id].
+///
+/// # Errors
+/// If `id` does not start with `sat_short_name` followed by digits.
+pub fn granule_id_to_iet(
+    sat_short_name: &str,
+    base_time: IetMicros,
+    id: &str,
+) -> Result<IetMicros> {
+    parse_granule_id(sat_short_name, base_time, id, JPSS_GRANULE_ID_RESOLUTION)
+}
+
+/// Microseconds per unit of the JPSS granule id number.
+pub(crate) const JPSS_GRANULE_ID_RESOLUTION: u64 = 100_000;
+/// Number of digits in the JPSS granule id number.
+pub(crate) const JPSS_GRANULE_ID_WIDTH: usize = 12;
+
+/// Granule id of `prefix` followed by the number of `resolution` microseconds from
+/// `base_time` to `rdr_iet`, zero padded to `width` digits.
+pub(crate) fn format_granule_id(
+    prefix: &str,
+    base_time: IetMicros,
+    rdr_iet: IetMicros,
+    resolution: u64,
+    width: usize,
+) -> Result<String> {
     if rdr_iet < base_time {
-        return Err(Error::RdrError(RdrError::InvalidGranuleStart(rdr_iet)));
+        return Err(Error::RdrError(RdrError::InvalidGranuleStart(rdr_iet.0)));
     }
-    let t = (rdr_iet - base_time) / 100_000;
-    Ok(format!("{}{:012}", sat_short_name.to_uppercase(), t))
+    let t = (rdr_iet - base_time) / resolution;
+    Ok(format!("{prefix}{t:0width$}"))
+}
+
+/// Inverse of [format_granule_id], ignoring case.
+pub(crate) fn parse_granule_id(
+    prefix: &str,
+    base_time: IetMicros,
+    id: &str,
+    resolution: u64,
+) -> Result<IetMicros> {
+    let invalid = || Error::RdrError(RdrError::Invalid(format!("invalid granule id {id}")));
+    let prefix = prefix.to_uppercase();
+    let digits = id
+        .to_uppercase()
+        .strip_prefix(&prefix)
+        .ok_or_else(invalid)?
+        .to_string();
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let t: u64 = digits.parse().map_err(|_| invalid())?;
+    Ok(base_time + t * resolution)
 }
 
 /// [RdrData] compiled into metadata and raw data for a single RDR.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Rdr {
     /// Standard RDR granule metadata.
     pub meta: GranuleMeta,
@@ -91,23 +177,36 @@ pub struct Rdr {
 }
 
 impl Rdr {
-    pub(crate) fn from_data(rdr_data: &RdrData, data: Vec<u8>) -> Result<Self> {
-        let satid = rdr_data.header.satellite.to_lowercase().to_string();
-        let Some(config) = get_default(&satid)? else {
-            return Err(Error::ConfigNotFound(satid));
-        };
-        let Some(product) = config
-            .products
-            .iter()
-            .find(|p| p.short_name == rdr_data.short_name)
-        else {
-            return Err(Error::ConfigNotFound(format!(
-                "product {}",
-                rdr_data.short_name
-            )));
+    /// Create from an already compiled Common RDR granule `data`, e.g., produced by another
+    /// processor, so it can be packaged using the writers in this crate.
+    ///
+    /// # Errors
+    /// If `data` is not a structurally valid Common RDR. See [Rdr::validate].
+    pub fn from_bytes(product_id: &str, meta: GranuleMeta, data: Vec<u8>) -> Result<Self> {
+        let rdr = Self {
+            meta,
+            product_id: product_id.to_string(),
+            data,
         };
+        rdr.validate()?;
+        Ok(rdr)
+    }
+
+    /// Verify the Common RDR static header offsets are consistent with each other and with
+    /// the length of the data.
+    ///
+    /// # Errors
+    /// See [validate_common_rdr].
+    pub fn validate(&self) -> Result<()> {
+        validate_common_rdr(&self.data)
+    }
+
+    /// Create from collected `rdr_data` and its compiled Common RDR `data`, with granule
+    /// metadata from the satellite and product the data was collected with.
+    pub(crate) fn from_data(rdr_data: &RdrData, data: Vec<u8>) -> Result<Self> {
+        let product = &rdr_data.product;
         let time = Time::from_iet(rdr_data.header.start_boundary);
-        let mut meta = GranuleMeta::new(time, &config.satellite, product)?;
+        let mut meta = GranuleMeta::new(time, &rdr_data.sat, product)?;
         let mut names: Vec<String> = Vec::default();
         let mut counts: Vec<u32> = Vec::default();
         for a in rdr_data.apid_list.values() {
@@ -116,6 +215,21 @@ impl Rdr {
         }
         meta.packet_type_count = counts;
         meta.packet_type = names;
+        let padding: u64 = rdr_data
+            .apid_list
+            .values()
+            .map(|a| u64::from(a.pkts_padding))
+            .sum();
+        if padding > 0 {
+            meta.set_padding(padding, rdr_data.padding.unwrap_or_default());
+        }
+        if rdr_data.short_name == "VIIRS-SCIENCE-RDR" {
+            let header = StaticHeader::from_bytes(&data)?;
+            let ap_storage = data
+                .get(header.ap_storage_offset as usize..)
+                .unwrap_or_default();
+            meta.scans = Some(viirs::count_scans(ap_storage));
+        }
         Ok(Self {
             meta,
             product_id: product.product_id.to_string(),
@@ -124,6 +238,131 @@ impl Rdr {
     }
 }
 
+/// Verify the Common RDR static header offsets in `data` are consistent with each other and
+/// with the length of `data`.
+///
+/// # Errors
+/// [RdrError::Invalid] describing the first inconsistency found.
+pub fn validate_common_rdr(data: &[u8]) -> Result<()> {
+    let header = StaticHeader::from_bytes(data)?;
+    let invalid = |msg: String| Err(Error::RdrError(RdrError::Invalid(msg)));
+    let len = data.len();
+    let apid_list = header.apid_list_offset as usize;
+    let trackers = header.pkt_tracker_offset as usize;
+    let storage = header.ap_storage_offset as usize;
+
+    if apid_list != StaticHeader::LEN {
+        return invalid(format!(
+            "apid list offset {apid_list} != static header length {}",
+            StaticHeader::LEN
+        ));
+    }
+    if !(apid_list <= trackers && trackers <= storage && storage <= len) {
+        return invalid(format!(
+            "offsets out of order or beyond data length {len}: apid_list={apid_list} \
+             packet_tracker={trackers} ap_storage={storage}"
+        ));
+    }
+    if (trackers - apid_list) % ApidInfo::LEN != 0 {
+        return invalid(format!(
+            "apid list length {} is not a multiple of {}",
+            trackers - apid_list,
+            ApidInfo::LEN
+        ));
+    }
+    if (storage - trackers) % PacketTracker::LEN != 0 {
+        return invalid(format!(
+            "packet tracker length {} is not a multiple of {}",
+            storage - trackers,
+            PacketTracker::LEN
+        ));
+    }
+    let next_pkt = header.next_pkt_position as usize;
+    if storage + next_pkt > len {
+        return invalid(format!(
+            "next packet position {next_pkt} is beyond ap storage length {}",
+            len - storage
+        ));
+    }
+    Ok(())
+}
+
+/// How [RdrData] handles duplicate packets, i.e., packets with the same APID, sequence id,
+/// and time as a packet already added, such as from replayed downlinks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Keep all packets, including duplicates
+    Keep,
+    /// Drop duplicates, keeping the first packet added
+    #[default]
+    KeepFirst,
+    /// Replace the previously added packet with the duplicate. A [crate::Collector] drops
+    /// duplicates for granules it has already completed.
+    KeepLast,
+}
+
+/// Order of packets in the Common RDR application packet storage.
+///
+/// Packet trackers for each APID are ordered the same as their packets in the storage and
+/// always point to their packet regardless of the order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StorageOrder {
+    /// The order packets were added
+    #[default]
+    Arrival,
+    /// Packet time order, with packets having the same time in the order they were added
+    Time,
+    /// APID order, then packet time order within each APID
+    ApidThenTime,
+}
+
+/// Reorder `live` packets and their `trackers` according to `order`, returning the reordered
+/// packets and trackers with offsets updated for the new storage layout.
+///
+/// Tracker offsets must be the offsets of their packets when `live` is written in order.
+fn reorder_storage(
+    order: StorageOrder,
+    live: &[StoredPacket],
+    trackers: &HashMap<Apid, Vec<PacketTracker>>,
+) -> (Vec<StoredPacket>, HashMap<Apid, Vec<PacketTracker>>) {
+    let mut old_offsets = Vec::with_capacity(live.len());
+    let mut offset: i32 = 0;
+    for pkt in live {
+        old_offsets.push(offset);
+        offset += i32::try_from(pkt.size).expect("packet size fits i32");
+    }
+
+    // Stable sorts keep arrival order for packets that compare equal
+    let mut indexes: Vec<usize> = (0..live.len()).collect();
+    match order {
+        StorageOrder::Arrival => {}
+        StorageOrder::Time => indexes.sort_by_key(|idx| live[*idx].time),
+        StorageOrder::ApidThenTime => indexes.sort_by_key(|idx| (live[*idx].apid, live[*idx].time)),
+    }
+
+    let mut offsets: HashMap<i32, i32> = HashMap::default();
+    let mut reordered = Vec::with_capacity(live.len());
+    let mut offset: i32 = 0;
+    for idx in indexes {
+        offsets.insert(old_offsets[idx], offset);
+        offset += i32::try_from(live[idx].size).expect("packet size fits i32");
+        reordered.push(live[idx].clone());
+    }
+
+    let mut trackers = trackers.clone();
+    for apid_trackers in trackers.values_mut() {
+        for tracker in apid_trackers.iter_mut() {
+            if let Some(offset) = offsets.get(&tracker.offset) {
+                tracker.offset = *offset;
+            }
+        }
+        if order != StorageOrder::Arrival {
+            apid_trackers.sort_by_key(|t| t.offset);
+        }
+    }
+    (reordered, trackers)
+}
+
 /// Used to collect packets for a single Common RDR.
 #[derive(Debug, Clone)]
 pub struct RdrData {
@@ -131,12 +370,42 @@ pub struct RdrData {
     pub header: StaticHeader,
     pub apid_list: HashMap<Apid, ApidInfo>,
     pub trackers: HashMap<Apid, Vec<PacketTracker>>,
-    pub ap_storage: VecDeque<(u64, Packet)>,
+    pub(crate) ap_storage: PacketStorage,
     pub ap_storage_offset: i32,
+
+    duplicates: DuplicatePolicy,
+    storage_order: StorageOrder,
+    /// Maps apid, sequence id, and time of packets added to the index of their tracker and
+    /// their index in the storage.
+    seen: HashMap<(Apid, u16, u64), (usize, usize)>,
+    /// Storage indexes of packets replaced by a duplicate
+    superseded: HashSet<usize>,
+    /// Time of the latest packet added for each apid
+    latest: HashMap<Apid, u64>,
+    /// Number of duplicate packets added
+    num_duplicates: usize,
+    /// Minimum number of packet trackers for each apid. See [ProductSpec::reserve_trackers].
+    reserved: HashMap<Apid, u32>,
+    /// Pre and post-roll window of padding packets. See [ProductSpec::padding].
+    padding: Option<GranulePadding>,
+    /// Satellite and product the data is collected for, used for the granule metadata
+    sat: SatSpec,
+    product: ProductSpec,
 }
 
 impl RdrData {
     pub fn new(sat: &SatSpec, product: &ProductSpec, time: &Time) -> Self {
+        Self::with_storage(sat, product, time, PacketStorage::memory())
+    }
+
+    /// Create using the provided [PacketStorage] for the application packets, e.g., to spill
+    /// packet data to disk.
+    pub(crate) fn with_storage(
+        sat: &SatSpec,
+        product: &ProductSpec,
+        time: &Time,
+        storage: PacketStorage,
+    ) -> Self {
         Self {
             short_name: product.short_name.to_string(),
             apid_list: product
@@ -146,100 +415,251 @@ impl RdrData {
                 .collect(),
             header: StaticHeader::new(time, sat.short_name.to_string(), product),
             trackers: HashMap::default(),
-            ap_storage: VecDeque::default(),
+            ap_storage: storage,
             ap_storage_offset: 0,
+            duplicates: DuplicatePolicy::default(),
+            storage_order: StorageOrder::default(),
+            seen: HashMap::default(),
+            superseded: HashSet::default(),
+            latest: HashMap::default(),
+            num_duplicates: 0,
+            reserved: if product.reserve_trackers {
+                product
+                    .apids
+                    .iter()
+                    .map(|a| (a.num, u32::try_from(a.max_expected).unwrap_or(u32::MAX)))
+                    .collect()
+            } else {
+                HashMap::default()
+            },
+            padding: product.padding,
+            sat: sat.clone(),
+            product: product.clone(),
         }
     }
 
+    /// Set the policy for handling duplicate packets. See [DuplicatePolicy].
+    #[must_use]
+    pub fn with_duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Set the order of packets in the application packet storage. See [StorageOrder].
+    #[must_use]
+    pub fn with_storage_order(mut self, order: StorageOrder) -> Self {
+        self.storage_order = order;
+        self
+    }
+
+    /// Number of duplicate packets added, i.e., dropped or replacing an earlier packet
+    /// according to the [DuplicatePolicy]. Always 0 for [DuplicatePolicy::Keep].
+    #[must_use]
+    pub fn num_duplicates(&self) -> usize {
+        self.num_duplicates
+    }
+
+    /// The apid, sequence id, and time of each packet added, used to identify duplicates.
+    /// Empty for [DuplicatePolicy::Keep].
+    pub(crate) fn packet_keys(&self) -> impl Iterator<Item = &(Apid, u16, u64)> {
+        self.seen.keys()
+    }
+
     /// Add a packet.
     ///
+    /// Duplicate packets are handled according to the configured [DuplicatePolicy] and are
+    /// counted in [ApidInfo::pkts_duplicate]. Packets older than the latest packet for their
+    /// apid are added, but counted in [ApidInfo::pkts_out_of_order].
+    ///
     /// # Errors
     /// On packet decode errors, typically, numerical overflow of expected header value types.
     pub fn add_packet(&mut self, pkt_time: &Time, pkt: Packet) -> Result<()> {
+        let apid = pkt.header.apid;
+        let iet = pkt_time.iet();
         let info = self
             .apid_list
-            .get_mut(&pkt.header.apid)
+            .get_mut(&apid)
             .ok_or(RdrError::InvalidPacket(pkt.header))?;
-        info.pkts_reserved += 1;
-        info.pkts_received += 1;
 
         let pkt_size =
             i32::try_from(pkt.data.len()).map_err(|_| RdrError::InvalidPacket(pkt.header))?;
-        let trackers = self.trackers.entry(pkt.header.apid).or_default();
+        let key = (apid, pkt.header.sequence_id, iet);
+        let existing = match self.duplicates {
+            DuplicatePolicy::Keep => None,
+            _ => self.seen.get(&key).copied(),
+        };
+        if let Some((tracker_idx, storage_idx)) = existing {
+            info.pkts_duplicate += 1;
+            self.num_duplicates += 1;
+            trace!("duplicate packet apid={apid} seq={} time={iet}", key.1);
+            if self.duplicates == DuplicatePolicy::KeepLast {
+                let tracker =
+                    &mut self.trackers.get_mut(&apid).expect("tracker for seen")[tracker_idx];
+                tracker.size = pkt_size;
+                tracker.offset = self.ap_storage_offset;
+                self.superseded.insert(storage_idx);
+                self.seen.insert(key, (tracker_idx, self.ap_storage.len()));
+                self.ap_storage.push(IetMicros(iet), &pkt)?;
+                self.ap_storage_offset += pkt_size;
+            }
+            return Ok(());
+        }
+
+        let latest = self.latest.entry(apid).or_insert(iet);
+        if iet < *latest {
+            info.pkts_out_of_order += 1;
+        } else {
+            *latest = iet;
+        }
+        info.pkts_reserved += 1;
+        info.pkts_received += 1;
+
+        let trackers = self.trackers.entry(apid).or_default();
         trackers.push(PacketTracker {
-            obs_time: i64::try_from(pkt_time.iet())
-                .map_err(|_| RdrError::InvalidTime(pkt_time.iet()))?,
+            obs_time: i64::try_from(iet).map_err(|_| RdrError::InvalidTime(iet))?,
             sequence_number: i32::from(pkt.header.sequence_id),
             size: pkt_size,
             offset: self.ap_storage_offset,
             // FIXME: How to figure out
             fill_percent: 0,
         });
+        if self.duplicates != DuplicatePolicy::Keep {
+            self.seen
+                .insert(key, (trackers.len() - 1, self.ap_storage.len()));
+        }
 
-        self.ap_storage.push_back((pkt_time.iet(), pkt));
+        self.ap_storage.push(IetMicros(iet), &pkt)?;
         self.ap_storage_offset += pkt_size;
 
         Ok(())
     }
 
+    /// Add a packet from the pre or post-roll window outside the granule time range, counted
+    /// in [ApidInfo::pkts_padding]. See [ProductSpec::padding].
+    ///
+    /// # Errors
+    /// See [RdrData::add_packet].
+    pub fn add_padding_packet(&mut self, pkt_time: &Time, pkt: Packet) -> Result<()> {
+        let apid = pkt.header.apid;
+        let received = self
+            .apid_list
+            .get(&apid)
+            .map_or(0, |info| info.pkts_received);
+        self.add_packet(pkt_time, pkt)?;
+        if let Some(info) = self.apid_list.get_mut(&apid) {
+            // Not counted if dropped as a duplicate
+            if info.pkts_received > received {
+                info.pkts_padding += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of packet trackers written for `apid`; the packets received, or more if trackers
+    /// are reserved for the product. See [ProductSpec::reserve_trackers].
+    fn pkts_reserved(&self, apid: Apid) -> u32 {
+        let collected = self
+            .apid_list
+            .get(&apid)
+            .map_or(0, |info| info.pkts_reserved);
+        collected.max(self.reserved.get(&apid).copied().unwrap_or(0))
+    }
+
     /// Create bytes for a Common RDR from the current state.
     ///
+    /// Trackers are written in apid order, followed by fill trackers for any reserved but
+    /// unused entries.
+    ///
     /// # Panics
     /// If structure counts overflow rdr structure types
     pub fn compile(&self) -> Result<Rdr> {
         let mut apids = self.apid_list.keys().collect::<Vec<_>>();
         apids.sort_unstable();
-        let mut apid_list = self.apid_list.clone();
-
-        // Compute and set the packet_tracker_offset based on the APID-first-seen order.
-        let mut tracker_offset: u32 = 0;
-        for apid in &apids {
-            let info = apid_list
-                .get_mut(apid)
-                .expect("apid_list must be init'd in new");
-            info.pkt_tracker_start_idx = tracker_offset;
-            tracker_offset += info.pkts_received;
-        }
 
         // Fill out computed header fields
         let mut header = self.header.clone();
         header.pkt_tracker_offset = header.apid_list_offset
-            + u32::try_from(apid_list.len() * ApidInfo::LEN).map_err(RdrError::IntError)?;
-        let tracker_count: u32 = self
-            .trackers
-            .values()
-            .map(|v| u32::try_from(v.len()).expect("number of trackers does not fit in u32"))
-            .sum();
+            + u32::try_from(self.apid_list.len() * ApidInfo::LEN).map_err(RdrError::IntError)?;
+        let tracker_count: u32 = apids.iter().map(|apid| self.pkts_reserved(**apid)).sum();
         header.ap_storage_offset =
             header.pkt_tracker_offset + tracker_count * PacketTracker::LEN as u32;
-        header.next_pkt_position = self.ap_storage_offset as u32;
+
+        // Packets replaced by duplicates are not written so the tracker offsets must be
+        // adjusted to account for the removed packets.
+        let mut remapped: Option<HashMap<Apid, Vec<PacketTracker>>> = None;
+        let live: Cow<[StoredPacket]> = if self.superseded.is_empty() {
+            header.next_pkt_position = self.ap_storage_offset as u32;
+            Cow::Borrowed(self.ap_storage.packets())
+        } else {
+            let mut offsets: HashMap<i32, i32> = HashMap::default();
+            let mut live = Vec::default();
+            let mut offset: i32 = 0;
+            for (idx, pkt) in self.ap_storage.packets().iter().enumerate() {
+                if self.superseded.contains(&idx) {
+                    continue;
+                }
+                offsets.insert(
+                    i32::try_from(pkt.offset).expect("storage offset fits i32"),
+                    offset,
+                );
+                offset += i32::try_from(pkt.size).expect("packet size fits i32");
+                live.push(pkt.clone());
+            }
+            let mut trackers = self.trackers.clone();
+            for tracker in trackers.values_mut().flatten() {
+                tracker.offset = offsets[&tracker.offset];
+            }
+            remapped = Some(trackers);
+            header.next_pkt_position = offset as u32;
+            Cow::Owned(live)
+        };
+        let trackers = remapped.as_ref().unwrap_or(&self.trackers);
+        let reordered = match self.storage_order {
+            StorageOrder::Arrival => None,
+            order => Some(reorder_storage(order, &live, trackers)),
+        };
+        let (live, trackers) = match &reordered {
+            Some((live, trackers)) => (live.as_slice(), trackers),
+            None => (&*live, trackers),
+        };
+
+        // Size for the whole granule up front; packet data can be hundreds of MB for VIIRS
+        let num_bytes: usize = live.iter().map(|p| p.size).sum();
+        let mut data = Vec::with_capacity(header.ap_storage_offset as usize + num_bytes);
 
         // start by writing static header
-        let mut data = Vec::from(header.as_bytes());
+        data.extend_from_slice(&header.as_bytes());
 
-        // Write apid list in the order in which apids were first seen.
+        // Write apid list in apid order, computing each packet tracker start index as we go.
+        let mut tracker_offset: u32 = 0;
         for apid in &apids {
-            let info = apid_list
+            let mut info = self
+                .apid_list
                 .get(apid)
-                .expect("apid_list must be init'd in new");
-            data.extend_from_slice(&info.as_bytes());
+                .expect("apid_list must be init'd in new")
+                .clone();
+            info.pkts_reserved = self.pkts_reserved(**apid);
+            data.extend_from_slice(&info.as_bytes_with_start_idx(tracker_offset));
+            tracker_offset += info.pkts_reserved;
         }
 
-        // Write trackers. This must be done in apid list order because that's how we set the
-        // info.pkt_tracker_start_idx above.
+        // Write trackers. This must be done in apid list order because that's how we computed
+        // the pkt_tracker_start_idx values above.
         for apid in &apids {
-            if let Some(trackers) = self.trackers.get(apid) {
-                for tracker in trackers {
-                    data.extend_from_slice(&tracker.as_bytes());
-                }
+            let trackers = trackers.get(apid).map_or(&[][..], Vec::as_slice);
+            for tracker in trackers {
+                data.extend_from_slice(&tracker.as_bytes());
+            }
+            let num_fill = self.pkts_reserved(**apid) as usize - trackers.len();
+            for _ in 0..num_fill {
+                data.extend_from_slice(&PacketTracker::FILL.as_bytes());
             }
         }
 
-        // Finally, packets get written in the order they were received. The packet trackers have
-        // their offset based on writing packets in this order.
-        for (_, pkt) in &self.ap_storage {
-            data.extend_from_slice(&pkt.data);
-        }
+        // Finally, packets get written in storage order, by default the order they were
+        // received. The packet trackers have their offset based on writing packets in this
+        // order.
+        self.ap_storage.read_into(live, &mut data)?;
 
         Rdr::from_data(self, data)
     }
@@ -259,22 +679,93 @@ impl Display for Rdr {
 
 macro_rules! attr_string {
     ($obj:expr, $name:expr) => {
-        $obj.attr($name)?
-            .read_2d::<FixedAscii<MAX_STR_LEN>>()
-            .map_err(|e| Error::Hdf5Other(format!("reading string attr {}: {}", $name, e)))?[[0, 0]]
-        .to_string()
+        read_attr_string($obj, $name)?
     };
 }
 
 macro_rules! attr_u64 {
     ($obj:expr, $name:expr) => {
-        $obj.attr($name)?
-            .read_2d::<u64>()
-            .map_err(|e| Error::Hdf5Other(format!("reading u64 attr {}: {}", $name, e)))?[[0, 0]]
+        read_attr_u64($obj, $name)?
+    };
+}
+
+/// Get attribute `name` from `loc`.
+///
+/// Some legacy IDPS files use different casing for attribute names so if there is no exact
+/// match a case-insensitive match is used.
+fn find_attr(loc: &Location, name: &str) -> Result<Attribute> {
+    if let Ok(attr) = loc.attr(name) {
+        return Ok(attr);
+    }
+    let names = loc.attr_names()?;
+    let Some(actual) = names.iter().find(|n| n.eq_ignore_ascii_case(name)) else {
+        return Err(Error::Hdf5Other(format!("attr {name} not found")));
+    };
+    Ok(loc.attr(actual)?)
+}
+
+/// Read the first value of the string attribute `name` from `loc`.
+///
+/// Attributes are normally fixed length strings with shape [1, 1], but any shape and
+/// variable length strings, as found in some legacy IDPS files, are also accepted.
+///
+/// # Errors
+/// If the attribute does not exist, is empty, or is not a string.
+pub fn read_attr_string(loc: &Location, name: &str) -> Result<String> {
+    let attr = find_attr(loc, name)?;
+    let value = match attr.read_raw::<FixedAscii<MAX_STR_LEN>>() {
+        Ok(values) => values.first().map(ToString::to_string),
+        Err(_) => attr
+            .read_raw::<VarLenAscii>()
+            .map_err(|e| Error::Hdf5Other(format!("reading string attr {name}: {e}")))?
+            .first()
+            .map(ToString::to_string),
+    };
+    value.ok_or_else(|| Error::Hdf5Other(format!("string attr {name} is empty")))
+}
+
+/// Read the first value of the numeric attribute `name` from `loc` as a u64.
+///
+/// Other integer types are converted, and floating point values, as found in some legacy
+/// IDPS files, are truncated.
+///
+/// # Errors
+/// If the attribute does not exist, is empty, or is not numeric.
+pub(crate) fn read_attr_u64(loc: &Location, name: &str) -> Result<u64> {
+    let attr = find_attr(loc, name)?;
+    let value = match attr.read_raw::<u64>() {
+        Ok(values) => values.first().copied(),
+        Err(_) => attr
+            .read_raw::<f64>()
+            .map_err(|e| Error::Hdf5Other(format!("reading u64 attr {name}: {e}")))?
+            .first()
+            .map(|v| *v as u64),
+    };
+    value.ok_or_else(|| Error::Hdf5Other(format!("u64 attr {name} is empty")))
+}
+
+/// Read the first value of the floating point attribute `name` from `loc` as a f32.
+///
+/// Legacy IDPS files may use float64 rather than float32.
+///
+/// # Errors
+/// If the attribute does not exist, is empty, or is not numeric.
+pub(crate) fn read_attr_f32(loc: &Location, name: &str) -> Result<f32> {
+    let attr = find_attr(loc, name)?;
+    let value = match attr.read_raw::<f32>() {
+        Ok(values) => values.first().copied(),
+        Err(_) => attr
+            .read_raw::<f64>()
+            .map_err(|e| Error::Hdf5Other(format!("reading f32 attr {name}: {e}")))?
+            .first()
+            .map(|v| *v as f32),
     };
+    value.ok_or_else(|| Error::Hdf5Other(format!("f32 attr {name} is empty")))
 }
 
-/// Create an IDPS style RDR filename
+/// Create an IDPS style RDR filename.
+///
+/// The orbit number field is always `b00000`; use [filename_with_orbit] to set it.
 pub fn filename(
     satid: &str,
     origin: &str,
@@ -285,7 +776,6 @@ pub fn filename(
     product_ids: &[String],
 ) -> String {
     format!(
-        // FIXME: hard-coded orbit number
         "{}_{}_d{}_t{}_e{}_b00000_c{}_{}u_{}.h5",
         product_ids.join("-"),
         satid,
@@ -298,6 +788,79 @@ pub fn filename(
     )
 }
 
+/// Replace the orbit number field, e.g., `b00000`, of an IDPS style RDR filename created by
+/// [filename] with `orbit`. Filenames without an orbit number field are returned as is.
+#[must_use]
+pub fn filename_with_orbit(fname: &str, orbit: u64) -> String {
+    fname
+        .split('_')
+        .map(|field| match field.strip_prefix('b') {
+            Some(num) if num.len() == 5 && num.chars().all(|c| c.is_ascii_digit()) => {
+                format!("b{orbit:05}")
+            }
+            _ => field.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Expand an output directory layout `template` for a file containing the granules of
+/// `short_name`, from product `product_id`, starting at `start`.
+///
+/// Supported placeholders are `{satid}`, `{short_name}`, `{product_id}`, `{yyyy}`, `{mm}`,
+/// `{dd}`, `{yyyymmdd}`, and `{doy}`, with dates in UTC, e.g., `{satid}/{short_name}/{yyyymmdd}`.
+///
+/// # Errors
+/// If `template` contains an unknown or unterminated placeholder, or expands to a path that is
+/// not relative to the output directory, e.g., is absolute or contains `..`.
+pub fn output_subdir(
+    template: &str,
+    satid: &str,
+    short_name: &str,
+    product_id: &str,
+    start: &Time,
+) -> Result<PathBuf> {
+    let invalid = |msg: String| Error::RdrError(RdrError::Invalid(msg));
+    let mut expanded = String::default();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        expanded.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('}') else {
+            return Err(invalid(format!("unterminated placeholder in {template:?}")));
+        };
+        let name = &rest[open + 1..open + close];
+        let value = match name {
+            "satid" => satid.to_string(),
+            "short_name" => short_name.to_string(),
+            "product_id" => product_id.to_string(),
+            "yyyy" => start.format_utc("%Y"),
+            "mm" => start.format_utc("%m"),
+            "dd" => start.format_utc("%d"),
+            "yyyymmdd" => start.format_utc("%Y%m%d"),
+            "doy" => start.format_utc("%j"),
+            _ => {
+                return Err(invalid(format!(
+                    "unknown placeholder {{{name}}} in {template:?}"
+                )))
+            }
+        };
+        expanded.push_str(&value);
+        rest = &rest[open + close + 1..];
+    }
+    expanded.push_str(rest);
+
+    let path = PathBuf::from(expanded);
+    if !path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(invalid(format!(
+            "output layout {template:?} must be relative to the output directory"
+        )));
+    }
+    Ok(path)
+}
+
 pub(crate) fn attr_date(dt: &Time) -> String {
     dt.format_utc("%Y%m%d")
 }
@@ -310,7 +873,9 @@ pub(crate) fn attr_time(dt: &Time) -> String {
 /// Aggregation metadata for the `/Data_Products/<short_name>/<shortname>_Aggr` dataset.
 #[derive(Debug, Clone, Serialize)]
 pub struct AggrMeta {
-    pub begin_orbit_nubmer: u32,
+    /// Lowest granule `N_Beginning_Orbit_Number`
+    pub begin_orbit_number: u32,
+    /// Highest granule `N_Beginning_Orbit_Number`
     pub end_orbit_number: u32,
     pub num_granules: u32,
     pub begin_date: String,
@@ -327,52 +892,86 @@ impl AggrMeta {
     /// # Panics
     /// If `rdrs` is empty
     pub fn from_rdrs(rdrs: &Vec<Rdr>) -> Self {
-        assert!(!rdrs.is_empty());
-        let mut start_rdr: Option<&Rdr> = None;
-        let mut end_rdr: Option<&Rdr> = None;
+        let granules: Vec<&GranuleMeta> = rdrs.iter().map(|r| &r.meta).collect();
+        Self::from_granules(&granules)
+    }
+
+    /// Create meta for a collection without any granules.
+    #[must_use]
+    pub fn empty() -> Self {
+        Self {
+            begin_orbit_number: 0,
+            end_orbit_number: 0,
+            num_granules: 0,
+            begin_date: String::default(),
+            begin_time: String::default(),
+            begin_granule_id: String::default(),
+            end_date: String::default(),
+            end_time: String::default(),
+            end_granule_id: String::default(),
+        }
+    }
+
+    /// Create meta from the provided granule metadata.
+    ///
+    /// # Panics
+    /// If `granules` is empty
+    pub fn from_granules(granules: &[&GranuleMeta]) -> Self {
+        assert!(!granules.is_empty());
+        let mut start: Option<&GranuleMeta> = None;
+        let mut end: Option<&GranuleMeta> = None;
         let mut count: u32 = 0;
-        for rdr in rdrs {
-            start_rdr = Some(std::cmp::min_by(start_rdr.unwrap_or(rdr), rdr, |a, b| {
-                a.meta.begin_time_iet.cmp(&b.meta.begin_time_iet)
+        let mut orbits: Option<(u64, u64)> = None;
+        for &gran in granules {
+            let num = gran.orbit_number;
+            orbits = Some(orbits.map_or((num, num), |(lo, hi)| (lo.min(num), hi.max(num))));
+            start = Some(std::cmp::min_by(start.unwrap_or(gran), gran, |a, b| {
+                a.begin_time_iet.cmp(&b.begin_time_iet)
             }));
-            end_rdr = Some(std::cmp::max_by(end_rdr.unwrap_or(rdr), rdr, |a, b| {
-                a.meta.end_time_iet.cmp(&b.meta.end_time_iet)
+            end = Some(std::cmp::max_by(end.unwrap_or(gran), gran, |a, b| {
+                a.end_time_iet.cmp(&b.end_time_iet)
             }));
             count += 1;
         }
 
-        let start_rdr = start_rdr.expect("always set if > 1 rdrs");
-        let end_rdr = end_rdr.expect("always set if > 1 rdrs");
+        let start = start.expect("always set if > 1 granules");
+        let end = end.expect("always set if > 1 granules");
+        let (begin_orbit, end_orbit) = orbits.expect("always set if > 1 granules");
+        let to_u32 = |num: u64| u32::try_from(num).unwrap_or(u32::MAX);
         Self {
-            begin_orbit_nubmer: 1,
-            end_orbit_number: 1,
+            begin_orbit_number: to_u32(begin_orbit),
+            end_orbit_number: to_u32(end_orbit),
             num_granules: count,
-            begin_date: start_rdr.meta.begin_date.clone(),
-            begin_time: start_rdr.meta.begin_time.clone(),
-            begin_granule_id: start_rdr.meta.id.to_string(),
-            end_date: end_rdr.meta.end_date.clone(),
-            end_time: end_rdr.meta.end_time.clone(),
-            end_granule_id: end_rdr.meta.id.to_string(),
+            begin_date: start.begin_date.clone(),
+            begin_time: start.begin_time.clone(),
+            begin_granule_id: start.id.to_string(),
+            end_date: end.end_date.clone(),
+            end_time: end.end_time.clone(),
+            end_granule_id: end.id.to_string(),
         }
     }
 }
 
 /// Metadata associated with a particular granule dataset from RDR path
 /// `/Data_Products/<shortname>/<shortname>_Gran_<idx>`.
-#[derive(Debug, Clone, Serialize)]
+///
+/// `begin` and `end` are not serialized and are set from `begin_time_iet` and `end_time_iet`
+/// when deserialized.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub struct GranuleMeta {
     pub instrument: String,
     pub collection: String,
-    #[serde(skip)]
+    #[serde(skip, default = "Time::now")]
     pub begin: Time,
     pub begin_date: String,
     pub begin_time: String,
-    pub begin_time_iet: u64,
-    #[serde(skip)]
+    pub begin_time_iet: IetMicros,
+    #[serde(skip, default = "Time::now")]
     pub end: Time,
     pub end_date: String,
     pub end_time: String,
-    pub end_time_iet: u64,
+    pub end_time_iet: IetMicros,
     pub creation_date: String,
     pub creation_time: String,
     pub orbit_number: u64,
@@ -387,6 +986,49 @@ pub struct GranuleMeta {
     pub percent_missing: f32,
     pub reference_id: String,
     pub software_version: String,
+    /// Scan counts for VIIRS science granules. Not part of the RDR granule attributes so is
+    /// only available for newly created granules.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scans: Option<ScanCounts>,
+    /// Details of the granule's `RawApplicationPackets` dataset. Only available when read
+    /// using [Meta::from_file_with] with a [RawDatasetDetail] other than `None`. Not
+    /// deserialized.
+    #[serde(skip_serializing_if = "Option::is_none", skip_deserializing)]
+    pub raw_dataset: Option<RawDatasetInfo>,
+    /// Non-standard granule dataset attributes, e.g., an ascending/descending or day/night
+    /// flag set by a [crate::GranuleAnnotator]. Written as fixed length ASCII strings.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra_attrs: BTreeMap<String, String>,
+    /// String attributes of the granule's `RawApplicationPackets` dataset, which some
+    /// producers use for extra provenance. Preserved when the granule is written; attributes
+    /// of other types are dropped with a warning when read.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub raw_attrs: BTreeMap<String, String>,
+    /// Number of packets from the pre or post-roll window outside the granule time range, if
+    /// any. See [ProductSpec::padding]. Recorded in the
+    /// [GranuleMeta::PADDING_PACKETS_ATTR] extra attribute.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub padding_packets: Option<u64>,
+}
+
+impl Serialize for GranuleMeta {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        GranuleMeta::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for GranuleMeta {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let mut meta = GranuleMeta::deserialize(deserializer)?;
+        meta.begin = Time::from(meta.begin_time_iet);
+        meta.end = Time::from(meta.end_time_iet);
+        Ok(meta)
+    }
 }
 
 impl GranuleMeta {
@@ -395,67 +1037,206 @@ impl GranuleMeta {
     const DEFAULT_LEOA_FLAG: &str = "Off";
     const DEFAULT_MODE: &str = "dev";
 
+    /// `N_Software_Version` of granules written by this crate.
+    pub const SOFTWARE_VERSION: &str = concat!("rdr", env!("CARGO_PKG_VERSION"));
+    /// Extra attribute recording the `N_Software_Version` of the software that originally
+    /// produced a re-stamped granule. See [GranuleMeta::restamp_software].
+    pub const SOURCE_SOFTWARE_VERSION_ATTR: &str = "N_Source_Software_Version";
+    /// Extra attribute recording [GranuleMeta::padding_packets]. See [GranuleMeta::set_padding].
+    pub const PADDING_PACKETS_ATTR: &str = "N_Padding_Packets";
+    /// Extra attribute recording the pre-roll window, in microseconds, of padding packets.
+    pub const PADDING_PRE_ATTR: &str = "N_Padding_Pre";
+    /// Extra attribute recording the post-roll window, in microseconds, of padding packets.
+    pub const PADDING_POST_ATTR: &str = "N_Padding_Post";
+
+    /// Total number of packets in the granule, from the per-APID packet counts.
+    #[must_use]
+    pub fn num_packets(&self) -> u64 {
+        self.packet_type_count.iter().map(|c| u64::from(*c)).sum()
+    }
+
     pub fn new(time: Time, sat: &SatSpec, product: &ProductSpec) -> Result<Self> {
         let created = Time::now();
         let begin = &time;
-        let end = &Time::from_iet(begin.iet() + product.gran_len);
-        let id = granule_id(&sat.short_name, sat.base_time, begin.iet())?;
+        let end = &Time::from(begin.iet_micros() + product.gran_len);
+        let id = sat.granule_id(begin.iet_micros())?;
 
         Ok(Self {
-            instrument: product.sensor.to_string(),
+            instrument: product.instrument().to_string(),
             collection: product.short_name.to_string(),
             begin: begin.clone(),
             begin_date: attr_date(begin),
             begin_time: attr_time(begin),
-            begin_time_iet: begin.iet(),
+            begin_time_iet: begin.iet_micros(),
             end: end.clone(),
             end_date: attr_date(end),
             end_time: attr_time(end),
-            end_time_iet: end.iet(),
+            end_time_iet: end.iet_micros(),
             creation_date: attr_date(&created),
             creation_time: attr_time(&created),
-            orbit_number: 1,
+            // Orbit numbers are unknown without configured orbits
+            orbit_number: sat.orbits.map_or(1, |o| o.orbit_number(begin.iet_micros())),
             id: id.to_string(),
             status: Self::DEFAULT_STATUS.to_string(),
             version: Self::DEFAULT_VERSION.to_string(),
             idps_mode: Self::DEFAULT_MODE.to_string(),
-            jpss_doc: "".to_string(),
+            jpss_doc: product.document_ref.clone().unwrap_or_default(),
             leoa_flag: Self::DEFAULT_LEOA_FLAG.to_string(),
             packet_type: Vec::default(),
             packet_type_count: Vec::default(),
             percent_missing: 0.0,
             reference_id: format!("{}:{}:{}", product.short_name, id, Self::DEFAULT_VERSION),
-            software_version: concat!("rdr", env!("CARGO_PKG_VERSION")).to_string(),
+            software_version: Self::SOFTWARE_VERSION.to_string(),
+            scans: None,
+            raw_dataset: None,
+            extra_attrs: BTreeMap::default(),
+            raw_attrs: BTreeMap::default(),
+            padding_packets: None,
         })
     }
 
+    /// True if this packed granule, e.g., SPACECRAFT-DIARY, is packed with the `primary`
+    /// granule; its start is within its granule length of the primary granule start and less
+    /// than the primary granule end.
+    #[must_use]
+    pub fn is_packed_with(&self, primary: &GranuleMeta) -> bool {
+        is_packed_overlap(
+            primary.begin_time_iet,
+            primary.end_time_iet,
+            self.begin_time_iet,
+            GranLen::from_micros(self.end_time_iet - self.begin_time_iet),
+        )
+    }
+
+    /// Set the `N_Granule_Version`, e.g., A2, also updating the reference id, which includes
+    /// the version.
+    ///
+    /// # Errors
+    /// If `version` is not a valid granule version. See [next_granule_version].
+    pub fn set_version(&mut self, version: &str) -> Result<()> {
+        parse_granule_version(version)?;
+        self.version = version.to_string();
+        self.reference_id = format!("{}:{}:{}", self.collection, self.id, version);
+        Ok(())
+    }
+
+    /// Increment the granule version to the next version, e.g., A1 to A2, when re-generating
+    /// a granule. See [next_granule_version].
+    ///
+    /// # Errors
+    /// If the current version is invalid or the last possible version.
+    pub fn bump_version(&mut self) -> Result<()> {
+        let version = next_granule_version(&self.version)?;
+        self.set_version(&version)
+    }
+
+    /// Set the `N_Software_Version` to [GranuleMeta::SOFTWARE_VERSION], recording the
+    /// original version in the [GranuleMeta::SOURCE_SOFTWARE_VERSION_ATTR] extra attribute so
+    /// lineage is not lost when a granule is re-written, e.g., by aggregation.
+    ///
+    /// A source version already recorded by an earlier re-stamp is kept, so it always refers
+    /// to the software that produced the granule data.
+    pub fn restamp_software(&mut self) {
+        let source = std::mem::replace(&mut self.software_version, Self::SOFTWARE_VERSION.into());
+        self.extra_attrs
+            .entry(Self::SOURCE_SOFTWARE_VERSION_ATTR.to_string())
+            .or_insert(source);
+    }
+
+    /// Set the number of padding `packets` from the pre and post-roll `window`, recording them
+    /// in extra attributes so they are available when the granule is read back, e.g., so out
+    /// of window checks do not report padding packets.
+    pub fn set_padding(&mut self, packets: u64, window: GranulePadding) {
+        self.padding_packets = Some(packets);
+        for (name, value) in [
+            (Self::PADDING_PACKETS_ATTR, packets),
+            (Self::PADDING_PRE_ATTR, window.pre),
+            (Self::PADDING_POST_ATTR, window.post),
+        ] {
+            self.extra_attrs.insert(name.to_string(), value.to_string());
+        }
+    }
+
+    /// The pre and post-roll window recorded for the granule's padding packets, if any. See
+    /// [GranuleMeta::set_padding].
+    #[must_use]
+    pub fn padding_window(&self) -> Option<GranulePadding> {
+        let value = |name: &str| self.extra_attrs.get(name).and_then(|v| v.parse().ok());
+        Some(GranulePadding {
+            pre: value(Self::PADDING_PRE_ATTR)?,
+            post: value(Self::PADDING_POST_ATTR)?,
+        })
+    }
+
+    /// Software that produced the granule data, i.e., the
+    /// [GranuleMeta::SOURCE_SOFTWARE_VERSION_ATTR] if the granule was re-stamped, otherwise
+    /// the `N_Software_Version`.
+    #[must_use]
+    pub fn source_software_version(&self) -> &str {
+        self.extra_attrs
+            .get(Self::SOURCE_SOFTWARE_VERSION_ATTR)
+            .unwrap_or(&self.software_version)
+    }
+
+    /// Reconstruct granule metadata from Common RDR `data` for files without a granule
+    /// dataset in `Data_Products`.
+    ///
+    /// Times come from the static header boundaries and packet types and counts from the APID
+    /// list; everything else uses the same defaults as [GranuleMeta::new].
+    ///
+    /// # Errors
+    /// If `data` is not a valid Common RDR or its start is before the satellite base time.
+    pub fn from_common_rdr(data: &[u8], sat: &SatSpec, product: &ProductSpec) -> Result<Self> {
+        let common = CommonRdr::from_bytes(data)?;
+        let header = &common.static_header;
+        let mut meta = Self::new(Time::from_iet(header.start_boundary), sat, product)?;
+        let end = Time::from_iet(header.end_boundary);
+        meta.end_date = attr_date(&end);
+        meta.end_time = attr_time(&end);
+        meta.end_time_iet = end.iet_micros();
+        meta.end = end;
+        meta.packet_type = common.apid_list.iter().map(|a| a.name.clone()).collect();
+        meta.packet_type_count = common.apid_list.iter().map(|a| a.pkts_received).collect();
+        Ok(meta)
+    }
+
     /// Read RDR grnaule metadata from a [Dataset].
     fn from_dataset(instrument: &str, collection: &str, ds: &Dataset) -> Result<Self> {
         // Read packet type
-        let attr = try_h5!(ds.attr("N_Packet_Type"), "accessing N_Packet_Type")?;
+        let attr = find_attr(ds, "N_Packet_Type")?;
         let packet_type: Vec<String> = try_h5!(
-            attr.read_2d::<FixedAscii<MAX_STR_LEN>>(),
+            attr.read_raw::<FixedAscii<MAX_STR_LEN>>(),
             "reading N_Packet_Type"
         )?
-        .as_slice()
-        .ok_or(Error::Hdf5Other(
-            "failed to create slice for N_Packet_Type".to_string(),
-        ))
-        .into_iter()
-        .flat_map(|x| x.iter())
+        .iter()
         .map(|fa| fa.to_string())
         .collect();
 
         // Read packet type count
-        let packet_type_count: Vec<u32> = ds
-            .attr("N_Packet_Type_Count")?
-            .read_2d::<u64>()?
-            .as_slice()
-            .ok_or(Error::Hdf5Other("failed to read dataset".to_string()))?
+        let packet_type_count: Vec<u32> = find_attr(ds, "N_Packet_Type_Count")?
+            .read_raw::<u64>()?
             .iter()
             .map(|v| u32::try_from(*v).unwrap_or_default())
             .collect();
 
+        // Not present in all files and float64 in some legacy files
+        let percent_missing = read_attr_f32(ds, "N_Percent_Missing_Data").unwrap_or_default();
+
+        // Any string attributes not in the standard set, e.g., from a GranuleAnnotator
+        let mut extra_attrs = BTreeMap::default();
+        for name in ds.attr_names()? {
+            if crate::writer::is_granule_attr(&name) {
+                continue;
+            }
+            if let Ok(value) = read_attr_string(ds, &name) {
+                extra_attrs.insert(name, value);
+            }
+        }
+
+        let padding_packets = extra_attrs
+            .get(Self::PADDING_PACKETS_ATTR)
+            .and_then(|v| v.parse().ok());
+
         let begin = Time::from_iet(attr_u64!(&ds, "N_Beginning_Time_IET"));
         let end = Time::from_iet(attr_u64!(&ds, "N_Ending_Time_IET"));
         Ok(Self {
@@ -464,11 +1245,11 @@ impl GranuleMeta {
             begin,
             begin_date: attr_string!(&ds, "Beginning_Date"),
             begin_time: attr_string!(&ds, "Beginning_Time"),
-            begin_time_iet: attr_u64!(&ds, "N_Beginning_Time_IET"),
+            begin_time_iet: IetMicros(attr_u64!(&ds, "N_Beginning_Time_IET")),
             end,
             end_date: attr_string!(&ds, "Ending_Date"),
             end_time: attr_string!(&ds, "Ending_Time"),
-            end_time_iet: attr_u64!(&ds, "N_Ending_Time_IET"),
+            end_time_iet: IetMicros(attr_u64!(&ds, "N_Ending_Time_IET")),
             creation_date: attr_string!(&ds, "N_Creation_Date"),
             creation_time: attr_string!(&ds, "N_Creation_Time"),
             orbit_number: attr_u64!(&ds, "N_Beginning_Orbit_Number"),
@@ -480,13 +1261,197 @@ impl GranuleMeta {
             leoa_flag: attr_string!(&ds, "N_LEOA_Flag"),
             packet_type,
             packet_type_count,
-            percent_missing: 0.0,
+            percent_missing,
             reference_id: attr_string!(&ds, "N_Reference_ID"),
             software_version: attr_string!(&ds, "N_Software_Version"),
+            scans: None,
+            raw_dataset: None,
+            extra_attrs,
+            raw_attrs: BTreeMap::default(),
+            padding_packets,
+        })
+    }
+}
+
+/// How much detail about `RawApplicationPackets` datasets to include when reading [Meta].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RawDatasetDetail {
+    /// Do not read any dataset details
+    #[default]
+    None,
+    /// Record the dataset byte size
+    Size,
+    /// Record the dataset byte size and its [RawDatasetLayout]. This requires reading all
+    /// dataset data.
+    Layout,
+    /// Record the dataset byte size, its [RawDatasetLayout], and a CRC-32 of its contents. This
+    /// requires reading all dataset data.
+    Checksum,
+}
+
+/// Size and checksum of the `/All_Data/<shortname>_All/RawApplicationPackets_<idx>` dataset
+/// for a granule.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct RawDatasetInfo {
+    pub name: String,
+    /// Size in bytes. A missing dataset has a size of 0.
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<RawDatasetLayout>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crc32: Option<u32>,
+}
+
+/// Byte breakdown of a Common RDR computed from its [StaticHeader] offsets, useful for
+/// diagnosing oversized granules and incorrect offsets.
+///
+/// Section sizes are computed from the offsets as written, saturating at 0, so they may not
+/// add up to the data size if the offsets are invalid.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct RawDatasetLayout {
+    pub num_apids: u32,
+    /// Number of packet trackers that fit between the tracker and AP storage offsets
+    pub num_trackers: u64,
+    /// Packets received according to the APID list
+    pub pkts_received: u64,
+    pub header_bytes: u64,
+    pub apid_list_bytes: u64,
+    pub tracker_bytes: u64,
+    /// Bytes of AP storage used by packets, i.e., the next packet position
+    pub ap_storage_bytes: u64,
+    /// Bytes following the used AP storage
+    pub unused_bytes: u64,
+    /// Whether the offsets are consistent with each other and the data size. See
+    /// [validate_common_rdr].
+    pub valid: bool,
+}
+
+impl RawDatasetLayout {
+    /// Compute the layout of Common RDR `data`, or `None` if it is too short to contain a
+    /// [StaticHeader].
+    #[must_use]
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let header = StaticHeader::from_bytes(data).ok()?;
+        let len = data.len() as u64;
+        let apid_list = u64::from(header.apid_list_offset);
+        let trackers = u64::from(header.pkt_tracker_offset);
+        let storage = u64::from(header.ap_storage_offset);
+        let storage_used = u64::from(header.next_pkt_position);
+
+        let pkts_received = (0..header.num_apids as usize)
+            .map_while(|idx| {
+                let start = apid_list as usize + idx * ApidInfo::LEN;
+                data.get(start..start + ApidInfo::LEN)
+                    .and_then(|buf| ApidInfo::from_bytes(buf).ok())
+            })
+            .map(|info| u64::from(info.pkts_received))
+            .sum();
+        let tracker_bytes = storage.saturating_sub(trackers);
+
+        Some(Self {
+            num_apids: header.num_apids,
+            num_trackers: tracker_bytes / PacketTracker::LEN as u64,
+            pkts_received,
+            header_bytes: apid_list.min(len),
+            apid_list_bytes: trackers.saturating_sub(apid_list),
+            tracker_bytes,
+            ap_storage_bytes: storage_used,
+            unused_bytes: len.saturating_sub(storage + storage_used),
+            valid: validate_common_rdr(data).is_ok(),
         })
     }
 }
 
+impl RawDatasetInfo {
+    /// Read info for the raw dataset associated with granule dataset `gran_ds`.
+    fn from_granule_dataset(
+        file: &hdf5::File,
+        collection: &str,
+        gran_ds: &Dataset,
+        detail: RawDatasetDetail,
+    ) -> Result<Option<Self>> {
+        if detail == RawDatasetDetail::None {
+            return Ok(None);
+        }
+        let name = raw_dataset_name(collection, gran_ds)?;
+        let ds = file.dataset(&name).ok();
+        Self::from_dataset(name, ds.as_ref(), detail)
+    }
+
+    /// Read info for the raw dataset `ds` at path `name`, which may not exist.
+    fn from_dataset(
+        name: String,
+        ds: Option<&Dataset>,
+        detail: RawDatasetDetail,
+    ) -> Result<Option<Self>> {
+        if detail == RawDatasetDetail::None {
+            return Ok(None);
+        }
+        let Some(ds) = ds else {
+            return Ok(Some(Self {
+                name,
+                size: 0,
+                layout: None,
+                crc32: None,
+            }));
+        };
+        if detail == RawDatasetDetail::Size {
+            return Ok(Some(Self {
+                name,
+                size: (ds.size() * ds.dtype()?.size()) as u64,
+                layout: None,
+                crc32: None,
+            }));
+        }
+        let data = ds.read_raw::<u8>()?;
+        let crc32 = (detail == RawDatasetDetail::Checksum).then(|| {
+            let mut crc = flate2::Crc::new();
+            crc.update(&data);
+            crc.sum()
+        });
+        Ok(Some(Self {
+            name,
+            size: data.len() as u64,
+            layout: RawDatasetLayout::from_bytes(&data),
+            crc32,
+        }))
+    }
+}
+
+/// Path of the `RawApplicationPackets` dataset associated with granule dataset `gran_ds`.
+fn raw_dataset_name(collection: &str, gran_ds: &Dataset) -> Result<String> {
+    let gran_name = gran_ds.name();
+    let Some((_, idx)) = gran_name.rsplit_once("_Gran_") else {
+        return Err(Error::Hdf5Other(format!(
+            "invalid granule dataset name {gran_name}"
+        )));
+    };
+    Ok(format!(
+        "/All_Data/{collection}_All/RawApplicationPackets_{idx}"
+    ))
+}
+
+/// All string attributes of `loc`. Attributes that are not strings, e.g., numeric
+/// attributes, are skipped with a warning since they will not be preserved.
+fn read_string_attrs(
+    loc: &Location,
+    warnings: &mut RateLimitedWarnings,
+) -> Result<BTreeMap<String, String>> {
+    let mut attrs = BTreeMap::default();
+    for name in loc.attr_names()? {
+        match read_attr_string(loc, &name) {
+            Ok(value) => {
+                attrs.insert(name, value);
+            }
+            Err(err) => warnings.warn(
+                "dropping attribute that is not a string; only string attributes are preserved",
+                format_args!("{}/{name}: {err}", loc.name()),
+            ),
+        }
+    }
+    Ok(attrs)
+}
+
 /// Metadata associated with a particular product group from RDR path
 /// `/Data_Products/<shortname>`.
 #[derive(Debug, Clone, Serialize)]
@@ -495,6 +1460,11 @@ pub struct ProductMeta {
     pub collection: String,
     pub processing_domain: String,
     pub dataset_type: String,
+    /// String attributes of the `/All_Data/<shortname>_All` group, which some producers use
+    /// for extra provenance. Preserved when the product is written; attributes of other types
+    /// are dropped with a warning when read.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub all_data_attrs: BTreeMap<String, String>,
 }
 
 impl ProductMeta {
@@ -507,15 +1477,20 @@ impl ProductMeta {
             collection: rdr.meta.collection.to_string(),
             processing_domain: Self::DEFAULT_PROC_DOMAIN.to_string(),
             dataset_type: Self::DEFAULT_TYPE_TAG.to_string(),
+            all_data_attrs: BTreeMap::default(),
         }
     }
 
     fn from_product(product: &ProductSpec) -> Self {
         Self {
-            instrument: product.sensor.to_string(),
+            instrument: product.instrument().to_string(),
             collection: product.short_name.to_string(),
             processing_domain: Self::DEFAULT_PROC_DOMAIN.to_string(),
-            dataset_type: Self::DEFAULT_TYPE_TAG.to_string(),
+            dataset_type: product
+                .dataset_type
+                .clone()
+                .unwrap_or_else(|| Self::DEFAULT_TYPE_TAG.to_string()),
+            all_data_attrs: BTreeMap::default(),
         }
     }
 
@@ -525,6 +1500,7 @@ impl ProductMeta {
             collection: attr_string!(&grp, "N_Collection_Short_Name"),
             processing_domain: attr_string!(&grp, "N_Processing_Domain"),
             dataset_type: attr_string!(&grp, "N_Dataset_Type_Tag"),
+            all_data_attrs: BTreeMap::default(),
         })
     }
 }
@@ -541,26 +1517,204 @@ pub struct Meta {
     pub products: HashMap<String, ProductMeta>,
     /// Product name to the granules for that product
     pub granules: HashMap<String, Vec<GranuleMeta>>,
+    /// Lengths used when writing the file level string attributes
+    #[serde(skip)]
+    pub attr_lengths: FileAttrLengths,
+    /// How string attribute values that are too long are handled when writing
+    #[serde(skip)]
+    pub attr_truncation: AttrTruncation,
 }
 
 impl Meta {
     /// Create from the contents of a hdf5 file.
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_file_with(path, RawDatasetDetail::None)
+    }
+
+    /// Create from the contents of a hdf5 file, including `detail` about each granule's
+    /// `RawApplicationPackets` dataset.
+    pub fn from_file_with<P: AsRef<Path>>(path: P, detail: RawDatasetDetail) -> Result<Self> {
+        let file = hdf5::File::open(path)?;
+        Ok(Self::read(&file, detail, false)?.0)
+    }
+
+    /// Create from the contents of a hdf5 file like [Meta::from_file_with], tolerating
+    /// missing or incomplete `Data_Products` entries, e.g., in malformed files from some
+    /// producers.
+    ///
+    /// Product groups and granule datasets that cannot be read are skipped, as are missing
+    /// file attributes. Metadata for `RawApplicationPackets` datasets without a readable
+    /// granule dataset is then reconstructed from the Common RDR using the products and
+    /// satellite in `config`, or the default config for the file's platform if `None`. See
+    /// [GranuleMeta::from_common_rdr]. `RawApplicationPackets` datasets that cannot be read
+    /// are skipped with a warning.
+    ///
+    /// # Errors
+    /// If the file cannot be opened or there is no config for the file's platform.
+    pub fn from_file_lenient<P: AsRef<Path>>(
+        path: P,
+        config: Option<&Config>,
+        detail: RawDatasetDetail,
+    ) -> Result<Self> {
         let file = hdf5::File::open(path)?;
+        let (mut meta, found) = Self::read(&file, detail, true)?;
+        let mut warnings = RateLimitedWarnings::default();
+        let default;
+        let config = match config {
+            Some(config) => config,
+            None => {
+                default = get_default_for_platform(&meta.platform)?.ok_or_else(|| {
+                    Error::ConfigInvalid(format!("no config for platform {:?}", meta.platform))
+                })?;
+                &default
+            }
+        };
+        if meta.platform.is_empty() {
+            meta.platform = config.satellite.short_name.clone();
+        }
+
+        let Ok(all_data) = file.group("All_Data") else {
+            warn!("no All_Data group");
+            return Ok(meta);
+        };
+        for group in all_data.groups()? {
+            let group_name = group.name();
+            let Some(collection) = group_name
+                .rsplit('/')
+                .next()
+                .and_then(|n| n.strip_suffix("_All"))
+            else {
+                continue;
+            };
+            let Some(product) = config.products.iter().find(|p| p.short_name == collection) else {
+                warn!("no product configured for {collection}; skipping {group_name}");
+                continue;
+            };
+            let mut datasets: Vec<(u64, Dataset)> = group
+                .datasets()?
+                .into_iter()
+                .filter(|ds| !found.contains(&ds.name()))
+                .filter_map(|ds| {
+                    let idx = ds
+                        .name()
+                        .rsplit_once("RawApplicationPackets_")?
+                        .1
+                        .parse()
+                        .ok()?;
+                    Some((idx, ds))
+                })
+                .collect();
+            datasets.sort_by_key(|(idx, _)| *idx);
+
+            for (_, ds) in datasets {
+                let name = ds.name();
+                let data = match ds.read_raw::<u8>() {
+                    Ok(data) => data,
+                    Err(err) => {
+                        warn!("cannot read {name}; skipping: {err}");
+                        continue;
+                    }
+                };
+                let mut gran_meta =
+                    match GranuleMeta::from_common_rdr(&data, &config.satellite, product) {
+                        Ok(gran_meta) => gran_meta,
+                        Err(err) => {
+                            warn!("cannot reconstruct granule metadata for {name}: {err}");
+                            continue;
+                        }
+                    };
+                debug!(collection, granule_id = %gran_meta.id, "reconstructed from {name}");
+                let attrs = read_string_attrs(&ds, &mut warnings).and_then(|attrs| {
+                    Ok((
+                        attrs,
+                        RawDatasetInfo::from_dataset(name.clone(), Some(&ds), detail)?,
+                    ))
+                });
+                match attrs {
+                    Ok((attrs, info)) => {
+                        gran_meta.raw_attrs = attrs;
+                        gran_meta.raw_dataset = info;
+                    }
+                    Err(err) => {
+                        warn!("cannot read attributes of {name}; skipping: {err}");
+                        continue;
+                    }
+                }
+                if !meta.products.contains_key(collection) {
+                    let mut product_meta = ProductMeta::from_product(product);
+                    product_meta.all_data_attrs = read_string_attrs(&group, &mut warnings)?;
+                    meta.products.insert(collection.to_string(), product_meta);
+                }
+                meta.granules
+                    .entry(collection.to_string())
+                    .or_default()
+                    .push(gran_meta);
+            }
+        }
+
+        Ok(meta)
+    }
+
+    /// Read metadata from `file`, also returning the paths of the raw datasets associated with
+    /// granule datasets. If `lenient`, missing file attributes and product groups or granule
+    /// datasets that cannot be read are skipped rather than an error.
+    fn read(
+        file: &hdf5::File,
+        detail: RawDatasetDetail,
+        lenient: bool,
+    ) -> Result<(Self, HashSet<String>)> {
+        let file_attr = |name: &str| match read_attr_string(file, name) {
+            Err(err) if lenient => {
+                warn!("missing file attribute {name}: {err}");
+                Ok(String::default())
+            }
+            zult => zult,
+        };
         let mut meta = Meta {
-            distributor: attr_string!(&file, "Distributor"),
-            mission: attr_string!(&file, "Mission_Name"),
-            dataset_source: attr_string!(&file, "N_Dataset_Source"),
-            platform: attr_string!(&file, "Platform_Short_Name"),
+            distributor: file_attr("Distributor")?,
+            mission: file_attr("Mission_Name")?,
+            dataset_source: file_attr("N_Dataset_Source")?,
+            platform: file_attr("Platform_Short_Name")?,
             created: Time::now(),
             products: HashMap::default(),
             granules: HashMap::default(),
+            attr_lengths: FileAttrLengths::default(),
+            attr_truncation: AttrTruncation::default(),
         };
+        // Preserve values from files written with longer attributes
+        let lengths = &mut meta.attr_lengths;
+        for (len, value) in [
+            (&mut lengths.distributor, &meta.distributor),
+            (&mut lengths.mission, &meta.mission),
+            (&mut lengths.platform, &meta.platform),
+            (&mut lengths.dataset_source, &meta.dataset_source),
+        ] {
+            *len = value.len().clamp(*len, MAX_FILE_ATTR_LEN);
+        }
 
-        let data_products = file.group("Data_Products")?;
+        let mut found = HashSet::default();
+        let mut warnings = RateLimitedWarnings::default();
+        let data_products = match file.group("Data_Products") {
+            Ok(group) => group,
+            Err(err) if lenient => {
+                warn!("missing Data_Products group: {err}");
+                return Ok((meta, found));
+            }
+            Err(err) => return Err(err.into()),
+        };
         for product_group in data_products.groups()? {
-            let product_meta = ProductMeta::from_group(&product_group)?;
+            let mut product_meta = match ProductMeta::from_group(&product_group) {
+                Ok(product_meta) => product_meta,
+                Err(err) if lenient => {
+                    warn!("skipping product group {}: {err}", product_group.name());
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
             let product_name = &product_meta.collection.clone();
+            if let Ok(group) = file.group(&format!("All_Data/{product_name}_All")) {
+                product_meta.all_data_attrs = read_string_attrs(&group, &mut warnings)?;
+            }
 
             // all datasets in product group, skipping _Aggr b/c we'll create our own aggr
             let gran_datasets = product_group
@@ -569,11 +1723,33 @@ impl Meta {
                 .filter(|d| !d.name().ends_with("_Aggr"));
 
             for gran_dataset in gran_datasets {
-                let gran_meta = GranuleMeta::from_dataset(
+                let mut gran_meta = match GranuleMeta::from_dataset(
                     &product_meta.instrument,
                     &product_meta.collection,
                     &gran_dataset,
+                ) {
+                    Ok(gran_meta) => gran_meta,
+                    Err(err) if lenient => {
+                        warn!("skipping granule dataset {}: {err}", gran_dataset.name());
+                        continue;
+                    }
+                    Err(err) => return Err(err),
+                };
+                if let Ok(name) = raw_dataset_name(&product_meta.collection, &gran_dataset) {
+                    found.insert(name);
+                }
+                gran_meta.raw_dataset = RawDatasetInfo::from_granule_dataset(
+                    &file,
+                    &product_meta.collection,
+                    &gran_dataset,
+                    detail,
                 )?;
+                if let Some(ds) = raw_dataset_name(&product_meta.collection, &gran_dataset)
+                    .ok()
+                    .and_then(|name| file.dataset(&name).ok())
+                {
+                    gran_meta.raw_attrs = read_string_attrs(&ds, &mut warnings)?;
+                }
                 meta.granules
                     .entry(product_name.to_string())
                     .or_default()
@@ -583,37 +1759,341 @@ impl Meta {
             meta.products.insert(product_name.clone(), product_meta);
         }
 
-        Ok(meta)
-    }
+        Ok((meta, found))
+    }
+
+    /// Check that primary granules, e.g., science, have all packed granules, e.g.,
+    /// SPACECRAFT-DIARY, required by the packing rules in `config`, and that packed granules
+    /// are aligned to their granule boundaries and overlap a primary granule.
+    ///
+    /// See [GranuleMeta::is_packed_with].
+    #[must_use]
+    pub fn packed_coverage(&self, config: &Config) -> PackedCoverage {
+        let base_time = config.satellite.base_time;
+        let product = |id: &str| config.products.iter().find(|p| p.product_id == id);
+        let mut coverage = PackedCoverage::default();
+        // Packed short name to the primary granule (begin, end) spans it is packed with
+        let mut primary_spans: HashMap<&str, Vec<(IetMicros, IetMicros)>> = HashMap::default();
+
+        for rdr in &config.rdrs {
+            let Some(primary) = product(&rdr.product) else {
+                continue;
+            };
+            let Some(primary_granules) = self.granules.get(&primary.short_name) else {
+                continue;
+            };
+            coverage.primary_granules += primary_granules.len();
+            for packed in rdr.packed_with.iter().filter_map(|id| product(id)) {
+                let packed_begins: HashSet<IetMicros> = self
+                    .granules
+                    .get(&packed.short_name)
+                    .map(|g| g.iter().map(|g| g.begin_time_iet).collect())
+                    .unwrap_or_default();
+                let spans = primary_spans.entry(&packed.short_name).or_default();
+                for granule in primary_granules {
+                    let (start, end) = (granule.begin_time_iet, granule.end_time_iet);
+                    spans.push((start, end));
+                    if start < base_time {
+                        continue;
+                    }
+                    let mut missing = Vec::default();
+                    let mut packed_start = get_granule_start(start, packed.gran_len, base_time);
+                    while packed_start < end {
+                        if !packed_begins.contains(&packed_start) {
+                            missing.push(packed_start.0);
+                        }
+                        packed_start = packed_start + packed.gran_len;
+                    }
+                    if !missing.is_empty() {
+                        coverage.gaps.push(PackedCoverageGap {
+                            short_name: primary.short_name.clone(),
+                            granule_id: granule.id.clone(),
+                            packed_short_name: packed.short_name.clone(),
+                            missing,
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut packed_names: Vec<&&str> = primary_spans.keys().collect();
+        packed_names.sort();
+        for short_name in packed_names {
+            let Some(packed) = config
+                .products
+                .iter()
+                .find(|p| p.short_name == **short_name)
+            else {
+                continue;
+            };
+            let spans = &primary_spans[*short_name];
+            let len = packed.gran_len;
+            for granule in self.granules.get(*short_name).into_iter().flatten() {
+                let begin = granule.begin_time_iet;
+                let reason = if begin < base_time || (begin - base_time) % len.micros() != 0 {
+                    "not aligned to a granule boundary"
+                } else if !spans.is_empty()
+                    && !spans
+                        .iter()
+                        .any(|(start, end)| is_packed_overlap(*start, *end, begin, len))
+                {
+                    "does not overlap any primary granule"
+                } else {
+                    continue;
+                };
+                coverage.misaligned.push(MisalignedGranule {
+                    short_name: short_name.to_string(),
+                    granule_id: granule.id.clone(),
+                    reason: reason.to_string(),
+                });
+            }
+        }
+
+        coverage
+    }
+
+    /// Create a Meta with the file level metadata from `config` and no products.
+    pub fn from_config(config: &Config) -> Self {
+        Meta {
+            distributor: config.distributor.clone(),
+            mission: config.satellite.mission.clone(),
+            dataset_source: config.distributor.clone(),
+            created: Time::now(),
+            platform: config.satellite.short_name.clone(),
+            products: HashMap::default(),
+            granules: HashMap::default(),
+            attr_lengths: config.attr_lengths,
+            attr_truncation: config.attr_truncation,
+        }
+    }
+
+    /// Create a Meta configured for all products in `product_ids`.
+    ///
+    /// Returns `None` if either product are not found in `config`.
+    pub fn from_products(product_ids: &[String], config: &Config) -> Option<Self> {
+        let products = config
+            .products
+            .iter()
+            .filter(|p| product_ids.contains(&p.short_name))
+            .collect::<Vec<&ProductSpec>>();
+        if products.is_empty() {
+            return None;
+        }
+        Some(Meta {
+            products: products
+                .iter()
+                .map(|p| (p.short_name.clone(), ProductMeta::from_product(p)))
+                .collect(),
+            granules: products
+                .iter()
+                .map(|p| (p.short_name.clone(), Vec::default()))
+                .collect(),
+            ..Self::from_config(config)
+        })
+    }
+
+    /// Create the IDPS style filename for the granules in this Meta, e.g., to rename a file
+    /// whose name no longer matches its contents.
+    ///
+    /// The product ids are those of the collections with granules, looked up by short name in
+    /// `config`. As for created RDRs, the file time range is that of the SCIENCE granules, or of
+    /// all granules if there are none, and the orbit number field is the orbit of the first of
+    /// those granules.
+    pub fn filename(
+        &self,
+        config: &Config,
+        origin: &str,
+        mode: &str,
+        created: &Time,
+    ) -> Result<String> {
+        let mut product_ids = Vec::default();
+        for (short_name, granules) in &self.granules {
+            if granules.is_empty() {
+                continue;
+            }
+            let product = config
+                .products
+                .iter()
+                .find(|p| &p.short_name == short_name)
+                .ok_or_else(|| Error::ConfigNotFound(format!("collection {short_name}")))?;
+            product_ids.push(product.product_id.clone());
+        }
+        product_ids.sort();
+        product_ids.dedup();
+
+        let all = self.granules.values().flatten();
+        let mut granules: Vec<&GranuleMeta> = all
+            .clone()
+            .filter(|g| g.collection.contains("SCIENCE"))
+            .collect();
+        if granules.is_empty() {
+            granules = all.collect();
+        }
+        let Some(first) = granules.iter().min_by_key(|g| g.begin_time_iet) else {
+            return Err(Error::RdrError(RdrError::Invalid(
+                "no granules to name file from".to_string(),
+            )));
+        };
+        let end = granules
+            .iter()
+            .map(|g| g.end_time_iet)
+            .max()
+            .unwrap_or_default();
+
+        let fname = filename(
+            &config.satellite.id,
+            origin,
+            mode,
+            created,
+            &Time::from(first.begin_time_iet),
+            &Time::from(end),
+            &product_ids,
+        );
+        Ok(filename_with_orbit(&fname, first.orbit_number))
+    }
+}
+
+/// Summary of a single collection in an RDR file. See [list].
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct CollectionSummary {
+    pub short_name: String,
+    pub num_granules: usize,
+    /// Earliest granule `N_Beginning_Time_IET`, if there are any granules
+    pub begin_time_iet: Option<IetMicros>,
+    /// Latest granule `N_Ending_Time_IET`, if there are any granules
+    pub end_time_iet: Option<IetMicros>,
+    /// Names of the `/Data_Products/<shortname>/<shortname>_Gran_<idx>` datasets
+    pub granule_datasets: Vec<String>,
+    /// Names of the `/All_Data/<shortname>_All/RawApplicationPackets_<idx>` datasets
+    pub raw_datasets: Vec<String>,
+}
+
+/// List the collections in the RDR file at `path`.
+///
+/// Unlike [Meta::from_file] only the collection name and granule begin/end time attributes
+/// are read, and no dataset data, making it suitable for quickly scanning many files.
+///
+/// # Errors
+/// If the file cannot be opened or a required attribute is missing.
+pub fn list<P: AsRef<Path>>(path: P) -> Result<Vec<CollectionSummary>> {
+    let file = hdf5::File::open(path)?;
+    let mut summaries = Vec::default();
+    for product_group in file.group("Data_Products")?.groups()? {
+        let short_name = read_attr_string(&product_group, "N_Collection_Short_Name")?;
+        let mut summary = CollectionSummary {
+            short_name: short_name.clone(),
+            num_granules: 0,
+            begin_time_iet: None,
+            end_time_iet: None,
+            granule_datasets: Vec::default(),
+            raw_datasets: Vec::default(),
+        };
+        for name in product_group.member_names()? {
+            if !name.contains("_Gran_") {
+                continue;
+            }
+            let ds = product_group.dataset(&name)?;
+            let begin = IetMicros(read_attr_u64(&ds, "N_Beginning_Time_IET")?);
+            let end = IetMicros(read_attr_u64(&ds, "N_Ending_Time_IET")?);
+            summary.begin_time_iet = Some(summary.begin_time_iet.map_or(begin, |t| t.min(begin)));
+            summary.end_time_iet = Some(summary.end_time_iet.map_or(end, |t| t.max(end)));
+            summary.num_granules += 1;
+            summary.granule_datasets.push(name);
+        }
+        if let Ok(group) = file.group(&format!("All_Data/{short_name}_All")) {
+            summary.raw_datasets = group
+                .member_names()?
+                .into_iter()
+                .filter(|n| n.starts_with("RawApplicationPackets_"))
+                .collect();
+        }
+        summaries.push(summary);
+    }
+    Ok(summaries)
+}
+
+/// Split a granule version into its letter and number, e.g., A1 into (A, 1).
+fn parse_granule_version(version: &str) -> Result<(u8, u8)> {
+    match version.as_bytes() {
+        [letter @ b'A'..=b'Z', num @ b'1'..=b'9'] => Ok((*letter, num - b'0')),
+        _ => Err(Error::RdrError(RdrError::Invalid(format!(
+            "invalid granule version {version:?}; expected a letter A-Z and a number 1-9"
+        )))),
+    }
+}
+
+/// Check that `version` is a valid granule version. See [next_granule_version].
+///
+/// # Errors
+/// If `version` is not an uppercase letter followed by a number 1-9.
+pub fn validate_granule_version(version: &str) -> Result<()> {
+    parse_granule_version(version).map(|_| ())
+}
+
+/// The granule version following `version`.
+///
+/// Following IDPS conventions, versions are an uppercase letter followed by a number 1-9,
+/// starting at A1. The number is incremented for each re-generation of a granule, and once it
+/// reaches 9 the letter is incremented and the number starts over, e.g., A1, A2, ..., A9, B1.
+///
+/// # Errors
+/// If `version` is not a valid granule version or is Z9, the last possible version.
+pub fn next_granule_version(version: &str) -> Result<String> {
+    let (letter, num) = parse_granule_version(version)?;
+    let (letter, num) = match (letter, num) {
+        (b'Z', 9) => {
+            return Err(Error::RdrError(RdrError::Invalid(format!(
+                "no granule version after {version}"
+            ))))
+        }
+        (_, 9) => (letter + 1, 1),
+        _ => (letter, num + 1),
+    };
+    Ok(format!("{}{num}", letter as char))
+}
+
+/// The latest `N_Granule_Version` of each granule in the RDR files in `dir`, keyed by
+/// collection short name and granule id, e.g., to determine the version of a granule being
+/// re-generated.
+///
+/// Only files with an `.h5` extension are read, and only their granule attributes. Files
+/// that cannot be read are skipped.
+///
+/// # Errors
+/// If `dir` cannot be read.
+pub fn granule_versions<P: AsRef<Path>>(dir: P) -> Result<HashMap<(String, String), String>> {
+    let read_file = |path: &Path, versions: &mut HashMap<(String, String), String>| {
+        let file = hdf5::File::open(path)?;
+        for product_group in file.group("Data_Products")?.groups()? {
+            let short_name = read_attr_string(&product_group, "N_Collection_Short_Name")?;
+            for name in product_group.member_names()? {
+                if !name.contains("_Gran_") {
+                    continue;
+                }
+                let ds = product_group.dataset(&name)?;
+                let id = read_attr_string(&ds, "N_Granule_ID")?;
+                let version = read_attr_string(&ds, "N_Granule_Version")?;
+                let existing = versions.entry((short_name.clone(), id)).or_default();
+                // Valid versions sort in version order
+                if version > *existing {
+                    *existing = version;
+                }
+            }
+        }
+        Ok::<_, Error>(())
+    };
 
-    /// Create a Meta configured for all products in `product_ids`.
-    ///
-    /// Returns `None` if either product are not found in `config`.
-    pub fn from_products(product_ids: &[String], config: &Config) -> Option<Self> {
-        let products = config
-            .products
-            .iter()
-            .filter(|p| product_ids.contains(&p.short_name))
-            .collect::<Vec<&ProductSpec>>();
-        if products.is_empty() {
-            return None;
+    let mut versions = HashMap::default();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() || !path.extension().is_some_and(|e| e == "h5") {
+            continue;
+        }
+        if let Err(err) = read_file(&path, &mut versions) {
+            debug!("skipping {path:?} reading granule versions: {err}");
         }
-        Some(Meta {
-            distributor: config.distributor.clone(),
-            mission: config.satellite.mission.clone(),
-            dataset_source: config.distributor.clone(),
-            created: Time::now(),
-            platform: config.satellite.short_name.clone(),
-            products: products
-                .iter()
-                .map(|p| (p.short_name.clone(), ProductMeta::from_product(p)))
-                .collect(),
-            granules: products
-                .iter()
-                .map(|p| (p.short_name.clone(), Vec::default()))
-                .collect(),
-        })
     }
+    Ok(versions)
 }
 
 /// Common RDR static header
@@ -636,7 +2116,7 @@ impl StaticHeader {
 
     pub fn new(time: &Time, sat: String, product: &ProductSpec) -> Self {
         let start_iet = time.iet();
-        let end_iet = start_iet + product.gran_len;
+        let end_iet = start_iet + product.gran_len.micros();
         StaticHeader {
             satellite: sat.clone(),
             sensor: product.sensor.clone(),
@@ -697,6 +2177,15 @@ pub struct ApidInfo {
     pub pkt_tracker_start_idx: u32,
     pub pkts_reserved: u32,
     pub pkts_received: u32,
+    /// Number of duplicate packets encountered while collecting. Not part of the Common RDR
+    /// structure.
+    pub pkts_duplicate: u32,
+    /// Number of packets encountered while collecting that were older than a packet already
+    /// collected. Not part of the Common RDR structure.
+    pub pkts_out_of_order: u32,
+    /// Number of packets received from the pre or post-roll window outside the granule time
+    /// range. See [ProductSpec::padding]. Not part of the Common RDR structure.
+    pub pkts_padding: u32,
 }
 
 impl ApidInfo {
@@ -709,15 +2198,23 @@ impl ApidInfo {
             pkt_tracker_start_idx: u32::MAX,
             pkts_reserved: 0,
             pkts_received: 0,
+            pkts_duplicate: 0,
+            pkts_out_of_order: 0,
+            pkts_padding: 0,
         }
     }
 
     #[must_use]
     pub fn as_bytes(&self) -> [u8; Self::LEN] {
+        self.as_bytes_with_start_idx(self.pkt_tracker_start_idx)
+    }
+
+    /// Bytes with `pkt_tracker_start_idx` in place of the current value.
+    fn as_bytes_with_start_idx(&self, pkt_tracker_start_idx: u32) -> [u8; Self::LEN] {
         let mut buf = [0u8; Self::LEN];
         copy_with_len(&mut buf[..16], self.name.as_bytes(), 16);
         buf[16..20].copy_from_slice(&self.value.to_be_bytes());
-        buf[20..24].copy_from_slice(&self.pkt_tracker_start_idx.to_be_bytes());
+        buf[20..24].copy_from_slice(&pkt_tracker_start_idx.to_be_bytes());
         buf[24..28].copy_from_slice(&self.pkts_reserved.to_be_bytes());
         buf[28..32].copy_from_slice(&self.pkts_received.to_be_bytes());
 
@@ -734,6 +2231,9 @@ impl ApidInfo {
             pkt_tracker_start_idx: from_bytes4!(u32, data, 20),
             pkts_reserved: from_bytes4!(u32, data, 24),
             pkts_received: from_bytes4!(u32, data, 28),
+            pkts_duplicate: 0,
+            pkts_out_of_order: 0,
+            pkts_padding: 0,
         };
 
         Ok(info)
@@ -763,6 +2263,14 @@ pub struct PacketTracker {
 
 impl PacketTracker {
     pub const LEN: usize = 24;
+    /// Tracker for a reserved entry without a packet.
+    pub const FILL: Self = Self {
+        obs_time: -1,
+        sequence_number: -1,
+        size: -1,
+        offset: -1,
+        fill_percent: 0,
+    };
 
     #[must_use]
     pub fn as_bytes(&self) -> [u8; Self::LEN] {
@@ -802,13 +2310,40 @@ pub struct CommonRdr {
 }
 
 impl CommonRdr {
+    /// Decode the Common RDR structures from `data`.
+    ///
+    /// Offsets come from the static header in `data`, so they are checked before use; a
+    /// corrupt granule results in an error rather than a panic.
+    ///
+    /// # Errors
+    /// If `data` is shorter than the static header, the apid list does not directly follow the
+    /// static header, or the apid list or packet tracker offsets are out of order or beyond the
+    /// end of `data`.
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
-        let static_header = StaticHeader::from_bytes(&data[..StaticHeader::LEN])?;
+        let static_header = StaticHeader::from_bytes(data)?;
+        let section = |name: &str, start: u32, end: u32| {
+            data.get(start as usize..end as usize).ok_or_else(|| {
+                Error::RdrError(RdrError::Invalid(format!(
+                    "{name} offsets {start}..{end} invalid for data length {}",
+                    data.len()
+                )))
+            })
+        };
+        if static_header.apid_list_offset as usize != StaticHeader::LEN {
+            return Err(Error::RdrError(RdrError::Invalid(format!(
+                "apid list offset {} != static header length {}",
+                static_header.apid_list_offset,
+                StaticHeader::LEN
+            ))));
+        }
+
         let mut apid_list: Vec<ApidInfo> = Vec::default();
-        let start = static_header.apid_list_offset as usize;
-        assert_eq!(start, StaticHeader::LEN);
-        let end = static_header.pkt_tracker_offset as usize;
-        for buf in data[start..end].chunks(ApidInfo::LEN) {
+        let apid_data = section(
+            "apid list",
+            static_header.apid_list_offset,
+            static_header.pkt_tracker_offset,
+        )?;
+        for buf in apid_data.chunks(ApidInfo::LEN) {
             if buf.len() < ApidInfo::LEN {
                 debug!("ApidInfo data < {}; bailing!", ApidInfo::LEN);
                 break;
@@ -817,9 +2352,12 @@ impl CommonRdr {
         }
 
         let mut packet_trackers: Vec<PacketTracker> = Vec::default();
-        let start = static_header.pkt_tracker_offset as usize;
-        let end = static_header.ap_storage_offset as usize;
-        for buf in data[start..end].chunks(PacketTracker::LEN) {
+        let tracker_data = section(
+            "packet tracker",
+            static_header.pkt_tracker_offset,
+            static_header.ap_storage_offset,
+        )?;
+        for buf in tracker_data.chunks(PacketTracker::LEN) {
             if buf.len() < PacketTracker::LEN {
                 debug!("packet tracker data < {}; bailing!", PacketTracker::LEN);
                 break;
@@ -835,6 +2373,59 @@ impl CommonRdr {
             packet_trackers,
         })
     }
+
+    /// APIDs with packet tracker observation times outside the static header
+    /// `start_boundary..end_boundary` window, widened by `pre` and `post` microseconds, e.g.,
+    /// for a product's [crate::config::GranulePadding]. Fill trackers are ignored.
+    #[must_use]
+    pub fn out_of_window(&self, pre: u64, post: u64) -> Vec<ApidTimeSkew> {
+        let start = self.static_header.start_boundary.saturating_sub(pre);
+        let end = self.static_header.end_boundary.saturating_add(post);
+        let mut skews = Vec::default();
+        for info in &self.apid_list {
+            let first = info.pkt_tracker_start_idx as usize;
+            let last = first + info.pkts_received as usize;
+            let mut skew = ApidTimeSkew {
+                apid: info.value,
+                name: info.name.clone(),
+                ..Default::default()
+            };
+            for tracker in self.packet_trackers.get(first..last).unwrap_or_default() {
+                let Ok(obs_time) = u64::try_from(tracker.obs_time) else {
+                    continue;
+                };
+                if obs_time < start {
+                    skew.before += 1;
+                } else if obs_time >= end {
+                    skew.after += 1;
+                } else {
+                    continue;
+                }
+                skew.min_obs_time = Some(skew.min_obs_time.map_or(obs_time, |t| t.min(obs_time)));
+                skew.max_obs_time = Some(skew.max_obs_time.map_or(obs_time, |t| t.max(obs_time)));
+            }
+            if skew.before > 0 || skew.after > 0 {
+                skews.push(skew);
+            }
+        }
+        skews
+    }
+}
+
+/// Packets of a single APID with observation times outside of their granule's time window.
+/// See [CommonRdr::out_of_window].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ApidTimeSkew {
+    pub apid: u32,
+    pub name: String,
+    /// Packets before the window start
+    pub before: u32,
+    /// Packets at or after the window end
+    pub after: u32,
+    /// Earliest out of window observation time, IET microseconds
+    pub min_obs_time: Option<u64>,
+    /// Latest out of window observation time, IET microseconds
+    pub max_obs_time: Option<u64>,
 }
 
 fn copy_with_len<'a>(dst: &'a mut [u8], src: &'a [u8], len: usize) {
@@ -850,11 +2441,14 @@ fn copy_with_len<'a>(dst: &'a mut [u8], src: &'a [u8], len: usize) {
 
 #[cfg(test)]
 mod tests {
-    use std::path::PathBuf;
+    use std::{path::PathBuf, sync::OnceLock};
+
+    use proptest::{prelude::*, sample::Index};
 
     use super::*;
+    use crate::config::get_default;
 
-    const BASE_TIME: u64 = 1698019234000000;
+    const BASE_TIME: IetMicros = IetMicros(1698019234000000);
 
     fn fixture_file(name: &str) -> PathBuf {
         let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -868,12 +2462,444 @@ mod tests {
         path
     }
 
+    fn duplicate_fixture(policy: DuplicatePolicy) -> (RdrData, Vec<Packet>) {
+        let mut dat: Vec<u8> = Vec::default();
+        for (seq, fill) in [(1u8, 0xaa), (1, 0xbb), (2, 0xcc)] {
+            // apid 826 w/ secondary header, unsegmented, 4 bytes of user data
+            dat.extend_from_slice(&[0x0b, 0x3a, 0xc0, seq, 0x00, 0x03]);
+            dat.extend_from_slice(&[fill; 4]);
+        }
+        let packets: Vec<Packet> = ccsds::spacepacket::decode_packets(&dat[..])
+            .filter_map(|p| p.ok())
+            .collect();
+
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let time = Time::from(config.satellite.base_time);
+        let mut rdr_data =
+            RdrData::new(&config.satellite, product, &time).with_duplicate_policy(policy);
+        for (pkt, offset) in packets.iter().zip([10, 10, 20]) {
+            rdr_data
+                .add_packet(&Time::from_iet(time.iet() + offset), pkt.clone())
+                .unwrap();
+        }
+        (rdr_data, packets)
+    }
+
+    #[test]
+    fn test_storage_order() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let time = Time::from(config.satellite.base_time);
+        // (apid header bytes, time offset, fill), in arrival order
+        let inputs = [
+            ([0x0b, 0x3a], 30, 0xaa),
+            ([0x0b, 0x20], 20, 0xbb),
+            ([0x0b, 0x3a], 10, 0xcc),
+            ([0x0b, 0x20], 40, 0xdd),
+        ];
+
+        for (order, expected) in [
+            (StorageOrder::Arrival, [0xaa, 0xbb, 0xcc, 0xdd]),
+            (StorageOrder::Time, [0xcc, 0xbb, 0xaa, 0xdd]),
+            (StorageOrder::ApidThenTime, [0xbb, 0xdd, 0xcc, 0xaa]),
+        ] {
+            let mut rdr_data =
+                RdrData::new(&config.satellite, product, &time).with_storage_order(order);
+            for (seq, (apid, offset, fill)) in inputs.iter().enumerate() {
+                let mut dat = vec![apid[0], apid[1], 0xc0, seq as u8, 0x00, 0x03];
+                dat.extend_from_slice(&[*fill; 4]);
+                let pkt = ccsds::spacepacket::decode_packets(&dat[..])
+                    .next()
+                    .unwrap()
+                    .unwrap();
+                rdr_data
+                    .add_packet(&Time::from_iet(time.iet() + offset), pkt)
+                    .unwrap();
+            }
+            let rdr = rdr_data.compile().unwrap();
+
+            // storage layout
+            let common = CommonRdr::from_bytes(&rdr.data).unwrap();
+            let storage = &rdr.data[common.static_header.ap_storage_offset as usize..];
+            let fills: Vec<u8> = storage.chunks(10).map(|pkt| pkt[6]).collect();
+            assert_eq!(fills, expected, "{order:?}");
+
+            // trackers point to their packets
+            for tracker in &common.packet_trackers {
+                let offset = tracker.obs_time as u64 - time.iet();
+                let (_, _, fill) = inputs.iter().find(|(_, o, _)| *o == offset).unwrap();
+                let start = tracker.offset as usize;
+                assert_eq!(storage[start + 6], *fill, "{order:?} {tracker:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_duplicate_policy() {
+        let (rdr_data, _) = duplicate_fixture(DuplicatePolicy::Keep);
+        assert_eq!(rdr_data.apid_list[&826].pkts_received, 3);
+        assert_eq!(rdr_data.apid_list[&826].pkts_duplicate, 0);
+
+        let (rdr_data, packets) = duplicate_fixture(DuplicatePolicy::KeepFirst);
+        let info = &rdr_data.apid_list[&826];
+        assert_eq!(info.pkts_received, 2);
+        assert_eq!(info.pkts_duplicate, 1);
+        let rdr = rdr_data.compile().unwrap();
+        let expected = [packets[0].data.clone(), packets[2].data.clone()].concat();
+        assert!(rdr.data.ends_with(&expected));
+
+        let (rdr_data, packets) = duplicate_fixture(DuplicatePolicy::KeepLast);
+        let info = &rdr_data.apid_list[&826];
+        assert_eq!(info.pkts_received, 2);
+        assert_eq!(info.pkts_duplicate, 1);
+        let rdr = rdr_data.compile().unwrap();
+        let expected = [packets[2].data.clone(), packets[1].data.clone()].concat();
+        assert!(rdr.data.ends_with(&expected));
+        let header = StaticHeader::from_bytes(&rdr.data).unwrap();
+        assert_eq!(header.next_pkt_position, 20);
+        let offset = header.pkt_tracker_offset as usize;
+        let first = PacketTracker::from_bytes(&rdr.data[offset..]).unwrap();
+        assert_eq!(
+            first.offset, 10,
+            "replaced packet is after the second packet"
+        );
+    }
+
+    /// Common RDR data for a valid granule with 3 packets
+    fn common_rdr_fixture() -> &'static [u8] {
+        static DATA: OnceLock<Vec<u8>> = OnceLock::new();
+        DATA.get_or_init(|| {
+            let (rdr_data, _) = duplicate_fixture(DuplicatePolicy::Keep);
+            rdr_data.compile().unwrap().data
+        })
+    }
+
+    /// Decode `data` with all the Common RDR parsers, ignoring results; only panics matter.
+    fn parse_all(data: &[u8]) {
+        let _ = StaticHeader::from_bytes(data);
+        let _ = ApidInfo::from_bytes(data);
+        let _ = ApidInfo::all_from_bytes(data);
+        let _ = PacketTracker::from_bytes(data);
+        let _ = CommonRdr::from_bytes(data);
+        let _ = validate_common_rdr(data);
+        let _ = crate::common_rdr_packets(data);
+    }
+
+    #[test]
+    fn test_common_rdr_from_bytes_invalid() {
+        let data = common_rdr_fixture();
+        let with_u32 = |pos: usize, value: u32| {
+            let mut data = data.to_vec();
+            data[pos..pos + 4].copy_from_slice(&value.to_be_bytes());
+            data
+        };
+
+        assert!(CommonRdr::from_bytes(data).is_ok());
+        assert!(CommonRdr::from_bytes(&data[..StaticHeader::LEN - 1]).is_err());
+        // apid list offset
+        assert!(CommonRdr::from_bytes(&with_u32(40, 0)).is_err());
+        // packet tracker offset before the apid list and beyond the data
+        assert!(CommonRdr::from_bytes(&with_u32(44, 0)).is_err());
+        assert!(CommonRdr::from_bytes(&with_u32(44, u32::MAX)).is_err());
+        // ap storage offset before the packet trackers and beyond the data
+        assert!(CommonRdr::from_bytes(&with_u32(48, 0)).is_err());
+        assert!(CommonRdr::from_bytes(&with_u32(48, data.len() as u32 + 1)).is_err());
+        // truncated before the end of the packet trackers
+        let header = StaticHeader::from_bytes(data).unwrap();
+        assert!(CommonRdr::from_bytes(&data[..header.ap_storage_offset as usize - 1]).is_err());
+    }
+
+    #[test]
+    fn test_out_of_window() {
+        let mut common = CommonRdr::from_bytes(common_rdr_fixture()).unwrap();
+        assert!(common.out_of_window(0, 0).is_empty());
+
+        let start = common.static_header.start_boundary;
+        let end = common.static_header.end_boundary;
+        common.packet_trackers[0].obs_time = start as i64 - 5;
+        common.packet_trackers[1].obs_time = end as i64;
+        let skews = common.out_of_window(0, 0);
+        assert_eq!(skews.len(), 1);
+        assert_eq!(skews[0].apid, 826);
+        assert_eq!((skews[0].before, skews[0].after), (1, 1));
+        assert_eq!(skews[0].min_obs_time, Some(start - 5));
+        assert_eq!(skews[0].max_obs_time, Some(end));
+
+        // padding widens the window
+        assert!(common.out_of_window(5, 1).is_empty());
+        // fill trackers are ignored
+        common.packet_trackers[0].obs_time = -1;
+        assert_eq!(common.out_of_window(0, 0)[0].before, 0);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_static_header_roundtrip(
+            satellite in "[A-Z0-9]{0,4}",
+            sensor in "[A-Z-]{0,16}",
+            type_id in "[A-Z-]{0,16}",
+            offsets in any::<[u32; 5]>(),
+            start_boundary in any::<u64>(),
+            end_boundary in any::<u64>(),
+        ) {
+            let header = StaticHeader {
+                satellite,
+                sensor,
+                type_id,
+                num_apids: offsets[0],
+                apid_list_offset: offsets[1],
+                pkt_tracker_offset: offsets[2],
+                ap_storage_offset: offsets[3],
+                next_pkt_position: offsets[4],
+                start_boundary,
+                end_boundary,
+            };
+            prop_assert_eq!(StaticHeader::from_bytes(&header.as_bytes()).unwrap(), header);
+        }
+
+        #[test]
+        fn prop_apid_info_roundtrip(name in "[A-Z0-9-]{0,16}", values in any::<[u32; 4]>()) {
+            let info = ApidInfo {
+                name,
+                value: values[0],
+                pkt_tracker_start_idx: values[1],
+                pkts_reserved: values[2],
+                pkts_received: values[3],
+                pkts_duplicate: 0,
+                pkts_out_of_order: 0,
+                pkts_padding: 0,
+            };
+            prop_assert_eq!(ApidInfo::from_bytes(&info.as_bytes()).unwrap(), info);
+        }
+
+        #[test]
+        fn prop_packet_tracker_roundtrip(obs_time in any::<i64>(), values in any::<[i32; 4]>()) {
+            let tracker = PacketTracker {
+                obs_time,
+                sequence_number: values[0],
+                size: values[1],
+                offset: values[2],
+                fill_percent: values[3],
+            };
+            prop_assert_eq!(PacketTracker::from_bytes(&tracker.as_bytes()).unwrap(), tracker);
+        }
+
+        #[test]
+        fn prop_arbitrary_bytes_no_panic(data in proptest::collection::vec(any::<u8>(), 0..512)) {
+            parse_all(&data);
+        }
+
+        #[test]
+        fn prop_corrupt_common_rdr_no_panic(
+            corruptions in proptest::collection::vec((any::<Index>(), any::<u8>()), 0..16),
+            truncate in any::<Index>(),
+        ) {
+            let mut data = common_rdr_fixture().to_vec();
+            for (idx, byte) in corruptions {
+                let pos = idx.index(data.len());
+                data[pos] = byte;
+            }
+            data.truncate(truncate.index(data.len() + 1));
+            parse_all(&data);
+        }
+
+        #[test]
+        fn prop_corrupt_offsets_no_panic(
+            offsets in any::<[u32; 4]>(),
+            tracker in any::<[u8; PacketTracker::LEN]>(),
+        ) {
+            let mut data = common_rdr_fixture().to_vec();
+            for (idx, offset) in offsets.iter().enumerate() {
+                data[40 + idx * 4..44 + idx * 4].copy_from_slice(&offset.to_be_bytes());
+            }
+            let header = StaticHeader::from_bytes(common_rdr_fixture()).unwrap();
+            let pos = header.pkt_tracker_offset as usize;
+            data[pos..pos + PacketTracker::LEN].copy_from_slice(&tracker);
+            parse_all(&data);
+        }
+    }
+
+    #[test]
+    fn test_aggr_meta_orbit_numbers() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let time = Time::from(config.satellite.base_time);
+        let granules: Vec<GranuleMeta> = [7, 5, 6]
+            .into_iter()
+            .enumerate()
+            .map(|(idx, orbit)| {
+                let time = Time::from(time.iet_micros() + product.gran_len * idx as u64);
+                let mut meta = GranuleMeta::new(time, &config.satellite, product).unwrap();
+                meta.orbit_number = orbit;
+                meta
+            })
+            .collect();
+
+        let meta = AggrMeta::from_granules(&granules.iter().collect::<Vec<_>>());
+        assert_eq!(meta.begin_orbit_number, 5);
+        assert_eq!(meta.end_orbit_number, 7);
+        assert_eq!(meta.num_granules, 3);
+
+        let meta = AggrMeta::empty();
+        assert_eq!((meta.begin_orbit_number, meta.end_orbit_number), (0, 0));
+    }
+
+    #[test]
+    fn test_restamp_software() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let time = Time::from(config.satellite.base_time);
+        let mut meta = GranuleMeta::new(time, &config.satellite, product).unwrap();
+        meta.software_version = "I2.0.03.00".to_string();
+        assert_eq!(meta.source_software_version(), "I2.0.03.00");
+
+        meta.restamp_software();
+        assert_eq!(meta.software_version, GranuleMeta::SOFTWARE_VERSION);
+        assert_eq!(meta.source_software_version(), "I2.0.03.00");
+
+        // re-stamping again keeps the original source
+        meta.restamp_software();
+        assert_eq!(
+            meta.extra_attrs[GranuleMeta::SOURCE_SOFTWARE_VERSION_ATTR],
+            "I2.0.03.00"
+        );
+    }
+
+    #[test]
+    fn test_next_granule_version() {
+        assert_eq!(next_granule_version("A1").unwrap(), "A2");
+        assert_eq!(next_granule_version("A9").unwrap(), "B1");
+        assert_eq!(next_granule_version("C4").unwrap(), "C5");
+        assert!(next_granule_version("Z9").is_err());
+        for invalid in ["", "A", "A0", "a1", "AA", "A10"] {
+            assert!(next_granule_version(invalid).is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_granule_versions() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let time = Time::from(config.satellite.base_time);
+        let dat = crate::fixtures::viirs_packets(&time, 1);
+        let pkt = ccsds::spacepacket::decode_packets(&dat[..])
+            .next()
+            .unwrap()
+            .unwrap();
+        let mut rdr_data = RdrData::new(&config.satellite, product, &time);
+        rdr_data.add_packet(&time, pkt).unwrap();
+        let mut rdr = rdr_data.compile().unwrap();
+        assert_eq!(rdr.meta.version, "A1");
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let meta = || Meta::from_products(&[product.short_name.clone()], &config).unwrap();
+        crate::create_rdr(dir.path().join("a1.h5"), meta(), &[rdr.clone()]).unwrap();
+        rdr.meta.bump_version().unwrap();
+        assert_eq!(rdr.meta.version, "A2");
+        assert!(rdr.meta.reference_id.ends_with(":A2"));
+        crate::create_rdr(dir.path().join("a2.h5"), meta(), &[rdr.clone()]).unwrap();
+        std::fs::write(dir.path().join("invalid.h5"), b"not hdf5").unwrap();
+
+        let versions = granule_versions(dir.path()).unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(
+            versions[&(product.short_name.clone(), rdr.meta.id.clone())],
+            "A2"
+        );
+        assert!(rdr.meta.set_version("A0").is_err());
+    }
+
+    #[test]
+    fn test_out_of_order() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let time = Time::from(config.satellite.base_time);
+        let (_, packets) = duplicate_fixture(DuplicatePolicy::Keep);
+        let mut rdr_data = RdrData::new(&config.satellite, product, &time);
+
+        rdr_data
+            .add_packet(&Time::from_iet(time.iet() + 20), packets[2].clone())
+            .unwrap();
+        rdr_data
+            .add_packet(&Time::from_iet(time.iet() + 10), packets[0].clone())
+            .unwrap();
+
+        assert_eq!(rdr_data.apid_list[&826].pkts_out_of_order, 1);
+        assert_eq!(rdr_data.apid_list[&826].pkts_received, 2);
+    }
+
+    #[test]
+    fn test_compile_reserve_trackers() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RNSCA")
+            .unwrap();
+        assert!(product.reserve_trackers);
+        let time = Time::from(config.satellite.base_time);
+        // apid 11 w/ secondary header, unsegmented, 2 bytes of user data
+        let dat = [0x08, 0x0b, 0xc0, 0x01, 0x00, 0x01, 0x00, 0x00];
+        let pkt = ccsds::spacepacket::decode_packets(&dat[..])
+            .next()
+            .unwrap()
+            .unwrap();
+        let mut rdr_data = RdrData::new(&config.satellite, product, &time);
+        rdr_data.add_packet(&time, pkt).unwrap();
+
+        let rdr = rdr_data.compile().unwrap();
+        let common = CommonRdr::from_bytes(&rdr.data).unwrap();
+
+        let apids: Vec<u32> = common.apid_list.iter().map(|a| a.value).collect();
+        assert_eq!(apids, vec![0, 8, 11]);
+        for (idx, info) in common.apid_list.iter().enumerate() {
+            assert_eq!(info.pkts_reserved, 21, "{info:?}");
+            assert_eq!(info.pkt_tracker_start_idx, idx as u32 * 21, "{info:?}");
+            assert_eq!(info.pkts_received, u32::from(info.value == 11), "{info:?}");
+        }
+        assert_eq!(common.packet_trackers.len(), 63);
+        assert_eq!(common.packet_trackers[42].offset, 0);
+        assert_eq!(common.packet_trackers[42].size, 8);
+        for tracker in common.packet_trackers[..42]
+            .iter()
+            .chain(&common.packet_trackers[43..])
+        {
+            assert_eq!(tracker, &PacketTracker::FILL);
+        }
+        assert_eq!(crate::common_rdr_packets(&rdr.data).unwrap().len(), 1);
+    }
+
     #[test]
     fn test_get_granule_start() {
         // test data from an ERB rdr with expected value produced by edosl0util.rdrgen.get_granule_start
-        let pkt_time_iet: u64 = 2112504636060127;
-        let gran_len: u64 = 85350000;
-        let expected: u64 = 2112504609700000;
+        let pkt_time_iet = IetMicros(2112504636060127);
+        let gran_len = GranLen::from_micros(85350000);
+        let expected = IetMicros(2112504609700000);
         let zult = get_granule_start(pkt_time_iet, gran_len, BASE_TIME);
         assert_eq!(
             expected,
@@ -887,14 +2913,87 @@ mod tests {
 
     #[test]
     fn test_granule_id() {
-        let rdr_iet = 2112504394000000;
+        let rdr_iet = IetMicros(2112504394000000);
         let zult = granule_id("NPP", BASE_TIME, rdr_iet).unwrap();
         assert_eq!(zult, "NPP004144851600");
     }
 
+    #[test]
+    fn test_granule_id_to_iet() {
+        let rdr_iet = IetMicros(2112504394000000);
+        assert_eq!(
+            granule_id_to_iet("NPP", BASE_TIME, "NPP004144851600").unwrap(),
+            rdr_iet
+        );
+        assert!(granule_id_to_iet("NPP", BASE_TIME, "J01004144851600").is_err());
+        assert!(granule_id_to_iet("NPP", BASE_TIME, "NPP").is_err());
+    }
+
     mod meta {
         use super::*;
 
+        #[test]
+        fn test_is_packed_with() {
+            let config = get_default("npp").unwrap().unwrap();
+            let sat = &config.satellite;
+            let product = |id: &str| config.products.iter().find(|p| p.product_id == id).unwrap();
+            let (viirs, diary) = (product("RVIRS"), product("RNSCA"));
+
+            let start = sat.base_time + viirs.gran_len * 10;
+            let science = GranuleMeta::new(Time::from(start), sat, viirs).unwrap();
+            let packed = |begin| GranuleMeta::new(Time::from(begin), sat, diary).unwrap();
+
+            assert!(packed(start).is_packed_with(&science));
+            // starts before, but ends after, the science granule start
+            assert!(packed(start - diary.gran_len.micros() + 1).is_packed_with(&science));
+            assert!(!packed(start - diary.gran_len.micros()).is_packed_with(&science));
+            assert!(!packed(science.end_time_iet).is_packed_with(&science));
+        }
+
+        #[test]
+        fn test_packed_coverage() {
+            let config = get_default("npp").unwrap().unwrap();
+            let sat = &config.satellite;
+            let product = |id: &str| config.products.iter().find(|p| p.product_id == id).unwrap();
+            let (viirs, diary) = (product("RVIRS"), product("RNSCA"));
+            let mut meta = Meta::from_products(
+                &[viirs.short_name.clone(), diary.short_name.clone()],
+                &config,
+            )
+            .unwrap();
+
+            let start = sat.base_time + viirs.gran_len * 10;
+            let science = GranuleMeta::new(Time::from(start), sat, viirs).unwrap();
+            let first = get_granule_start(start, diary.gran_len, sat.base_time);
+            // 85.35s science granule requires 5 20s diary granules; omit the last and add one
+            // that is not on a granule boundary
+            let mut diaries = Vec::default();
+            for begin in [
+                first,
+                first + diary.gran_len,
+                first + diary.gran_len * 2,
+                first + diary.gran_len * 3,
+                first + 1,
+            ] {
+                diaries.push(GranuleMeta::new(Time::from(begin), sat, diary).unwrap());
+            }
+            meta.granules
+                .insert(viirs.short_name.clone(), vec![science]);
+            meta.granules.insert(diary.short_name.clone(), diaries);
+
+            let coverage = meta.packed_coverage(&config);
+
+            assert_eq!(coverage.primary_granules, 1);
+            assert_eq!(coverage.gaps.len(), 1, "{coverage}");
+            assert_eq!(coverage.gaps[0].packed_short_name, diary.short_name);
+            assert_eq!(
+                coverage.gaps[0].missing,
+                vec![(first + diary.gran_len * 4).0]
+            );
+            assert_eq!(coverage.misaligned.len(), 1, "{coverage}");
+            assert!(!coverage.is_complete());
+        }
+
         #[test]
         fn test_meta_from_file() {
             let path = fixture_file("RCRIS-RNSCA_j02_d20240627_t1930197_e1943077_b00001_c20240627194303766000_drlu_ops.h5");
@@ -914,9 +3013,325 @@ mod tests {
             assert_eq!(meta.granules["CRIS-SCIENCE-RDR"].len(), 24);
             let gran = &meta.granules["CRIS-SCIENCE-RDR"][0];
             assert_eq!(gran.packet_type.len(), 82);
+            assert!(gran.raw_dataset.is_none());
 
             dbg!(meta);
         }
+
+        #[test]
+        fn test_list() {
+            let path = fixture_file("RCRIS-RNSCA_j02_d20240627_t1930197_e1943077_b00001_c20240627194303766000_drlu_ops.h5");
+
+            let summaries = list(&path).unwrap();
+            let meta = Meta::from_file(&path).unwrap();
+
+            assert_eq!(summaries.len(), 2);
+            for summary in &summaries {
+                let granules = &meta.granules[&summary.short_name];
+                assert_eq!(summary.num_granules, granules.len());
+                assert_eq!(summary.granule_datasets.len(), granules.len());
+                assert_eq!(summary.raw_datasets.len(), granules.len());
+                assert_eq!(
+                    summary.begin_time_iet,
+                    granules.iter().map(|g| g.begin_time_iet).min()
+                );
+                assert_eq!(
+                    summary.end_time_iet,
+                    granules.iter().map(|g| g.end_time_iet).max()
+                );
+            }
+        }
+
+        #[test]
+        fn test_meta_from_file_lenient() {
+            let path = fixture_file("RCRIS-RNSCA_j02_d20240627_t1930197_e1943077_b00001_c20240627194303766000_drlu_ops.h5");
+            let expected = Meta::from_file(&path).unwrap();
+
+            let dir = tempfile::tempdir().unwrap();
+            let malformed = dir.path().join("malformed.h5");
+            std::fs::copy(&path, &malformed).unwrap();
+            {
+                let file = hdf5::File::open_rw(&malformed).unwrap();
+                file.unlink("Data_Products").unwrap();
+            }
+            assert!(Meta::from_file(&malformed).is_err());
+
+            let meta = Meta::from_file_lenient(&malformed, None, RawDatasetDetail::Size).unwrap();
+            assert_eq!(meta.platform, expected.platform);
+            assert_eq!(meta.products.len(), expected.products.len());
+            for (short_name, granules) in &expected.granules {
+                let mut granules: Vec<&GranuleMeta> = granules.iter().collect();
+                granules.sort_by_key(|g| g.begin_time_iet);
+                let mut reconstructed: Vec<&GranuleMeta> =
+                    meta.granules[short_name].iter().collect();
+                reconstructed.sort_by_key(|g| g.begin_time_iet);
+                assert_eq!(reconstructed.len(), granules.len(), "{short_name}");
+                for (want, got) in granules.iter().zip(reconstructed) {
+                    assert_eq!(got.id, want.id);
+                    assert_eq!(got.begin_time_iet, want.begin_time_iet);
+                    assert_eq!(got.end_time_iet, want.end_time_iet);
+                    assert_eq!(got.packet_type, want.packet_type);
+                    assert_eq!(got.packet_type_count, want.packet_type_count);
+                    assert!(got.raw_dataset.is_some());
+                }
+            }
+
+            // well-formed files read the same as strict mode
+            let meta = Meta::from_file_lenient(&path, None, RawDatasetDetail::None).unwrap();
+            for (short_name, granules) in &expected.granules {
+                assert_eq!(meta.granules[short_name].len(), granules.len());
+            }
+        }
+
+        #[test]
+        fn test_compile_matches_reference_diary() {
+            let path = fixture_file("RCRIS-RNSCA_j02_d20240627_t1930197_e1943077_b00001_c20240627194303766000_drlu_ops.h5");
+            let file = hdf5::File::open(&path).unwrap();
+            let data = file
+                .dataset("/All_Data/SPACECRAFT-DIARY-RDR_All/RawApplicationPackets_0")
+                .unwrap()
+                .read_raw::<u8>()
+                .unwrap();
+            let reference = CommonRdr::from_bytes(&data).unwrap();
+
+            let config = get_default("j02").unwrap().unwrap();
+            let product = config
+                .products
+                .iter()
+                .find(|p| p.product_id == "RNSCA")
+                .unwrap();
+            let time = Time::from_iet(reference.static_header.start_boundary);
+            let mut rdr_data = RdrData::new(&config.satellite, product, &time);
+            let storage = reference.static_header.ap_storage_offset as usize;
+            for info in &reference.apid_list {
+                let start = info.pkt_tracker_start_idx as usize;
+                let end = start + info.pkts_received as usize;
+                for tracker in &reference.packet_trackers[start..end] {
+                    let (Ok(offset), Ok(size)) = (
+                        usize::try_from(tracker.offset),
+                        usize::try_from(tracker.size),
+                    ) else {
+                        continue;
+                    };
+                    let bytes = &data[storage + offset..storage + offset + size];
+                    let pkt = ccsds::spacepacket::decode_packets(bytes)
+                        .next()
+                        .unwrap()
+                        .unwrap();
+                    let pkt_time = Time::from_iet(u64::try_from(tracker.obs_time).unwrap());
+                    rdr_data.add_packet(&pkt_time, pkt).unwrap();
+                }
+            }
+
+            let rdr = rdr_data.compile().unwrap();
+            let compiled = CommonRdr::from_bytes(&rdr.data).unwrap();
+
+            assert_eq!(compiled.apid_list.len(), reference.apid_list.len());
+            for (got, want) in compiled.apid_list.iter().zip(&reference.apid_list) {
+                assert_eq!(got.name, want.name);
+                assert_eq!(got.value, want.value);
+                assert_eq!(
+                    got.pkt_tracker_start_idx, want.pkt_tracker_start_idx,
+                    "{want:?}"
+                );
+                assert_eq!(got.pkts_reserved, want.pkts_reserved, "{want:?}");
+                assert_eq!(got.pkts_received, want.pkts_received, "{want:?}");
+            }
+            assert_eq!(
+                compiled.packet_trackers.len(),
+                reference.packet_trackers.len()
+            );
+        }
+
+        /// Live packet trackers of `rdr` for each apid, in apid list order.
+        fn live_trackers(rdr: &CommonRdr) -> Vec<Vec<(i64, i32, i32, i32)>> {
+            rdr.apid_list
+                .iter()
+                .map(|info| {
+                    let start = info.pkt_tracker_start_idx as usize;
+                    rdr.packet_trackers[start..start + info.pkts_received as usize]
+                        .iter()
+                        .filter(|t| t.offset >= 0)
+                        .map(|t| (t.obs_time, t.sequence_number, t.size, t.offset))
+                        .collect()
+                })
+                .collect()
+        }
+
+        #[test]
+        fn test_storage_order_matches_reference() {
+            let path = fixture_file("RCRIS-RNSCA_j02_d20240627_t1930197_e1943077_b00001_c20240627194303766000_drlu_ops.h5");
+            let file = hdf5::File::open(&path).unwrap();
+            let config = get_default("j02").unwrap().unwrap();
+
+            for product_id in ["RCRIS", "RNSCA"] {
+                let product = config
+                    .products
+                    .iter()
+                    .find(|p| p.product_id == product_id)
+                    .unwrap();
+                let data = file
+                    .dataset(&format!(
+                        "/All_Data/{}_All/RawApplicationPackets_0",
+                        product.short_name
+                    ))
+                    .unwrap()
+                    .read_raw::<u8>()
+                    .unwrap();
+                let reference = CommonRdr::from_bytes(&data).unwrap();
+                let storage = reference.static_header.ap_storage_offset as usize;
+
+                // Packets grouped by apid, as they are in the trackers, rather than in the
+                // reference storage order, so the storage order has to come from the policy
+                let mut packets = Vec::default();
+                for tracker in live_trackers(&reference).iter().flatten() {
+                    let (obs_time, _, size, offset) = *tracker;
+                    let start = storage + offset as usize;
+                    let pkt =
+                        ccsds::spacepacket::decode_packets(&data[start..start + size as usize])
+                            .next()
+                            .unwrap()
+                            .unwrap();
+                    packets.push((Time::from_iet(obs_time as u64), pkt));
+                }
+
+                let time = Time::from_iet(reference.static_header.start_boundary);
+                let matching: Vec<StorageOrder> = [
+                    StorageOrder::Arrival,
+                    StorageOrder::Time,
+                    StorageOrder::ApidThenTime,
+                ]
+                .into_iter()
+                .filter(|order| {
+                    let mut rdr_data =
+                        RdrData::new(&config.satellite, product, &time).with_storage_order(*order);
+                    for (pkt_time, pkt) in &packets {
+                        rdr_data.add_packet(pkt_time, pkt.clone()).unwrap();
+                    }
+                    let rdr = rdr_data.compile().unwrap();
+                    let compiled = CommonRdr::from_bytes(&rdr.data).unwrap();
+                    // Tracker offsets match only if packets are stored in the same order
+                    live_trackers(&compiled) == live_trackers(&reference)
+                })
+                .collect();
+                assert!(
+                    !matching.is_empty(),
+                    "no storage order reproduces the {product_id} reference layout"
+                );
+            }
+        }
+
+        #[test]
+        fn test_meta_from_file_with_raw_dataset() {
+            let path = fixture_file("RCRIS-RNSCA_j02_d20240627_t1930197_e1943077_b00001_c20240627194303766000_drlu_ops.h5");
+
+            let sizes = Meta::from_file_with(&path, RawDatasetDetail::Size).unwrap();
+            let checksums = Meta::from_file_with(&path, RawDatasetDetail::Checksum).unwrap();
+
+            for (short_name, granules) in &checksums.granules {
+                for (idx, gran) in granules.iter().enumerate() {
+                    let info = gran.raw_dataset.as_ref().unwrap();
+                    assert!(info.size > 0, "{short_name} {}", info.name);
+                    assert!(info.crc32.is_some());
+                    let size_info = sizes.granules[short_name][idx]
+                        .raw_dataset
+                        .as_ref()
+                        .unwrap();
+                    assert_eq!(size_info.name, info.name);
+                    assert_eq!(size_info.size, info.size);
+                    assert_eq!(size_info.crc32, None);
+                    assert_eq!(size_info.layout, None);
+
+                    let layout = info.layout.as_ref().unwrap();
+                    assert!(layout.valid, "{short_name} {}", info.name);
+                    assert_eq!(layout.header_bytes, StaticHeader::LEN as u64);
+                    assert_eq!(
+                        layout.header_bytes
+                            + layout.apid_list_bytes
+                            + layout.tracker_bytes
+                            + layout.ap_storage_bytes
+                            + layout.unused_bytes,
+                        info.size
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_raw_dataset_layout() {
+        let (rdr_data, _) = duplicate_fixture(DuplicatePolicy::Keep);
+        let data = rdr_data.compile().unwrap().data;
+        let header = StaticHeader::from_bytes(&data).unwrap();
+
+        let layout = RawDatasetLayout::from_bytes(&data).unwrap();
+        assert!(layout.valid);
+        assert_eq!(layout.num_apids, header.num_apids);
+        assert_eq!(
+            layout.apid_list_bytes,
+            u64::from(header.num_apids) * ApidInfo::LEN as u64
+        );
+        assert_eq!(
+            layout.num_trackers * PacketTracker::LEN as u64,
+            layout.tracker_bytes
+        );
+        assert_eq!(layout.ap_storage_bytes, u64::from(header.next_pkt_position));
+
+        // bogus offset past the end of the data
+        let mut bad = data.clone();
+        bad[48..52].copy_from_slice(&(data.len() as u32 + 100).to_be_bytes());
+        let layout = RawDatasetLayout::from_bytes(&bad).unwrap();
+        assert!(!layout.valid);
+        assert_eq!(layout.unused_bytes, 0);
+
+        assert!(RawDatasetLayout::from_bytes(&data[..10]).is_none());
+    }
+
+    #[test]
+    fn test_rdr_from_bytes() {
+        let (rdr_data, _) = duplicate_fixture(DuplicatePolicy::Keep);
+        let rdr = rdr_data.compile().unwrap();
+
+        let valid = Rdr::from_bytes(&rdr.product_id, rdr.meta.clone(), rdr.data.clone()).unwrap();
+        assert_eq!(valid.data, rdr.data);
+
+        let mut data = rdr.data.clone();
+        data.truncate(data.len() - 1);
+        assert!(Rdr::from_bytes(&rdr.product_id, rdr.meta.clone(), data).is_err());
+
+        let mut header = StaticHeader::from_bytes(&rdr.data).unwrap();
+        header.pkt_tracker_offset += 1;
+        let mut data = rdr.data.clone();
+        data[..StaticHeader::LEN].copy_from_slice(&header.as_bytes());
+        assert!(Rdr::from_bytes(&rdr.product_id, rdr.meta.clone(), data).is_err());
+
+        assert!(Rdr::from_bytes(&rdr.product_id, rdr.meta, vec![0u8; 10]).is_err());
+    }
+
+    #[test]
+    fn test_legacy_attrs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = hdf5::File::create(dir.path().join("legacy.h5")).unwrap();
+        let ds = file.new_dataset::<u8>().create("granule").unwrap();
+        ds.new_attr::<f64>()
+            .create("N_Percent_Missing_Data")
+            .unwrap()
+            .write_scalar(&12.5f64)
+            .unwrap();
+        ds.new_attr_builder()
+            .with_data(&ndarray::arr1(&[5u32]))
+            .create("n_beginning_time_iet")
+            .unwrap();
+        ds.new_attr::<VarLenAscii>()
+            .create("N_Granule_ID")
+            .unwrap()
+            .write_scalar(&VarLenAscii::from_ascii("NPP001").unwrap())
+            .unwrap();
+
+        assert_eq!(read_attr_f32(&ds, "N_Percent_Missing_Data").unwrap(), 12.5);
+        assert_eq!(read_attr_u64(&ds, "N_Beginning_Time_IET").unwrap(), 5);
+        assert_eq!(read_attr_string(&ds, "N_Granule_ID").unwrap(), "NPP001");
+        assert!(read_attr_string(&ds, "N_Missing").is_err());
     }
 
     #[test]
@@ -948,6 +3363,9 @@ mod tests {
             pkt_tracker_start_idx: 10,
             pkts_reserved: 20,
             pkts_received: 30,
+            pkts_duplicate: 0,
+            pkts_out_of_order: 0,
+            pkts_padding: 0,
         };
 
         let dat = info.as_bytes();
@@ -1019,5 +3437,95 @@ mod tests {
                 "Filename does not contain date string"
             );
         }
+
+        #[test]
+        fn test_filename_with_orbit() {
+            let fname =
+                "RVIRS_npp_d20200101_t1213141_e1214566_b00000_c20200101121314000000_origu_ops.h5";
+
+            assert_eq!(
+                filename_with_orbit(fname, 42),
+                "RVIRS_npp_d20200101_t1213141_e1214566_b00042_c20200101121314000000_origu_ops.h5"
+            );
+            assert_eq!(filename_with_orbit("RVIRS_npp.h5", 42), "RVIRS_npp.h5");
+        }
+
+        #[test]
+        fn test_meta_filename() {
+            let config = get_default("npp").unwrap().unwrap();
+            let sat = &config.satellite;
+            let product = |id: &str| config.products.iter().find(|p| p.product_id == id).unwrap();
+            let (viirs, diary) = (product("RVIRS"), product("RNSCA"));
+            let mut meta = Meta::from_products(
+                &[viirs.short_name.clone(), diary.short_name.clone()],
+                &config,
+            )
+            .unwrap();
+            let created = Time::from_epoch(Epoch::from_str("2020-01-01T12:13:14.123456Z").unwrap());
+
+            assert!(meta.filename(&config, "origin", "ops", &created).is_err());
+
+            let start = sat.base_time + viirs.gran_len * 10;
+            let mut science = GranuleMeta::new(Time::from(start), sat, viirs).unwrap();
+            science.orbit_number = 42;
+            let diaries = (0..5)
+                .map(|i| {
+                    let begin = get_granule_start(start, diary.gran_len, sat.base_time);
+                    GranuleMeta::new(Time::from(begin + diary.gran_len * i), sat, diary).unwrap()
+                })
+                .collect::<Vec<_>>();
+            meta.granules
+                .insert(viirs.short_name.clone(), vec![science.clone()]);
+            meta.granules.insert(diary.short_name.clone(), diaries);
+
+            let fname = meta.filename(&config, "origin", "ops", &created).unwrap();
+
+            // Times are from the science granule only
+            assert_eq!(
+                fname,
+                filename_with_orbit(
+                    &filename(
+                        "npp",
+                        "origin",
+                        "ops",
+                        &created,
+                        &science.begin,
+                        &science.end,
+                        &["RNSCA".to_string(), "RVIRS".to_string()],
+                    ),
+                    42
+                )
+            );
+            assert!(
+                fname.contains("_b00042_c20200101121314123456_oriu_ops.h5"),
+                "{fname}"
+            );
+
+            meta.granules.get_mut(&viirs.short_name).unwrap().clear();
+            let fname = meta.filename(&config, "origin", "ops", &created).unwrap();
+            assert!(fname.starts_with("RNSCA_npp_"), "{fname}");
+        }
+
+        #[test]
+        fn test_output_subdir() {
+            let time = Time::from_epoch(Epoch::from_str("2020-02-03T12:13:14Z").unwrap());
+            let subdir = |template: &str| {
+                output_subdir(template, "npp", "VIIRS-SCIENCE-RDR", "RVIRS", &time)
+            };
+
+            assert_eq!(
+                subdir("{satid}/{short_name}/{yyyymmdd}").unwrap(),
+                PathBuf::from("npp/VIIRS-SCIENCE-RDR/20200203")
+            );
+            assert_eq!(
+                subdir("rdr/{product_id}/{yyyy}/{doy}/{mm}{dd}").unwrap(),
+                PathBuf::from("rdr/RVIRS/2020/034/0203")
+            );
+            assert_eq!(subdir("").unwrap(), PathBuf::from(""));
+            assert!(subdir("{bogus}").is_err());
+            assert!(subdir("{satid").is_err());
+            assert!(subdir("/abs/{satid}").is_err());
+            assert!(subdir("../{satid}").is_err());
+        }
     }
 }
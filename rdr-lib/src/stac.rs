@@ -0,0 +1,199 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::{GranuleMeta, Meta, Time};
+
+/// Media type used for RDR file assets.
+pub const HDF5_MEDIA_TYPE: &str = "application/x-hdf5";
+
+/// Minimal [STAC](https://stacspec.org) Item for an RDR file or granule.
+///
+/// RDRs contain no geolocation so `geometry` is always null and there is no `bbox`.
+#[derive(Debug, Clone, Serialize)]
+pub struct StacItem {
+    #[serde(rename = "type")]
+    pub type_: &'static str,
+    pub stac_version: &'static str,
+    pub id: String,
+    pub geometry: Option<()>,
+    pub properties: StacProperties,
+    pub assets: BTreeMap<String, StacAsset>,
+    pub links: Vec<()>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StacProperties {
+    /// Always null, the time range is given by `start_datetime` and `end_datetime`
+    pub datetime: Option<String>,
+    pub start_datetime: String,
+    pub end_datetime: String,
+    pub created: String,
+    pub platform: String,
+    pub instruments: Vec<String>,
+    /// Collection short names, e.g., VIIRS-SCIENCE-RDR
+    #[serde(rename = "jpss:collections")]
+    pub collections: Vec<String>,
+    /// Granule ids, e.g., NPP001212012345
+    #[serde(rename = "jpss:granule_ids")]
+    pub granule_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StacAsset {
+    pub href: String,
+    #[serde(rename = "type")]
+    pub media_type: &'static str,
+    pub roles: Vec<&'static str>,
+}
+
+/// Format as RFC 3339 as required by STAC.
+fn datetime(time: &Time) -> String {
+    time.format_utc("%Y-%m-%dT%H:%M:%S.%fZ")
+}
+
+impl StacItem {
+    pub const TYPE: &'static str = "Feature";
+    pub const STAC_VERSION: &'static str = "1.0.0";
+
+    fn new(id: String, meta: &Meta, granules: &[&GranuleMeta], href: &str) -> Option<Self> {
+        let begin = granules.iter().map(|g| g.begin_time_iet).min()?;
+        let end = granules.iter().map(|g| g.end_time_iet).max()?;
+        let instruments: BTreeSet<String> = granules
+            .iter()
+            .map(|g| g.instrument.to_lowercase())
+            .collect();
+        let collections: BTreeSet<String> = granules.iter().map(|g| g.collection.clone()).collect();
+        let mut granule_ids: Vec<String> = granules.iter().map(|g| g.id.clone()).collect();
+        granule_ids.sort();
+        granule_ids.dedup();
+
+        Some(Self {
+            type_: Self::TYPE,
+            stac_version: Self::STAC_VERSION,
+            id,
+            geometry: None,
+            properties: StacProperties {
+                datetime: None,
+                start_datetime: datetime(&Time::from(begin)),
+                end_datetime: datetime(&Time::from(end)),
+                created: datetime(&meta.created),
+                platform: meta.platform.to_lowercase(),
+                instruments: instruments.into_iter().collect(),
+                collections: collections.into_iter().collect(),
+                granule_ids,
+            },
+            assets: BTreeMap::from([(
+                "data".to_string(),
+                StacAsset {
+                    href: href.to_string(),
+                    media_type: HDF5_MEDIA_TYPE,
+                    roles: vec!["data"],
+                },
+            )]),
+            links: Vec::default(),
+        })
+    }
+
+    /// Create an item for a whole RDR file spanning all the granules in `meta`.
+    ///
+    /// Returns `None` if `meta` has no granules.
+    #[must_use]
+    pub fn from_file(id: &str, meta: &Meta, href: &str) -> Option<Self> {
+        let granules: Vec<&GranuleMeta> = meta.granules.values().flatten().collect();
+        Self::new(id.to_string(), meta, &granules, href)
+    }
+
+    /// Create an item for a single granule in the file described by `meta`.
+    ///
+    /// The item id is `<collection>_<granule_id>`.
+    #[must_use]
+    pub fn from_granule(meta: &Meta, granule: &GranuleMeta, href: &str) -> Self {
+        let id = format!("{}_{}", granule.collection, granule.id);
+        Self::new(id, meta, &[granule], href).expect("a single granule always has a time range")
+    }
+
+    /// Create an item for each granule in `meta`, ordered by collection then granule time.
+    #[must_use]
+    pub fn from_granules(meta: &Meta, href: &str) -> Vec<Self> {
+        let mut granules: Vec<&GranuleMeta> = meta.granules.values().flatten().collect();
+        granules.sort_by(|a, b| {
+            (&a.collection, a.begin_time_iet).cmp(&(&b.collection, b.begin_time_iet))
+        });
+        granules
+            .into_iter()
+            .map(|g| Self::from_granule(meta, g, href))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_default;
+
+    fn meta() -> Meta {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let mut meta = Meta::from_products(&[product.short_name.clone()], &config).unwrap();
+        for num in [1, 0] {
+            let time = Time::from(config.satellite.base_time + product.gran_len * num);
+            meta.granules
+                .get_mut(&product.short_name)
+                .unwrap()
+                .push(GranuleMeta::new(time, &config.satellite, product).unwrap());
+        }
+        meta
+    }
+
+    #[test]
+    fn test_from_file() {
+        let meta = meta();
+        let item = StacItem::from_file("test", &meta, "file.h5").unwrap();
+        let value = serde_json::to_value(&item).unwrap();
+
+        assert_eq!(value["type"], "Feature");
+        assert!(value["geometry"].is_null());
+        assert!(value["properties"]["datetime"].is_null());
+        assert_eq!(value["properties"]["platform"], "npp");
+        assert_eq!(value["properties"]["instruments"][0], "viirs");
+        assert_eq!(
+            value["properties"]["jpss:granule_ids"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(value["assets"]["data"]["href"], "file.h5");
+        assert!(value["properties"]["start_datetime"]
+            .as_str()
+            .unwrap()
+            .starts_with("2011-10-23T"));
+    }
+
+    #[test]
+    fn test_from_granules() {
+        let meta = meta();
+        let items = StacItem::from_granules(&meta, "file.h5");
+
+        assert_eq!(items.len(), 2);
+        assert!(items[0].properties.start_datetime < items[1].properties.start_datetime);
+        assert_eq!(
+            items[0].id,
+            format!("VIIRS-SCIENCE-RDR_{}", items[0].properties.granule_ids[0])
+        );
+        assert!(StacItem::from_file(
+            "empty",
+            &Meta {
+                granules: Default::default(),
+                ..meta
+            },
+            "x"
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,893 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashSet},
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::{
+    config::Config,
+    error::Result,
+    rdr::{ApidTimeSkew, CommonRdr, RawDatasetDetail},
+    GranuleMeta, IetMicros, Meta, Rdr, Time,
+};
+
+/// Granule size statistics for a single collection.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CollectionStats {
+    pub num_granules: usize,
+    pub min_bytes: usize,
+    pub max_bytes: usize,
+    pub total_bytes: usize,
+    /// Number of scans, for collections where scans are counted, e.g., VIIRS science
+    pub scans: u64,
+    /// Number of incomplete scans, for collections where scans are counted
+    pub incomplete_scans: u64,
+}
+
+impl CollectionStats {
+    pub fn add(&mut self, rdr: &Rdr) {
+        let size = rdr.data.len();
+        if self.num_granules == 0 {
+            self.min_bytes = size;
+            self.max_bytes = size;
+        } else {
+            self.min_bytes = std::cmp::min(self.min_bytes, size);
+            self.max_bytes = std::cmp::max(self.max_bytes, size);
+        }
+        self.num_granules += 1;
+        self.total_bytes += size;
+        if let Some(scans) = &rdr.meta.scans {
+            self.scans += u64::from(scans.num_scans);
+            self.incomplete_scans += u64::from(scans.incomplete_scans);
+        }
+    }
+
+    #[must_use]
+    pub fn mean_bytes(&self) -> usize {
+        if self.num_granules == 0 {
+            return 0;
+        }
+        self.total_bytes / self.num_granules
+    }
+}
+
+/// Statistics for the packet groups read by a [crate::PacketTimeIter].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PacketTimeStats {
+    /// Number of packet groups read
+    pub groups: usize,
+    /// Number of packets in groups with a valid time
+    pub packets: usize,
+    /// Number of groups skipped because the time of their first packet could not be decoded
+    pub groups_dropped: usize,
+    /// Number of packets in skipped groups
+    pub packets_dropped: usize,
+    /// Earliest packet group time as IET microseconds
+    pub first_time_iet: Option<u64>,
+    /// Latest packet group time as IET microseconds
+    pub last_time_iet: Option<u64>,
+}
+
+impl PacketTimeStats {
+    pub(crate) fn add_group(&mut self, num_packets: usize, time: &Time) {
+        let iet = time.iet();
+        self.groups += 1;
+        self.packets += num_packets;
+        self.first_time_iet = Some(self.first_time_iet.map_or(iet, |t| t.min(iet)));
+        self.last_time_iet = Some(self.last_time_iet.map_or(iet, |t| t.max(iet)));
+    }
+
+    pub(crate) fn drop_group(&mut self, num_packets: usize) {
+        self.groups += 1;
+        self.groups_dropped += 1;
+        self.packets_dropped += num_packets;
+    }
+}
+
+/// Summary of an RDR creation run.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CreateSummary {
+    /// Collection short name to stats for granules written
+    pub collections: BTreeMap<String, CollectionStats>,
+    pub files_written: usize,
+    /// Number of packets processed
+    pub packets: usize,
+    /// Packet groups read from the input, including groups dropped because their time could
+    /// not be decoded
+    pub packet_times: PacketTimeStats,
+    /// Number of packets that could not be added to a granule
+    pub dropped_packets: usize,
+    /// Number of packets quarantined due to out of bounds packet times
+    pub quarantined_packets: usize,
+    /// Number of duplicate packets, e.g., from multi-station input, dropped or replaced
+    /// according to the duplicate policy
+    pub duplicate_packets: usize,
+    /// Number of granules below their product's minimum packet count, which are quarantined
+    /// or skipped rather than written to the normal output
+    pub undersized_granules: usize,
+    /// Number of files removed because they failed verification after being written
+    pub verify_failures: usize,
+    /// Number of packed granules pruned from the collector without being packed with a
+    /// primary granule
+    pub pruned_packed_granules: usize,
+    /// Number of packets with times before the spacecraft base time, which are skipped,
+    /// clamped, or fail collection according to the base time policy
+    pub before_base_time_packets: usize,
+    pub elapsed: Duration,
+}
+
+impl CreateSummary {
+    /// Record the granules for a written file.
+    pub fn add_file(&mut self, rdrs: &[Rdr]) {
+        self.files_written += 1;
+        for rdr in rdrs {
+            self.collections
+                .entry(rdr.meta.collection.clone())
+                .or_default()
+                .add(rdr);
+        }
+    }
+}
+
+impl Display for CreateSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "files={} packets={} packet_groups={} dropped_groups={} dropped_packets={} quarantined_packets={} duplicate_packets={} undersized_granules={} verify_failures={} pruned_packed_granules={} before_base_time_packets={} elapsed={:.3}s",
+            self.files_written,
+            self.packets,
+            self.packet_times.groups,
+            self.packet_times.groups_dropped,
+            self.dropped_packets,
+            self.quarantined_packets,
+            self.duplicate_packets,
+            self.undersized_granules,
+            self.verify_failures,
+            self.pruned_packed_granules,
+            self.before_base_time_packets,
+            self.elapsed.as_secs_f64()
+        )?;
+        if let (Some(first), Some(last)) = (
+            self.packet_times.first_time_iet,
+            self.packet_times.last_time_iet,
+        ) {
+            let fmt = "%Y-%m-%dT%H:%M:%S.%fZ";
+            write!(
+                f,
+                "\npacket times: first={} last={}",
+                Time::from_iet(first).format_utc(fmt),
+                Time::from_iet(last).format_utc(fmt)
+            )?;
+        }
+        for (short_name, stats) in &self.collections {
+            write!(
+                f,
+                "\n{short_name}: granules={} min_bytes={} mean_bytes={} max_bytes={}",
+                stats.num_granules,
+                stats.min_bytes,
+                stats.mean_bytes(),
+                stats.max_bytes
+            )?;
+            if stats.scans > 0 {
+                write!(
+                    f,
+                    " scans={} incomplete_scans={}",
+                    stats.scans, stats.incomplete_scans
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of reading a single aggregation input.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum InputStatus {
+    /// Input was read and contributed `granules` granules to the output
+    Ok { granules: usize },
+    /// Input could not be read and was skipped entirely
+    Failed { reason: String },
+}
+
+/// Status of a single aggregation input.
+#[derive(Debug, Clone, Serialize)]
+pub struct InputReport {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub status: InputStatus,
+}
+
+/// A granule from an otherwise readable input that was not aggregated.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedGranule {
+    pub input: PathBuf,
+    pub short_name: String,
+    pub granule_id: String,
+    pub reason: String,
+}
+
+/// A granule that was not aggregated because a later version of the same granule, i.e., with
+/// the same granule id and a later creation date/time, was aggregated instead.
+#[derive(Debug, Clone, Serialize)]
+pub struct SupersededGranule {
+    pub input: PathBuf,
+    pub short_name: String,
+    pub granule_id: String,
+    /// `N_Creation_Date` and `N_Creation_Time` of the superseded granule
+    pub creation: String,
+    /// Input containing the granule version that was aggregated
+    pub superseded_by: PathBuf,
+}
+
+/// A time gap between consecutive aggregated granules of a collection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GranuleGap {
+    pub short_name: String,
+    /// Granule before the gap
+    pub prev_granule_id: String,
+    /// Granule after the gap
+    pub next_granule_id: String,
+    /// Microseconds from the end of the earlier granule to the beginning of the later granule
+    pub delta: u64,
+    /// Granule ids of the granule boundaries missing within the gap; may be empty for gaps
+    /// shorter than a granule, e.g., misaligned granules
+    pub missing: Vec<String>,
+}
+
+/// Report of an aggregation run.
+///
+/// Inputs that fail to read, and granules that cannot be aggregated, are skipped rather than
+/// failing the run. Use [AggrReport::is_complete] to tell a complete aggregation from a
+/// partial one.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AggrReport {
+    /// Files written
+    pub outputs: Vec<PathBuf>,
+    /// Status for each input, in the order provided
+    pub inputs: Vec<InputReport>,
+    /// Granules that were skipped
+    pub skipped: Vec<SkippedGranule>,
+    /// Granules replaced by a later version of the same granule. Superseded granules are
+    /// expected when aggregating reprocessed granules, so do not make a report incomplete.
+    pub superseded: Vec<SupersededGranule>,
+    /// Number of aggregated granules by the software that produced them. See
+    /// [GranuleMeta::source_software_version].
+    pub software_versions: BTreeMap<String, usize>,
+    /// Gaps between aggregated granules, if contiguity was checked
+    pub gaps: Vec<GranuleGap>,
+}
+
+impl AggrReport {
+    /// Record an input that was read successfully.
+    pub fn input_ok(&mut self, path: impl Into<PathBuf>, granules: usize) {
+        self.inputs.push(InputReport {
+            path: path.into(),
+            status: InputStatus::Ok { granules },
+        });
+    }
+
+    /// Record an input that could not be read.
+    pub fn input_failed(&mut self, path: impl Into<PathBuf>, reason: impl Display) {
+        self.inputs.push(InputReport {
+            path: path.into(),
+            status: InputStatus::Failed {
+                reason: reason.to_string(),
+            },
+        });
+    }
+
+    /// Record a granule that was skipped.
+    pub fn skip_granule(
+        &mut self,
+        input: impl Into<PathBuf>,
+        short_name: &str,
+        granule_id: &str,
+        reason: impl Display,
+    ) {
+        self.skipped.push(SkippedGranule {
+            input: input.into(),
+            short_name: short_name.to_string(),
+            granule_id: granule_id.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Record a granule that was replaced by a later version of the same granule.
+    pub fn supersede_granule(&mut self, granule: SupersededGranule) {
+        self.superseded.push(granule);
+    }
+
+    /// Record the software version of an aggregated granule.
+    pub fn add_software_version(&mut self, version: &str) {
+        *self
+            .software_versions
+            .entry(version.to_string())
+            .or_default() += 1;
+    }
+
+    /// Record a gap between aggregated granules.
+    pub fn add_gap(&mut self, gap: GranuleGap) {
+        self.gaps.push(gap);
+    }
+
+    /// True if no gaps between aggregated granules were recorded.
+    #[must_use]
+    pub fn is_contiguous(&self) -> bool {
+        self.gaps.is_empty()
+    }
+
+    /// Number of inputs that could not be read.
+    #[must_use]
+    pub fn failed_inputs(&self) -> usize {
+        self.inputs
+            .iter()
+            .filter(|i| matches!(i.status, InputStatus::Failed { .. }))
+            .count()
+    }
+
+    /// True if all inputs were read and no granules were skipped.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.failed_inputs() == 0 && self.skipped.is_empty()
+    }
+}
+
+impl Display for AggrReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "outputs={} inputs={} failed_inputs={} skipped_granules={} superseded_granules={} complete={}",
+            self.outputs.len(),
+            self.inputs.len(),
+            self.failed_inputs(),
+            self.skipped.len(),
+            self.superseded.len(),
+            self.is_complete()
+        )?;
+        for input in &self.inputs {
+            match &input.status {
+                InputStatus::Ok { granules } => {
+                    write!(f, "\n{:?}: ok granules={granules}", input.path)?
+                }
+                InputStatus::Failed { reason } => {
+                    write!(f, "\n{:?}: failed: {reason}", input.path)?
+                }
+            }
+        }
+        for granule in &self.skipped {
+            write!(
+                f,
+                "\n{:?}: skipped {}/{}: {}",
+                granule.input, granule.short_name, granule.granule_id, granule.reason
+            )?;
+        }
+        for granule in &self.superseded {
+            write!(
+                f,
+                "\n{:?}: superseded {}/{} created {} by {:?}",
+                granule.input,
+                granule.short_name,
+                granule.granule_id,
+                granule.creation,
+                granule.superseded_by
+            )?;
+        }
+        for gap in &self.gaps {
+            write!(
+                f,
+                "\ngap in {} of {}us between {} and {}",
+                gap.short_name, gap.delta, gap.prev_granule_id, gap.next_granule_id
+            )?;
+            if !gap.missing.is_empty() {
+                write!(f, "; missing {}", gap.missing.join(" "))?;
+            }
+        }
+        if self.software_versions.len() > 1 {
+            let versions: Vec<String> = self
+                .software_versions
+                .iter()
+                .map(|(version, count)| format!("{version}={count}"))
+                .collect();
+            write!(f, "\nsoftware versions: {}", versions.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A primary, e.g., science, granule missing packed granules required by the packing rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackedCoverageGap {
+    pub short_name: String,
+    pub granule_id: String,
+    pub packed_short_name: String,
+    /// Begin times, in IET microseconds, of the missing packed granules
+    pub missing: Vec<u64>,
+}
+
+/// A packed granule that is not on a granule boundary or does not overlap any primary
+/// granule it is packed with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MisalignedGranule {
+    pub short_name: String,
+    pub granule_id: String,
+    pub reason: String,
+}
+
+/// Report of packed granule coverage, e.g., SPACECRAFT-DIARY granules, for the primary
+/// granules in an RDR.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PackedCoverage {
+    /// Number of primary granules checked
+    pub primary_granules: usize,
+    /// Primary granules lacking required packed granules
+    pub gaps: Vec<PackedCoverageGap>,
+    pub misaligned: Vec<MisalignedGranule>,
+}
+
+impl PackedCoverage {
+    /// True if all primary granules have all required packed granules and all packed
+    /// granules are aligned.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.gaps.is_empty() && self.misaligned.is_empty()
+    }
+}
+
+impl Display for PackedCoverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "primary_granules={} gaps={} misaligned={} complete={}",
+            self.primary_granules,
+            self.gaps.len(),
+            self.misaligned.len(),
+            self.is_complete()
+        )?;
+        for gap in &self.gaps {
+            write!(
+                f,
+                "\n{}/{}: missing {} {} granules beginning {:?}",
+                gap.short_name,
+                gap.granule_id,
+                gap.missing.len(),
+                gap.packed_short_name,
+                gap.missing
+            )?;
+        }
+        for granule in &self.misaligned {
+            write!(
+                f,
+                "\n{}/{}: {}",
+                granule.short_name, granule.granule_id, granule.reason
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A gap or overlap between consecutive granules of a collection, possibly from different
+/// files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Discontinuity {
+    /// Earlier granule id
+    pub prev_granule_id: String,
+    /// File containing the earlier granule
+    pub prev_path: PathBuf,
+    /// Later granule id
+    pub next_granule_id: String,
+    /// File containing the later granule
+    pub next_path: PathBuf,
+    /// Microseconds from the end of the earlier granule to the beginning of the later granule;
+    /// negative for overlaps
+    pub delta: i64,
+}
+
+/// Granule timeline for a single collection across one or more files.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CollectionTimeline {
+    /// Number of distinct granules
+    pub num_granules: usize,
+    /// Number of files containing granules for the collection
+    pub num_files: usize,
+    /// Granules found in more than one file with the same id and times, e.g., packed
+    /// granules included with adjacent primary granules
+    pub duplicates: usize,
+    pub begin_time_iet: Option<IetMicros>,
+    pub end_time_iet: Option<IetMicros>,
+    pub gaps: Vec<Discontinuity>,
+    pub overlaps: Vec<Discontinuity>,
+}
+
+/// Continuity of the granules for each collection across a set of RDR files, e.g., a day of
+/// per-pass RDRs, identifying gaps and overlaps both within and between files.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ContinuityReport {
+    /// Collection short name to timeline
+    pub collections: BTreeMap<String, CollectionTimeline>,
+}
+
+impl ContinuityReport {
+    /// Stitch together the granules from the RDR metadata for each file in `files`.
+    pub fn new<'a, I>(files: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a Path, &'a Meta)>,
+    {
+        // short name to (granule, file) for all files
+        let mut granules: BTreeMap<&str, Vec<(&GranuleMeta, &Path)>> = BTreeMap::default();
+        for (path, meta) in files {
+            for (short_name, metas) in &meta.granules {
+                let entry = granules.entry(short_name).or_default();
+                entry.extend(metas.iter().map(|g| (g, path)));
+            }
+        }
+
+        let mut report = Self::default();
+        for (short_name, mut granules) in granules {
+            granules.sort_by_key(|(g, _)| (g.begin_time_iet, g.end_time_iet));
+            let mut timeline = CollectionTimeline {
+                num_files: granules
+                    .iter()
+                    .map(|(_, path)| *path)
+                    .collect::<HashSet<_>>()
+                    .len(),
+                begin_time_iet: granules.first().map(|(g, _)| g.begin_time_iet),
+                end_time_iet: granules.iter().map(|(g, _)| g.end_time_iet).max(),
+                ..Default::default()
+            };
+            // Granule with the latest end time so far
+            let mut prev: Option<(&GranuleMeta, &Path)> = None;
+            for (gran, path) in granules {
+                if let Some((prev_gran, prev_path)) = prev {
+                    if prev_gran.id == gran.id
+                        && prev_gran.begin_time_iet == gran.begin_time_iet
+                        && prev_gran.end_time_iet == gran.end_time_iet
+                    {
+                        timeline.duplicates += 1;
+                        continue;
+                    }
+                    let delta = gran.begin_time_iet.0 as i64 - prev_gran.end_time_iet.0 as i64;
+                    let discontinuity = || Discontinuity {
+                        prev_granule_id: prev_gran.id.clone(),
+                        prev_path: prev_path.to_path_buf(),
+                        next_granule_id: gran.id.clone(),
+                        next_path: path.to_path_buf(),
+                        delta,
+                    };
+                    match delta.cmp(&0) {
+                        Ordering::Greater => timeline.gaps.push(discontinuity()),
+                        Ordering::Less => timeline.overlaps.push(discontinuity()),
+                        Ordering::Equal => {}
+                    }
+                    if gran.end_time_iet < prev_gran.end_time_iet {
+                        timeline.num_granules += 1;
+                        continue;
+                    }
+                }
+                timeline.num_granules += 1;
+                prev = Some((gran, path));
+            }
+            report.collections.insert(short_name.to_string(), timeline);
+        }
+        report
+    }
+
+    /// True if no collection has gaps or overlaps.
+    #[must_use]
+    pub fn is_continuous(&self) -> bool {
+        self.collections
+            .values()
+            .all(|c| c.gaps.is_empty() && c.overlaps.is_empty())
+    }
+}
+
+impl Display for ContinuityReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "collections={} continuous={}",
+            self.collections.len(),
+            self.is_continuous()
+        )?;
+        for (short_name, c) in &self.collections {
+            write!(
+                f,
+                "\n{short_name}: granules={} files={} duplicates={} gaps={} overlaps={}",
+                c.num_granules,
+                c.num_files,
+                c.duplicates,
+                c.gaps.len(),
+                c.overlaps.len()
+            )?;
+            for (kind, items) in [("gap", &c.gaps), ("overlap", &c.overlaps)] {
+                for d in items {
+                    write!(
+                        f,
+                        "\n{short_name}: {kind} of {}us between {} in {:?} and {} in {:?}",
+                        d.delta.abs(),
+                        d.prev_granule_id,
+                        d.prev_path,
+                        d.next_granule_id,
+                        d.next_path
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A granule with packets outside of its static header time window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GranuleTimeSkew {
+    pub short_name: String,
+    pub granule_id: String,
+    /// Static header start boundary, IET microseconds
+    pub start_boundary: u64,
+    /// Static header end boundary, IET microseconds
+    pub end_boundary: u64,
+    pub apids: Vec<ApidTimeSkew>,
+}
+
+/// Report of packets whose packet tracker observation times are outside of their granule's
+/// static header start and end boundaries, e.g., from a bad packet time or granule boundary
+/// computation.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TimeSkewReport {
+    /// Number of granules checked
+    pub granules_checked: usize,
+    /// Granules with out of window packets
+    pub granules: Vec<GranuleTimeSkew>,
+}
+
+impl TimeSkewReport {
+    /// Check the time window of every granule in the RDR at `path`.
+    ///
+    /// The window of a granule with padding packets is widened by the padding window recorded
+    /// with the granule (see [crate::GranuleMeta::padding_window]), or, if `config` is provided,
+    /// the product's configured [crate::config::GranulePadding], so padding packets are not
+    /// reported.
+    ///
+    /// # Errors
+    /// If the file cannot be read or a granule is not a valid Common RDR.
+    pub fn from_file<P: AsRef<Path>>(path: P, config: Option<&Config>) -> Result<Self> {
+        let path = path.as_ref();
+        let meta = Meta::from_file_with(path, RawDatasetDetail::Size)?;
+        let file = hdf5::File::open(path)?;
+        let mut report = Self::default();
+        let mut short_names: Vec<&String> = meta.granules.keys().collect();
+        short_names.sort();
+        for short_name in short_names {
+            let configured = config
+                .and_then(|c| c.products.iter().find(|p| &p.short_name == short_name))
+                .and_then(|p| p.padding);
+            for granule in &meta.granules[short_name] {
+                let padding = granule.padding_window().or(configured).unwrap_or_default();
+                let Some(dataset) = &granule.raw_dataset else {
+                    continue;
+                };
+                if dataset.size == 0 {
+                    continue;
+                }
+                let data = file.dataset(&dataset.name)?.read_raw::<u8>()?;
+                let common = CommonRdr::from_bytes(&data)?;
+                report.granules_checked += 1;
+                let apids = common.out_of_window(padding.pre, padding.post);
+                if apids.is_empty() {
+                    continue;
+                }
+                report.granules.push(GranuleTimeSkew {
+                    short_name: short_name.clone(),
+                    granule_id: granule.id.clone(),
+                    start_boundary: common.static_header.start_boundary,
+                    end_boundary: common.static_header.end_boundary,
+                    apids,
+                });
+            }
+        }
+        Ok(report)
+    }
+
+    /// True if no granule has out of window packets.
+    #[must_use]
+    pub fn is_within_window(&self) -> bool {
+        self.granules.is_empty()
+    }
+}
+
+impl Display for TimeSkewReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "granules_checked={} skewed={} within_window={}",
+            self.granules_checked,
+            self.granules.len(),
+            self.is_within_window()
+        )?;
+        for granule in &self.granules {
+            for apid in &granule.apids {
+                write!(
+                    f,
+                    "\n{}/{}: apid {} ({}) has {} packets before and {} after window {}..{}; \
+                     out of window obs times {:?}..{:?}",
+                    granule.short_name,
+                    granule.granule_id,
+                    apid.apid,
+                    apid.name,
+                    apid.before,
+                    apid.after,
+                    granule.start_boundary,
+                    granule.end_boundary,
+                    apid.min_obs_time,
+                    apid.max_obs_time,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggr_report() {
+        let mut report = AggrReport::default();
+        report.input_ok("a.h5", 2);
+        assert!(report.is_complete());
+
+        report.input_failed("b.h5", "bad file");
+        report.skip_granule("a.h5", "RNSCA", "NPP001", "no product");
+        assert!(!report.is_complete());
+        assert_eq!(report.failed_inputs(), 1);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["inputs"][0]["status"], "ok");
+        assert_eq!(json["inputs"][0]["granules"], 2);
+        assert_eq!(json["inputs"][1]["status"], "failed");
+        assert_eq!(json["inputs"][1]["reason"], "bad file");
+
+        report.supersede_granule(SupersededGranule {
+            input: "a.h5".into(),
+            short_name: "RNSCA".into(),
+            granule_id: "NPP002".into(),
+            creation: "20240101 000000.000000Z".into(),
+            superseded_by: "c.h5".into(),
+        });
+        assert_eq!(report.superseded.len(), 1);
+        assert!(report.to_string().contains("superseded RNSCA/NPP002"));
+
+        assert!(report.is_contiguous());
+        report.add_gap(GranuleGap {
+            short_name: "RNSCA".into(),
+            prev_granule_id: "NPP001".into(),
+            next_granule_id: "NPP004".into(),
+            delta: 40_000_000,
+            missing: vec!["NPP002".into(), "NPP003".into()],
+        });
+        assert!(!report.is_contiguous());
+        assert!(report.to_string().contains(
+            "gap in RNSCA of 40000000us between NPP001 and NPP004; missing NPP002 NPP003"
+        ));
+
+        report.add_software_version("I2.0.03.00");
+        assert!(!report.to_string().contains("software versions"));
+        report.add_software_version("I2.0.03.00");
+        report.add_software_version("rdr0.1.0");
+        assert!(report
+            .to_string()
+            .contains("software versions: I2.0.03.00=2 rdr0.1.0=1"));
+    }
+
+    #[test]
+    fn test_continuity_report() {
+        let config = crate::config::get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let base = config.satellite.base_time;
+        let meta = |nums: &[u64]| {
+            let mut meta = Meta::from_products(&[product.short_name.clone()], &config).unwrap();
+            for num in nums {
+                let time = crate::Time::from(base + product.gran_len * *num);
+                meta.granules
+                    .get_mut(&product.short_name)
+                    .unwrap()
+                    .push(GranuleMeta::new(time, &config.satellite, product).unwrap());
+            }
+            meta
+        };
+        // a.h5 and b.h5 share granule 2, then a gap before c.h5
+        let (a, b, c) = (meta(&[0, 1, 2]), meta(&[2, 3]), meta(&[5]));
+        let mut overlap = meta(&[]);
+        let mut gran = GranuleMeta::new(
+            crate::Time::from(base + product.gran_len * 3 + 10),
+            &config.satellite,
+            product,
+        )
+        .unwrap();
+        gran.id = "other".to_string();
+        overlap
+            .granules
+            .get_mut(&product.short_name)
+            .unwrap()
+            .push(gran);
+
+        let report = ContinuityReport::new([
+            (Path::new("c.h5"), &c),
+            (Path::new("a.h5"), &a),
+            (Path::new("b.h5"), &b),
+        ]);
+        let timeline = &report.collections[&product.short_name];
+        assert_eq!(timeline.num_granules, 5);
+        assert_eq!(timeline.num_files, 3);
+        assert_eq!(timeline.duplicates, 1);
+        assert!(timeline.overlaps.is_empty());
+        assert_eq!(timeline.gaps.len(), 1);
+        assert_eq!(timeline.gaps[0].prev_path, PathBuf::from("b.h5"));
+        assert_eq!(timeline.gaps[0].next_path, PathBuf::from("c.h5"));
+        assert_eq!(timeline.gaps[0].delta, product.gran_len.micros() as i64);
+        assert!(!report.is_continuous());
+
+        let report =
+            ContinuityReport::new([(Path::new("b.h5"), &b), (Path::new("overlap.h5"), &overlap)]);
+        let timeline = &report.collections[&product.short_name];
+        assert_eq!(timeline.overlaps.len(), 1);
+        assert_eq!(
+            timeline.overlaps[0].delta,
+            -(product.gran_len.micros() as i64) + 10
+        );
+    }
+
+    #[test]
+    fn test_time_skew_report_from_file() {
+        let config = crate::config::get_default("npp").unwrap().unwrap();
+        let product = crate::fixtures::product(&config).unwrap();
+        let meta = || Meta::from_products(&[product.short_name.clone()], &config).unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+
+        let rdrs: Vec<Rdr> = crate::fixtures::viirs_granules(&config, 0, 2)
+            .unwrap()
+            .into_iter()
+            .map(|mut rdrs| rdrs.remove(0))
+            .collect();
+        let path = dir.path().join("ok.h5");
+        crate::create_rdr(&path, meta(), &rdrs).unwrap();
+
+        let report = TimeSkewReport::from_file(&path, Some(&config)).unwrap();
+        assert_eq!(report.granules_checked, 2);
+        assert!(report.is_within_window(), "{report}");
+
+        // A packet 10s after the end of its granule
+        let start = Time::from(config.satellite.base_time);
+        let late = Time::from(config.satellite.base_time + product.gran_len + 10_000_000);
+        let dat = crate::fixtures::viirs_packets(&late, 1);
+        let pkt = ccsds::spacepacket::decode_packets(&dat[..])
+            .next()
+            .unwrap()
+            .unwrap();
+        let mut rdr_data = crate::RdrData::new(&config.satellite, product, &start);
+        rdr_data.add_packet(&late, pkt).unwrap();
+        let rdr = rdr_data.compile().unwrap();
+        let path = dir.path().join("skewed.h5");
+        crate::create_rdr(&path, meta(), &[rdr.clone()]).unwrap();
+
+        let report = TimeSkewReport::from_file(&path, None).unwrap();
+        assert_eq!(report.granules_checked, 1);
+        assert!(!report.is_within_window());
+        assert_eq!(report.granules.len(), 1);
+        let granule = &report.granules[0];
+        assert_eq!(granule.short_name, product.short_name);
+        assert_eq!(granule.granule_id, rdr.meta.id);
+        assert_eq!(granule.apids.len(), 1);
+        assert_eq!(granule.apids[0].apid, u32::from(crate::fixtures::APID));
+        assert_eq!((granule.apids[0].before, granule.apids[0].after), (0, 1));
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use ccsds::spacepacket::{Apid, Packet};
+
+use crate::{error::Result, IetMicros};
+
+/// Location and identifying information for a single packet in a [PacketStorage].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StoredPacket {
+    pub time: IetMicros,
+    pub apid: Apid,
+    pub sequence_id: u16,
+    /// Offset to the packet bytes in the storage backend
+    pub offset: u64,
+    /// Size of the packet in bytes
+    pub size: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Backend {
+    Memory(Vec<u8>),
+    /// Anonymous temporary file that is removed when the last handle is dropped. The handle
+    /// is shared so clones of the storage remain valid.
+    Disk(Arc<Mutex<File>>),
+}
+
+/// Application packet storage for a single Common RDR.
+///
+/// Packets are kept in the order they were added. By default the packet bytes are kept in
+/// memory, but they may instead be spilled to a temporary file so that only the packet index
+/// is kept in memory, which keeps memory bounded for large backlogs of granules.
+#[derive(Debug, Clone)]
+pub(crate) struct PacketStorage {
+    packets: Vec<StoredPacket>,
+    backend: Backend,
+}
+
+impl Default for PacketStorage {
+    fn default() -> Self {
+        Self::memory()
+    }
+}
+
+impl PacketStorage {
+    /// Storage that keeps all packet data in memory.
+    #[must_use]
+    pub fn memory() -> Self {
+        Self {
+            packets: Vec::default(),
+            backend: Backend::Memory(Vec::default()),
+        }
+    }
+
+    /// Storage that spills packet data to a temporary file in `dir`.
+    ///
+    /// # Errors
+    /// If the spill file cannot be created.
+    pub fn spill_in<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let file = tempfile::tempfile_in(dir)?;
+        Ok(Self {
+            packets: Vec::default(),
+            backend: Backend::Disk(Arc::new(Mutex::new(file))),
+        })
+    }
+
+    /// Add a packet with the provided time.
+    ///
+    /// # Errors
+    /// If writing the packet to the spill file fails.
+    pub fn push(&mut self, time: IetMicros, pkt: &Packet) -> Result<()> {
+        let offset = match &mut self.backend {
+            Backend::Memory(buf) => {
+                let offset = buf.len() as u64;
+                buf.extend_from_slice(&pkt.data);
+                offset
+            }
+            Backend::Disk(file) => {
+                let mut file = file.lock().expect("spill file lock poisoned");
+                let offset = file.seek(SeekFrom::End(0))?;
+                file.write_all(&pkt.data)?;
+                offset
+            }
+        };
+        self.packets.push(StoredPacket {
+            time,
+            apid: pkt.header.apid,
+            sequence_id: pkt.header.sequence_id,
+            offset,
+            size: pkt.data.len(),
+        });
+        Ok(())
+    }
+
+    /// Index of all packets in the order they were added.
+    #[must_use]
+    pub fn packets(&self) -> &[StoredPacket] {
+        &self.packets
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    /// Append the bytes of `packets`, in the order provided, to `buf`.
+    ///
+    /// # Errors
+    /// If reading the spill file fails.
+    pub fn read_into(&self, packets: &[StoredPacket], buf: &mut Vec<u8>) -> Result<()> {
+        match &self.backend {
+            Backend::Memory(data) => {
+                for pkt in packets {
+                    let start = usize::try_from(pkt.offset).expect("memory offset fits usize");
+                    buf.extend_from_slice(&data[start..start + pkt.size]);
+                }
+            }
+            Backend::Disk(file) => {
+                let mut file = file.lock().expect("spill file lock poisoned");
+                for pkt in packets {
+                    let start = buf.len();
+                    buf.resize(start + pkt.size, 0);
+                    file.seek(SeekFrom::Start(pkt.offset))?;
+                    file.read_exact(&mut buf[start..])?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ccsds::spacepacket::decode_packets;
+
+    use super::*;
+
+    fn packets() -> Vec<Packet> {
+        let mut dat: Vec<u8> = Vec::default();
+        for (seq, fill) in [(1u8, 0xaa), (2, 0xbb), (3, 0xcc)] {
+            // apid 826 w/ secondary header, unsegmented, 4 bytes of user data
+            dat.extend_from_slice(&[0x0b, 0x3a, 0xc0, seq, 0x00, 0x03]);
+            dat.extend_from_slice(&[fill; 4]);
+        }
+        decode_packets(&dat[..]).filter_map(|p| p.ok()).collect()
+    }
+
+    fn check_roundtrip(mut storage: PacketStorage) {
+        let packets = packets();
+        let mut expected: Vec<u8> = Vec::default();
+        for (idx, pkt) in packets.iter().enumerate() {
+            storage.push(IetMicros(idx as u64), pkt).unwrap();
+            expected.extend_from_slice(&pkt.data);
+        }
+
+        assert_eq!(storage.len(), 3);
+        assert_eq!(storage.packets().iter().map(|p| p.size).sum::<usize>(), 30);
+        assert_eq!(storage.packets()[1].sequence_id, 2);
+        assert_eq!(storage.packets()[2].apid, 826);
+
+        let mut zult = Vec::default();
+        storage.read_into(storage.packets(), &mut zult).unwrap();
+        assert_eq!(zult, expected);
+
+        let mut zult = Vec::default();
+        storage
+            .read_into(&storage.packets()[2..], &mut zult)
+            .unwrap();
+        assert_eq!(zult, packets[2].data);
+    }
+
+    #[test]
+    fn test_memory() {
+        check_roundtrip(PacketStorage::memory());
+    }
+
+    #[test]
+    fn test_spilled() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let storage = PacketStorage::spill_in(dir.path()).unwrap();
+        assert!(matches!(storage.backend, Backend::Disk(_)));
+        check_roundtrip(storage);
+    }
+}
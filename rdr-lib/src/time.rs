@@ -1,10 +1,147 @@
-use std::ops::Deref;
+use std::fmt::Display;
+use std::ops::{Add, Deref, Mul, Sub};
 use std::str::FromStr;
 
 use hifitime::efmt::{Format, Formatter};
 use hifitime::{Epoch, TimeScale};
 use serde::{Deserialize, Serialize};
 
+/// IET microseconds, i.e., TAI microseconds since Jan 1, 1958.
+///
+/// Used rather than a bare `u64` where a time is passed as a number so an IET value cannot be
+/// used where a UTC value is expected, or vice versa. Adding or subtracting a `u64` offsets the
+/// time by that many microseconds and subtracting two times gives the microseconds between
+/// them. Subtraction saturates at zero rather than underflowing; use [IetMicros::checked_sub]
+/// or [IetMicros::abs_diff] where a time may be before the one subtracted.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct IetMicros(pub u64);
+
+/// UTC microseconds since Jan 1, 1970. See [IetMicros].
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct UtcMicros(pub u64);
+
+macro_rules! impl_micros {
+    ($type:ty) => {
+        impl Display for $type {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl Add<u64> for $type {
+            type Output = Self;
+
+            fn add(self, micros: u64) -> Self {
+                Self(self.0 + micros)
+            }
+        }
+
+        impl Sub<u64> for $type {
+            type Output = Self;
+
+            fn sub(self, micros: u64) -> Self {
+                Self(self.0.saturating_sub(micros))
+            }
+        }
+
+        impl Sub for $type {
+            type Output = u64;
+
+            fn sub(self, other: Self) -> u64 {
+                self.0.saturating_sub(other.0)
+            }
+        }
+
+        impl $type {
+            /// Microseconds from `other` to this time, or `None` if `other` is later.
+            #[must_use]
+            pub fn checked_sub(self, other: Self) -> Option<u64> {
+                self.0.checked_sub(other.0)
+            }
+
+            /// Microseconds between this time and `other`, regardless of their order.
+            #[must_use]
+            pub fn abs_diff(self, other: Self) -> u64 {
+                self.0.abs_diff(other.0)
+            }
+        }
+    };
+}
+
+impl_micros!(IetMicros);
+impl_micros!(UtcMicros);
+
+/// RDR granule length in microseconds.
+///
+/// Constructed from explicit units so a length in seconds cannot be mistaken for
+/// microseconds. Multiplying by a `u64` gives the length of that many granules, and adding to
+/// or subtracting from an [IetMicros] offsets the time by the length.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct GranLen(u64);
+
+impl GranLen {
+    #[must_use]
+    pub const fn from_micros(micros: u64) -> Self {
+        Self(micros)
+    }
+
+    /// Granule length from seconds, rounded to the nearest microsecond, e.g., `85.35` for
+    /// VIIRS.
+    #[must_use]
+    pub fn from_secs_f64(secs: f64) -> Self {
+        Self((secs * 1_000_000.0).round() as u64)
+    }
+
+    #[must_use]
+    pub const fn micros(self) -> u64 {
+        self.0
+    }
+
+    #[must_use]
+    pub fn as_secs_f64(self) -> f64 {
+        self.0 as f64 / 1_000_000.0
+    }
+}
+
+impl Display for GranLen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}s", self.as_secs_f64())
+    }
+}
+
+impl Mul<u64> for GranLen {
+    type Output = Self;
+
+    fn mul(self, num: u64) -> Self {
+        Self(self.0 * num)
+    }
+}
+
+impl Add<GranLen> for IetMicros {
+    type Output = Self;
+
+    fn add(self, len: GranLen) -> Self {
+        Self(self.0 + len.0)
+    }
+}
+
+impl Sub<GranLen> for IetMicros {
+    type Output = Self;
+
+    fn sub(self, len: GranLen) -> Self {
+        Self(self.0.saturating_sub(len.0))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Time(Epoch);
 
@@ -59,6 +196,16 @@ impl Time {
         self.0.to_tai(hifitime::Unit::Microsecond) as u64 - Self::IET_DELTA
     }
 
+    /// Return this time as [IetMicros].
+    pub fn iet_micros(&self) -> IetMicros {
+        IetMicros(self.iet())
+    }
+
+    /// Return this time as [UtcMicros].
+    pub fn utc_micros(&self) -> UtcMicros {
+        UtcMicros(self.utc())
+    }
+
     /// Format ourself using the provided format string.
     ///
     /// See [hifitime::efmt::Format].
@@ -69,6 +216,27 @@ impl Time {
     }
 }
 
+impl From<IetMicros> for Time {
+    fn from(value: IetMicros) -> Self {
+        Self::from_iet(value.0)
+    }
+}
+
+impl From<UtcMicros> for Time {
+    fn from(value: UtcMicros) -> Self {
+        Self::from_utc(value.0)
+    }
+}
+
+impl FromStr for Time {
+    type Err = <Epoch as FromStr>::Err;
+
+    /// Parse a time string supported by [Epoch], e.g., `2024-01-01T00:00:00Z`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Epoch::from_str(s).map(Self::from_epoch)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use hifitime::Unit;
@@ -105,6 +273,55 @@ mod test {
         assert_eq!(Time::from_iet(iet).iet(), iet);
     }
 
+    #[test]
+    fn test_micros() {
+        let time = Time(Epoch::from_unix_seconds(0.0));
+        assert_eq!(time.iet_micros(), IetMicros(378_691_200_000_000));
+        assert_eq!(time.utc_micros(), UtcMicros(0));
+        assert_eq!(Time::from(time.iet_micros()), time);
+        assert_eq!(Time::from(time.utc_micros()).utc(), 0);
+
+        let iet = IetMicros(1_000);
+        assert_eq!(iet + 500, IetMicros(1_500));
+        assert_eq!((iet + 500) - iet, 500);
+        assert_eq!(iet - 500, IetMicros(500));
+        // saturates rather than underflowing
+        assert_eq!(iet - 1_500, IetMicros(0));
+        assert_eq!(iet - (iet + 500), 0);
+        assert_eq!(iet.checked_sub(iet + 500), None);
+        assert_eq!((iet + 500).checked_sub(iet), Some(500));
+        assert_eq!(iet.abs_diff(iet + 500), 500);
+        assert_eq!(iet.to_string(), "1000");
+        assert_eq!(serde_json::to_string(&iet).unwrap(), "1000");
+    }
+
+    #[test]
+    fn test_gran_len() {
+        let len = GranLen::from_secs_f64(85.35);
+        assert_eq!(len, GranLen::from_micros(85_350_000));
+        assert_eq!(len.micros(), 85_350_000);
+        assert_eq!(len.as_secs_f64(), 85.35);
+        assert_eq!(len.to_string(), "85.35s");
+        assert_eq!(GranLen::from_secs_f64(31.997).micros(), 31_997_000);
+
+        let iet = IetMicros(1_000);
+        assert_eq!(iet + len * 2, IetMicros(170_701_000));
+        assert_eq!((iet + len) - len, iet);
+        assert_eq!(iet - len, IetMicros(0));
+        assert_eq!(
+            serde_json::from_str::<GranLen>("20000000")
+                .unwrap()
+                .micros(),
+            20_000_000
+        );
+    }
+
+    #[test]
+    fn test_from_str() {
+        let time = Time::from_str("1970-01-01T00:00:00Z").unwrap();
+        assert_eq!(time.utc(), 0);
+    }
+
     #[test]
     fn test_hifitime() {
         let epoch = Epoch::from_str("1970-01-01T00:00:00Z").unwrap();
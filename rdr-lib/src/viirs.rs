@@ -0,0 +1,152 @@
+//! Minimal VIIRS science packet decoding.
+//!
+//! Only enough of the VIIRS science packet secondary header is decoded to count scans, see
+//! [count_scans].
+use std::collections::BTreeMap;
+
+use ccsds::spacepacket::{decode_packets, Apid, Packet};
+use serde::{Deserialize, Serialize};
+
+/// VIIRS science APIDs, i.e., the M-band, I-band, and DNB earth view APIDs.
+///
+/// Calibration (825) and engineering (826) packets are not part of a scan's science data.
+pub const VIIRS_SCIENCE_APIDS: [Apid; 26] = [
+    800, 801, 802, 803, 804, 805, 806, 807, 808, 809, 810, 811, 812, 813, 814, 815, 816, 817, 818,
+    819, 820, 821, 822, 823, 827, 828,
+];
+
+const SEQ_CONTINUATION: u8 = 0;
+const SEQ_FIRST: u8 = 1;
+const SEQ_LAST: u8 = 2;
+const SEQ_UNSEGMENTED: u8 = 3;
+
+/// Offset of the VIIRS sequence count, i.e., the scan number, in the first packet of a group.
+///
+/// Primary header (6), timecode (8), number of packets (1), spare (1).
+const SCAN_NUMBER_OFFSET: usize = 16;
+
+/// Scan counts for a VIIRS science granule.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScanCounts {
+    /// Number of distinct scans with at least one packet group
+    pub num_scans: u32,
+    /// Number of scans with at least one incomplete packet group, i.e., a group missing its
+    /// last packet or with a gap in packet sequence ids.
+    pub incomplete_scans: u32,
+}
+
+/// Decode the VIIRS scan number from the first packet of a packet group.
+#[must_use]
+pub fn scan_number(pkt: &Packet) -> Option<u32> {
+    let bytes = pkt.data.get(SCAN_NUMBER_OFFSET..SCAN_NUMBER_OFFSET + 4)?;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+/// Count the VIIRS scans in `ap_storage`, the raw packets from a Common RDR application packet
+/// storage area.
+///
+/// Packets for APIDs other than [VIIRS_SCIENCE_APIDS] are ignored.
+#[must_use]
+pub fn count_scans(ap_storage: &[u8]) -> ScanCounts {
+    // scan number to whether all of its groups are complete
+    let mut scans: BTreeMap<u32, bool> = BTreeMap::default();
+    // apid to the current group's scan number and last sequence id
+    let mut groups: BTreeMap<Apid, (u32, u16)> = BTreeMap::default();
+
+    for pkt in decode_packets(ap_storage).filter_map(|p| p.ok()) {
+        let apid = pkt.header.apid;
+        if !VIIRS_SCIENCE_APIDS.contains(&apid) {
+            continue;
+        }
+        let seq = pkt.header.sequence_id;
+        match pkt.header.sequence_flags {
+            SEQ_FIRST | SEQ_UNSEGMENTED => {
+                // A group without a last packet before the next first packet is incomplete
+                if let Some((scan, _)) = groups.remove(&apid) {
+                    scans.insert(scan, false);
+                }
+                let Some(scan) = scan_number(&pkt) else {
+                    continue;
+                };
+                scans.entry(scan).or_insert(true);
+                if pkt.header.sequence_flags == SEQ_FIRST {
+                    groups.insert(apid, (scan, seq));
+                }
+            }
+            SEQ_CONTINUATION | SEQ_LAST => {
+                let Some((scan, last_seq)) = groups.get_mut(&apid) else {
+                    // group without a first packet; cannot determine the scan
+                    continue;
+                };
+                if seq != (last_seq.wrapping_add(1) & 0x3fff) {
+                    scans.insert(*scan, false);
+                }
+                *last_seq = seq;
+                if pkt.header.sequence_flags == SEQ_LAST {
+                    groups.remove(&apid);
+                }
+            }
+            _ => {}
+        }
+    }
+    for (scan, _) in groups.into_values() {
+        scans.insert(scan, false);
+    }
+
+    ScanCounts {
+        num_scans: u32::try_from(scans.len()).unwrap_or(u32::MAX),
+        incomplete_scans: u32::try_from(scans.values().filter(|complete| !**complete).count())
+            .unwrap_or(u32::MAX),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Packet bytes for `apid` with the given sequence flags and id and the scan number at the
+    /// VIIRS sequence count location.
+    fn packet(apid: u16, flags: u8, seq: u16, scan: u32) -> Vec<u8> {
+        let mut dat = Vec::default();
+        dat.extend_from_slice(&(0x0800 | apid).to_be_bytes());
+        dat.extend_from_slice(&((u16::from(flags) << 14) | seq).to_be_bytes());
+        // 14 bytes of user data: 10 bytes of secondary header and the scan number
+        dat.extend_from_slice(&13u16.to_be_bytes());
+        dat.extend_from_slice(&[0u8; 10]);
+        dat.extend_from_slice(&scan.to_be_bytes());
+        dat
+    }
+
+    #[test]
+    fn test_count_scans() {
+        let mut dat = Vec::default();
+        // scan 1, complete on 2 apids
+        for apid in [800, 801] {
+            dat.extend(packet(apid, SEQ_FIRST, 1, 1));
+            dat.extend(packet(apid, SEQ_CONTINUATION, 2, 1));
+            dat.extend(packet(apid, SEQ_LAST, 3, 1));
+        }
+        // scan 2, complete on 800 but missing continuation on 801
+        dat.extend(packet(800, SEQ_FIRST, 4, 2));
+        dat.extend(packet(800, SEQ_CONTINUATION, 5, 2));
+        dat.extend(packet(800, SEQ_LAST, 6, 2));
+        dat.extend(packet(801, SEQ_FIRST, 4, 2));
+        dat.extend(packet(801, SEQ_LAST, 6, 2));
+        // scan 3, missing last packet
+        dat.extend(packet(800, SEQ_FIRST, 7, 3));
+        dat.extend(packet(800, SEQ_CONTINUATION, 8, 3));
+        // not VIIRS
+        dat.extend(packet(100, SEQ_UNSEGMENTED, 1, 99));
+        // VIIRS calibration and engineering
+        dat.extend(packet(825, SEQ_UNSEGMENTED, 1, 98));
+        dat.extend(packet(826, SEQ_UNSEGMENTED, 1, 97));
+
+        assert_eq!(
+            count_scans(&dat),
+            ScanCounts {
+                num_scans: 3,
+                incomplete_scans: 2
+            }
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use tracing::{trace, warn};
+
+/// Aggregates repetitive warnings, such as per-packet warnings for a bad input, so they do not
+/// flood the logs or slow processing.
+///
+/// The first warning for each reason is logged immediately. Subsequent warnings are counted
+/// and a summary count per reason is logged at most every `interval` or `every` warnings,
+/// whichever comes first, as well as on [RateLimitedWarnings::flush] and drop. The detail of
+/// summarized warnings is only available at trace level.
+#[derive(Debug)]
+pub struct RateLimitedWarnings {
+    interval: Duration,
+    every: u64,
+    /// Reason to total number of warnings
+    totals: BTreeMap<&'static str, u64>,
+    /// Reason to number of warnings not yet summarized
+    pending: BTreeMap<&'static str, u64>,
+    num_pending: u64,
+    last_flush: Instant,
+}
+
+impl Default for RateLimitedWarnings {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(10), 100_000)
+    }
+}
+
+impl RateLimitedWarnings {
+    #[must_use]
+    pub fn new(interval: Duration, every: u64) -> Self {
+        Self {
+            interval,
+            every,
+            totals: BTreeMap::default(),
+            pending: BTreeMap::default(),
+            num_pending: 0,
+            last_flush: Instant::now(),
+        }
+    }
+
+    /// Record a warning for `reason` with additional `detail`, e.g., the packet apid.
+    pub fn warn(&mut self, reason: &'static str, detail: impl Display) {
+        let total = self.totals.entry(reason).or_default();
+        *total += 1;
+        if *total == 1 {
+            warn!("{reason}: {detail}; further occurrences will be summarized");
+            return;
+        }
+        trace!("{reason}: {detail}");
+        *self.pending.entry(reason).or_default() += 1;
+        self.num_pending += 1;
+        if self.num_pending >= self.every || self.last_flush.elapsed() >= self.interval {
+            self.flush();
+        }
+    }
+
+    /// Log a summary for all warnings not yet summarized.
+    pub fn flush(&mut self) {
+        for (reason, count) in std::mem::take(&mut self.pending) {
+            warn!(count, "{reason}: {count} more occurrences");
+        }
+        self.num_pending = 0;
+        self.last_flush = Instant::now();
+    }
+
+    /// Total number of warnings for `reason`.
+    #[must_use]
+    pub fn total(&self, reason: &str) -> u64 {
+        self.totals.get(reason).copied().unwrap_or_default()
+    }
+
+    /// Total number of warnings for each reason.
+    #[must_use]
+    pub fn totals(&self) -> &BTreeMap<&'static str, u64> {
+        &self.totals
+    }
+}
+
+impl Drop for RateLimitedWarnings {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limited_warnings() {
+        let mut warnings = RateLimitedWarnings::new(Duration::from_secs(3600), 3);
+        for i in 0..5 {
+            warnings.warn("bad packet", i);
+        }
+        warnings.warn("bad time", "x");
+
+        assert_eq!(warnings.total("bad packet"), 5);
+        assert_eq!(warnings.total("bad time"), 1);
+        assert_eq!(warnings.total("other"), 0);
+        // first is logged immediately, 3 more triggered a flush, leaving 1 pending
+        assert_eq!(warnings.num_pending, 1);
+        warnings.flush();
+        assert_eq!(warnings.num_pending, 0);
+        assert!(warnings.pending.is_empty());
+    }
+}
@@ -0,0 +1,640 @@
+//! Typed HDF5 attribute writing for the RDR attributes defined in CDFCB-X Vol 2.
+//!
+//! Each group of attributes, e.g., file or granule dataset attributes, is described by a
+//! table of [AttrSpec]s giving the attribute name and HDF5 type. An [AttrWriter] writes values
+//! for a table to a single HDF5 object, failing for attributes not in the table or values that
+//! do not match the attribute type. These tables are the only source of attribute lengths.
+//!
+//! Truncation warnings are collected by an [AttrWarnings], which can be shared by all the
+//! writers for a file so a value truncated in every granule is summarized rather than logged
+//! for each granule.
+use std::cell::RefCell;
+
+use hdf5::{
+    types::{FixedAscii, TypeDescriptor},
+    Location,
+};
+use ndarray::arr2;
+
+use crate::{
+    config::{AttrTruncation, MAX_FILE_ATTR_LEN},
+    error::{Error, Result},
+    RateLimitedWarnings,
+};
+
+/// HDF5 type of an attribute. All attributes are written with shape `[N, 1]`, where `N` is 1
+/// for scalar values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AttrType {
+    /// Fixed length ASCII string of the given length. Longer values are handled according
+    /// to the writer's [AttrTruncation].
+    FixedAscii(usize),
+    U32,
+    U64,
+    F32,
+}
+
+/// A value to write to an attribute.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum AttrValue<'a> {
+    Str(&'a str),
+    StrArray(&'a [String]),
+    U32(u32),
+    U64(u64),
+    U64Array(&'a [u64]),
+    F32(f32),
+}
+
+impl<'a> From<&'a str> for AttrValue<'a> {
+    fn from(value: &'a str) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl<'a> From<&'a String> for AttrValue<'a> {
+    fn from(value: &'a String) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl<'a> From<&'a [String]> for AttrValue<'a> {
+    fn from(value: &'a [String]) -> Self {
+        Self::StrArray(value)
+    }
+}
+
+impl From<u32> for AttrValue<'_> {
+    fn from(value: u32) -> Self {
+        Self::U32(value)
+    }
+}
+
+impl From<u64> for AttrValue<'_> {
+    fn from(value: u64) -> Self {
+        Self::U64(value)
+    }
+}
+
+impl<'a> From<&'a [u64]> for AttrValue<'a> {
+    fn from(value: &'a [u64]) -> Self {
+        Self::U64Array(value)
+    }
+}
+
+impl From<f32> for AttrValue<'_> {
+    fn from(value: f32) -> Self {
+        Self::F32(value)
+    }
+}
+
+type WriteFn = fn(&Location, &AttrSpec, AttrValue, AttrTruncation, &AttrWarnings) -> Result<()>;
+
+/// Name and type of a single attribute.
+#[derive(Clone, Copy)]
+pub(crate) struct AttrSpec {
+    pub name: &'static str,
+    pub ty: AttrType,
+    write: WriteFn,
+}
+
+impl AttrSpec {
+    const fn ascii<const N: usize>(name: &'static str) -> Self {
+        Self {
+            name,
+            ty: AttrType::FixedAscii(N),
+            write: write_ascii::<N>,
+        }
+    }
+
+    const fn u32(name: &'static str) -> Self {
+        Self {
+            name,
+            ty: AttrType::U32,
+            write: write_num::<u32>,
+        }
+    }
+
+    const fn u64(name: &'static str) -> Self {
+        Self {
+            name,
+            ty: AttrType::U64,
+            write: write_num::<u64>,
+        }
+    }
+
+    const fn f32(name: &'static str) -> Self {
+        Self {
+            name,
+            ty: AttrType::F32,
+            write: write_num::<f32>,
+        }
+    }
+}
+
+/// File attributes (CDFCB-X Vol 2, File Level Metadata)
+pub(crate) const FILE_ATTRS: &[AttrSpec] = &[
+    AttrSpec::ascii::<4>("Distributor"),
+    AttrSpec::ascii::<20>("Mission_Name"),
+    AttrSpec::ascii::<3>("Platform_Short_Name"),
+    AttrSpec::ascii::<4>("N_Dataset_Source"),
+    AttrSpec::ascii::<8>("N_HDF_Creation_Date"),
+    AttrSpec::ascii::<16>("N_HDF_Creation_Time"),
+];
+
+/// `Data_Products/<shortname>` group attributes (CDFCB-X Vol 2, Data Product Metadata)
+pub(crate) const PRODUCT_ATTRS: &[AttrSpec] = &[
+    AttrSpec::ascii::<10>("Instrument_Short_Name"),
+    AttrSpec::ascii::<20>("N_Collection_Short_Name"),
+    AttrSpec::ascii::<3>("N_Dataset_Type_Tag"),
+    AttrSpec::ascii::<3>("N_Processing_Domain"),
+];
+
+/// `Data_Products/<shortname>/<shortname>_Gran_<N>` dataset attributes (CDFCB-X Vol 2,
+/// Granule Metadata)
+pub(crate) const GRANULE_ATTRS: &[AttrSpec] = &[
+    AttrSpec::ascii::<8>("Beginning_Date"),
+    AttrSpec::ascii::<16>("Beginning_Time"),
+    AttrSpec::ascii::<8>("Ending_Date"),
+    AttrSpec::ascii::<16>("Ending_Time"),
+    AttrSpec::ascii::<8>("N_Creation_Date"),
+    AttrSpec::ascii::<16>("N_Creation_Time"),
+    AttrSpec::ascii::<3>("N_Granule_Status"),
+    AttrSpec::ascii::<2>("N_Granule_Version"),
+    AttrSpec::ascii::<52>("N_JPSS_Document_Ref"),
+    AttrSpec::ascii::<3>("N_LEOA_Flag"),
+    AttrSpec::ascii::<39>("N_Reference_ID"),
+    AttrSpec::ascii::<15>("N_Granule_ID"),
+    AttrSpec::ascii::<3>("N_IDPS_Mode"),
+    AttrSpec::ascii::<19>("N_Software_Version"),
+    AttrSpec::u64("N_Beginning_Orbit_Number"),
+    AttrSpec::u64("N_Beginning_Time_IET"),
+    AttrSpec::u64("N_Ending_Time_IET"),
+    AttrSpec::ascii::<17>("N_Packet_Type"),
+    AttrSpec::u64("N_Packet_Type_Count"),
+    AttrSpec::f32("N_Percent_Missing_Data"),
+];
+
+/// `Data_Products/<shortname>/<shortname>_Aggr` dataset attributes (CDFCB-X Vol 2,
+/// Aggregation Metadata)
+pub(crate) const AGGR_ATTRS: &[AttrSpec] = &[
+    AttrSpec::u32("AggregateBeginningOrbitNumber"),
+    AttrSpec::u32("AggregateEndingOrbitNumber"),
+    AttrSpec::u32("AggregateNumberGranules"),
+    AttrSpec::ascii::<20>("AggregateBeginningDate"),
+    AttrSpec::ascii::<20>("AggregateBeginningTime"),
+    AttrSpec::ascii::<20>("AggregateBeginningGranuleID"),
+    AttrSpec::ascii::<20>("AggregateEndingDate"),
+    AttrSpec::ascii::<20>("AggregateEndingTime"),
+    AttrSpec::ascii::<20>("AggregateEndingGranuleID"),
+];
+
+/// Fixed length of the file level string attribute `name` from [FILE_ATTRS].
+///
+/// # Panics
+/// If `name` is not a file level string attribute.
+pub(crate) fn file_attr_len(name: &str) -> usize {
+    match FILE_ATTRS.iter().find(|s| s.name == name).map(|s| s.ty) {
+        Some(AttrType::FixedAscii(len)) => len,
+        _ => panic!("{name} is not a file level string attribute"),
+    }
+}
+
+/// Reason for attribute truncation warnings, see [AttrWarnings].
+const TRUNCATION_WARNING: &str = "truncating attribute values longer than the attribute length";
+
+/// `value` limited to `len` characters according to `truncation`, with truncation warnings
+/// recorded in `warnings`.
+fn fit<'v>(
+    name: &str,
+    value: &'v str,
+    len: usize,
+    truncation: AttrTruncation,
+    warnings: &AttrWarnings,
+) -> Result<&'v str> {
+    if value.len() <= len {
+        return Ok(value);
+    }
+    match truncation {
+        AttrTruncation::Warn => {
+            warnings.0.borrow_mut().warn(
+                TRUNCATION_WARNING,
+                format_args!("{name} value {value:?} to {len} characters"),
+            );
+            Ok(value.get(..len).unwrap_or(value))
+        }
+        AttrTruncation::Error => Err(Error::Hdf5Other(format!(
+            "attr {name} value {value:?} is longer than {len} characters"
+        ))),
+    }
+}
+
+/// Attribute truncation warnings for everything written by the [AttrWriter]s sharing it.
+#[derive(Default)]
+pub(crate) struct AttrWarnings(RefCell<RateLimitedWarnings>);
+
+fn to_ascii<const N: usize>(name: &str, value: &str) -> Result<FixedAscii<N>> {
+    FixedAscii::<N>::from_ascii(value.as_bytes())
+        .map_err(|e| Error::Hdf5Other(format!("creating ascii value {name} for {value}: {e}")))
+}
+
+fn write_ascii<const N: usize>(
+    loc: &Location,
+    spec: &AttrSpec,
+    value: AttrValue,
+    truncation: AttrTruncation,
+    warnings: &AttrWarnings,
+) -> Result<()> {
+    let name = spec.name;
+    let data: Vec<[FixedAscii<N>; 1]> = match value {
+        AttrValue::Str(value) => {
+            vec![[to_ascii(name, fit(name, value, N, truncation, warnings)?)?]]
+        }
+        AttrValue::StrArray(values) => values
+            .iter()
+            .map(|value| Ok([to_ascii(name, fit(name, value, N, truncation, warnings)?)?]))
+            .collect::<Result<_>>()?,
+        _ => return Err(mismatch(spec, value)),
+    };
+    loc.new_attr_builder()
+        .with_data(&arr2(&data))
+        .create(name)
+        .map_err(|e| Error::Hdf5Other(format!("creating attr {name}: {e}")))?;
+    Ok(())
+}
+
+/// Numeric attribute types.
+trait AttrNum: hdf5::H5Type + Copy {
+    fn values(value: AttrValue) -> Option<Vec<Self>>;
+}
+
+impl AttrNum for u32 {
+    fn values(value: AttrValue) -> Option<Vec<Self>> {
+        match value {
+            AttrValue::U32(v) => Some(vec![v]),
+            _ => None,
+        }
+    }
+}
+
+impl AttrNum for u64 {
+    fn values(value: AttrValue) -> Option<Vec<Self>> {
+        match value {
+            AttrValue::U64(v) => Some(vec![v]),
+            AttrValue::U64Array(v) => Some(v.to_vec()),
+            _ => None,
+        }
+    }
+}
+
+impl AttrNum for f32 {
+    fn values(value: AttrValue) -> Option<Vec<Self>> {
+        match value {
+            AttrValue::F32(v) => Some(vec![v]),
+            _ => None,
+        }
+    }
+}
+
+fn write_num<T: AttrNum>(
+    loc: &Location,
+    spec: &AttrSpec,
+    value: AttrValue,
+    _truncation: AttrTruncation,
+    _warnings: &AttrWarnings,
+) -> Result<()> {
+    let name = spec.name;
+    let Some(values) = T::values(value) else {
+        return Err(mismatch(spec, value));
+    };
+    let attr = loc
+        .new_attr::<T>()
+        .shape([values.len(), 1])
+        .create(name)
+        .map_err(|e| Error::Hdf5Other(format!("creating attr {name}: {e}")))?;
+    attr.write_raw(&values)
+        .map_err(|e| Error::Hdf5Other(format!("writing attr {name}: {e}")))?;
+    Ok(())
+}
+
+fn mismatch(spec: &AttrSpec, value: AttrValue) -> Error {
+    Error::Hdf5Other(format!(
+        "invalid value for attr {} of type {:?}: {value:?}",
+        spec.name, spec.ty
+    ))
+}
+
+/// Write `value` as a scalar fixed length ASCII string attribute of length `len`. Longer values
+/// are handled according to `truncation`.
+fn write_ascii_len(
+    loc: &Location,
+    name: &str,
+    value: &str,
+    len: usize,
+    truncation: AttrTruncation,
+    warnings: &AttrWarnings,
+) -> Result<()> {
+    if !(1..=MAX_FILE_ATTR_LEN).contains(&len) {
+        return Err(Error::Hdf5Other(format!(
+            "invalid length {len} for attr {name}"
+        )));
+    }
+    let data = [to_ascii::<MAX_FILE_ATTR_LEN>(
+        name,
+        fit(name, value, len, truncation, warnings)?,
+    )?];
+    // HDF5 converts the value to the shorter attribute string type on write
+    let attr = loc
+        .new_attr_builder()
+        .empty_as(&TypeDescriptor::FixedAscii(len))
+        .shape([1, 1])
+        .create(name)
+        .map_err(|e| Error::Hdf5Other(format!("creating attr {name}: {e}")))?;
+    attr.write_raw(&data)
+        .map_err(|e| Error::Hdf5Other(format!("writing attr {name}: {e}")))?;
+    Ok(())
+}
+
+/// Writes the attributes described by an [AttrSpec] table to an HDF5 object.
+pub(crate) struct AttrWriter<'a> {
+    loc: &'a Location,
+    specs: &'static [AttrSpec],
+    truncation: AttrTruncation,
+    warnings: Option<&'a AttrWarnings>,
+    /// Warnings used if not provided with [AttrWriter::with_warnings]
+    own_warnings: AttrWarnings,
+}
+
+impl<'a> AttrWriter<'a> {
+    pub fn new(loc: &'a Location, specs: &'static [AttrSpec]) -> Self {
+        Self {
+            loc,
+            specs,
+            truncation: AttrTruncation::default(),
+            warnings: None,
+            own_warnings: AttrWarnings::default(),
+        }
+    }
+
+    /// Record truncation warnings in `warnings`, e.g., shared by the writers for all the
+    /// granules of a file, rather than for this writer only.
+    #[must_use]
+    pub fn with_warnings(mut self, warnings: &'a AttrWarnings) -> Self {
+        self.warnings = Some(warnings);
+        self
+    }
+
+    fn warnings(&self) -> &AttrWarnings {
+        self.warnings.unwrap_or(&self.own_warnings)
+    }
+
+    /// Set how string values longer than their attribute length are handled.
+    #[must_use]
+    pub fn with_truncation(mut self, truncation: AttrTruncation) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
+    /// Write `value` to the attribute `name`.
+    ///
+    /// # Errors
+    /// If `name` is not in this writer's table, `value` does not match the attribute type, or
+    /// on any HDF5 error.
+    pub fn write<'v>(&self, name: &str, value: impl Into<AttrValue<'v>>) -> Result<()> {
+        let spec = self.spec(name)?;
+        (spec.write)(
+            self.loc,
+            spec,
+            value.into(),
+            self.truncation,
+            self.warnings(),
+        )
+    }
+
+    /// Write `value` to the string attribute `name` using a length of `len`, e.g., from
+    /// configuration, rather than the table length.
+    ///
+    /// # Errors
+    /// If `name` is not a string attribute in this writer's table, `len` is 0 or greater than
+    /// [MAX_FILE_ATTR_LEN], or on any HDF5 error.
+    pub fn write_str_len(&self, name: &str, value: &str, len: usize) -> Result<()> {
+        let spec = self.spec(name)?;
+        if !matches!(spec.ty, AttrType::FixedAscii(_)) {
+            return Err(mismatch(spec, AttrValue::Str(value)));
+        }
+        write_ascii_len(self.loc, name, value, len, self.truncation, self.warnings())
+    }
+
+    /// Write `value` to the non-standard string attribute `name`, e.g., a granule
+    /// ascending/descending flag. The attribute length is the value length, limited to
+    /// [MAX_FILE_ATTR_LEN].
+    ///
+    /// # Errors
+    /// If `name` is in this writer's table, i.e., would replace a standard attribute, `name`
+    /// already exists, or on any HDF5 error.
+    pub fn write_extra(&self, name: &str, value: &str) -> Result<()> {
+        if self.specs.iter().any(|s| s.name == name) {
+            return Err(Error::Hdf5Other(format!(
+                "extra attr {name} conflicts with a standard attr"
+            )));
+        }
+        write_ascii_len(
+            self.loc,
+            name,
+            value,
+            value.len().clamp(1, MAX_FILE_ATTR_LEN),
+            self.truncation,
+            self.warnings(),
+        )
+    }
+
+    fn spec(&self, name: &str) -> Result<&'static AttrSpec> {
+        self.specs
+            .iter()
+            .find(|s| s.name == name)
+            .ok_or_else(|| Error::Hdf5Other(format!("unknown attr {name}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hdf5::types::{FloatSize, IntSize, TypeDescriptor};
+
+    use super::*;
+
+    fn sample<'a>(spec: &AttrSpec, packet_types: &'a [String]) -> AttrValue<'a> {
+        match (spec.name, spec.ty) {
+            ("N_Packet_Type", _) => AttrValue::StrArray(packet_types),
+            ("N_Packet_Type_Count", _) => AttrValue::U64Array(&[1, 2]),
+            (_, AttrType::FixedAscii(_)) => AttrValue::Str("x"),
+            (_, AttrType::U32) => AttrValue::U32(1),
+            (_, AttrType::U64) => AttrValue::U64(1),
+            (_, AttrType::F32) => AttrValue::F32(1.0),
+        }
+    }
+
+    #[test]
+    fn test_attr_types_and_shapes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = hdf5::File::create(dir.path().join("attrs.h5")).unwrap();
+        let packet_types = vec!["A".to_string(), "B".to_string()];
+
+        for (group_name, specs) in [
+            ("file", FILE_ATTRS),
+            ("product", PRODUCT_ATTRS),
+            ("granule", GRANULE_ATTRS),
+            ("aggr", AGGR_ATTRS),
+        ] {
+            let group = file.create_group(group_name).unwrap();
+            let writer = AttrWriter::new(&group, specs);
+            for spec in specs {
+                let value = sample(spec, &packet_types);
+                writer.write(spec.name, value).unwrap();
+
+                let attr = group.attr(spec.name).unwrap();
+                let expected_len = match value {
+                    AttrValue::StrArray(v) => v.len(),
+                    AttrValue::U64Array(v) => v.len(),
+                    _ => 1,
+                };
+                assert_eq!(attr.shape(), vec![expected_len, 1], "{}", spec.name);
+                let expected = match spec.ty {
+                    AttrType::FixedAscii(n) => TypeDescriptor::FixedAscii(n),
+                    AttrType::U32 => TypeDescriptor::Unsigned(IntSize::U4),
+                    AttrType::U64 => TypeDescriptor::Unsigned(IntSize::U8),
+                    AttrType::F32 => TypeDescriptor::Float(FloatSize::U4),
+                };
+                assert_eq!(
+                    attr.dtype().unwrap().to_descriptor().unwrap(),
+                    expected,
+                    "{}",
+                    spec.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_attr_writer_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = hdf5::File::create(dir.path().join("attrs.h5")).unwrap();
+        let writer = AttrWriter::new(&file, GRANULE_ATTRS);
+
+        assert!(writer.write("Not_An_Attr", "x").is_err());
+        assert!(writer.write("N_Granule_ID", 1u64).is_err());
+        assert!(writer.write("N_Beginning_Time_IET", "x").is_err());
+
+        // scalar strings are truncated to the attribute length
+        writer.write("N_Granule_Version", "A1234").unwrap();
+        let value = file
+            .attr("N_Granule_Version")
+            .unwrap()
+            .read_raw::<FixedAscii<2>>()
+            .unwrap();
+        assert_eq!(value[0].as_str(), "A1");
+    }
+
+    #[test]
+    fn test_attr_writer_truncation_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = hdf5::File::create(dir.path().join("attrs.h5")).unwrap();
+        let writer = AttrWriter::new(&file, GRANULE_ATTRS).with_truncation(AttrTruncation::Error);
+
+        assert!(writer.write("N_Granule_Version", "A1234").is_err());
+        assert!(writer
+            .write("N_Packet_Type", ["X".repeat(18)].as_slice())
+            .is_err());
+        writer.write("N_Granule_Version", "A1").unwrap();
+        writer
+            .write("N_Packet_Type", ["X".repeat(17)].as_slice())
+            .unwrap();
+
+        let writer = AttrWriter::new(&file, FILE_ATTRS).with_truncation(AttrTruncation::Error);
+        assert!(writer.write_str_len("Mission_Name", "MISSION", 4).is_err());
+        writer.write_str_len("Mission_Name", "MISS", 4).unwrap();
+        assert!(writer
+            .write_extra("Long_Attr", &"x".repeat(MAX_FILE_ATTR_LEN + 1))
+            .is_err());
+    }
+
+    #[test]
+    fn test_file_attr_len() {
+        assert_eq!(file_attr_len("Distributor"), 4);
+        assert_eq!(file_attr_len("Mission_Name"), 20);
+    }
+
+    #[test]
+    fn test_attr_writer_str_len() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = hdf5::File::create(dir.path().join("attrs.h5")).unwrap();
+        let writer = AttrWriter::new(&file, FILE_ATTRS);
+
+        writer
+            .write_str_len("Platform_Short_Name", "GCOMW1", 6)
+            .unwrap();
+        let attr = file.attr("Platform_Short_Name").unwrap();
+        assert_eq!(
+            attr.dtype().unwrap().to_descriptor().unwrap(),
+            TypeDescriptor::FixedAscii(6)
+        );
+        assert_eq!(
+            attr.read_raw::<FixedAscii<6>>().unwrap()[0].as_str(),
+            "GCOMW1"
+        );
+
+        writer.write_str_len("Mission_Name", "MISSION", 4).unwrap();
+        let value = file
+            .attr("Mission_Name")
+            .unwrap()
+            .read_raw::<FixedAscii<4>>()
+            .unwrap();
+        assert_eq!(value[0].as_str(), "MISS");
+
+        assert!(writer.write_str_len("Distributor", "x", 0).is_err());
+        assert!(writer
+            .write_str_len("Distributor", "x", MAX_FILE_ATTR_LEN + 1)
+            .is_err());
+        assert!(AttrWriter::new(&file, GRANULE_ATTRS)
+            .write_str_len("N_Beginning_Time_IET", "x", 4)
+            .is_err());
+    }
+
+    #[test]
+    fn test_attr_writer_extra() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = hdf5::File::create(dir.path().join("attrs.h5")).unwrap();
+        let writer = AttrWriter::new(&file, GRANULE_ATTRS);
+
+        writer
+            .write_extra("Ascending_Descending", "Ascending")
+            .unwrap();
+        let attr = file.attr("Ascending_Descending").unwrap();
+        assert_eq!(
+            attr.dtype().unwrap().to_descriptor().unwrap(),
+            TypeDescriptor::FixedAscii(9)
+        );
+        assert_eq!(
+            attr.read_raw::<FixedAscii<9>>().unwrap()[0].as_str(),
+            "Ascending"
+        );
+
+        assert!(writer.write_extra("N_Granule_ID", "x").is_err());
+        assert!(writer.write_extra("Ascending_Descending", "x").is_err());
+    }
+
+    #[test]
+    fn test_attr_warnings_shared() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = hdf5::File::create(dir.path().join("attrs.h5")).unwrap();
+        let warnings = AttrWarnings::default();
+
+        // truncation warnings are counted across all the writers sharing the warnings
+        for idx in 0..2 {
+            let group = file.create_group(&format!("gran{idx}")).unwrap();
+            AttrWriter::new(&group, GRANULE_ATTRS)
+                .with_warnings(&warnings)
+                .write("N_Granule_Version", "A1234")
+                .unwrap();
+        }
+        assert_eq!(warnings.0.borrow().total(TRUNCATION_WARNING), 2);
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    fs::{create_dir_all, write, File},
+    path::{Path, PathBuf},
+};
+
+use crate::{error::Result, rdr::Rdr, Meta};
+
+use super::RdrWriter;
+
+/// [RdrWriter] that writes a directory containing the raw Common RDR bytes for each granule
+/// in `<short_name>_<granule_id>.dat` files along with a `meta.json` file containing the
+/// global and granule metadata.
+///
+/// This does not require HDF5 and is useful for testing and for alternative archive formats.
+#[derive(Debug, Default, Clone)]
+pub struct BlobWriter;
+
+impl BlobWriter {
+    pub const META_NAME: &str = "meta.json";
+}
+
+impl RdrWriter for BlobWriter {
+    /// Writes to a directory at `dest` with any extension removed.
+    fn write(&self, dest: &Path, mut meta: Meta, rdrs: &[Rdr]) -> Result<PathBuf> {
+        let dir = dest.with_extension("");
+        create_dir_all(&dir)?;
+
+        for rdr in rdrs {
+            let fpath = dir.join(format!("{}_{}.dat", rdr.meta.collection, rdr.meta.id));
+            write(&fpath, &rdr.data)?;
+            meta.granules
+                .entry(rdr.meta.collection.clone())
+                .or_default()
+                .push(rdr.meta.clone());
+        }
+
+        let file = File::create(dir.join(Self::META_NAME))?;
+        serde_json::to_writer_pretty(file, &meta)?;
+        crate::metrics::rdr_written(rdrs);
+
+        Ok(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::get_default, GranuleMeta, Time};
+
+    #[test]
+    fn test_blob_writer() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let rdr = Rdr {
+            meta: GranuleMeta::new(
+                Time::from(config.satellite.base_time),
+                &config.satellite,
+                product,
+            )
+            .unwrap(),
+            product_id: product.product_id.clone(),
+            data: vec![1, 2, 3],
+        };
+        let meta = Meta::from_products(&[product.short_name.clone()], &config).unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+
+        let zult = BlobWriter
+            .write(&dir.path().join("test.h5"), meta, &[rdr.clone()])
+            .unwrap();
+
+        assert_eq!(zult, dir.path().join("test"));
+        let data = std::fs::read(zult.join(format!("{}_{}.dat", product.short_name, rdr.meta.id)))
+            .unwrap();
+        assert_eq!(data, rdr.data);
+        let meta: serde_json::Value =
+            serde_json::from_reader(File::open(zult.join(BlobWriter::META_NAME)).unwrap()).unwrap();
+        assert_eq!(meta["granules"][&product.short_name][0]["id"], rdr.meta.id);
+    }
+}
@@ -1,83 +1,186 @@
+mod attrs;
+mod blob;
 mod hdfc;
+mod options;
 
 use core::fmt;
 use std::{
-    collections::{HashMap, HashSet},
-    path::Path,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
 };
 
-use hdf5::{types::FixedAscii, File};
+pub(crate) use attrs::file_attr_len;
+use attrs::{AttrWarnings, AttrWriter, AGGR_ATTRS, FILE_ATTRS, GRANULE_ATTRS, PRODUCT_ATTRS};
+use hdf5::File;
 use hdfc::{create_dataproducts_aggr_dataset, create_dataproducts_gran_dataset};
-use ndarray::{arr1, arr2, Dim};
 
 use crate::{
-    attr_date, attr_time,
-    error::{Error, Result},
-    rdr::Rdr,
-    AggrMeta, GranuleMeta, Meta, ProductMeta, Time,
+    config::AttrTruncation,
+    error::{Error, RdrError, Result},
+    metrics,
+    rdr::{attr_date, attr_time, read_attr_string, validate_common_rdr, Rdr},
+    AggrMeta, CommonRdr, GranuleMeta, IetMicros, Meta, ProductMeta, Time,
 };
 
-/// Write a string attr with specific len with shape [1, 1]
-macro_rules! wattstr {
-    ($obj:expr, $name:expr, $value:expr, $maxlen:expr) => {
-        $obj.new_attr_builder()
-            .with_data::<'_, _, _, Dim<[usize; 2]>>(&arr2(&[[FixedAscii::<$maxlen>::from_ascii(
-                &(($value.clone())[..std::cmp::min($maxlen, $value.len())]),
-            )
-            .map_err(|e| {
-                Error::Hdf5Other(format!(
-                    "creating ascii value {} for {}: {e}",
-                    $name, $value
-                ))
-            })?]]))
-            .create($name)
-            .map_err(|e| {
-                Error::Hdf5Other(format!(
-                    "creating ascii value {} for {}: {e}",
-                    $name, $value
-                ))
-            })?
-    };
+pub use blob::BlobWriter;
+pub use options::{FileOptions, LibverBounds, WriteOptions};
+
+/// A backend for writing collected [Rdr]s.
+pub trait RdrWriter: Send + Sync {
+    /// Write `rdrs` with the global metadata `meta` to `dest`.
+    ///
+    /// Returns the path actually written, which may differ from `dest` depending on the
+    /// backend.
+    fn write(&self, dest: &Path, meta: Meta, rdrs: &[Rdr]) -> Result<PathBuf>;
 }
 
-/// Write a u64 attr
-macro_rules! wattnum {
-    ($obj:expr, $ty:ty, $name:expr, $value:expr) => {
-        $obj.new_attr_builder()
-            .with_data::<'_, _, $ty, Dim<[usize; 2]>>(&arr2(&[[$value]]))
-            .create($name)
-            .map_err(|e| {
-                Error::Hdf5Other(format!(
-                    "creating numeric attr {} value={}: {e}",
-                    $name, $value
-                ))
-            })?
-    };
+/// How collections in the RDR [Meta] products that have no granules are written, e.g., a
+/// packed product with no granules overlapping the primary granule.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmptyCollections {
+    /// Do not write anything for the collection
+    #[default]
+    Omit,
+    /// Write the collection groups and an `_Aggr` dataset with `AggregateNumberGranules` of 0
+    Write,
+}
+
+/// Suffix added to the destination path of files being written when
+/// [Hdf5Writer::with_rename_on_complete] is set.
+///
+/// Downstream pollers should ignore files with this suffix. Files are only renamed to their
+/// final path after they are completely written and closed, so a file without the suffix is
+/// safe to read. HDF5 holds a lock on files open for writing, so readers that do not ignore
+/// in-progress files may fail with file locking errors.
+pub const IN_PROGRESS_SUFFIX: &str = ".inprogress";
+
+/// Path that `dest` is written to before it is complete. See [IN_PROGRESS_SUFFIX].
+#[must_use]
+pub fn in_progress_path(dest: &Path) -> PathBuf {
+    let mut path = dest.as_os_str().to_owned();
+    path.push(IN_PROGRESS_SUFFIX);
+    PathBuf::from(path)
+}
+
+/// [RdrWriter] that writes JPSS H5 RDR files. See [create_rdr].
+#[derive(Debug, Default, Clone)]
+pub struct Hdf5Writer {
+    empty_collections: EmptyCollections,
+    file_options: FileOptions,
+    rename_on_complete: bool,
+}
+
+impl Hdf5Writer {
+    /// Set how collections without granules are written. See [EmptyCollections].
+    #[must_use]
+    pub fn with_empty_collections(mut self, empty: EmptyCollections) -> Self {
+        self.empty_collections = empty;
+        self
+    }
+
+    /// Set HDF5 file creation properties, e.g., to keep files readable by HDF5 1.8 software.
+    /// See [FileOptions].
+    #[must_use]
+    pub fn with_file_options(mut self, options: FileOptions) -> Self {
+        self.file_options = options;
+        self
+    }
+
+    /// Write files to the destination path with [IN_PROGRESS_SUFFIX] and rename them to the
+    /// destination once complete, so readers never see partially written files.
+    #[must_use]
+    pub fn with_rename_on_complete(mut self, rename: bool) -> Self {
+        self.rename_on_complete = rename;
+        self
+    }
+}
+
+impl RdrWriter for Hdf5Writer {
+    fn write(&self, dest: &Path, meta: Meta, rdrs: &[Rdr]) -> Result<PathBuf> {
+        let data_size = Some(rdrs.iter().map(|r| r.data.len()).sum());
+        if !self.rename_on_complete {
+            let file = self.file_options.create_sized(dest, data_size)?;
+            write_rdr(&file, meta, rdrs, self.empty_collections)?;
+            file.close()?;
+            metrics::rdr_written(rdrs);
+            return Ok(dest.to_path_buf());
+        }
+
+        let tmp_path = in_progress_path(dest);
+        let zult = self
+            .file_options
+            .create_sized(&tmp_path, data_size)
+            .and_then(|file| {
+                write_rdr(&file, meta, rdrs, self.empty_collections)?;
+                Ok(file.close()?)
+            });
+        if let Err(err) = zult {
+            // Do not leave partial files behind; they would never be renamed
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(err);
+        }
+        std::fs::rename(&tmp_path, dest)?;
+        metrics::rdr_written(rdrs);
+        Ok(dest.to_path_buf())
+    }
 }
 
 /// Write a JPSS H5 RDR file from the provided RDR metadata and granule data.
+///
+/// Collections in `meta` without any granules are omitted. See [create_rdr_with].
 pub fn create_rdr<P: AsRef<Path> + fmt::Debug>(fpath: P, meta: Meta, rdrs: &[Rdr]) -> Result<()> {
+    create_rdr_with(fpath, meta, rdrs, EmptyCollections::Omit)
+}
+
+/// Write a JPSS H5 RDR file from the provided RDR metadata and granule data, writing any
+/// collections in `meta` without granules according to `empty`.
+pub fn create_rdr_with<P: AsRef<Path> + fmt::Debug>(
+    fpath: P,
+    meta: Meta,
+    rdrs: &[Rdr],
+    empty: EmptyCollections,
+) -> Result<()> {
     let file = File::create(&fpath)?;
+    write_rdr(&file, meta, rdrs, empty)
+}
 
-    write_rdr_meta(
-        &file,
-        &meta.distributor,
-        &meta.mission,
-        &meta.platform,
-        &meta.dataset_source,
-        &meta.created,
-    )?;
+/// Write the RDR metadata and granules to the newly created `file`.
+fn write_rdr(file: &File, meta: Meta, rdrs: &[Rdr], empty: EmptyCollections) -> Result<()> {
+    let truncation = meta.attr_truncation;
+    let opts = WriteOptions::default().with_attr_truncation(truncation);
+    write_rdr_meta(file, &meta, &opts)?;
 
     // Make sure top-level required groups exist
     file.create_group("All_Data")?;
     file.create_group("Data_Products")?;
 
-    // Write RDR granule datasets (All_Data, Data_Products)
+    // Write product groups from the file metadata, which may include configured attribute
+    // overrides, before granules so they are not created from granule defaults.
+    let mut products: Vec<&ProductMeta> = meta
+        .products
+        .values()
+        .filter(|p| rdrs.iter().any(|r| r.meta.collection == p.collection))
+        .collect();
+    products.sort_by(|a, b| a.collection.cmp(&b.collection));
+    for product_meta in products {
+        write_dataproduct_group(file, product_meta, truncation)?;
+        write_all_data_attrs(
+            file,
+            &product_meta.collection,
+            &product_meta.all_data_attrs,
+            truncation,
+        )?;
+    }
+
+    // Write RDR granule datasets (All_Data, Data_Products). Granules are written in time order
+    // so granule indexes for each collection are in time order.
+    let mut sorted: Vec<&Rdr> = rdrs.iter().collect();
+    sorted.sort_by_key(|r| r.meta.begin_time_iet);
     let mut short_names: HashSet<String> = HashSet::default();
     let mut indexes: HashMap<String, usize> = HashMap::default();
-    for rdr in rdrs.iter() {
+    for rdr in sorted {
         let gran_idx = indexes.get(&rdr.meta.collection).unwrap_or(&0);
-        write_rdr_granule(&file, *gran_idx, rdr)?;
+        write_rdr_granule(file, *gran_idx, rdr, &opts)?;
         short_names.insert(rdr.meta.collection.to_string());
         indexes.insert(rdr.meta.collection.to_string(), gran_idx + 1);
     }
@@ -90,33 +193,68 @@ pub fn create_rdr<P: AsRef<Path> + fmt::Debug>(fpath: P, meta: Meta, rdrs: &[Rdr
             .cloned()
             .collect::<Vec<Rdr>>();
         let meta = AggrMeta::from_rdrs(&rdrs);
-        write_aggr_dataset(&file, &short_name, &meta)?;
+        write_aggr_dataset(file, &short_name, &meta, truncation)?;
+    }
+
+    if empty == EmptyCollections::Write {
+        let mut empty_products: Vec<&ProductMeta> = meta
+            .products
+            .values()
+            .filter(|p| !indexes.contains_key(&p.collection))
+            .collect();
+        empty_products.sort_by(|a, b| a.collection.cmp(&b.collection));
+        for product_meta in empty_products {
+            write_dataproduct_group(file, product_meta, truncation)?;
+            write_all_data_attrs(
+                file,
+                &product_meta.collection,
+                &product_meta.all_data_attrs,
+                truncation,
+            )?;
+            write_aggr_dataset(
+                file,
+                &product_meta.collection,
+                &AggrMeta::empty(),
+                truncation,
+            )?;
+        }
     }
 
     Ok(())
 }
 
-pub fn write_rdr_meta(
-    file: &File,
-    dist: &str,
-    mission: &str,
-    plat: &str,
-    source: &str,
-    created: &Time,
-) -> Result<()> {
-    wattstr!(file, "Distributor", dist, 4);
-    wattstr!(file, "Mission_Name", mission, 20);
-    wattstr!(file, "Platform_Short_Name", plat, 3);
-    wattstr!(file, "N_Dataset_Source", source, 4);
-    wattstr!(file, "N_HDF_Creation_Date", attr_date(created), 8);
-    wattstr!(file, "N_HDF_Creation_Time", attr_time(created), 16);
+/// Write the file level attributes from `meta`, with string attribute lengths from
+/// `meta.attr_lengths`. Values too long for their attribute are handled according to
+/// [WriteOptions::with_attr_truncation].
+pub fn write_rdr_meta(file: &File, meta: &Meta, opts: &WriteOptions) -> Result<()> {
+    let lengths = &meta.attr_lengths;
+    let attrs = AttrWriter::new(file, FILE_ATTRS)
+        .with_truncation(opts.attr_truncation())
+        .with_warnings(&opts.warnings);
+    attrs.write_str_len("Distributor", &meta.distributor, lengths.distributor)?;
+    attrs.write_str_len("Mission_Name", &meta.mission, lengths.mission)?;
+    attrs.write_str_len("Platform_Short_Name", &meta.platform, lengths.platform)?;
+    attrs.write_str_len(
+        "N_Dataset_Source",
+        &meta.dataset_source,
+        lengths.dataset_source,
+    )?;
+    attrs.write("N_HDF_Creation_Date", &attr_date(&meta.created))?;
+    attrs.write("N_HDF_Creation_Time", &attr_time(&meta.created))?;
     Ok(())
 }
 
-pub fn write_rdr_granule(file: &File, gran_idx: usize, rdr: &Rdr) -> Result<()> {
-    let rawdata_path = write_rdr_to_alldata(file, gran_idx, rdr)?;
+/// Write the granule `rdr` using the granule index `gran_idx`. See [WriteOptions].
+pub fn write_rdr_granule(
+    file: &File,
+    gran_idx: usize,
+    rdr: &Rdr,
+    opts: &WriteOptions,
+) -> Result<()> {
+    let (truncation, warnings) = (opts.attr_truncation(), &opts.warnings);
+    let rawdata_path = write_rdr_to_alldata(file, gran_idx, rdr, truncation, warnings)?;
     let product_meta = ProductMeta::from_rdr(rdr);
-    write_dataproduct_group(file, &product_meta)?;
+    write_dataproduct_group(file, &product_meta, truncation)?;
 
     let dataset_path = create_dataproducts_gran_dataset(file, &rdr.meta.collection, &rawdata_path)
         .map_err(|e| {
@@ -126,32 +264,82 @@ pub fn write_rdr_granule(file: &File, gran_idx: usize, rdr: &Rdr) -> Result<()>
             ))
         })?;
 
-    write_product_dataset_attrs(file, &rdr.meta, &dataset_path)?;
+    write_product_dataset_attrs(file, &rdr.meta, &dataset_path, truncation, warnings)?;
 
     Ok(())
 }
 
 /// Write the `/All_Data/<shortname>_All/RawApplicationPackets_<idx>` dataset.
 ///
+/// The granule data is written directly from a view of `rdr.data` rather than an owned array,
+/// so a granule is not copied again before being handed to HDF5.
+///
 /// Returns the path of the written dataset.
-fn write_rdr_to_alldata(file: &File, gran_idx: usize, rdr: &Rdr) -> Result<String> {
+fn write_rdr_to_alldata(
+    file: &File,
+    gran_idx: usize,
+    rdr: &Rdr,
+    truncation: AttrTruncation,
+    warnings: &AttrWarnings,
+) -> Result<String> {
     if file.group("All_Data").is_err() {
         file.create_group("All_Data")?;
     }
+    let group_name = format!("All_Data/{}_All", rdr.meta.collection);
+    if file.group(&group_name).is_err() {
+        file.create_group(&group_name)?;
+    }
     let name = format!(
         "/All_Data/{}_All/RawApplicationPackets_{gran_idx}",
         rdr.meta.collection
     );
-    file.new_dataset_builder()
-        .with_data(&arr1(&rdr.data))
+    let dataset = file
+        .new_dataset_builder()
+        .with_data(rdr.data.as_slice())
         .create(name.clone().as_str())?;
+    let attrs = AttrWriter::new(&dataset, &[])
+        .with_truncation(truncation)
+        .with_warnings(warnings);
+    for (name, value) in &rdr.meta.raw_attrs {
+        attrs.write_extra(name, value)?;
+    }
     Ok(name)
 }
 
+/// Write `attrs` to the `/All_Data/<shortname>_All` group, creating it if necessary. Attributes
+/// the group already has are not overwritten, so the first written is kept, e.g., when
+/// aggregating granules from multiple files.
+pub fn write_all_data_attrs(
+    file: &File,
+    short_name: &str,
+    attrs: &BTreeMap<String, String>,
+    truncation: AttrTruncation,
+) -> Result<()> {
+    if attrs.is_empty() {
+        return Ok(());
+    }
+    let group_name = format!("All_Data/{short_name}_All");
+    let group = match file.group(&group_name) {
+        Ok(group) => group,
+        Err(_) => file.create_group(&group_name)?,
+    };
+    let writer = AttrWriter::new(&group, &[]).with_truncation(truncation);
+    for (name, value) in attrs {
+        if group.attr(name).is_err() {
+            writer.write_extra(name, value)?;
+        }
+    }
+    Ok(())
+}
+
 /// Create Data_Products/<shortname> and set attribtes.
 ///
 /// Returns the path to the group written.
-fn write_dataproduct_group(file: &File, meta: &ProductMeta) -> Result<String> {
+fn write_dataproduct_group(
+    file: &File,
+    meta: &ProductMeta,
+    truncation: AttrTruncation,
+) -> Result<String> {
     if file.group("Data_Products").is_err() {
         file.create_group("Data_Products")?;
     }
@@ -159,10 +347,11 @@ fn write_dataproduct_group(file: &File, meta: &ProductMeta) -> Result<String> {
     if file.group(&group_name).is_err() {
         let group = file.create_group(&group_name)?;
 
-        wattstr!(group, "Instrument_Short_Name", meta.instrument, 10);
-        wattstr!(group, "N_Collection_Short_Name", meta.collection, 20);
-        wattstr!(group, "N_Dataset_Type_Tag", meta.dataset_type, 3);
-        wattstr!(group, "N_Processing_Domain", meta.processing_domain, 3);
+        let attrs = AttrWriter::new(&group, PRODUCT_ATTRS).with_truncation(truncation);
+        attrs.write("Instrument_Short_Name", &meta.instrument)?;
+        attrs.write("N_Collection_Short_Name", &meta.collection)?;
+        attrs.write("N_Dataset_Type_Tag", &meta.dataset_type)?;
+        attrs.write("N_Processing_Domain", &meta.processing_domain)?;
     }
     Ok(group_name)
 }
@@ -170,76 +359,68 @@ fn write_dataproduct_group(file: &File, meta: &ProductMeta) -> Result<String> {
 /// Write attribute data from `meta` to the `Data_Products/<shortname>/<shortname>_Gran_<X>` dataset.
 ///
 /// The dataset at `dataset_path` must already exist.
-fn write_product_dataset_attrs(file: &File, meta: &GranuleMeta, dataset_path: &str) -> Result<()> {
+fn write_product_dataset_attrs(
+    file: &File,
+    meta: &GranuleMeta,
+    dataset_path: &str,
+    truncation: AttrTruncation,
+    warnings: &AttrWarnings,
+) -> Result<()> {
     let dataset = file
         .dataset(dataset_path)
         .unwrap_or_else(|_| panic!("expected just written dataset {dataset_path} to exist"));
 
-    wattstr!(dataset, "Beginning_Date", meta.begin_date, 8);
-    wattstr!(dataset, "Beginning_Time", meta.begin_time, 16);
-    wattstr!(dataset, "Ending_Date", meta.end_date, 8);
-    wattstr!(dataset, "Ending_Time", meta.end_time, 16);
-    wattstr!(dataset, "N_Creation_Date", meta.creation_date, 8);
-    wattstr!(dataset, "N_Creation_Time", meta.creation_time, 16);
-    wattstr!(dataset, "N_Granule_Status", meta.status, 3);
-    wattstr!(dataset, "N_Granule_Version", meta.version, 2);
-    wattstr!(dataset, "N_JPSS_Document_Ref", meta.jpss_doc, 52);
-    wattstr!(dataset, "N_LEOA_Flag", meta.leoa_flag, 3);
-    wattstr!(dataset, "N_Reference_ID", meta.reference_id, 39);
-    wattstr!(dataset, "N_Granule_ID", meta.id, 15);
-    wattstr!(dataset, "N_IDPS_Mode", meta.idps_mode, 3);
-    wattstr!(dataset, "N_Software_Version", meta.software_version, 19);
-    wattnum!(dataset, u64, "N_Beginning_Orbit_Number", meta.orbit_number);
-    wattnum!(dataset, u64, "N_Beginning_Time_IET", meta.begin_time_iet);
-    wattnum!(dataset, u64, "N_Ending_Time_IET", meta.end_time_iet);
-
-    // Compute packet type/count arrays
-    let mut pkt_type_arr: Vec<[FixedAscii<17>; 1]> = Vec::default();
-    let mut pkt_type_cnt_arr: Vec<u64> = Vec::default();
-    for (name, count) in meta.packet_type.iter().zip(&meta.packet_type_count) {
-        let ascii = FixedAscii::<17>::from_ascii(name.as_bytes()).map_err(|e| {
-            Error::Hdf5Other(format!("creating packet type attr ascii for {name}: {e}"))
-        })?;
-        pkt_type_arr.push([ascii]);
-        pkt_type_cnt_arr.push(u64::from(*count));
-    }
-
-    // Write N_Packet_Type
-    let name = "N_Packet_Type";
-    let attr = dataset
-        .new_attr::<FixedAscii<17>>()
-        .shape([pkt_type_arr.len(), 1])
-        .create(name)
-        .map_err(|e| Error::Hdf5Other(format!("creating attr N_Packet_Type for {name}: {e}")))?;
-    let arr = ndarray::arr2(&pkt_type_arr);
-    attr.write(&arr)
-        .map_err(|e| Error::Hdf5Other(format!("writing N_Packet_Type for {name}: {e}")))?;
-
-    let name = "N_Packet_Type_Count";
-    let attr = dataset
-        .new_attr::<u64>()
-        .shape([pkt_type_cnt_arr.len(), 1])
-        .create(name)
-        .map_err(|e| Error::Hdf5Other(format!("creating attr N_Packet_Count for {name}: {e}")))?;
-    attr.write_raw(&pkt_type_cnt_arr)
-        .map_err(|e| Error::Hdf5Other(format!("writing N_Packet_Count for {name}: {e}")))?;
-
-    let (name, val) = ("N_Percent_Missing_Data", meta.percent_missing);
-    let attr = dataset
-        .new_attr::<f32>()
-        .shape([1, 1])
-        .create(name)
-        .map_err(|e| Error::Hdf5Other(format!("creating attr {name}: {e}")))?;
-    attr.write_raw(&[val])
-        .map_err(|e| Error::Hdf5Other(format!("writing attr {name}: {e}")))?;
+    let attrs = AttrWriter::new(&dataset, GRANULE_ATTRS)
+        .with_truncation(truncation)
+        .with_warnings(warnings);
+    attrs.write("Beginning_Date", &meta.begin_date)?;
+    attrs.write("Beginning_Time", &meta.begin_time)?;
+    attrs.write("Ending_Date", &meta.end_date)?;
+    attrs.write("Ending_Time", &meta.end_time)?;
+    attrs.write("N_Creation_Date", &meta.creation_date)?;
+    attrs.write("N_Creation_Time", &meta.creation_time)?;
+    attrs.write("N_Granule_Status", &meta.status)?;
+    attrs.write("N_Granule_Version", &meta.version)?;
+    attrs.write("N_JPSS_Document_Ref", &meta.jpss_doc)?;
+    attrs.write("N_LEOA_Flag", &meta.leoa_flag)?;
+    attrs.write("N_Reference_ID", &meta.reference_id)?;
+    attrs.write("N_Granule_ID", &meta.id)?;
+    attrs.write("N_IDPS_Mode", &meta.idps_mode)?;
+    attrs.write("N_Software_Version", &meta.software_version)?;
+    attrs.write("N_Beginning_Orbit_Number", meta.orbit_number)?;
+    attrs.write("N_Beginning_Time_IET", meta.begin_time_iet.0)?;
+    attrs.write("N_Ending_Time_IET", meta.end_time_iet.0)?;
+
+    let packet_type_count: Vec<u64> = meta
+        .packet_type_count
+        .iter()
+        .map(|count| u64::from(*count))
+        .collect();
+    attrs.write("N_Packet_Type", meta.packet_type.as_slice())?;
+    attrs.write("N_Packet_Type_Count", packet_type_count.as_slice())?;
+    attrs.write("N_Percent_Missing_Data", meta.percent_missing)?;
+
+    for (name, value) in &meta.extra_attrs {
+        attrs.write_extra(name, value)?;
+    }
 
     Ok(())
 }
 
+/// Whether `name` is one of the standard granule dataset attributes.
+pub(crate) fn is_granule_attr(name: &str) -> bool {
+    GRANULE_ATTRS.iter().any(|s| s.name == name)
+}
+
 /// Write the `Data_Products/<shortname>/<shortname>_Aggr` dataset.
 ///
 /// Returns the path to the dataset.
-fn write_aggr_dataset(file: &File, short_name: &str, meta: &AggrMeta) -> Result<String> {
+fn write_aggr_dataset(
+    file: &File,
+    short_name: &str,
+    meta: &AggrMeta,
+    truncation: AttrTruncation,
+) -> Result<String> {
     let group_name = format!("All_Data/{}_All", short_name);
     if file.group(&group_name).is_err() {
         file.create_group(&group_name)?;
@@ -251,55 +432,826 @@ fn write_aggr_dataset(file: &File, short_name: &str, meta: &AggrMeta) -> Result<
         .dataset(&dataset_path)
         .map_err(|e| Error::Hdf5Other(format!("opening dataset {dataset_path}: {e}")))?;
 
-    wattnum!(
-        dataset,
-        u32,
-        "AggregateBeginningOrbitNumber",
-        meta.begin_orbit_nubmer
-    );
-    wattnum!(
-        dataset,
-        u32,
-        "AggregateEndingOrbitNumber",
-        meta.end_orbit_number
-    );
-    wattnum!(dataset, u32, "AggregateNumberGranules", meta.num_granules);
-
-    wattstr!(
-        dataset,
-        "AggregateBeginningDate",
-        meta.begin_date.to_string(),
-        20
-    );
-    wattstr!(
-        dataset,
-        "AggregateBeginningTime",
-        meta.begin_time.to_string(),
-        20
-    );
-    wattstr!(
-        dataset,
-        "AggregateBeginningGranuleID",
-        meta.begin_granule_id.to_string(),
-        20
-    );
-    wattstr!(
-        dataset,
-        "AggregateEndingDate",
-        meta.end_date.to_string(),
-        20
-    );
-    wattstr!(
-        dataset,
-        "AggregateEndingTime",
-        meta.end_time.to_string(),
-        20
-    );
-    wattstr!(
-        dataset,
-        "AggregateEndingGranuleID",
-        meta.end_granule_id.to_string(),
-        20
-    );
+    let attrs = AttrWriter::new(&dataset, AGGR_ATTRS).with_truncation(truncation);
+    attrs.write("AggregateBeginningOrbitNumber", meta.begin_orbit_number)?;
+    attrs.write("AggregateEndingOrbitNumber", meta.end_orbit_number)?;
+    attrs.write("AggregateNumberGranules", meta.num_granules)?;
+    attrs.write("AggregateBeginningDate", &meta.begin_date)?;
+    attrs.write("AggregateBeginningTime", &meta.begin_time)?;
+    attrs.write("AggregateBeginningGranuleID", &meta.begin_granule_id)?;
+    attrs.write("AggregateEndingDate", &meta.end_date)?;
+    attrs.write("AggregateEndingTime", &meta.end_time)?;
+    attrs.write("AggregateEndingGranuleID", &meta.end_granule_id)?;
     Ok(dataset_path)
 }
+
+/// Rename the granule datasets for each collection in the RDR at `path` such that the
+/// `RawApplicationPackets_<N>` and `<short_name>_Gran_<N>` indexes are contiguous and in
+/// granule begin time order.
+///
+/// Granule dataset region references refer to the referenced dataset object rather than its
+/// name, so they remain valid after renaming.
+///
+/// Granules are renamed in a copy of the file that replaces it once complete, so the file is
+/// left unchanged on error. Files already in order are not copied.
+///
+/// Returns the number of granules whose index changed.
+pub fn normalize_rdr<P: AsRef<Path>>(path: P) -> Result<usize> {
+    let path = path.as_ref();
+    if normalize_at(path, false)? == 0 {
+        return Ok(0);
+    }
+
+    let tmp_path = in_progress_path(path);
+    std::fs::copy(path, &tmp_path)?;
+    let changed = match normalize_at(&tmp_path, true) {
+        Ok(changed) => changed,
+        Err(err) => {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(err);
+        }
+    };
+    std::fs::rename(&tmp_path, path)?;
+
+    Ok(changed)
+}
+
+/// Number of granules in the RDR at `path` whose index is not in time order, renaming them if
+/// `rename` is set. See [normalize_rdr].
+fn normalize_at(path: &Path, rename: bool) -> Result<usize> {
+    let file = if rename {
+        File::open_rw(path)?
+    } else {
+        File::open(path)?
+    };
+    let mut changed = 0;
+
+    for group in file.group("Data_Products")?.groups()? {
+        let short_name = group
+            .name()
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let gran_prefix = format!("{short_name}_Gran_");
+        let rawap_prefix = "RawApplicationPackets_";
+
+        // Current granule index and granule begin time
+        let mut granules: Vec<(usize, u64)> = Vec::default();
+        for name in group.member_names()? {
+            let Some(idx) = name
+                .strip_prefix(&gran_prefix)
+                .and_then(|s| s.parse::<usize>().ok())
+            else {
+                continue;
+            };
+            let begin = group
+                .dataset(&name)?
+                .attr("N_Beginning_Time_IET")?
+                .read_2d::<u64>()?[[0, 0]];
+            granules.push((idx, begin));
+        }
+        granules.sort_by_key(|(idx, begin)| (*begin, *idx));
+        if granules
+            .iter()
+            .enumerate()
+            .all(|(new_idx, (idx, _))| new_idx == *idx)
+        {
+            continue;
+        }
+        if !rename {
+            changed += granules
+                .iter()
+                .enumerate()
+                .filter(|(new_idx, (idx, _))| new_idx != idx)
+                .count();
+            continue;
+        }
+
+        let all_data = file.group(&format!("All_Data/{short_name}_All"))?;
+        // Move everything to temporary names first so renames cannot collide
+        for (idx, _) in &granules {
+            group.relink(
+                &format!("{gran_prefix}{idx}"),
+                &format!("{gran_prefix}{idx}_tmp"),
+            )?;
+            all_data.relink(
+                &format!("{rawap_prefix}{idx}"),
+                &format!("{rawap_prefix}{idx}_tmp"),
+            )?;
+        }
+        for (new_idx, (idx, _)) in granules.iter().enumerate() {
+            group.relink(
+                &format!("{gran_prefix}{idx}_tmp"),
+                &format!("{gran_prefix}{new_idx}"),
+            )?;
+            all_data.relink(
+                &format!("{rawap_prefix}{idx}_tmp"),
+                &format!("{rawap_prefix}{new_idx}"),
+            )?;
+            if new_idx != *idx {
+                changed += 1;
+            }
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Verify the RDR file at `path` can be read back, e.g., immediately after writing it.
+///
+/// Checks that file, product, and granule attributes can be read, that every granule has a
+/// readable `RawApplicationPackets` dataset, and that each dataset is a valid Common RDR
+/// whose packet trackers are within its packet storage.
+///
+/// Returns the number of granules verified.
+///
+/// # Errors
+/// [RdrError::Invalid] describing the first problem found, or any HDF5 error.
+pub fn verify_rdr<P: AsRef<Path>>(path: P) -> Result<usize> {
+    let path = path.as_ref();
+    let meta = Meta::from_file(path)?;
+    let file = File::open(path)?;
+    let invalid = |msg: String| Error::RdrError(RdrError::Invalid(msg));
+
+    let mut verified = 0;
+    for (short_name, granules) in &meta.granules {
+        let all_data = file
+            .group(&format!("All_Data/{short_name}_All"))
+            .map_err(|e| invalid(format!("{short_name}: missing All_Data group: {e}")))?;
+        let mut num_datasets = 0;
+        for name in all_data.member_names()? {
+            if !name.starts_with("RawApplicationPackets_") {
+                continue;
+            }
+            num_datasets += 1;
+            let data = all_data
+                .dataset(&name)
+                .and_then(|ds| ds.read_raw::<u8>())
+                .map_err(|e| invalid(format!("{short_name}/{name}: {e}")))?;
+            validate_common_rdr(&data).map_err(|e| invalid(format!("{short_name}/{name}: {e}")))?;
+            let common = CommonRdr::from_bytes(&data)?;
+            let storage_len = data.len() - common.static_header.ap_storage_offset as usize;
+            for tracker in &common.packet_trackers {
+                // Negative offsets indicate fill for missing packets
+                let (Ok(offset), Ok(size)) = (
+                    usize::try_from(tracker.offset),
+                    usize::try_from(tracker.size),
+                ) else {
+                    continue;
+                };
+                if offset + size > storage_len {
+                    return Err(invalid(format!(
+                        "{short_name}/{name}: packet tracker offset={offset} size={size} beyond \
+                         ap storage length {storage_len}"
+                    )));
+                }
+            }
+            verified += 1;
+        }
+        if num_datasets != granules.len() {
+            return Err(invalid(format!(
+                "{short_name}: {} granules but {num_datasets} RawApplicationPackets datasets",
+                granules.len()
+            )));
+        }
+    }
+
+    Ok(verified)
+}
+
+/// Append the granule `rdr` to the existing RDR file at `path`.
+///
+/// The granule is written using the next available granule index for its collection and the
+/// collection `_Aggr` dataset is recreated to include it. If the file does not yet contain the
+/// collection its `Data_Products` and `All_Data` groups are created. If the file has an IDPS
+/// style name it is renamed so the name reflects the product ids and time span of the file
+/// after the append.
+///
+/// Returns the path to the file, which may differ from `path` if the file was renamed.
+///
+/// # Errors
+/// If a granule with the same granule id already exists for the collection or on any HDF5 or
+/// IO error.
+pub fn append_rdr_granule<P: AsRef<Path>>(path: P, rdr: &Rdr) -> Result<PathBuf> {
+    let path = path.as_ref();
+    let meta = Meta::from_file(path)?;
+    let short_name = &rdr.meta.collection;
+    let existing: Vec<&GranuleMeta> = meta
+        .granules
+        .get(short_name)
+        .map(|g| g.iter().collect())
+        .unwrap_or_default();
+    if existing.iter().any(|g| g.id == rdr.meta.id) {
+        return Err(Error::RdrError(RdrError::Invalid(format!(
+            "granule {} already exists in {short_name}",
+            rdr.meta.id
+        ))));
+    }
+
+    let file = File::open_rw(path)?;
+    let opts = WriteOptions::default().with_attr_truncation(meta.attr_truncation);
+    write_rdr_granule(&file, existing.len(), rdr, &opts)?;
+
+    let mut granules = existing;
+    granules.push(&rdr.meta);
+    let aggr_name = format!("{short_name}_Aggr");
+    let group = file.group(&format!("Data_Products/{short_name}"))?;
+    if group.link_exists(&aggr_name) {
+        group.unlink(&aggr_name)?;
+    }
+    write_aggr_dataset(
+        &file,
+        short_name,
+        &AggrMeta::from_granules(&granules),
+        meta.attr_truncation,
+    )?;
+    file.close()?;
+
+    // Science granules determine the file time, if there are any, otherwise all granules
+    let mut all: Vec<&GranuleMeta> = meta.granules.values().flatten().collect();
+    all.push(&rdr.meta);
+    let science: Vec<&GranuleMeta> = all
+        .iter()
+        .copied()
+        .filter(|g| g.collection.contains("SCIENCE"))
+        .collect();
+    let timed = if science.is_empty() { &all } else { &science };
+    let start = timed.iter().map(|g| g.begin_time_iet).min();
+    let end = timed.iter().map(|g| g.end_time_iet).max();
+    let (Some(start), Some(end)) = (start, end) else {
+        return Ok(path.to_path_buf());
+    };
+    let Some(name) = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .and_then(|n| appended_filename(n, &rdr.product_id, start, end))
+    else {
+        return Ok(path.to_path_buf());
+    };
+    let dest = path.with_file_name(name);
+    if dest != path {
+        std::fs::rename(path, &dest)?;
+    }
+    Ok(dest)
+}
+
+/// Replace the granule in the existing RDR file at `path` having the same collection and
+/// granule id as `rdr`, in place, e.g., to repair a single bad granule.
+///
+/// The granule's `RawApplicationPackets_<N>` and `<short_name>_Gran_<N>` datasets are
+/// re-written from `rdr` using the same granule index, and the collection `_Aggr` dataset is
+/// recreated; all other file content is unchanged. HDF5 does not reclaim the space used by
+/// the replaced datasets, so the file may grow; use `h5repack` to reclaim it if necessary.
+///
+/// The granule is replaced in a copy of the file at [in_progress_path] which is renamed over
+/// `path` once complete, so a failure leaves the original file unchanged.
+///
+/// Returns the index of the replaced granule.
+///
+/// # Errors
+/// If the file has no granule with the collection and granule id of `rdr` or on any HDF5
+/// error.
+pub fn replace_rdr_granule<P: AsRef<Path>>(path: P, rdr: &Rdr) -> Result<usize> {
+    let path = path.as_ref();
+    let meta = Meta::from_file(path)?;
+    let short_name = &rdr.meta.collection;
+    let not_found = || {
+        Error::RdrError(RdrError::Invalid(format!(
+            "granule {} does not exist in {short_name}",
+            rdr.meta.id
+        )))
+    };
+    let granules = meta.granules.get(short_name).ok_or_else(not_found)?;
+    if !granules.iter().any(|g| g.id == rdr.meta.id) {
+        return Err(not_found());
+    }
+
+    let file = File::open(path)?;
+    let group = file.group(&format!("Data_Products/{short_name}"))?;
+    let gran_prefix = format!("{short_name}_Gran_");
+    let mut gran_idx = None;
+    for name in group.member_names()? {
+        let Some(idx) = name
+            .strip_prefix(&gran_prefix)
+            .and_then(|s| s.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        if read_attr_string(&group.dataset(&name)?, "N_Granule_ID")? == rdr.meta.id {
+            gran_idx = Some(idx);
+            break;
+        }
+    }
+    let gran_idx = gran_idx.ok_or_else(not_found)?;
+    file.close()?;
+
+    let tmp_path = in_progress_path(path);
+    std::fs::copy(path, &tmp_path)?;
+    let zult = replace_granule_at(&tmp_path, gran_idx, rdr, &meta, granules);
+    if let Err(err) = zult {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(err);
+    }
+    std::fs::rename(&tmp_path, path)?;
+
+    Ok(gran_idx)
+}
+
+/// Replace granule `gran_idx` of the file at `path`, whose metadata is `meta` and
+/// collection granules are `granules`, with `rdr`. See [replace_rdr_granule].
+fn replace_granule_at(
+    path: &Path,
+    gran_idx: usize,
+    rdr: &Rdr,
+    meta: &Meta,
+    granules: &[GranuleMeta],
+) -> Result<()> {
+    let short_name = &rdr.meta.collection;
+    let file = File::open_rw(path)?;
+    let group = file.group(&format!("Data_Products/{short_name}"))?;
+    group.unlink(&format!("{short_name}_Gran_{gran_idx}"))?;
+    file.group(&format!("All_Data/{short_name}_All"))?
+        .unlink(&format!("RawApplicationPackets_{gran_idx}"))?;
+    let opts = WriteOptions::default().with_attr_truncation(meta.attr_truncation);
+    write_rdr_granule(&file, gran_idx, rdr, &opts)?;
+
+    let granules: Vec<&GranuleMeta> = granules
+        .iter()
+        .map(|g| if g.id == rdr.meta.id { &rdr.meta } else { g })
+        .collect();
+    let aggr_name = format!("{short_name}_Aggr");
+    if group.link_exists(&aggr_name) {
+        group.unlink(&aggr_name)?;
+    }
+    write_aggr_dataset(
+        &file,
+        short_name,
+        &AggrMeta::from_granules(&granules),
+        meta.attr_truncation,
+    )?;
+    Ok(file.close()?)
+}
+
+/// Update the product ids and start/end times of the IDPS style RDR filename `name`.
+///
+/// Returns `None` if `name` is not an IDPS style filename.
+fn appended_filename(
+    name: &str,
+    product_id: &str,
+    start: IetMicros,
+    end: IetMicros,
+) -> Option<String> {
+    let parts: Vec<&str> = name.split('_').collect();
+    if parts.len() < 8
+        || !parts[2].starts_with('d')
+        || !parts[3].starts_with('t')
+        || !parts[4].starts_with('e')
+    {
+        return None;
+    }
+    let mut product_ids: Vec<&str> = parts[0].split('-').collect();
+    if !product_ids.contains(&product_id) {
+        product_ids.push(product_id);
+        product_ids.sort_unstable();
+    }
+    let (start, end) = (Time::from(start), Time::from(end));
+    let product_ids = product_ids.join("-");
+    let date = format!("d{}", start.format_utc("%Y%m%d"));
+    let start = format!("t{}", &start.format_utc("%H%M%S%f")[..7]);
+    let end = format!("e{}", &end.format_utc("%H%M%S%f")[..7]);
+    let mut parts = parts;
+    parts[0] = product_ids.as_str();
+    parts[2] = date.as_str();
+    parts[3] = start.as_str();
+    parts[4] = end.as_str();
+    Some(parts.join("_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::config::get_default;
+
+    #[test]
+    fn test_normalize_rdr() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("test.h5");
+
+        // Write granules in reverse time order
+        let file = File::create(&path).unwrap();
+        let opts = WriteOptions::default();
+        write_rdr_meta(&file, &Meta::from_config(&config), &opts).unwrap();
+        let mut rdrs = Vec::default();
+        for (idx, num) in [2, 1, 0].into_iter().enumerate() {
+            let time = Time::from(config.satellite.base_time + product.gran_len * num);
+            let rdr = Rdr {
+                meta: GranuleMeta::new(time, &config.satellite, product).unwrap(),
+                product_id: product.product_id.clone(),
+                data: vec![num as u8; 10],
+            };
+            write_rdr_granule(&file, idx, &rdr, &opts).unwrap();
+            rdrs.push(rdr);
+        }
+        write_aggr_dataset(
+            &file,
+            &product.short_name,
+            &AggrMeta::from_rdrs(&rdrs),
+            AttrTruncation::Warn,
+        )
+        .unwrap();
+        file.close().unwrap();
+
+        assert_eq!(normalize_rdr(&path).unwrap(), 2);
+        assert!(!in_progress_path(&path).exists());
+
+        {
+            let file = File::open(&path).unwrap();
+            for idx in 0..3 {
+                let data = file
+                    .dataset(&format!(
+                        "All_Data/{}_All/RawApplicationPackets_{idx}",
+                        product.short_name
+                    ))
+                    .unwrap()
+                    .read_raw::<u8>()
+                    .unwrap();
+                assert_eq!(data, vec![idx as u8; 10]);
+            }
+        }
+        // already normalized
+        assert_eq!(normalize_rdr(&path).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_appended_filename() {
+        let name = "RVIRS_npp_d20240101_t0000000_e0001000_b00000_c20240101000200000000_cspp_dev.h5";
+        let start = Time::from_str("2024-01-01T00:00:00Z").unwrap().iet_micros();
+        let end = Time::from_str("2024-01-01T00:02:00Z").unwrap().iet_micros();
+        assert_eq!(
+            appended_filename(name, "RNSCA", start, end).unwrap(),
+            "RNSCA-RVIRS_npp_d20240101_t0000000_e0002000_b00000_c20240101000200000000_cspp_dev.h5"
+        );
+        assert!(appended_filename("test.h5", "RNSCA", start, end).is_none());
+    }
+
+    #[test]
+    fn test_verify_rdr() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let time = Time::from(config.satellite.base_time);
+        // apid 826 w/ secondary header, unsegmented, 4 bytes of user data
+        let dat = [0x0b, 0x3a, 0xc0, 0x01, 0x00, 0x03, 0xaa, 0xaa, 0xaa, 0xaa];
+        let pkt = ccsds::spacepacket::decode_packets(&dat[..])
+            .next()
+            .unwrap()
+            .unwrap();
+        let mut rdr_data = crate::RdrData::new(&config.satellite, product, &time);
+        rdr_data.add_packet(&time, pkt).unwrap();
+        let rdr = rdr_data.compile().unwrap();
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("valid.h5");
+        let meta = Meta::from_products(&[product.short_name.clone()], &config).unwrap();
+        create_rdr(&path, meta.clone(), &[rdr.clone()]).unwrap();
+        assert_eq!(verify_rdr(&path).unwrap(), 1);
+
+        let path = dir.path().join("invalid.h5");
+        let invalid = Rdr {
+            data: rdr.data[..rdr.data.len() - 1].to_vec(),
+            ..rdr
+        };
+        create_rdr(&path, meta, &[invalid]).unwrap();
+        assert!(verify_rdr(&path).is_err());
+    }
+
+    #[test]
+    fn test_append_rdr_granule() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let rdr = |num: u64| {
+            let time = Time::from(config.satellite.base_time + product.gran_len * num);
+            Rdr {
+                meta: GranuleMeta::new(time, &config.satellite, product).unwrap(),
+                product_id: product.product_id.clone(),
+                data: vec![num as u8; 10],
+            }
+        };
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("test.h5");
+        let meta = Meta::from_products(&[product.short_name.clone()], &config).unwrap();
+        create_rdr(&path, meta, &[rdr(0)]).unwrap();
+
+        let zult = append_rdr_granule(&path, &rdr(1)).unwrap();
+        assert_eq!(zult, path, "non-IDPS names are not changed");
+        assert!(
+            append_rdr_granule(&path, &rdr(1)).is_err(),
+            "duplicate granule"
+        );
+
+        let meta = Meta::from_file(&path).unwrap();
+        assert_eq!(meta.granules[&product.short_name].len(), 2);
+        let file = File::open(&path).unwrap();
+        let aggr = file
+            .dataset(&format!("Data_Products/{0}/{0}_Aggr", product.short_name))
+            .unwrap();
+        assert_eq!(
+            aggr.attr("AggregateNumberGranules")
+                .unwrap()
+                .read_2d::<u32>()
+                .unwrap()[[0, 0]],
+            2
+        );
+        let data = file
+            .dataset(&format!(
+                "All_Data/{}_All/RawApplicationPackets_1",
+                product.short_name
+            ))
+            .unwrap()
+            .read_raw::<u8>()
+            .unwrap();
+        assert_eq!(data, vec![1u8; 10]);
+        drop(file);
+
+        // A collection not already in the file
+        let diary = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RNSCA")
+            .unwrap();
+        let time = Time::from(config.satellite.base_time);
+        let rdr = Rdr {
+            meta: GranuleMeta::new(time, &config.satellite, diary).unwrap(),
+            product_id: diary.product_id.clone(),
+            data: vec![9u8; 10],
+        };
+        append_rdr_granule(&path, &rdr).unwrap();
+
+        let meta = Meta::from_file(&path).unwrap();
+        assert_eq!(meta.granules[&product.short_name].len(), 2);
+        assert_eq!(meta.granules[&diary.short_name].len(), 1);
+        assert_eq!(
+            meta.products[&diary.short_name].collection,
+            diary.short_name
+        );
+    }
+
+    #[test]
+    fn test_append_rdr_granule_file_time() {
+        let config = get_default("npp").unwrap().unwrap();
+        let rdr = |product_id: &str, time: u64| {
+            let product = config
+                .products
+                .iter()
+                .find(|p| p.product_id == product_id)
+                .unwrap();
+            Rdr {
+                meta: GranuleMeta::new(Time::from(time), &config.satellite, product).unwrap(),
+                product_id: product.product_id.clone(),
+                data: vec![0u8; 10],
+            }
+        };
+        let diary = rdr("RNSCA", config.satellite.base_time + 60_000_000);
+        let telemetry = rdr("RATMT", config.satellite.base_time);
+        let dir = tempfile::TempDir::new().unwrap();
+        let name = "RNSCA_npp_d20240101_t0000000_e0001000_b00000_c20240101000200000000_cspp_dev.h5";
+        let path = dir.path().join(name);
+        let meta = Meta::from_products(&[diary.meta.collection.clone()], &config).unwrap();
+        create_rdr(&path, meta, &[diary.clone()]).unwrap();
+
+        // Without science granules the file time spans all granules, not just the appended
+        // collection's
+        let zult = append_rdr_granule(&path, &telemetry).unwrap();
+        let start = telemetry.meta.begin_time_iet;
+        let end = diary.meta.end_time_iet.max(telemetry.meta.end_time_iet);
+        assert_eq!(
+            zult,
+            dir.path()
+                .join(appended_filename(name, "RATMT", start, end).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_replace_rdr_granule() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let rdr = |num: u64, fill: u8| {
+            let time = Time::from(config.satellite.base_time + product.gran_len * num);
+            Rdr {
+                meta: GranuleMeta::new(time, &config.satellite, product).unwrap(),
+                product_id: product.product_id.clone(),
+                data: vec![fill; 10],
+            }
+        };
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("test.h5");
+        let meta = Meta::from_products(&[product.short_name.clone()], &config).unwrap();
+        create_rdr(&path, meta, &[rdr(0, 0), rdr(1, 1), rdr(2, 2)]).unwrap();
+
+        let mut replacement = rdr(1, 0xff);
+        replacement.meta.set_version("A2").unwrap();
+        assert_eq!(replace_rdr_granule(&path, &replacement).unwrap(), 1);
+        assert!(!in_progress_path(&path).exists());
+        assert!(
+            replace_rdr_granule(&path, &rdr(3, 3)).is_err(),
+            "granule does not exist"
+        );
+
+        let meta = Meta::from_file(&path).unwrap();
+        let granules = &meta.granules[&product.short_name];
+        assert_eq!(granules.len(), 3);
+        let replaced = granules
+            .iter()
+            .find(|g| g.id == replacement.meta.id)
+            .unwrap();
+        assert_eq!(replaced.version, "A2");
+        let file = File::open(&path).unwrap();
+        for (idx, fill) in [(0, 0u8), (1, 0xff), (2, 2)] {
+            let data = file
+                .dataset(&format!(
+                    "All_Data/{}_All/RawApplicationPackets_{idx}",
+                    product.short_name
+                ))
+                .unwrap()
+                .read_raw::<u8>()
+                .unwrap();
+            assert_eq!(data, vec![fill; 10], "granule {idx}");
+        }
+    }
+
+    #[test]
+    fn test_all_data_attrs() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let time = Time::from(config.satellite.base_time);
+        let mut rdr = Rdr {
+            meta: GranuleMeta::new(time, &config.satellite, product).unwrap(),
+            product_id: product.product_id.clone(),
+            data: vec![0u8; 10],
+        };
+        rdr.meta
+            .raw_attrs
+            .insert("Source_File".to_string(), "input.pds".to_string());
+        let mut meta = Meta::from_products(&[product.short_name.clone()], &config).unwrap();
+        meta.products
+            .get_mut(&product.short_name)
+            .unwrap()
+            .all_data_attrs
+            .insert("Provenance".to_string(), "station-a".to_string());
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("test.h5");
+        create_rdr(&path, meta, &[rdr]).unwrap();
+
+        let meta = Meta::from_file(&path).unwrap();
+        assert_eq!(
+            meta.products[&product.short_name].all_data_attrs["Provenance"],
+            "station-a"
+        );
+        assert_eq!(
+            meta.granules[&product.short_name][0].raw_attrs["Source_File"],
+            "input.pds"
+        );
+
+        // existing attributes are not overwritten
+        let file = File::open_rw(&path).unwrap();
+        let attrs = BTreeMap::from([("Provenance".to_string(), "station-b".to_string())]);
+        write_all_data_attrs(&file, &product.short_name, &attrs, AttrTruncation::Warn).unwrap();
+        // numeric attributes are dropped rather than failing the read
+        file.group(&format!("All_Data/{}_All", product.short_name))
+            .unwrap()
+            .new_attr::<u32>()
+            .create("Station_Id")
+            .unwrap()
+            .write_scalar(&7)
+            .unwrap();
+        drop(file);
+        let meta = Meta::from_file(&path).unwrap();
+        let attrs = &meta.products[&product.short_name].all_data_attrs;
+        assert_eq!(attrs["Provenance"], "station-a");
+        assert!(!attrs.contains_key("Station_Id"));
+    }
+
+    #[test]
+    fn test_rename_on_complete() {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let rdr = Rdr {
+            meta: GranuleMeta::new(
+                Time::from(config.satellite.base_time),
+                &config.satellite,
+                product,
+            )
+            .unwrap(),
+            product_id: product.product_id.clone(),
+            data: vec![0u8; 10],
+        };
+        let meta = Meta::from_products(&[product.short_name.clone()], &config).unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let dest = dir.path().join("test.h5");
+
+        let zult = Hdf5Writer::default()
+            .with_rename_on_complete(true)
+            .write(&dest, meta, &[rdr])
+            .unwrap();
+
+        assert_eq!(zult, dest);
+        assert!(dest.exists());
+        assert_eq!(
+            in_progress_path(&dest),
+            dir.path().join("test.h5.inprogress")
+        );
+        assert!(!in_progress_path(&dest).exists());
+        assert!(File::open(&dest).unwrap().group("All_Data").is_ok());
+    }
+
+    fn packed_fixture(empty: EmptyCollections) -> (tempfile::TempDir, PathBuf) {
+        let config = get_default("npp").unwrap().unwrap();
+        let product = config
+            .products
+            .iter()
+            .find(|p| p.product_id == "RVIRS")
+            .unwrap();
+        let rdr = Rdr {
+            meta: GranuleMeta::new(
+                Time::from(config.satellite.base_time),
+                &config.satellite,
+                product,
+            )
+            .unwrap(),
+            product_id: product.product_id.clone(),
+            data: vec![0u8; 10],
+        };
+        // Includes the packed SPACECRAFT-DIARY-RDR product, but with no granules
+        let meta = Meta::from_products(
+            &[
+                product.short_name.clone(),
+                "SPACECRAFT-DIARY-RDR".to_string(),
+            ],
+            &config,
+        )
+        .unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("test.h5");
+        create_rdr_with(&path, meta, &[rdr], empty).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn test_empty_collections_omit() {
+        let (_dir, path) = packed_fixture(EmptyCollections::Omit);
+
+        let file = File::open(&path).unwrap();
+        assert!(file.group("Data_Products/VIIRS-SCIENCE-RDR").is_ok());
+        assert!(file.group("Data_Products/SPACECRAFT-DIARY-RDR").is_err());
+        assert!(file.group("All_Data/SPACECRAFT-DIARY-RDR_All").is_err());
+    }
+
+    #[test]
+    fn test_empty_collections_write() {
+        let (_dir, path) = packed_fixture(EmptyCollections::Write);
+
+        let file = File::open(&path).unwrap();
+        assert!(file.group("All_Data/SPACECRAFT-DIARY-RDR_All").is_ok());
+        let aggr = file
+            .dataset("Data_Products/SPACECRAFT-DIARY-RDR/SPACECRAFT-DIARY-RDR_Aggr")
+            .unwrap();
+        assert_eq!(
+            aggr.attr("AggregateNumberGranules")
+                .unwrap()
+                .read_2d::<u32>()
+                .unwrap()[[0, 0]],
+            0
+        );
+        let meta = Meta::from_file(&path).unwrap();
+        assert!(meta.products.contains_key("SPACECRAFT-DIARY-RDR"));
+        assert!(!meta.granules.contains_key("SPACECRAFT-DIARY-RDR"));
+    }
+}
@@ -0,0 +1,226 @@
+use std::{fmt, path::Path};
+
+use hdf5::File;
+
+use super::attrs::AttrWarnings;
+use crate::{
+    config::AttrTruncation,
+    error::{Error, RdrError, Result},
+};
+
+/// Bounds on the HDF5 library versions whose object formats may be used when writing.
+///
+/// Restricting the bounds keeps files readable by software linked against older HDF5
+/// libraries, e.g., IDPS and SDR software linked against HDF5 1.8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibverBounds {
+    /// Use the earliest possible format for each object; the HDF5 default
+    Earliest,
+    /// Use only HDF5 1.8 object formats
+    V18,
+    /// Use only HDF5 1.10 object formats
+    V110,
+    /// Use the latest object formats supported by the linked library
+    Latest,
+}
+
+/// HDF5 file creation and access properties used when creating RDR files.
+///
+/// Properties that are not set use the HDF5 library defaults.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileOptions {
+    userblock: Option<u64>,
+    chunk_cache: Option<(usize, usize, f64)>,
+    libver: Option<LibverBounds>,
+    memory_image: Option<usize>,
+}
+
+/// Bytes by which the in-memory file image grows as it is written.
+const MEMORY_IMAGE_INCREMENT: usize = 1024 * 1024;
+
+impl FileOptions {
+    /// Reserve a userblock of `size` bytes at the start of the file. Must be 0 or a power of
+    /// 2 of at least 512.
+    #[must_use]
+    pub fn with_userblock(mut self, size: u64) -> Self {
+        self.userblock = Some(size);
+        self
+    }
+
+    /// Set the raw data chunk cache to `nslots` hash table slots and `nbytes` total size,
+    /// with preemption policy `w0`, between 0 and 1.
+    #[must_use]
+    pub fn with_chunk_cache(mut self, nslots: usize, nbytes: usize, w0: f64) -> Self {
+        self.chunk_cache = Some((nslots, nbytes, w0));
+        self
+    }
+
+    /// Restrict the object formats used to those supported by `bounds`. See [LibverBounds].
+    #[must_use]
+    pub fn with_libver_bounds(mut self, bounds: LibverBounds) -> Self {
+        self.libver = Some(bounds);
+        self
+    }
+
+    /// Build files whose granule data totals at most `max_size` bytes in memory, using the HDF5
+    /// core driver, and write the complete file image to disk in one write when closed. This
+    /// reduces filesystem metadata operations when writing many small files, e.g., to network
+    /// storage. Larger files are written directly to disk.
+    #[must_use]
+    pub fn with_memory_image(mut self, max_size: usize) -> Self {
+        self.memory_image = Some(max_size);
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(size) = self.userblock {
+            if size != 0 && (size < 512 || !size.is_power_of_two()) {
+                return Err(Error::RdrError(RdrError::Invalid(format!(
+                    "userblock size must be 0 or a power of 2 >= 512, got {size}"
+                ))));
+            }
+        }
+        if let Some((_, _, w0)) = self.chunk_cache {
+            if !(0.0..=1.0).contains(&w0) {
+                return Err(Error::RdrError(RdrError::Invalid(format!(
+                    "chunk cache w0 must be between 0 and 1, got {w0}"
+                ))));
+            }
+        }
+        Ok(())
+    }
+
+    /// Create, truncating any existing file, the HDF5 file at `path` using these options.
+    ///
+    /// # Errors
+    /// If the options are invalid or on any HDF5 error.
+    pub fn create<P: AsRef<Path>>(&self, path: P) -> Result<File> {
+        self.create_sized(path, None)
+    }
+
+    /// Create the HDF5 file at `path` like [FileOptions::create], where `data_size` is the
+    /// expected total size of granule data to be written, if known. The file is built in memory
+    /// if the size is within the [FileOptions::with_memory_image] limit.
+    ///
+    /// # Errors
+    /// If the options are invalid or on any HDF5 error.
+    pub fn create_sized<P: AsRef<Path>>(&self, path: P, data_size: Option<usize>) -> Result<File> {
+        self.validate()?;
+        let in_memory = matches!(
+            (self.memory_image, data_size),
+            (Some(max_size), Some(size)) if size <= max_size
+        );
+        let mut builder = File::with_options();
+        if let Some(size) = self.userblock {
+            builder.with_fcpl(|p| p.userblock(size));
+        }
+        builder.with_fapl(|p| {
+            if let Some((nslots, nbytes, w0)) = self.chunk_cache {
+                p.chunk_cache(nslots, nbytes, w0);
+            }
+            if in_memory {
+                // Back the image with the file so it is written to disk on close
+                p.core_options(MEMORY_IMAGE_INCREMENT, true);
+            }
+            match self.libver {
+                Some(LibverBounds::Earliest) => p.libver_earliest(),
+                Some(LibverBounds::V18) => p.libver_v18(),
+                Some(LibverBounds::V110) => p.libver_v110(),
+                Some(LibverBounds::Latest) => p.libver_latest(),
+                None => p,
+            }
+        });
+        Ok(builder.create(path)?)
+    }
+}
+
+/// Options for writing metadata and granules to a file with [crate::write_rdr_meta] and
+/// [crate::write_rdr_granule].
+///
+/// Use the same options for all granules written to a file so attribute truncation warnings
+/// are summarized rather than logged for every granule.
+#[derive(Default)]
+pub struct WriteOptions {
+    attr_truncation: AttrTruncation,
+    pub(super) warnings: AttrWarnings,
+}
+
+impl WriteOptions {
+    /// Set how string attribute values longer than their attribute length are handled.
+    #[must_use]
+    pub fn with_attr_truncation(mut self, truncation: AttrTruncation) -> Self {
+        self.attr_truncation = truncation;
+        self
+    }
+
+    /// How string attribute values longer than their attribute length are handled.
+    #[must_use]
+    pub fn attr_truncation(&self) -> AttrTruncation {
+        self.attr_truncation
+    }
+}
+
+impl fmt::Debug for WriteOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteOptions")
+            .field("attr_truncation", &self.attr_truncation)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hdf5::plist::file_access::FileDriver;
+
+    #[test]
+    fn test_file_options() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let opts = FileOptions::default()
+            .with_userblock(1024)
+            .with_chunk_cache(521, 4 * 1024 * 1024, 0.75)
+            .with_libver_bounds(LibverBounds::V18);
+        let file = opts.create(dir.path().join("opts.h5")).unwrap();
+        assert_eq!(file.fcpl().unwrap().userblock(), 1024);
+        file.close().unwrap();
+
+        assert!(FileOptions::default()
+            .with_userblock(100)
+            .create(dir.path().join("bad.h5"))
+            .is_err());
+        assert!(FileOptions::default()
+            .with_chunk_cache(521, 1024, 2.0)
+            .create(dir.path().join("bad.h5"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_memory_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = FileOptions::default().with_memory_image(1024);
+
+        let path = dir.path().join("small.h5");
+        let file = opts.create_sized(&path, Some(512)).unwrap();
+        assert!(matches!(file.fapl().unwrap().driver(), FileDriver::Core(_)));
+        file.new_attr::<u32>()
+            .create("test")
+            .unwrap()
+            .write_scalar(&7)
+            .unwrap();
+        file.close().unwrap();
+        // image was written to disk on close
+        let file = File::open(&path).unwrap();
+        assert_eq!(file.attr("test").unwrap().read_scalar::<u32>().unwrap(), 7);
+
+        for size in [Some(2048), None] {
+            let file = opts
+                .create_sized(dir.path().join("large.h5"), size)
+                .unwrap();
+            assert!(!matches!(
+                file.fapl().unwrap().driver(),
+                FileDriver::Core(_)
+            ));
+        }
+    }
+}